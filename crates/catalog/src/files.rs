@@ -0,0 +1,682 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{
+    sqlite::{Sqlite, SqlitePool},
+    Row, Transaction,
+};
+use uuid::Uuid;
+
+use crate::{
+    identifier::TableIdentifier,
+    tables::{parse_identifier, table_not_found},
+    AppState,
+};
+
+/// Columns databases created before they were tracked lack, with their
+/// tables and definitions.
+const ADDED_COLUMNS: [(&str, &str, &str); 7] = [
+    ("data_files", "value_counts", "TEXT NOT NULL DEFAULT '{}'"),
+    (
+        "data_files",
+        "null_value_counts",
+        "TEXT NOT NULL DEFAULT '{}'",
+    ),
+    (
+        "data_files",
+        "nan_value_counts",
+        "TEXT NOT NULL DEFAULT '{}'",
+    ),
+    (
+        "data_files",
+        "sequence_number",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+    ("data_files", "removed_sequence_number", "INTEGER"),
+    (
+        "snapshots",
+        "removed_files_count",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+    (
+        "snapshots",
+        "removed_records_count",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+];
+
+/// A data file written by the write service for a table.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DataFile {
+    pub path: String,
+    pub record_count: i64,
+    pub file_size_in_bytes: i64,
+    /// Values per column, nulls included, keyed by field id.
+    #[serde(default)]
+    pub value_counts: HashMap<String, i64>,
+    /// Nulls per column, keyed by field id.
+    #[serde(default)]
+    pub null_value_counts: HashMap<String, i64>,
+    /// NaN values per floating point column, keyed by field id.
+    #[serde(default)]
+    pub nan_value_counts: HashMap<String, i64>,
+    /// Lower bound per column, keyed by field id. Files registered before
+    /// field ids were used are keyed by column name.
+    #[serde(default)]
+    pub lower_bounds: HashMap<String, Value>,
+    /// Upper bound per column, keyed like `lower_bounds`.
+    #[serde(default)]
+    pub upper_bounds: HashMap<String, Value>,
+    /// Time the file was written, in milliseconds since the epoch.
+    pub written_at_ms: i64,
+    /// Sequence number of the snapshot that added the file, set by the
+    /// catalog.
+    #[serde(default)]
+    pub sequence_number: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DataFiles {
+    pub files: Vec<DataFile>,
+    /// Paths of current files the registered ones replace, such as the files
+    /// a compaction rewrote.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
+}
+
+/// A registration of data files, which the table's data can be read as of.
+/// A snapshot holds the files added by it and the snapshots before it, less
+/// those removed by then. Removed files stay registered for older snapshots.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub snapshot_id: i64,
+    pub parent_snapshot_id: Option<i64>,
+    pub sequence_number: i64,
+    /// Time the files were registered, in milliseconds since the epoch.
+    pub timestamp_ms: i64,
+    pub added_files_count: i64,
+    pub added_records_count: i64,
+    #[serde(default)]
+    pub removed_files_count: i64,
+    #[serde(default)]
+    pub removed_records_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshots {
+    /// Snapshots of the table, oldest first.
+    pub snapshots: Vec<Snapshot>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListDataFilesParams {
+    /// Only list the files of this snapshot rather than the current ones.
+    pub snapshot_id: Option<i64>,
+}
+
+pub async fn create_data_files_table(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS data_files (
+            table_name TEXT NOT NULL,
+            path TEXT NOT NULL,
+            record_count INTEGER NOT NULL,
+            file_size_in_bytes INTEGER NOT NULL,
+            lower_bounds TEXT NOT NULL,
+            upper_bounds TEXT NOT NULL,
+            written_at_ms INTEGER NOT NULL,
+            value_counts TEXT NOT NULL DEFAULT '{}',
+            null_value_counts TEXT NOT NULL DEFAULT '{}',
+            nan_value_counts TEXT NOT NULL DEFAULT '{}',
+            sequence_number INTEGER NOT NULL DEFAULT 0,
+            removed_sequence_number INTEGER,
+            PRIMARY KEY (table_name, path)
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS snapshots (
+            table_name TEXT NOT NULL,
+            snapshot_id INTEGER NOT NULL,
+            parent_snapshot_id INTEGER,
+            sequence_number INTEGER NOT NULL,
+            timestamp_ms INTEGER NOT NULL,
+            added_files_count INTEGER NOT NULL,
+            added_records_count INTEGER NOT NULL,
+            removed_files_count INTEGER NOT NULL DEFAULT 0,
+            removed_records_count INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (table_name, snapshot_id)
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    // Files registered before snapshots were taken have sequence number 0,
+    // and are part of every snapshot
+    for (table, column, definition) in ADDED_COLUMNS {
+        let exists = sqlx::query("SELECT 1 FROM pragma_table_info(?) WHERE name = ?")
+            .bind(table)
+            .bind(column)
+            .fetch_optional(pool)
+            .await?
+            .is_some();
+
+        if !exists {
+            sqlx::query(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table, column, definition
+            ))
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes every data file registered for a table and its snapshots,
+/// returning the paths of the files.
+pub async fn delete_data_files(
+    tx: &mut Transaction<'_, Sqlite>,
+    identifier: &TableIdentifier,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query("DELETE FROM snapshots WHERE table_name = ?")
+        .bind(identifier.to_string())
+        .execute(&mut **tx)
+        .await?;
+
+    let rows = sqlx::query("DELETE FROM data_files WHERE table_name = ? RETURNING path")
+        .bind(identifier.to_string())
+        .fetch_all(&mut **tx)
+        .await?;
+
+    Ok(rows.iter().map(|row| row.get("path")).collect())
+}
+
+async fn table_exists(
+    pool: &SqlitePool,
+    identifier: &TableIdentifier,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query("SELECT 1 FROM table_metadata WHERE name = ?")
+        .bind(identifier.to_string())
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.is_some())
+}
+
+/// Registers data files as a new snapshot, replacing the `removed` ones. A
+/// removed file that is no longer current, such as one another compaction
+/// replaced first, fails the whole registration with a conflict. Files
+/// already registered are left as they are, so retrying a registration adds
+/// no snapshot.
+pub async fn register_data_files(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(payload): Json<DataFiles>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let internal_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let identifier = parse_identifier(&name)?;
+
+    if !table_exists(&state.pool, &identifier)
+        .await
+        .map_err(internal_error)?
+    {
+        return Err(table_not_found(&identifier));
+    }
+
+    // Register all files or none of them, as a new snapshot
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+
+    let mut paths = HashSet::new();
+    let mut files = Vec::new();
+    for file in &payload.files {
+        let registered = sqlx::query("SELECT 1 FROM data_files WHERE table_name = ? AND path = ?")
+            .bind(identifier.to_string())
+            .bind(&file.path)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(internal_error)?
+            .is_some();
+
+        if !registered && paths.insert(&file.path) {
+            files.push(file);
+        }
+    }
+
+    if files.is_empty() && payload.removed.is_empty() {
+        return Ok(StatusCode::CREATED);
+    }
+
+    let parent = sqlx::query(
+        "SELECT snapshot_id, sequence_number FROM snapshots
+         WHERE table_name = ? ORDER BY sequence_number DESC LIMIT 1",
+    )
+    .bind(identifier.to_string())
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+
+    let sequence_number = parent
+        .as_ref()
+        .map_or(0, |row| row.get::<i64, _>("sequence_number"))
+        + 1;
+
+    let mut removed_records_count = 0;
+    for path in &payload.removed {
+        let removed = sqlx::query(
+            "UPDATE data_files SET removed_sequence_number = ?
+             WHERE table_name = ? AND path = ? AND removed_sequence_number IS NULL
+             RETURNING record_count",
+        )
+        .bind(sequence_number)
+        .bind(identifier.to_string())
+        .bind(path)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::CONFLICT,
+                format!(
+                    "Data file is not a current file of {}: {}",
+                    identifier, path
+                ),
+            )
+        })?;
+        removed_records_count += removed.get::<i64, _>("record_count");
+    }
+
+    let snapshot = Snapshot {
+        // Random positive ids, as Iceberg snapshots have
+        snapshot_id: (Uuid::new_v4().as_u64_pair().0 >> 1) as i64,
+        parent_snapshot_id: parent.as_ref().map(|row| row.get("snapshot_id")),
+        sequence_number,
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64),
+        added_files_count: files.len() as i64,
+        added_records_count: files.iter().map(|file| file.record_count).sum(),
+        removed_files_count: payload.removed.len() as i64,
+        removed_records_count,
+    };
+
+    sqlx::query(
+        "INSERT INTO snapshots (
+            table_name, snapshot_id, parent_snapshot_id, sequence_number, timestamp_ms,
+            added_files_count, added_records_count, removed_files_count, removed_records_count
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(identifier.to_string())
+    .bind(snapshot.snapshot_id)
+    .bind(snapshot.parent_snapshot_id)
+    .bind(snapshot.sequence_number)
+    .bind(snapshot.timestamp_ms)
+    .bind(snapshot.added_files_count)
+    .bind(snapshot.added_records_count)
+    .bind(snapshot.removed_files_count)
+    .bind(snapshot.removed_records_count)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+
+    for file in files {
+        let lower_bounds = to_json(&file.lower_bounds)?;
+        let upper_bounds = to_json(&file.upper_bounds)?;
+        let value_counts = to_json(&file.value_counts)?;
+        let null_value_counts = to_json(&file.null_value_counts)?;
+        let nan_value_counts = to_json(&file.nan_value_counts)?;
+
+        sqlx::query(
+            "INSERT INTO data_files (
+                table_name, path, record_count, file_size_in_bytes,
+                lower_bounds, upper_bounds, written_at_ms,
+                value_counts, null_value_counts, nan_value_counts, sequence_number
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(identifier.to_string())
+        .bind(&file.path)
+        .bind(file.record_count)
+        .bind(file.file_size_in_bytes)
+        .bind(&lower_bounds)
+        .bind(&upper_bounds)
+        .bind(file.written_at_ms)
+        .bind(&value_counts)
+        .bind(&null_value_counts)
+        .bind(&nan_value_counts)
+        .bind(snapshot.sequence_number)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    }
+
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(StatusCode::CREATED)
+}
+
+/// Lists the current data files of a table, or those of one of its snapshots.
+pub async fn list_data_files(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<ListDataFilesParams>,
+) -> Result<Json<DataFiles>, (StatusCode, String)> {
+    let internal_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let identifier = parse_identifier(&name)?;
+
+    if !table_exists(&state.pool, &identifier)
+        .await
+        .map_err(internal_error)?
+    {
+        return Err(table_not_found(&identifier));
+    }
+
+    let sequence_number = match params.snapshot_id {
+        Some(snapshot_id) => sqlx::query(
+            "SELECT sequence_number FROM snapshots WHERE table_name = ? AND snapshot_id = ?",
+        )
+        .bind(identifier.to_string())
+        .bind(snapshot_id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("Snapshot not found: {} of {}", snapshot_id, identifier),
+            )
+        })?
+        .get("sequence_number"),
+        None => i64::MAX,
+    };
+
+    let rows = sqlx::query(
+        "SELECT path, record_count, file_size_in_bytes, lower_bounds, upper_bounds, written_at_ms,
+                value_counts, null_value_counts, nan_value_counts, sequence_number
+         FROM data_files
+         WHERE table_name = ?1 AND sequence_number <= ?2
+             AND (removed_sequence_number IS NULL OR removed_sequence_number > ?2)
+         ORDER BY written_at_ms, path",
+    )
+    .bind(identifier.to_string())
+    .bind(sequence_number)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let mut files = Vec::with_capacity(rows.len());
+
+    for row in rows {
+        files.push(DataFile {
+            path: row.get("path"),
+            record_count: row.get("record_count"),
+            file_size_in_bytes: row.get("file_size_in_bytes"),
+            value_counts: from_json(row.get("value_counts"))?,
+            null_value_counts: from_json(row.get("null_value_counts"))?,
+            nan_value_counts: from_json(row.get("nan_value_counts"))?,
+            lower_bounds: from_json(row.get("lower_bounds"))?,
+            upper_bounds: from_json(row.get("upper_bounds"))?,
+            written_at_ms: row.get("written_at_ms"),
+            sequence_number: row.get("sequence_number"),
+        });
+    }
+
+    Ok(Json(DataFiles {
+        files,
+        removed: Vec::new(),
+    }))
+}
+
+/// Lists the snapshots of a table, oldest first.
+pub async fn list_snapshots(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<Snapshots>, (StatusCode, String)> {
+    let internal_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let identifier = parse_identifier(&name)?;
+
+    if !table_exists(&state.pool, &identifier)
+        .await
+        .map_err(internal_error)?
+    {
+        return Err(table_not_found(&identifier));
+    }
+
+    let rows = sqlx::query(
+        "SELECT snapshot_id, parent_snapshot_id, sequence_number, timestamp_ms,
+                added_files_count, added_records_count, removed_files_count, removed_records_count
+         FROM snapshots WHERE table_name = ? ORDER BY sequence_number",
+    )
+    .bind(identifier.to_string())
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let snapshots = rows
+        .iter()
+        .map(|row| Snapshot {
+            snapshot_id: row.get("snapshot_id"),
+            parent_snapshot_id: row.get("parent_snapshot_id"),
+            sequence_number: row.get("sequence_number"),
+            timestamp_ms: row.get("timestamp_ms"),
+            added_files_count: row.get("added_files_count"),
+            added_records_count: row.get("added_records_count"),
+            removed_files_count: row.get("removed_files_count"),
+            removed_records_count: row.get("removed_records_count"),
+        })
+        .collect();
+
+    Ok(Json(Snapshots { snapshots }))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, (StatusCode, String)> {
+    serde_json::to_string(value).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, (StatusCode, String)> {
+    serde_json::from_str(json).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    async fn test_state() -> AppState {
+        let state = crate::test_state("").await;
+
+        sqlx::query(
+            "INSERT INTO table_metadata (name, namespace, schema)
+             VALUES ('default.traffic', 'default', '{}')",
+        )
+        .execute(&state.pool)
+        .await
+        .unwrap();
+
+        state
+    }
+
+    fn data_file(path: &str, record_count: i64) -> DataFile {
+        DataFile {
+            path: path.to_string(),
+            record_count,
+            file_size_in_bytes: 1024,
+            value_counts: HashMap::from([("1".to_string(), record_count)]),
+            null_value_counts: HashMap::from([("1".to_string(), 0)]),
+            nan_value_counts: HashMap::new(),
+            lower_bounds: HashMap::from([("1".to_string(), json!(0))]),
+            upper_bounds: HashMap::from([("1".to_string(), json!(33))]),
+            written_at_ms: 1723320520000,
+            sequence_number: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_and_list_data_files() {
+        let state = test_state().await;
+
+        let files = DataFiles {
+            files: vec![
+                data_file("traffic/a.parquet", 4),
+                data_file("traffic/b.parquet", 2),
+            ],
+            removed: Vec::new(),
+        };
+        let status = register_data_files(
+            State(state.clone()),
+            Path("traffic".to_string()),
+            Json(files),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+
+        // Re-registering a path leaves the registered entry as it is
+        let files = DataFiles {
+            files: vec![data_file("traffic/a.parquet", 5)],
+            removed: Vec::new(),
+        };
+        register_data_files(
+            State(state.clone()),
+            Path("traffic".to_string()),
+            Json(files),
+        )
+        .await
+        .unwrap();
+
+        let Json(listed) = list_data_files(
+            State(state.clone()),
+            Path("traffic".to_string()),
+            Query(ListDataFilesParams::default()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(listed.files.len(), 2);
+        assert_eq!(listed.files[0].path, "traffic/a.parquet");
+        assert_eq!(listed.files[0].record_count, 4);
+        assert_eq!(listed.files[1].path, "traffic/b.parquet");
+        assert_eq!(listed.files[0].value_counts["1"], 4);
+        assert_eq!(listed.files[1].null_value_counts["1"], 0);
+        assert_eq!(listed.files[1].lower_bounds["1"], json!(0));
+        assert_eq!(listed.files[1].upper_bounds["1"], json!(33));
+    }
+
+    #[tokio::test]
+    async fn test_data_files_unknown_table() {
+        let state = test_state().await;
+
+        let files = DataFiles {
+            files: vec![data_file("missing/a.parquet", 1)],
+            removed: Vec::new(),
+        };
+        let result = register_data_files(
+            State(state.clone()),
+            Path("missing".to_string()),
+            Json(files),
+        )
+        .await;
+        assert!(matches!(result, Err((StatusCode::NOT_FOUND, _))));
+
+        let result = list_data_files(
+            State(state),
+            Path("missing".to_string()),
+            Query(ListDataFilesParams::default()),
+        )
+        .await;
+        assert!(matches!(result, Err((StatusCode::NOT_FOUND, _))));
+    }
+
+    #[tokio::test]
+    async fn test_snapshots() {
+        let state = test_state().await;
+        let register = |paths: &[&str], removed: &[&str]| {
+            let files = DataFiles {
+                files: paths.iter().map(|path| data_file(path, 1)).collect(),
+                removed: removed.iter().map(|path| path.to_string()).collect(),
+            };
+            register_data_files(
+                State(state.clone()),
+                Path("traffic".to_string()),
+                Json(files),
+            )
+        };
+        let list = |snapshot_id| {
+            list_data_files(
+                State(state.clone()),
+                Path("traffic".to_string()),
+                Query(ListDataFilesParams { snapshot_id }),
+            )
+        };
+
+        register(&["traffic/a.parquet", "traffic/b.parquet"], &[])
+            .await
+            .unwrap();
+        register(&["traffic/c.parquet"], &[]).await.unwrap();
+        // Re-registering a file adds no snapshot, and only new files count
+        register(&["traffic/a.parquet"], &[]).await.unwrap();
+        register(&["traffic/c.parquet", "traffic/d.parquet"], &[])
+            .await
+            .unwrap();
+
+        let Json(Snapshots { snapshots }) =
+            list_snapshots(State(state.clone()), Path("traffic".to_string()))
+                .await
+                .unwrap();
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(snapshots[0].parent_snapshot_id, None);
+        assert_eq!(snapshots[0].added_files_count, 2);
+        assert_eq!(
+            snapshots[1].parent_snapshot_id,
+            Some(snapshots[0].snapshot_id)
+        );
+        assert_eq!(snapshots[2].sequence_number, 3);
+        assert_eq!(snapshots[2].added_files_count, 1);
+
+        let Json(first) = list(Some(snapshots[0].snapshot_id)).await.unwrap();
+        assert_eq!(first.files.len(), 2);
+        assert!(first.files.iter().all(|file| file.sequence_number == 1));
+        let Json(current) = list(None).await.unwrap();
+        assert_eq!(current.files.len(), 4);
+
+        let result = list(Some(-1)).await;
+        assert!(matches!(result, Err((StatusCode::NOT_FOUND, _))));
+
+        // A compaction replaces files, which older snapshots still have
+        register(
+            &["traffic/ab.parquet"],
+            &["traffic/a.parquet", "traffic/b.parquet"],
+        )
+        .await
+        .unwrap();
+        let Json(current) = list(None).await.unwrap();
+        let paths: Vec<&str> = current.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "traffic/ab.parquet",
+                "traffic/c.parquet",
+                "traffic/d.parquet"
+            ]
+        );
+        let Json(first) = list(Some(snapshots[0].snapshot_id)).await.unwrap();
+        assert_eq!(first.files.len(), 2);
+
+        // Files are only replaced while current
+        let result = register(&["traffic/a2.parquet"], &["traffic/a.parquet"]).await;
+        assert!(matches!(result, Err((StatusCode::CONFLICT, _))));
+        let Json(current) = list(None).await.unwrap();
+        assert_eq!(current.files.len(), 3);
+    }
+}
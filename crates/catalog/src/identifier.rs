@@ -0,0 +1,110 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Namespace of tables created without one.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Identifies a table by its namespace levels and name, written as
+/// `ns1.ns2.table`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableIdentifier {
+    pub namespace: Vec<String>,
+    pub name: String,
+}
+
+impl TableIdentifier {
+    /// Namespace levels joined by dots, as namespaces are stored.
+    pub fn namespace_name(&self) -> String {
+        self.namespace.join(".")
+    }
+
+    /// Checks that no namespace level or the name is empty or contains a dot,
+    /// which would make the written form ambiguous, or a path separator.
+    pub fn validate(&self) -> Result<(), String> {
+        validate_namespace(&self.namespace)?;
+
+        if !is_valid_level(&self.name) {
+            return Err(format!("Invalid table name: {:?}", self.name));
+        }
+
+        Ok(())
+    }
+
+    /// Directory of the table's files in the warehouse, one level per
+    /// namespace level.
+    pub fn location(&self, warehouse: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            warehouse.trim_end_matches('/'),
+            self.namespace.join("/"),
+            self.name
+        )
+    }
+}
+
+pub fn validate_namespace(levels: &[String]) -> Result<(), String> {
+    if levels.is_empty() || !levels.iter().all(|l| is_valid_level(l)) {
+        return Err(format!("Invalid namespace: {:?}", levels));
+    }
+
+    Ok(())
+}
+
+/// Levels name directories of table locations, so can't contain separators.
+fn is_valid_level(level: &str) -> bool {
+    !level.is_empty() && !level.contains(['.', '/', '\\'])
+}
+
+impl FromStr for TableIdentifier {
+    type Err = String;
+
+    /// Parses `ns1.ns2.table`. A bare table name is in the default namespace.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut levels: Vec<String> = s.split('.').map(str::to_string).collect();
+        let name = levels.pop().unwrap_or_default();
+
+        if levels.is_empty() {
+            levels.push(DEFAULT_NAMESPACE.to_string());
+        }
+
+        let identifier = TableIdentifier {
+            namespace: levels,
+            name,
+        };
+        identifier
+            .validate()
+            .map_err(|_| format!("Invalid table identifier: {:?}", s))?;
+
+        Ok(identifier)
+    }
+}
+
+impl fmt::Display for TableIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.namespace_name(), self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_identifier() {
+        let identifier: TableIdentifier = "sales.eu.orders".parse().unwrap();
+        assert_eq!(identifier.namespace, vec!["sales", "eu"]);
+        assert_eq!(identifier.name, "orders");
+        assert_eq!(identifier.to_string(), "sales.eu.orders");
+
+        let identifier: TableIdentifier = "traffic".parse().unwrap();
+        assert_eq!(identifier.namespace, vec![DEFAULT_NAMESPACE]);
+        assert_eq!(identifier.to_string(), "default.traffic");
+
+        assert!("".parse::<TableIdentifier>().is_err());
+        assert!("sales..orders".parse::<TableIdentifier>().is_err());
+        assert!("sales.".parse::<TableIdentifier>().is_err());
+        assert!("sales/eu.orders".parse::<TableIdentifier>().is_err());
+        assert!("sales.eu\\orders".parse::<TableIdentifier>().is_err());
+    }
+}
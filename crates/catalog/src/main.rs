@@ -1,68 +1,42 @@
-use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
-use serde::{Deserialize, Serialize};
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use fileio::{FileIO, S3Config};
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
 use std::str::FromStr;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Field {
-    field: String,
-    #[serde(rename = "type")]
-    field_type: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct Schema {
-    fields: Vec<Field>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct TableRequest {
-    name: String,
-    schema: Schema,
-}
+mod files;
+mod identifier;
+mod properties;
+mod purge;
+mod rest;
+mod schema;
+mod tables;
 
 #[derive(Clone)]
 struct AppState {
     pool: SqlitePool,
+    /// Location new Iceberg tables are created under.
+    warehouse: String,
+    /// Metadata and data files of tables, wherever they live.
+    io: FileIO,
 }
 
-async fn create_metadata_table(pool: &SqlitePool) -> Result<(), sqlx::Error> {
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS table_metadata (
-            name TEXT PRIMARY KEY,
-            schema TEXT NOT NULL
-        )",
-    )
-    .execute(pool)
-    .await?;
+async fn create_catalog_tables(
+    pool: &SqlitePool,
+    warehouse: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    tables::create_metadata_table(pool).await?;
+    files::create_data_files_table(pool).await?;
+    rest::namespaces::create_namespaces_table(pool).await?;
+    tables::migrate_to_namespaces(pool).await?;
+    rest::tables::migrate_tables_table(pool).await?;
+    tables::backfill_iceberg_metadata(pool, warehouse).await?;
 
     Ok(())
 }
 
-async fn store_table_metadata(
-    State(state): State<AppState>,
-    Json(payload): Json<TableRequest>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    let schema_json = serde_json::to_string(&payload.schema)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let result = sqlx::query(
-        "INSERT INTO table_metadata (name, schema) VALUES (?, ?)
-         ON CONFLICT(name) DO UPDATE SET schema = excluded.schema",
-    )
-    .bind(&payload.name)
-    .bind(&schema_json)
-    .execute(&state.pool)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    if result.rows_affected() > 0 {
-        Ok(StatusCode::CREATED)
-    } else {
-        Ok(StatusCode::OK)
-    }
-}
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize the SQLite database
@@ -70,13 +44,41 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         SqliteConnectOptions::from_str("sqlite://data/table_metadata.db")?.create_if_missing(true);
     let pool = SqlitePool::connect_with(db_options).await?;
 
-    // Create the metadata table if it doesn't exist
-    create_metadata_table(&pool).await?;
+    let warehouse = match std::env::var("WAREHOUSE") {
+        Ok(warehouse) => warehouse,
+        Err(_) => std::path::absolute("data/warehouse")?
+            .to_string_lossy()
+            .into_owned(),
+    };
+
+    // Create the metadata tables if they don't exist
+    create_catalog_tables(&pool, &warehouse).await?;
 
     // Create the Axum app
-    let app_state = AppState { pool };
+    let app_state = AppState {
+        pool,
+        warehouse,
+        io: FileIO::new(S3Config::from_env()),
+    };
     let app = Router::new()
-        .route("/tables", post(store_table_metadata))
+        .route(
+            "/tables",
+            get(tables::list_tables).post(tables::store_table_metadata),
+        )
+        .route(
+            "/tables/:name",
+            get(tables::get_table).delete(tables::drop_table),
+        )
+        .route(
+            "/tables/:name/metadata",
+            post(tables::commit_metadata_location),
+        )
+        .route(
+            "/tables/:name/files",
+            get(files::list_data_files).post(files::register_data_files),
+        )
+        .route("/tables/:name/snapshots", get(files::list_snapshots))
+        .nest("/v1", rest::router())
         .with_state(app_state);
 
     // Run the server
@@ -86,3 +88,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+pub(crate) async fn test_state(warehouse: &str) -> AppState {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    // A single connection keeps every query on the same in-memory database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    create_catalog_tables(&pool, warehouse).await.unwrap();
+
+    AppState {
+        pool,
+        warehouse: warehouse.to_string(),
+        io: FileIO::default(),
+    }
+}
@@ -0,0 +1,198 @@
+//! Checks of the table properties the write service builds data files from,
+//! so that a bad value is refused when it is set rather than failing every
+//! later write. The rules follow the write service's `properties` and
+//! `cluster` modules; `write.parquet.` properties it has no setting for are
+//! accepted, as it ignores them.
+
+use std::collections::HashMap;
+
+use crate::schema::Schema;
+
+const PARQUET_PREFIX: &str = "write.parquet.";
+const COLUMN_INFIX: &str = ".column.";
+const CLUSTERING_PROPERTY: &str = "write.clustering";
+const COMPRESSION_CODEC: &str = "write.parquet.compression-codec";
+const COMPRESSION_LEVEL: &str = "write.parquet.compression-level";
+
+/// Columns a clustering curve can interleave.
+const MAX_CLUSTERING_COLUMNS: usize = 8;
+
+/// Checks the `write.parquet.` and `write.clustering` properties of a table
+/// with the given schema.
+pub fn validate(properties: &HashMap<String, String>, schema: &Schema) -> Result<(), String> {
+    validate_compression(
+        properties.get(COMPRESSION_CODEC).map(String::as_str),
+        properties.get(COMPRESSION_LEVEL).map(String::as_str),
+    )?;
+
+    for (key, value) in properties {
+        let Some(name) = key.strip_prefix(PARQUET_PREFIX) else {
+            continue;
+        };
+        let (name, column) = match name.split_once(COLUMN_INFIX) {
+            Some((name, column)) => (name, Some(column)),
+            None => (name, None),
+        };
+        let value = value.trim().to_lowercase();
+
+        let reason = match (name, column) {
+            (
+                "row-group-limit" | "page-size-bytes" | "page-row-limit" | "dict-size-bytes",
+                None,
+            )
+            | ("bloom-filter-ndv", Some(_))
+                if !value.parse::<usize>().is_ok_and(|n| n > 0) =>
+            {
+                "must be a positive integer"
+            }
+            ("dictionary-enabled", _) | ("bloom-filter-enabled", Some(_))
+                if value.parse::<bool>().is_err() =>
+            {
+                "must be true or false"
+            }
+            ("statistics-level", _) if !matches!(value.as_str(), "none" | "chunk" | "page") => {
+                "must be none, chunk or page"
+            }
+            ("bloom-filter-fpp", Some(_))
+                if !value.parse::<f64>().is_ok_and(|fpp| fpp > 0.0 && fpp < 1.0) =>
+            {
+                "must be between 0 and 1"
+            }
+            _ => continue,
+        };
+
+        return Err(format!(
+            "Invalid value {:?} of table property {}: {}",
+            properties[key], key, reason
+        ));
+    }
+
+    if let Some(clustering) = properties.get(CLUSTERING_PROPERTY) {
+        validate_clustering(clustering, schema)?;
+    }
+
+    Ok(())
+}
+
+/// Codec and level, given separately as in Iceberg. Levels are those Parquet
+/// accepts for the codec.
+fn validate_compression(codec: Option<&str>, level: Option<&str>) -> Result<(), String> {
+    let codec = codec.map_or("zstd".to_string(), |codec| codec.trim().to_lowercase());
+
+    let levels = match codec.as_str() {
+        "zstd" => Some(1..=22),
+        "gzip" => Some(0..=10),
+        "brotli" => Some(0..=11),
+        "snappy" | "lz4" | "lz4_raw" | "lzo" | "uncompressed" => None,
+        _ => return Err(format!("Unknown {}: {}", COMPRESSION_CODEC, codec)),
+    };
+
+    match (levels, level) {
+        (_, None) => Ok(()),
+        (Some(levels), Some(level)) => match level.trim().parse::<u32>() {
+            Ok(level) if levels.contains(&level) => Ok(()),
+            _ => Err(format!(
+                "Invalid {} {:?}: {} levels are {} to {}",
+                COMPRESSION_LEVEL,
+                level,
+                codec,
+                levels.start(),
+                levels.end()
+            )),
+        },
+        (None, Some(_)) => Err(format!("{} takes no {}", codec, COMPRESSION_LEVEL)),
+    }
+}
+
+/// Checks `zorder(a, b)` or `hilbert(a, b)` names top-level primitive
+/// columns of the table.
+fn validate_clustering(value: &str, schema: &Schema) -> Result<(), String> {
+    let invalid = || format!("Invalid {}: {}", CLUSTERING_PROPERTY, value);
+
+    let (curve, columns) = value
+        .trim()
+        .strip_suffix(')')
+        .and_then(|value| value.split_once('('))
+        .ok_or_else(invalid)?;
+
+    if !matches!(curve.trim().to_lowercase().as_str(), "zorder" | "hilbert") {
+        return Err(invalid());
+    }
+
+    let columns: Vec<&str> = columns.split(',').map(str::trim).collect();
+    for name in &columns {
+        let field = schema
+            .fields
+            .iter()
+            .find(|field| field.field == *name)
+            .ok_or_else(|| format!("Can't cluster by unknown column {}", name))?;
+
+        if !field.is_primitive() {
+            return Err(format!("Can't cluster by nested column {}", name));
+        }
+    }
+
+    if columns.is_empty() || columns.len() > MAX_CLUSTERING_COLUMNS {
+        return Err(format!(
+            "{} takes 1 to {} columns",
+            CLUSTERING_PROPERTY, MAX_CLUSTERING_COLUMNS
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema: Schema = serde_json::from_value(serde_json::json!({
+            "fields": [
+                {"id": 1, "field": "x", "type": "double"},
+                {"id": 2, "field": "y", "type": "double"},
+                {"id": 3, "field": "device", "type": "struct", "fields": [
+                    {"id": 4, "field": "os", "type": "string"}
+                ]}
+            ]
+        }))
+        .unwrap();
+
+        let valid = properties(&[
+            ("write.parquet.compression-codec", "ZSTD"),
+            ("write.parquet.compression-level", "9"),
+            ("write.parquet.row-group-limit", "10000"),
+            ("write.parquet.statistics-level.column.x", "page"),
+            ("write.parquet.bloom-filter-enabled.column.x", "true"),
+            ("write.parquet.bloom-filter-fpp.column.x", "0.01"),
+            ("write.parquet.row-group-size-bytes", "134217728"),
+            ("write.clustering", "hilbert(x, y)"),
+            ("owner", "analytics"),
+        ]);
+        assert_eq!(validate(&valid, &schema), Ok(()));
+
+        for invalid in [
+            ("write.parquet.compression-codec", "zip"),
+            ("write.parquet.compression-level", "30"),
+            ("write.parquet.row-group-limit", "0"),
+            ("write.parquet.dictionary-enabled", "yes"),
+            ("write.parquet.statistics-level", "all"),
+            ("write.parquet.bloom-filter-fpp.column.x", "1.5"),
+            ("write.parquet.bloom-filter-ndv.column.x", "-1"),
+            ("write.clustering", "spiral(x, y)"),
+            ("write.clustering", "zorder(x, z)"),
+            ("write.clustering", "zorder(x, device)"),
+        ] {
+            let result = validate(&properties(&[invalid]), &schema);
+            assert!(result.is_err(), "{:?}", invalid);
+        }
+    }
+}
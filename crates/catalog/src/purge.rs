@@ -0,0 +1,97 @@
+//! Deleting the files of purged tables.
+//!
+//! Only files a table tracks are deleted, never whole directories: a table's
+//! location is chosen by its clients and may be shared with other tables.
+
+use std::collections::BTreeSet;
+
+use fileio::{FileIO, FileIOError};
+use iceberg::manifest::{self, ManifestError};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::rest::metadata::TableMetadata;
+
+#[derive(Error, Debug)]
+pub enum PurgeError {
+    #[error("Failed to read {0}: {1}")]
+    Read(String, FileIOError),
+    #[error("Invalid manifest {0}: {1}")]
+    Manifest(String, ManifestError),
+    #[error("Failed to purge {0}: {1}")]
+    Delete(String, FileIOError),
+}
+
+/// Files a table tracks: the data files registered for it, its current and
+/// previous metadata files, and the manifest lists, manifests and data files
+/// of its snapshots.
+pub async fn table_files(
+    io: &FileIO,
+    data_files: Vec<String>,
+    metadata_location: Option<String>,
+    previous_metadata_location: Option<String>,
+    metadata: Option<&TableMetadata>,
+) -> Result<BTreeSet<String>, PurgeError> {
+    let mut files: BTreeSet<String> = data_files.into_iter().collect();
+    files.extend(metadata_location);
+    files.extend(previous_metadata_location);
+
+    let Some(metadata) = metadata else {
+        return Ok(files);
+    };
+
+    files.extend(
+        metadata
+            .metadata_log
+            .iter()
+            .map(|entry| entry.metadata_file.clone()),
+    );
+
+    // Snapshots list their manifests in a manifest list, or in v1 directly
+    let mut manifests = BTreeSet::new();
+    for snapshot in &metadata.snapshots {
+        if let Some(list) = snapshot.get("manifest-list").and_then(Value::as_str) {
+            files.insert(list.to_string());
+            if let Some(data) = read(io, list).await? {
+                let list_files = manifest::read_manifest_list(&data)
+                    .map_err(|e| PurgeError::Manifest(list.to_string(), e))?;
+                manifests.extend(list_files.into_iter().map(|file| file.manifest_path));
+            }
+        }
+        if let Some(paths) = snapshot.get("manifests").and_then(Value::as_array) {
+            manifests.extend(paths.iter().filter_map(Value::as_str).map(str::to_string));
+        }
+    }
+
+    for path in manifests {
+        if let Some(data) = read(io, &path).await? {
+            let entries = manifest::read_manifest(&data)
+                .map_err(|e| PurgeError::Manifest(path.clone(), e))?;
+            files.extend(entries.into_iter().map(|entry| entry.data_file.file_path));
+        }
+        files.insert(path);
+    }
+
+    Ok(files)
+}
+
+/// Reads a file, `None` if it's already gone, e.g. by an earlier purge.
+async fn read(io: &FileIO, location: &str) -> Result<Option<Vec<u8>>, PurgeError> {
+    match io.read(location).await {
+        Ok(data) => Ok(Some(data.to_vec())),
+        Err(FileIOError::NotFound(_)) => Ok(None),
+        Err(e) => Err(PurgeError::Read(location.to_string(), e)),
+    }
+}
+
+/// Deletes files, local or in buckets. Files already gone are skipped, so a
+/// purge can be retried.
+pub async fn delete_files(io: &FileIO, files: &BTreeSet<String>) -> Result<(), PurgeError> {
+    for file in files {
+        io.delete(file)
+            .await
+            .map_err(|e| PurgeError::Delete(file.clone(), e))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,677 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Table metadata, as stored in a table's metadata file.
+///
+/// Schemas, partition specs, sort orders and snapshots are kept as raw JSON:
+/// the catalog only reads the ids it needs to track and hands everything
+/// else back to clients untouched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TableMetadata {
+    pub format_version: u8,
+    pub table_uuid: String,
+    pub location: String,
+    pub last_sequence_number: i64,
+    pub last_updated_ms: i64,
+    pub last_column_id: i64,
+    pub schemas: Vec<Value>,
+    pub current_schema_id: i64,
+    pub partition_specs: Vec<Value>,
+    pub default_spec_id: i64,
+    pub last_partition_id: i64,
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+    #[serde(default)]
+    pub current_snapshot_id: Option<i64>,
+    #[serde(default)]
+    pub snapshots: Vec<Value>,
+    #[serde(default)]
+    pub snapshot_log: Vec<SnapshotLogEntry>,
+    #[serde(default)]
+    pub metadata_log: Vec<MetadataLogEntry>,
+    pub sort_orders: Vec<Value>,
+    pub default_sort_order_id: i64,
+    #[serde(default)]
+    pub refs: HashMap<String, SnapshotReference>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotLogEntry {
+    pub snapshot_id: i64,
+    pub timestamp_ms: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MetadataLogEntry {
+    pub metadata_file: String,
+    pub timestamp_ms: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotReference {
+    pub snapshot_id: i64,
+    #[serde(rename = "type")]
+    pub ref_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_snapshots_to_keep: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_snapshot_age_ms: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_ref_age_ms: Option<i64>,
+}
+
+/// A condition the current table metadata must meet for a commit to apply.
+// Variant names follow the requirement types of the spec
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TableRequirement {
+    AssertCreate,
+    AssertTableUuid {
+        uuid: String,
+    },
+    AssertRefSnapshotId {
+        #[serde(rename = "ref")]
+        ref_name: String,
+        #[serde(rename = "snapshot-id")]
+        snapshot_id: Option<i64>,
+    },
+    AssertLastAssignedFieldId {
+        #[serde(rename = "last-assigned-field-id")]
+        last_assigned_field_id: i64,
+    },
+    AssertCurrentSchemaId {
+        #[serde(rename = "current-schema-id")]
+        current_schema_id: i64,
+    },
+    AssertLastAssignedPartitionId {
+        #[serde(rename = "last-assigned-partition-id")]
+        last_assigned_partition_id: i64,
+    },
+    AssertDefaultSpecId {
+        #[serde(rename = "default-spec-id")]
+        default_spec_id: i64,
+    },
+    AssertDefaultSortOrderId {
+        #[serde(rename = "default-sort-order-id")]
+        default_sort_order_id: i64,
+    },
+}
+
+/// A change to apply to table metadata.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum TableUpdate {
+    AssignUuid {
+        uuid: String,
+    },
+    UpgradeFormatVersion {
+        #[serde(rename = "format-version")]
+        format_version: u8,
+    },
+    AddSchema {
+        schema: Value,
+        #[serde(rename = "last-column-id")]
+        last_column_id: Option<i64>,
+    },
+    SetCurrentSchema {
+        #[serde(rename = "schema-id")]
+        schema_id: i64,
+    },
+    AddSpec {
+        spec: Value,
+    },
+    SetDefaultSpec {
+        #[serde(rename = "spec-id")]
+        spec_id: i64,
+    },
+    AddSortOrder {
+        #[serde(rename = "sort-order")]
+        sort_order: Value,
+    },
+    SetDefaultSortOrder {
+        #[serde(rename = "sort-order-id")]
+        sort_order_id: i64,
+    },
+    AddSnapshot {
+        snapshot: Value,
+    },
+    SetSnapshotRef {
+        #[serde(rename = "ref-name")]
+        ref_name: String,
+        #[serde(flatten)]
+        reference: SnapshotReference,
+    },
+    RemoveSnapshots {
+        #[serde(rename = "snapshot-ids")]
+        snapshot_ids: Vec<i64>,
+    },
+    RemoveSnapshotRef {
+        #[serde(rename = "ref-name")]
+        ref_name: String,
+    },
+    SetLocation {
+        location: String,
+    },
+    SetProperties {
+        updates: HashMap<String, String>,
+    },
+    RemoveProperties {
+        removals: Vec<String>,
+    },
+}
+
+/// Id that refers to the last schema, spec or sort order added in a commit.
+pub const LAST_ADDED: i64 = -1;
+
+const MAIN_BRANCH: &str = "main";
+const PARTITION_DATA_ID_START: i64 = 1000;
+
+/// Reads an integer field of a JSON object.
+fn get_i64(value: &Value, field: &str) -> Option<i64> {
+    value.get(field).and_then(Value::as_i64)
+}
+
+/// Finds the highest field id in a schema, including nested fields.
+fn highest_field_id(value: &Value) -> i64 {
+    let mut highest = -1;
+
+    match value {
+        Value::Object(object) => {
+            for (key, child) in object {
+                if matches!(key.as_str(), "id" | "element-id" | "key-id" | "value-id") {
+                    highest = highest.max(child.as_i64().unwrap_or(-1));
+                } else {
+                    highest = highest.max(highest_field_id(child));
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                highest = highest.max(highest_field_id(item));
+            }
+        }
+        _ => {}
+    }
+
+    highest
+}
+
+/// Finds the highest partition field id in a partition spec.
+fn highest_partition_id(spec: &Value) -> i64 {
+    spec.get("fields")
+        .and_then(Value::as_array)
+        .map(|fields| fields.iter().filter_map(|f| get_i64(f, "field-id")).max())
+        .unwrap_or_default()
+        .unwrap_or(PARTITION_DATA_ID_START - 1)
+}
+
+impl TableMetadata {
+    /// Creates metadata for a new table with a single schema, spec and sort order.
+    pub fn new(
+        table_uuid: String,
+        location: String,
+        mut schema: Value,
+        partition_spec: Option<Value>,
+        sort_order: Option<Value>,
+        properties: HashMap<String, String>,
+        now_ms: i64,
+    ) -> Self {
+        schema["schema-id"] = Value::from(0);
+        let last_column_id = highest_field_id(&schema["fields"]).max(0);
+
+        // Partition fields get ids from 1000 onwards, unless the client assigned them
+        let mut spec = partition_spec.unwrap_or_else(|| serde_json::json!({ "fields": [] }));
+        spec["spec-id"] = Value::from(0);
+        if let Some(fields) = spec.get_mut("fields").and_then(Value::as_array_mut) {
+            for (i, field) in fields.iter_mut().enumerate() {
+                if field.get("field-id").is_none() {
+                    field["field-id"] = Value::from(PARTITION_DATA_ID_START + i as i64);
+                }
+            }
+        }
+        let last_partition_id = highest_partition_id(&spec);
+
+        // Order 0 is reserved for the unsorted order
+        let unsorted = serde_json::json!({ "order-id": 0, "fields": [] });
+        let (sort_orders, default_sort_order_id) = match sort_order {
+            Some(mut order) if !is_unsorted(&order) => {
+                order["order-id"] = Value::from(1);
+                (vec![unsorted, order], 1)
+            }
+            _ => (vec![unsorted], 0),
+        };
+
+        TableMetadata {
+            format_version: 2,
+            table_uuid,
+            location,
+            last_sequence_number: 0,
+            last_updated_ms: now_ms,
+            last_column_id,
+            schemas: vec![schema],
+            current_schema_id: 0,
+            partition_specs: vec![spec],
+            default_spec_id: 0,
+            last_partition_id,
+            properties,
+            current_snapshot_id: None,
+            snapshots: Vec::new(),
+            snapshot_log: Vec::new(),
+            metadata_log: Vec::new(),
+            sort_orders,
+            default_sort_order_id,
+            refs: HashMap::new(),
+        }
+    }
+
+    /// Metadata for a table that is created through a commit: the commit's
+    /// updates fill in everything.
+    pub fn empty(now_ms: i64) -> Self {
+        TableMetadata {
+            format_version: 2,
+            table_uuid: String::new(),
+            location: String::new(),
+            last_sequence_number: 0,
+            last_updated_ms: now_ms,
+            last_column_id: 0,
+            schemas: Vec::new(),
+            current_schema_id: LAST_ADDED,
+            partition_specs: Vec::new(),
+            default_spec_id: LAST_ADDED,
+            last_partition_id: PARTITION_DATA_ID_START - 1,
+            properties: HashMap::new(),
+            current_snapshot_id: None,
+            snapshots: Vec::new(),
+            snapshot_log: Vec::new(),
+            metadata_log: Vec::new(),
+            sort_orders: Vec::new(),
+            default_sort_order_id: LAST_ADDED,
+            refs: HashMap::new(),
+        }
+    }
+
+    /// Checks that the metadata meets a commit requirement. `exists` tells
+    /// whether the table is already in the catalog.
+    pub fn check(&self, requirement: &TableRequirement, exists: bool) -> Result<(), String> {
+        let ok = match requirement {
+            TableRequirement::AssertCreate => !exists,
+            TableRequirement::AssertTableUuid { uuid } => self.table_uuid == *uuid,
+            TableRequirement::AssertRefSnapshotId {
+                ref_name,
+                snapshot_id,
+            } => self.refs.get(ref_name).map(|r| r.snapshot_id) == *snapshot_id,
+            TableRequirement::AssertLastAssignedFieldId {
+                last_assigned_field_id,
+            } => self.last_column_id == *last_assigned_field_id,
+            TableRequirement::AssertCurrentSchemaId { current_schema_id } => {
+                self.current_schema_id == *current_schema_id
+            }
+            TableRequirement::AssertLastAssignedPartitionId {
+                last_assigned_partition_id,
+            } => self.last_partition_id == *last_assigned_partition_id,
+            TableRequirement::AssertDefaultSpecId { default_spec_id } => {
+                self.default_spec_id == *default_spec_id
+            }
+            TableRequirement::AssertDefaultSortOrderId {
+                default_sort_order_id,
+            } => self.default_sort_order_id == *default_sort_order_id,
+        };
+
+        if ok {
+            Ok(())
+        } else {
+            Err(format!("Requirement failed: {:?}", requirement))
+        }
+    }
+
+    /// Applies commit updates in order. Updates that refer to the last added
+    /// schema, spec or sort order resolve against earlier updates of the same
+    /// commit.
+    pub fn apply(&mut self, updates: Vec<TableUpdate>, now_ms: i64) -> Result<(), String> {
+        let mut last_added_schema_id = None;
+        let mut last_added_spec_id = None;
+        let mut last_added_order_id = None;
+
+        for update in updates {
+            match update {
+                TableUpdate::AssignUuid { uuid } => {
+                    if !self.table_uuid.is_empty() && self.table_uuid != uuid {
+                        return Err("Cannot reassign table uuid".to_string());
+                    }
+                    self.table_uuid = uuid;
+                }
+                TableUpdate::UpgradeFormatVersion { format_version } => {
+                    if format_version < self.format_version {
+                        return Err(format!(
+                            "Cannot downgrade format version from {} to {}",
+                            self.format_version, format_version
+                        ));
+                    }
+                    self.format_version = format_version;
+                }
+                TableUpdate::AddSchema {
+                    mut schema,
+                    last_column_id,
+                } => {
+                    let schema_id = match self.find_schema(&schema) {
+                        Some(existing) => existing,
+                        None => {
+                            let schema_id = self.next_id(&self.schemas, "schema-id");
+                            schema["schema-id"] = Value::from(schema_id);
+                            self.last_column_id = self
+                                .last_column_id
+                                .max(last_column_id.unwrap_or(-1))
+                                .max(highest_field_id(&schema["fields"]));
+                            self.schemas.push(schema);
+                            schema_id
+                        }
+                    };
+                    last_added_schema_id = Some(schema_id);
+                }
+                TableUpdate::SetCurrentSchema { schema_id } => {
+                    let schema_id = resolve(schema_id, last_added_schema_id, "schema")?;
+                    if !self.has_id(&self.schemas, "schema-id", schema_id) {
+                        return Err(format!("Unknown schema id: {}", schema_id));
+                    }
+                    self.current_schema_id = schema_id;
+                }
+                TableUpdate::AddSpec { mut spec } => {
+                    let spec_id = self.next_id(&self.partition_specs, "spec-id");
+                    spec["spec-id"] = Value::from(spec_id);
+                    self.last_partition_id =
+                        self.last_partition_id.max(highest_partition_id(&spec));
+                    self.partition_specs.push(spec);
+                    last_added_spec_id = Some(spec_id);
+                }
+                TableUpdate::SetDefaultSpec { spec_id } => {
+                    let spec_id = resolve(spec_id, last_added_spec_id, "partition spec")?;
+                    if !self.has_id(&self.partition_specs, "spec-id", spec_id) {
+                        return Err(format!("Unknown partition spec id: {}", spec_id));
+                    }
+                    self.default_spec_id = spec_id;
+                }
+                TableUpdate::AddSortOrder { mut sort_order } => {
+                    let order_id = if is_unsorted(&sort_order) {
+                        0
+                    } else {
+                        self.next_id(&self.sort_orders, "order-id").max(1)
+                    };
+                    if !self.has_id(&self.sort_orders, "order-id", order_id) {
+                        sort_order["order-id"] = Value::from(order_id);
+                        self.sort_orders.push(sort_order);
+                    }
+                    last_added_order_id = Some(order_id);
+                }
+                TableUpdate::SetDefaultSortOrder { sort_order_id } => {
+                    let order_id = resolve(sort_order_id, last_added_order_id, "sort order")?;
+                    if !self.has_id(&self.sort_orders, "order-id", order_id) {
+                        return Err(format!("Unknown sort order id: {}", order_id));
+                    }
+                    self.default_sort_order_id = order_id;
+                }
+                TableUpdate::AddSnapshot { snapshot } => {
+                    let snapshot_id = get_i64(&snapshot, "snapshot-id")
+                        .ok_or_else(|| "Snapshot is missing snapshot-id".to_string())?;
+                    if self.snapshot(snapshot_id).is_some() {
+                        return Err(format!("Snapshot already exists: {}", snapshot_id));
+                    }
+
+                    let sequence_number = get_i64(&snapshot, "sequence-number").unwrap_or(0);
+                    if self.format_version > 1 && sequence_number <= self.last_sequence_number {
+                        return Err(format!(
+                            "Snapshot sequence number {} is not after last sequence number {}",
+                            sequence_number, self.last_sequence_number
+                        ));
+                    }
+
+                    self.last_sequence_number = self.last_sequence_number.max(sequence_number);
+                    self.last_updated_ms = get_i64(&snapshot, "timestamp-ms").unwrap_or(now_ms);
+                    self.snapshots.push(snapshot);
+                }
+                TableUpdate::SetSnapshotRef {
+                    ref_name,
+                    reference,
+                } => {
+                    let snapshot = self
+                        .snapshot(reference.snapshot_id)
+                        .ok_or_else(|| format!("Unknown snapshot id: {}", reference.snapshot_id))?;
+
+                    if ref_name == MAIN_BRANCH {
+                        let timestamp_ms = get_i64(snapshot, "timestamp-ms").unwrap_or(now_ms);
+                        self.current_snapshot_id = Some(reference.snapshot_id);
+                        self.snapshot_log.push(SnapshotLogEntry {
+                            snapshot_id: reference.snapshot_id,
+                            timestamp_ms,
+                        });
+                    }
+
+                    self.refs.insert(ref_name, reference);
+                }
+                TableUpdate::RemoveSnapshots { snapshot_ids } => {
+                    self.snapshots.retain(|s| {
+                        get_i64(s, "snapshot-id").is_none_or(|id| !snapshot_ids.contains(&id))
+                    });
+                    self.snapshot_log
+                        .retain(|entry| !snapshot_ids.contains(&entry.snapshot_id));
+                    self.refs
+                        .retain(|_, reference| !snapshot_ids.contains(&reference.snapshot_id));
+
+                    if matches!(self.current_snapshot_id, Some(id) if snapshot_ids.contains(&id)) {
+                        self.current_snapshot_id = None;
+                    }
+                }
+                TableUpdate::RemoveSnapshotRef { ref_name } => {
+                    if ref_name == MAIN_BRANCH {
+                        self.current_snapshot_id = None;
+                    }
+                    self.refs.remove(&ref_name);
+                }
+                TableUpdate::SetLocation { location } => {
+                    self.location = location;
+                }
+                TableUpdate::SetProperties { updates } => {
+                    self.properties.extend(updates);
+                }
+                TableUpdate::RemoveProperties { removals } => {
+                    for key in removals {
+                        self.properties.remove(&key);
+                    }
+                }
+            }
+        }
+
+        if self.table_uuid.is_empty() {
+            return Err("Table uuid is not assigned".to_string());
+        }
+
+        self.last_updated_ms = self.last_updated_ms.max(now_ms);
+
+        Ok(())
+    }
+
+    /// The schema new data is written with.
+    pub fn current_schema(&self) -> Option<&Value> {
+        self.schemas
+            .iter()
+            .find(|s| get_i64(s, "schema-id") == Some(self.current_schema_id))
+    }
+
+    /// The order new data files are sorted in, `None` if unsorted.
+    pub fn default_sort_order(&self) -> Option<&Value> {
+        self.sort_orders
+            .iter()
+            .find(|o| get_i64(o, "order-id") == Some(self.default_sort_order_id))
+            .filter(|o| !is_unsorted(o))
+    }
+
+    fn snapshot(&self, snapshot_id: i64) -> Option<&Value> {
+        self.snapshots
+            .iter()
+            .find(|s| get_i64(s, "snapshot-id") == Some(snapshot_id))
+    }
+
+    fn find_schema(&self, schema: &Value) -> Option<i64> {
+        self.schemas
+            .iter()
+            .find(|s| s.get("fields") == schema.get("fields"))
+            .and_then(|s| get_i64(s, "schema-id"))
+    }
+
+    fn next_id(&self, items: &[Value], id_field: &str) -> i64 {
+        items
+            .iter()
+            .filter_map(|item| get_i64(item, id_field))
+            .max()
+            .map_or(0, |id| id + 1)
+    }
+
+    fn has_id(&self, items: &[Value], id_field: &str, id: i64) -> bool {
+        items.iter().any(|item| get_i64(item, id_field) == Some(id))
+    }
+}
+
+fn resolve(id: i64, last_added: Option<i64>, kind: &str) -> Result<i64, String> {
+    if id == LAST_ADDED {
+        last_added.ok_or_else(|| format!("No {} was added in this commit", kind))
+    } else {
+        Ok(id)
+    }
+}
+
+fn is_unsorted(sort_order: &Value) -> bool {
+    sort_order
+        .get("fields")
+        .and_then(Value::as_array)
+        .is_none_or(|fields| fields.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn metadata() -> TableMetadata {
+        let schema = json!({
+            "type": "struct",
+            "fields": [
+                {"id": 1, "name": "id", "type": "long", "required": true},
+                {"id": 2, "name": "tags", "type": {
+                    "type": "list", "element-id": 3, "element": "string", "element-required": false
+                }, "required": false}
+            ]
+        });
+        let spec = json!({
+            "fields": [{"source-id": 1, "transform": "bucket[16]", "name": "id_bucket"}]
+        });
+
+        TableMetadata::new(
+            "a1b2".to_string(),
+            "file:///warehouse/db/t".to_string(),
+            schema,
+            Some(spec),
+            None,
+            HashMap::new(),
+            1000,
+        )
+    }
+
+    #[test]
+    fn test_new_assigns_ids() {
+        let metadata = metadata();
+
+        assert_eq!(metadata.last_column_id, 3);
+        assert_eq!(metadata.schemas[0]["schema-id"], json!(0));
+        assert_eq!(
+            metadata.partition_specs[0]["fields"][0]["field-id"],
+            json!(1000)
+        );
+        assert_eq!(metadata.last_partition_id, 1000);
+        assert_eq!(metadata.default_sort_order_id, 0);
+    }
+
+    #[test]
+    fn test_check_requirements() {
+        let metadata = metadata();
+
+        assert!(metadata
+            .check(&TableRequirement::AssertCreate, false)
+            .is_ok());
+        assert!(metadata
+            .check(&TableRequirement::AssertCreate, true)
+            .is_err());
+
+        let requirement = TableRequirement::AssertRefSnapshotId {
+            ref_name: "main".to_string(),
+            snapshot_id: None,
+        };
+        assert!(metadata.check(&requirement, true).is_ok());
+
+        let requirement = TableRequirement::AssertCurrentSchemaId {
+            current_schema_id: 1,
+        };
+        assert!(metadata.check(&requirement, true).is_err());
+    }
+
+    #[test]
+    fn test_apply_schema_and_snapshot_updates() {
+        let mut metadata = metadata();
+
+        let updates: Vec<TableUpdate> = serde_json::from_value(json!([
+            {
+                "action": "add-schema",
+                "schema": {"type": "struct", "fields": [
+                    {"id": 1, "name": "id", "type": "long", "required": true},
+                    {"id": 4, "name": "name", "type": "string", "required": false}
+                ]},
+                "last-column-id": 4
+            },
+            {"action": "set-current-schema", "schema-id": -1},
+            {
+                "action": "add-snapshot",
+                "snapshot": {
+                    "snapshot-id": 42, "sequence-number": 1, "timestamp-ms": 2000,
+                    "manifest-list": "file:///warehouse/db/t/metadata/snap-42.avro",
+                    "summary": {"operation": "append"}
+                }
+            },
+            {"action": "set-snapshot-ref", "ref-name": "main", "type": "branch", "snapshot-id": 42},
+            {"action": "set-properties", "updates": {"owner": "analytics"}}
+        ]))
+        .unwrap();
+
+        metadata.apply(updates, 3000).unwrap();
+
+        assert_eq!(metadata.current_schema_id, 1);
+        assert_eq!(metadata.last_column_id, 4);
+        assert_eq!(metadata.current_snapshot_id, Some(42));
+        assert_eq!(metadata.last_sequence_number, 1);
+        assert_eq!(metadata.refs["main"].snapshot_id, 42);
+        assert_eq!(metadata.snapshot_log.len(), 1);
+        assert_eq!(metadata.properties["owner"], "analytics");
+        assert_eq!(metadata.last_updated_ms, 3000);
+    }
+
+    #[test]
+    fn test_apply_rejects_unknown_ids() {
+        let mut metadata = metadata();
+
+        let updates: Vec<TableUpdate> = serde_json::from_value(json!([
+            {"action": "set-snapshot-ref", "ref-name": "main", "type": "branch", "snapshot-id": 7}
+        ]))
+        .unwrap();
+        assert!(metadata.apply(updates, 3000).is_err());
+
+        let updates: Vec<TableUpdate> =
+            serde_json::from_value(json!([{"action": "set-current-schema", "schema-id": -1}]))
+                .unwrap();
+        assert!(metadata.apply(updates, 3000).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! Iceberg REST catalog protocol.
+//!
+//! Implements the endpoints of the Iceberg REST catalog spec that Spark and
+//! other Iceberg clients use, so they share the catalog with our services.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{identifier, AppState};
+
+pub mod metadata;
+pub mod namespaces;
+pub mod tables;
+
+/// Separator of namespace levels in URL paths.
+const NAMESPACE_SEPARATOR: char = '\u{1f}';
+
+/// Error response of the REST protocol.
+#[derive(Debug)]
+pub struct RestError {
+    code: StatusCode,
+    error_type: &'static str,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct ErrorModel<'a> {
+    message: &'a str,
+    #[serde(rename = "type")]
+    error_type: &'a str,
+    code: u16,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse<'a> {
+    error: ErrorModel<'a>,
+}
+
+impl RestError {
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "BadRequestException", message)
+    }
+
+    pub fn no_such_namespace(namespace: &[String]) -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            "NoSuchNamespaceException",
+            format!("Namespace does not exist: {}", namespace.join(".")),
+        )
+    }
+
+    pub fn no_such_table(namespace: &[String], name: &str) -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            "NoSuchTableException",
+            format!("Table does not exist: {}.{}", namespace.join("."), name),
+        )
+    }
+
+    pub fn already_exists(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, "AlreadyExistsException", message)
+    }
+
+    pub fn namespace_not_empty(namespace: &[String]) -> Self {
+        Self::new(
+            StatusCode::CONFLICT,
+            "NamespaceNotEmptyException",
+            format!("Namespace is not empty: {}", namespace.join(".")),
+        )
+    }
+
+    pub fn commit_failed(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, "CommitFailedException", message)
+    }
+
+    pub fn internal(message: impl ToString) -> Self {
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "ServiceFailureException",
+            message.to_string(),
+        )
+    }
+
+    fn new(code: StatusCode, error_type: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            error_type,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<sqlx::Error> for RestError {
+    fn from(e: sqlx::Error) -> Self {
+        RestError::internal(e)
+    }
+}
+
+impl From<serde_json::Error> for RestError {
+    fn from(e: serde_json::Error) -> Self {
+        RestError::internal(e)
+    }
+}
+
+impl From<fileio::FileIOError> for RestError {
+    fn from(e: fileio::FileIOError) -> Self {
+        RestError::internal(e)
+    }
+}
+
+impl From<crate::purge::PurgeError> for RestError {
+    fn from(e: crate::purge::PurgeError) -> Self {
+        RestError::internal(e)
+    }
+}
+
+/// Lets `/tables` handlers return REST errors as their own.
+impl From<RestError> for (StatusCode, String) {
+    fn from(e: RestError) -> Self {
+        (e.code, e.message)
+    }
+}
+
+impl IntoResponse for RestError {
+    fn into_response(self) -> Response {
+        let body = ErrorResponse {
+            error: ErrorModel {
+                message: &self.message,
+                error_type: self.error_type,
+                code: self.code.as_u16(),
+            },
+        };
+
+        (self.code, Json(body)).into_response()
+    }
+}
+
+/// Parses a namespace from its URL path form, where levels are separated by
+/// the unit separator character.
+fn parse_namespace(encoded: &str) -> Result<Vec<String>, RestError> {
+    let levels: Vec<String> = encoded
+        .split(NAMESPACE_SEPARATOR)
+        .map(str::to_string)
+        .collect();
+    validate_namespace(&levels)?;
+    Ok(levels)
+}
+
+/// Namespaces are stored with levels joined by dots, so levels can't
+/// contain dots themselves.
+fn validate_namespace(levels: &[String]) -> Result<(), RestError> {
+    identifier::validate_namespace(levels).map_err(RestError::bad_request)
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigParams {
+    warehouse: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CatalogConfig {
+    defaults: HashMap<String, String>,
+    overrides: HashMap<String, String>,
+}
+
+async fn get_config(
+    State(state): State<AppState>,
+    Query(params): Query<ConfigParams>,
+) -> Json<CatalogConfig> {
+    let warehouse = params.warehouse.unwrap_or(state.warehouse);
+
+    Json(CatalogConfig {
+        defaults: HashMap::from([("warehouse".to_string(), warehouse)]),
+        overrides: HashMap::new(),
+    })
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/config", get(get_config))
+        .route(
+            "/namespaces",
+            get(namespaces::list_namespaces).post(namespaces::create_namespace),
+        )
+        .route(
+            "/namespaces/:namespace",
+            get(namespaces::load_namespace)
+                .head(namespaces::namespace_exists)
+                .delete(namespaces::drop_namespace),
+        )
+        .route(
+            "/namespaces/:namespace/properties",
+            post(namespaces::update_properties),
+        )
+        .route(
+            "/namespaces/:namespace/tables",
+            get(tables::list_tables).post(tables::create_table),
+        )
+        .route(
+            "/namespaces/:namespace/tables/:table",
+            get(tables::load_table)
+                .head(tables::table_exists)
+                .post(tables::commit_table)
+                .delete(tables::drop_table),
+        )
+        .route("/tables/rename", post(tables::rename_table))
+}
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{sqlite::SqlitePool, Row};
+
+use crate::{identifier::DEFAULT_NAMESPACE, AppState};
+
+use super::{parse_namespace, validate_namespace, RestError};
+
+#[derive(Debug, Deserialize)]
+pub struct ListNamespacesParams {
+    parent: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListNamespacesResponse {
+    namespaces: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NamespaceRequest {
+    namespace: Vec<String>,
+    #[serde(default)]
+    properties: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NamespaceResponse {
+    namespace: Vec<String>,
+    properties: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePropertiesRequest {
+    #[serde(default)]
+    removals: Vec<String>,
+    #[serde(default)]
+    updates: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdatePropertiesResponse {
+    updated: Vec<String>,
+    removed: Vec<String>,
+    missing: Vec<String>,
+}
+
+pub async fn create_namespaces_table(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS iceberg_namespaces (
+            namespace TEXT PRIMARY KEY,
+            properties TEXT NOT NULL
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    // Tables created without a namespace live in the default one
+    sqlx::query(
+        "INSERT INTO iceberg_namespaces (namespace, properties) VALUES (?, '{}')
+         ON CONFLICT(namespace) DO NOTHING",
+    )
+    .bind(DEFAULT_NAMESPACE)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Loads the properties of a namespace, or `None` if it doesn't exist.
+pub async fn load_properties(
+    pool: &SqlitePool,
+    namespace: &[String],
+) -> Result<Option<HashMap<String, String>>, RestError> {
+    let row = sqlx::query("SELECT properties FROM iceberg_namespaces WHERE namespace = ?")
+        .bind(namespace.join("."))
+        .fetch_optional(pool)
+        .await?;
+
+    match row {
+        Some(row) => {
+            let properties: String = row.get("properties");
+            Ok(Some(serde_json::from_str(&properties)?))
+        }
+        None => Ok(None),
+    }
+}
+
+pub async fn list_namespaces(
+    State(state): State<AppState>,
+    Query(params): Query<ListNamespacesParams>,
+) -> Result<Json<ListNamespacesResponse>, RestError> {
+    let parent = match params.parent {
+        Some(parent) => {
+            let parent = parse_namespace(&parent)?;
+            if load_properties(&state.pool, &parent).await?.is_none() {
+                return Err(RestError::no_such_namespace(&parent));
+            }
+            parent
+        }
+        None => Vec::new(),
+    };
+
+    let rows = sqlx::query("SELECT namespace FROM iceberg_namespaces ORDER BY namespace")
+        .fetch_all(&state.pool)
+        .await?;
+
+    // Only direct children of the parent are listed
+    let namespaces = rows
+        .iter()
+        .map(|row| {
+            row.get::<String, _>("namespace")
+                .split('.')
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .filter(|levels| levels.len() == parent.len() + 1 && levels.starts_with(&parent))
+        .collect();
+
+    Ok(Json(ListNamespacesResponse { namespaces }))
+}
+
+pub async fn create_namespace(
+    State(state): State<AppState>,
+    Json(payload): Json<NamespaceRequest>,
+) -> Result<Json<NamespaceResponse>, RestError> {
+    validate_namespace(&payload.namespace)?;
+
+    let result = sqlx::query(
+        "INSERT INTO iceberg_namespaces (namespace, properties) VALUES (?, ?)
+         ON CONFLICT(namespace) DO NOTHING",
+    )
+    .bind(payload.namespace.join("."))
+    .bind(serde_json::to_string(&payload.properties)?)
+    .execute(&state.pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(RestError::already_exists(format!(
+            "Namespace already exists: {}",
+            payload.namespace.join(".")
+        )));
+    }
+
+    Ok(Json(NamespaceResponse {
+        namespace: payload.namespace,
+        properties: payload.properties,
+    }))
+}
+
+pub async fn load_namespace(
+    State(state): State<AppState>,
+    Path(namespace): Path<String>,
+) -> Result<Json<NamespaceResponse>, RestError> {
+    let namespace = parse_namespace(&namespace)?;
+
+    let properties = load_properties(&state.pool, &namespace)
+        .await?
+        .ok_or_else(|| RestError::no_such_namespace(&namespace))?;
+
+    Ok(Json(NamespaceResponse {
+        namespace,
+        properties,
+    }))
+}
+
+pub async fn namespace_exists(
+    State(state): State<AppState>,
+    Path(namespace): Path<String>,
+) -> Result<StatusCode, RestError> {
+    let namespace = parse_namespace(&namespace)?;
+
+    match load_properties(&state.pool, &namespace).await? {
+        Some(_) => Ok(StatusCode::NO_CONTENT),
+        None => Err(RestError::no_such_namespace(&namespace)),
+    }
+}
+
+pub async fn drop_namespace(
+    State(state): State<AppState>,
+    Path(namespace): Path<String>,
+) -> Result<StatusCode, RestError> {
+    let namespace = parse_namespace(&namespace)?;
+    let joined = namespace.join(".");
+
+    let mut tx = state.pool.begin().await?;
+
+    let has_tables = sqlx::query("SELECT 1 FROM table_metadata WHERE namespace = ? LIMIT 1")
+        .bind(&joined)
+        .fetch_optional(&mut *tx)
+        .await?
+        .is_some();
+
+    if has_tables {
+        return Err(RestError::namespace_not_empty(&namespace));
+    }
+
+    let result = sqlx::query("DELETE FROM iceberg_namespaces WHERE namespace = ?")
+        .bind(&joined)
+        .execute(&mut *tx)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(RestError::no_such_namespace(&namespace));
+    }
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn update_properties(
+    State(state): State<AppState>,
+    Path(namespace): Path<String>,
+    Json(payload): Json<UpdatePropertiesRequest>,
+) -> Result<Json<UpdatePropertiesResponse>, RestError> {
+    let namespace = parse_namespace(&namespace)?;
+
+    if let Some(key) = payload
+        .removals
+        .iter()
+        .find(|key| payload.updates.contains_key(*key))
+    {
+        return Err(RestError::bad_request(format!(
+            "Property is both updated and removed: {}",
+            key
+        )));
+    }
+
+    let mut tx = state.pool.begin().await?;
+
+    let row = sqlx::query("SELECT properties FROM iceberg_namespaces WHERE namespace = ?")
+        .bind(namespace.join("."))
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| RestError::no_such_namespace(&namespace))?;
+
+    let properties: String = row.get("properties");
+    let mut properties: HashMap<String, String> = serde_json::from_str(&properties)?;
+
+    let mut removed = Vec::new();
+    let mut missing = Vec::new();
+
+    for key in payload.removals {
+        match properties.remove(&key) {
+            Some(_) => removed.push(key),
+            None => missing.push(key),
+        }
+    }
+
+    let updated = payload.updates.keys().cloned().collect();
+    properties.extend(payload.updates);
+
+    sqlx::query("UPDATE iceberg_namespaces SET properties = ? WHERE namespace = ?")
+        .bind(serde_json::to_string(&properties)?)
+        .bind(namespace.join("."))
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(UpdatePropertiesResponse {
+        updated,
+        removed,
+        missing,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_state;
+
+    async fn create(state: &AppState, namespace: &[&str]) -> Result<(), RestError> {
+        let request = NamespaceRequest {
+            namespace: namespace.iter().map(|l| l.to_string()).collect(),
+            properties: HashMap::from([("owner".to_string(), "analytics".to_string())]),
+        };
+
+        create_namespace(State(state.clone()), Json(request))
+            .await
+            .map(|_| ())
+    }
+
+    async fn list(state: &AppState, parent: Option<&str>) -> Vec<Vec<String>> {
+        let params = ListNamespacesParams {
+            parent: parent.map(str::to_string),
+        };
+
+        let Json(response) = list_namespaces(State(state.clone()), Query(params))
+            .await
+            .unwrap();
+        response.namespaces
+    }
+
+    #[tokio::test]
+    async fn test_create_and_list_namespaces() {
+        let state = test_state("").await;
+
+        create(&state, &["sales"]).await.unwrap();
+        create(&state, &["sales", "eu"]).await.unwrap();
+        create(&state, &["sales", "eu", "de"]).await.unwrap();
+        create(&state, &["marketing"]).await.unwrap();
+
+        let err = create(&state, &["sales"]).await.unwrap_err();
+        assert_eq!(err.code, StatusCode::CONFLICT);
+        assert_eq!(err.error_type, "AlreadyExistsException");
+
+        let err = create(&state, &["sales.us"]).await.unwrap_err();
+        assert_eq!(err.code, StatusCode::BAD_REQUEST);
+
+        assert_eq!(
+            list(&state, None).await,
+            vec![vec!["default"], vec!["marketing"], vec!["sales"]]
+        );
+        assert_eq!(list(&state, Some("sales")).await, vec![vec!["sales", "eu"]]);
+        assert_eq!(
+            list(&state, Some("sales\u{1f}eu")).await,
+            vec![vec!["sales", "eu", "de"]]
+        );
+
+        let Json(namespace) =
+            load_namespace(State(state.clone()), Path("sales\u{1f}eu".to_string()))
+                .await
+                .unwrap();
+        assert_eq!(namespace.namespace, vec!["sales", "eu"]);
+        assert_eq!(namespace.properties["owner"], "analytics");
+    }
+
+    #[tokio::test]
+    async fn test_update_properties() {
+        let state = test_state("").await;
+        create(&state, &["sales"]).await.unwrap();
+
+        let request = UpdatePropertiesRequest {
+            removals: vec!["owner".to_string(), "missing".to_string()],
+            updates: HashMap::from([("retention".to_string(), "30d".to_string())]),
+        };
+        let Json(response) = update_properties(
+            State(state.clone()),
+            Path("sales".to_string()),
+            Json(request),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.updated, vec!["retention"]);
+        assert_eq!(response.removed, vec!["owner"]);
+        assert_eq!(response.missing, vec!["missing"]);
+
+        let properties = load_properties(&state.pool, &["sales".to_string()])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            properties,
+            HashMap::from([("retention".to_string(), "30d".to_string())])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_drop_namespace() {
+        let state = test_state("").await;
+        create(&state, &["sales"]).await.unwrap();
+
+        let status = drop_namespace(State(state.clone()), Path("sales".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let err = namespace_exists(State(state.clone()), Path("sales".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, StatusCode::NOT_FOUND);
+        assert_eq!(err.error_type, "NoSuchNamespaceException");
+
+        let err = drop_namespace(State(state), Path("sales".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, StatusCode::NOT_FOUND);
+    }
+}
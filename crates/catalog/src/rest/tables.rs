@@ -0,0 +1,876 @@
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{sqlite::SqlitePool, Row};
+use uuid::Uuid;
+
+use fileio::FileIO;
+
+use crate::{files, identifier::TableIdentifier, purge, schema::Schema, AppState};
+
+use super::{
+    metadata::{MetadataLogEntry, TableMetadata, TableRequirement, TableUpdate},
+    namespaces, parse_namespace, validate_namespace, RestError,
+};
+
+#[derive(Debug, Serialize)]
+pub struct ListTablesResponse {
+    identifiers: Vec<TableIdentifier>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CreateTableRequest {
+    name: String,
+    location: Option<String>,
+    schema: Value,
+    partition_spec: Option<Value>,
+    write_order: Option<Value>,
+    #[serde(default)]
+    stage_create: bool,
+    #[serde(default)]
+    properties: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LoadTableResponse {
+    metadata_location: Option<String>,
+    metadata: TableMetadata,
+    config: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommitTableRequest {
+    #[serde(default)]
+    requirements: Vec<TableRequirement>,
+    #[serde(default)]
+    updates: Vec<TableUpdate>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CommitTableResponse {
+    metadata_location: String,
+    metadata: TableMetadata,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DropTableParams {
+    #[serde(default)]
+    purge_requested: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenameTableRequest {
+    source: TableIdentifier,
+    destination: TableIdentifier,
+}
+
+/// Moves tables of databases created before the REST catalog shared the
+/// table registry into it.
+pub async fn migrate_tables_table(pool: &SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    let exists = sqlx::query("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?")
+        .bind("iceberg_tables")
+        .fetch_optional(pool)
+        .await?
+        .is_some();
+
+    if !exists {
+        return Ok(());
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let rows = sqlx::query(
+        "SELECT table_namespace, table_name, metadata_location, previous_metadata_location,
+            metadata
+         FROM iceberg_tables",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for row in rows {
+        let namespace: String = row.get("table_namespace");
+        let name: String = row.get("table_name");
+        let metadata: TableMetadata = serde_json::from_str(row.get("metadata"))?;
+        let columns = RegistryColumns::new(&metadata).map_err(|e| e.message)?;
+
+        sqlx::query(
+            "INSERT INTO table_metadata (
+                name, namespace, schema, metadata_location, previous_metadata_location,
+                properties, sort_order, last_column_id, metadata
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(name) DO NOTHING",
+        )
+        .bind(format!("{}.{}", namespace, name))
+        .bind(&namespace)
+        .bind(&columns.schema)
+        .bind(row.get::<String, _>("metadata_location"))
+        .bind(row.get::<Option<String>, _>("previous_metadata_location"))
+        .bind(&columns.properties)
+        .bind(&columns.sort_order)
+        .bind(metadata.last_column_id)
+        .bind(&columns.metadata)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query("DROP TABLE iceberg_tables")
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Columns of the table registry read by the write and query services,
+/// derived from a table's Iceberg metadata, and the metadata itself.
+pub(crate) struct RegistryColumns {
+    pub(crate) metadata: String,
+    pub(crate) schema: String,
+    pub(crate) properties: String,
+    pub(crate) sort_order: Option<String>,
+}
+
+impl RegistryColumns {
+    pub(crate) fn new(metadata: &TableMetadata) -> Result<Self, RestError> {
+        let schema = metadata
+            .current_schema()
+            .ok_or_else(|| RestError::bad_request("Table has no current schema"))?;
+        let schema = Schema::from_iceberg(schema).map_err(RestError::bad_request)?;
+
+        Ok(Self {
+            metadata: serde_json::to_string(metadata)?,
+            schema: serde_json::to_string(&schema)?,
+            properties: serde_json::to_string(&metadata.properties)?,
+            sort_order: metadata.default_sort_order().map(Value::to_string),
+        })
+    }
+}
+
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Writes a new metadata file for the table, local or in a bucket, and
+/// returns its location.
+async fn write_metadata(io: &FileIO, metadata: &TableMetadata) -> Result<String, RestError> {
+    let version = metadata.metadata_log.len();
+    let metadata_location = format!(
+        "{}/metadata/{:05}-{}.metadata.json",
+        metadata.location.trim_end_matches('/'),
+        version,
+        Uuid::new_v4()
+    );
+
+    io.write(
+        &metadata_location,
+        serde_json::to_vec_pretty(metadata)?.into(),
+    )
+    .await?;
+
+    Ok(metadata_location)
+}
+
+async fn require_namespace(pool: &SqlitePool, namespace: &[String]) -> Result<(), RestError> {
+    match namespaces::load_properties(pool, namespace).await? {
+        Some(_) => Ok(()),
+        None => Err(RestError::no_such_namespace(namespace)),
+    }
+}
+
+/// Loads the current metadata location and metadata of a table. Tables
+/// created through `/tables` have no metadata location until first committed.
+async fn load(
+    pool: &SqlitePool,
+    identifier: &TableIdentifier,
+) -> Result<Option<(Option<String>, TableMetadata)>, RestError> {
+    let row = sqlx::query("SELECT metadata_location, metadata FROM table_metadata WHERE name = ?")
+        .bind(identifier.to_string())
+        .fetch_optional(pool)
+        .await?;
+
+    match row {
+        Some(row) => {
+            let metadata: String = row.get("metadata");
+            Ok(Some((
+                row.get("metadata_location"),
+                serde_json::from_str(&metadata)?,
+            )))
+        }
+        None => Ok(None),
+    }
+}
+
+fn identifier(namespace: &[String], name: &str) -> TableIdentifier {
+    TableIdentifier {
+        namespace: namespace.to_vec(),
+        name: name.to_string(),
+    }
+}
+
+pub async fn list_tables(
+    State(state): State<AppState>,
+    Path(namespace): Path<String>,
+) -> Result<Json<ListTablesResponse>, RestError> {
+    let namespace = parse_namespace(&namespace)?;
+    require_namespace(&state.pool, &namespace).await?;
+
+    let rows = sqlx::query("SELECT name FROM table_metadata WHERE namespace = ? ORDER BY name")
+        .bind(namespace.join("."))
+        .fetch_all(&state.pool)
+        .await?;
+
+    let identifiers = rows
+        .iter()
+        .map(|row| row.get::<String, _>("name").parse())
+        .collect::<Result<_, _>>()
+        .map_err(RestError::bad_request)?;
+
+    Ok(Json(ListTablesResponse { identifiers }))
+}
+
+pub async fn create_table(
+    State(state): State<AppState>,
+    Path(namespace): Path<String>,
+    Json(payload): Json<CreateTableRequest>,
+) -> Result<Json<LoadTableResponse>, RestError> {
+    let namespace = parse_namespace(&namespace)?;
+    let identifier = identifier(&namespace, &payload.name);
+    identifier.validate().map_err(RestError::bad_request)?;
+    require_namespace(&state.pool, &namespace).await?;
+
+    if load(&state.pool, &identifier).await?.is_some() {
+        return Err(RestError::already_exists(format!(
+            "Table already exists: {}",
+            identifier
+        )));
+    }
+
+    let location = payload
+        .location
+        .unwrap_or_else(|| identifier.location(&state.warehouse));
+
+    let metadata = TableMetadata::new(
+        Uuid::new_v4().to_string(),
+        location,
+        payload.schema,
+        payload.partition_spec,
+        payload.write_order,
+        payload.properties,
+        now_millis(),
+    );
+    let columns = RegistryColumns::new(&metadata)?;
+
+    // Staged tables are only created by a later commit
+    if payload.stage_create {
+        return Ok(Json(LoadTableResponse {
+            metadata_location: None,
+            metadata,
+            config: HashMap::new(),
+        }));
+    }
+
+    let metadata_location = write_metadata(&state.io, &metadata).await?;
+
+    let result = sqlx::query(
+        "INSERT INTO table_metadata (
+            name, namespace, schema, metadata_location, properties, sort_order,
+            last_column_id, metadata
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(name) DO NOTHING",
+    )
+    .bind(identifier.to_string())
+    .bind(identifier.namespace_name())
+    .bind(&columns.schema)
+    .bind(&metadata_location)
+    .bind(&columns.properties)
+    .bind(&columns.sort_order)
+    .bind(metadata.last_column_id)
+    .bind(&columns.metadata)
+    .execute(&state.pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(RestError::already_exists(format!(
+            "Table already exists: {}",
+            identifier
+        )));
+    }
+
+    Ok(Json(LoadTableResponse {
+        metadata_location: Some(metadata_location),
+        metadata,
+        config: HashMap::new(),
+    }))
+}
+
+pub async fn load_table(
+    State(state): State<AppState>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> Result<Json<LoadTableResponse>, RestError> {
+    let namespace = parse_namespace(&namespace)?;
+
+    let (metadata_location, metadata) = load(&state.pool, &identifier(&namespace, &name))
+        .await?
+        .ok_or_else(|| RestError::no_such_table(&namespace, &name))?;
+
+    Ok(Json(LoadTableResponse {
+        metadata_location,
+        metadata,
+        config: HashMap::new(),
+    }))
+}
+
+pub async fn table_exists(
+    State(state): State<AppState>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> Result<StatusCode, RestError> {
+    let namespace = parse_namespace(&namespace)?;
+
+    match load(&state.pool, &identifier(&namespace, &name)).await? {
+        Some(_) => Ok(StatusCode::NO_CONTENT),
+        None => Err(RestError::no_such_table(&namespace, &name)),
+    }
+}
+
+pub async fn commit_table(
+    State(state): State<AppState>,
+    Path((namespace, name)): Path<(String, String)>,
+    Json(payload): Json<CommitTableRequest>,
+) -> Result<Json<CommitTableResponse>, RestError> {
+    let namespace = parse_namespace(&namespace)?;
+    let identifier = identifier(&namespace, &name);
+    let now_ms = now_millis();
+
+    let current = load(&state.pool, &identifier).await?;
+    let exists = current.is_some();
+
+    let creating = payload
+        .requirements
+        .iter()
+        .any(|r| matches!(r, TableRequirement::AssertCreate));
+
+    // Staged creates commit against a table that doesn't exist yet
+    let (previous_location, mut metadata) = match current {
+        Some((location, metadata)) => (location, metadata),
+        None if creating => {
+            identifier.validate().map_err(RestError::bad_request)?;
+            require_namespace(&state.pool, &namespace).await?;
+            (None, TableMetadata::empty(now_ms))
+        }
+        None => return Err(RestError::no_such_table(&namespace, &name)),
+    };
+
+    for requirement in &payload.requirements {
+        metadata
+            .check(requirement, exists)
+            .map_err(RestError::commit_failed)?;
+    }
+
+    metadata
+        .apply(payload.updates, now_ms)
+        .map_err(RestError::bad_request)?;
+
+    if let Some(previous_location) = &previous_location {
+        metadata.metadata_log.push(MetadataLogEntry {
+            metadata_file: previous_location.clone(),
+            timestamp_ms: now_ms,
+        });
+    }
+
+    let columns = RegistryColumns::new(&metadata)?;
+    let metadata_location = write_metadata(&state.io, &metadata).await?;
+
+    // The pointer only moves if nobody committed since the table was loaded,
+    // through this API or `/tables/:name/metadata`. `IS` also matches tables
+    // that were never committed.
+    let result = if exists {
+        sqlx::query(
+            "UPDATE table_metadata SET
+                metadata_location = ?, previous_metadata_location = ?, metadata = ?,
+                schema = ?, properties = ?, sort_order = ?, last_column_id = ?
+            WHERE name = ? AND metadata_location IS ?",
+        )
+        .bind(&metadata_location)
+        .bind(&previous_location)
+        .bind(&columns.metadata)
+        .bind(&columns.schema)
+        .bind(&columns.properties)
+        .bind(&columns.sort_order)
+        .bind(metadata.last_column_id)
+        .bind(identifier.to_string())
+        .bind(&previous_location)
+    } else {
+        sqlx::query(
+            "INSERT INTO table_metadata (
+                name, namespace, schema, metadata_location, properties, sort_order,
+                last_column_id, metadata
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(name) DO NOTHING",
+        )
+        .bind(identifier.to_string())
+        .bind(identifier.namespace_name())
+        .bind(&columns.schema)
+        .bind(&metadata_location)
+        .bind(&columns.properties)
+        .bind(&columns.sort_order)
+        .bind(metadata.last_column_id)
+        .bind(&columns.metadata)
+    }
+    .execute(&state.pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(RestError::commit_failed(format!(
+            "Table was concurrently updated: {}",
+            identifier
+        )));
+    }
+
+    Ok(Json(CommitTableResponse {
+        metadata_location,
+        metadata,
+    }))
+}
+
+pub async fn drop_table(
+    State(state): State<AppState>,
+    Path((namespace, name)): Path<(String, String)>,
+    Query(params): Query<DropTableParams>,
+) -> Result<StatusCode, RestError> {
+    let namespace = parse_namespace(&namespace)?;
+    let identifier = identifier(&namespace, &name);
+
+    let mut tx = state.pool.begin().await?;
+
+    let row = sqlx::query(
+        "DELETE FROM table_metadata WHERE name = ?
+         RETURNING metadata_location, previous_metadata_location, metadata",
+    )
+    .bind(identifier.to_string())
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| RestError::no_such_table(&namespace, &name))?;
+    let paths = files::delete_data_files(&mut tx, &identifier).await?;
+
+    tx.commit().await?;
+
+    // Only the files the table tracks are purged, not its location, which
+    // clients choose and other tables may share
+    if params.purge_requested {
+        let metadata: TableMetadata = serde_json::from_str(row.get("metadata"))?;
+        let files = purge::table_files(
+            &state.io,
+            paths,
+            row.get("metadata_location"),
+            row.get("previous_metadata_location"),
+            Some(&metadata),
+        )
+        .await?;
+        purge::delete_files(&state.io, &files).await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn rename_table(
+    State(state): State<AppState>,
+    Json(payload): Json<RenameTableRequest>,
+) -> Result<StatusCode, RestError> {
+    let source = payload.source;
+    let destination = payload.destination;
+
+    validate_namespace(&source.namespace)?;
+    destination.validate().map_err(RestError::bad_request)?;
+    require_namespace(&state.pool, &destination.namespace).await?;
+
+    if load(&state.pool, &destination).await?.is_some() {
+        return Err(RestError::already_exists(format!(
+            "Table already exists: {}",
+            destination
+        )));
+    }
+
+    let mut tx = state.pool.begin().await?;
+
+    let result = sqlx::query("UPDATE table_metadata SET name = ?, namespace = ? WHERE name = ?")
+        .bind(destination.to_string())
+        .bind(destination.namespace_name())
+        .bind(source.to_string())
+        .execute(&mut *tx)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(RestError::no_such_table(&source.namespace, &source.name));
+    }
+
+    // Data files registered by the write service move with the table
+    for query in [
+        "UPDATE data_files SET table_name = ? WHERE table_name = ?",
+        "UPDATE snapshots SET table_name = ? WHERE table_name = ?",
+    ] {
+        sqlx::query(query)
+            .bind(destination.to_string())
+            .bind(source.to_string())
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_state;
+
+    async fn setup(warehouse: &std::path::Path) -> AppState {
+        let state = test_state(&warehouse.to_string_lossy()).await;
+
+        sqlx::query("INSERT INTO iceberg_namespaces (namespace, properties) VALUES ('db', '{}')")
+            .execute(&state.pool)
+            .await
+            .unwrap();
+
+        state
+    }
+
+    fn create_request(name: &str, stage_create: bool) -> CreateTableRequest {
+        serde_json::from_value(json!({
+            "name": name,
+            "schema": {
+                "type": "struct",
+                "schema-id": 0,
+                "fields": [
+                    {"id": 1, "name": "id", "type": "long", "required": true},
+                    {"id": 2, "name": "data", "type": "string", "required": false}
+                ]
+            },
+            "stage-create": stage_create
+        }))
+        .unwrap()
+    }
+
+    fn commit_request(value: Value) -> CommitTableRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn table_path(name: &str) -> Path<(String, String)> {
+        Path(("db".to_string(), name.to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_create_and_commit_table() {
+        let warehouse = tempfile::tempdir().unwrap();
+        let state = setup(warehouse.path()).await;
+
+        let Json(created) = create_table(
+            State(state.clone()),
+            Path("db".to_string()),
+            Json(create_request("events", false)),
+        )
+        .await
+        .unwrap();
+
+        let metadata_location = created.metadata_location.unwrap();
+        assert!(metadata_location.starts_with(&format!(
+            "{}/db/events/metadata/00000-",
+            warehouse.path().display()
+        )));
+        assert!(std::path::Path::new(&metadata_location).exists());
+        assert_eq!(created.metadata.last_column_id, 2);
+
+        let err = create_table(
+            State(state.clone()),
+            Path("db".to_string()),
+            Json(create_request("events", false)),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code, StatusCode::CONFLICT);
+
+        let append = json!({
+            "requirements": [
+                {"type": "assert-table-uuid", "uuid": created.metadata.table_uuid},
+                {"type": "assert-ref-snapshot-id", "ref": "main", "snapshot-id": null}
+            ],
+            "updates": [
+                {"action": "add-snapshot", "snapshot": {
+                    "snapshot-id": 1, "sequence-number": 1, "timestamp-ms": 1723320520000_i64,
+                    "manifest-list": "snap-1.avro", "summary": {"operation": "append"}
+                }},
+                {"action": "set-snapshot-ref", "ref-name": "main", "type": "branch", "snapshot-id": 1}
+            ]
+        });
+
+        let Json(committed) = commit_table(
+            State(state.clone()),
+            table_path("events"),
+            Json(commit_request(append.clone())),
+        )
+        .await
+        .unwrap();
+
+        assert!(committed.metadata_location.contains("/metadata/00001-"));
+        assert_eq!(committed.metadata.current_snapshot_id, Some(1));
+        assert_eq!(
+            committed.metadata.metadata_log[0].metadata_file,
+            metadata_location
+        );
+
+        // The same commit again is stale: main has moved on
+        let err = commit_table(
+            State(state.clone()),
+            table_path("events"),
+            Json(commit_request(append)),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code, StatusCode::CONFLICT);
+        assert_eq!(err.error_type, "CommitFailedException");
+
+        let Json(loaded) = load_table(State(state.clone()), table_path("events"))
+            .await
+            .unwrap();
+        assert_eq!(loaded.metadata_location, Some(committed.metadata_location));
+        assert_eq!(loaded.metadata, committed.metadata);
+
+        // Tables outside the local filesystem get their metadata files too
+        let mut request = create_request("remote", false);
+        request.location = Some("memory://bucket/db/remote".to_string());
+        let Json(remote) =
+            create_table(State(state.clone()), Path("db".to_string()), Json(request))
+                .await
+                .unwrap();
+        let metadata_location = remote.metadata_location.unwrap();
+        assert!(metadata_location.starts_with("memory://bucket/db/remote/metadata/00000-"));
+        assert!(state.io.exists(&metadata_location).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_staged_create() {
+        let warehouse = tempfile::tempdir().unwrap();
+        let state = setup(warehouse.path()).await;
+
+        let Json(staged) = create_table(
+            State(state.clone()),
+            Path("db".to_string()),
+            Json(create_request("events", true)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(staged.metadata_location, None);
+
+        let err = table_exists(State(state.clone()), table_path("events"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, StatusCode::NOT_FOUND);
+
+        let create = json!({
+            "requirements": [{"type": "assert-create"}],
+            "updates": [
+                {"action": "assign-uuid", "uuid": staged.metadata.table_uuid},
+                {"action": "upgrade-format-version", "format-version": 2},
+                {"action": "add-schema", "schema": staged.metadata.schemas[0], "last-column-id": 2},
+                {"action": "set-current-schema", "schema-id": -1},
+                {"action": "add-spec", "spec": {"fields": []}},
+                {"action": "set-default-spec", "spec-id": -1},
+                {"action": "add-sort-order", "sort-order": {"order-id": 0, "fields": []}},
+                {"action": "set-default-sort-order", "sort-order-id": -1},
+                {"action": "set-location", "location": staged.metadata.location},
+                {"action": "set-properties", "updates": {"owner": "spark"}}
+            ]
+        });
+
+        let Json(committed) = commit_table(
+            State(state.clone()),
+            table_path("events"),
+            Json(commit_request(create.clone())),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(committed.metadata.table_uuid, staged.metadata.table_uuid);
+        assert_eq!(committed.metadata.current_schema_id, 0);
+        assert_eq!(committed.metadata.default_spec_id, 0);
+        assert_eq!(committed.metadata.properties["owner"], "spark");
+
+        let err = commit_table(
+            State(state),
+            table_path("events"),
+            Json(commit_request(create)),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code, StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_tables_share_registry() {
+        let warehouse = tempfile::tempdir().unwrap();
+        let state = setup(warehouse.path()).await;
+
+        // A table created through `/tables` is an Iceberg table too
+        let request: crate::tables::TableRequest = serde_json::from_value(json!({
+            "name": "db.clicks",
+            "schema": {"fields": [{"field": "user_id", "type": "uuid", "required": true}]}
+        }))
+        .unwrap();
+        crate::tables::store_table_metadata(State(state.clone()), Json(request))
+            .await
+            .unwrap();
+
+        let Json(clicks) = load_table(State(state.clone()), table_path("clicks"))
+            .await
+            .unwrap();
+        assert_eq!(clicks.metadata_location, None);
+        assert_eq!(
+            clicks.metadata.current_schema().unwrap()["fields"][0]["name"],
+            "user_id"
+        );
+
+        let Json(events) = create_table(
+            State(state.clone()),
+            Path("db".to_string()),
+            Json(create_request("events", false)),
+        )
+        .await
+        .unwrap();
+
+        let Json(tables) = list_tables(State(state.clone()), Path("db".to_string()))
+            .await
+            .unwrap();
+        let names: Vec<_> = tables.identifiers.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["clicks", "events"]);
+
+        // Both APIs read and swap the same metadata pointer
+        let Json(table) =
+            crate::tables::get_table(State(state.clone()), Path("db.events".to_string()))
+                .await
+                .unwrap();
+        assert_eq!(table.metadata_location, events.metadata_location);
+        assert_eq!(table.location, events.metadata.location);
+        assert_eq!(table.schema.fields[1].field, "data");
+
+        // A commit through `/tables/:name/metadata`, such as the iceberg
+        // crate's, moves the metadata REST clients load and commit on with
+        // the pointer
+        let mut metadata = events.metadata.clone();
+        metadata
+            .properties
+            .insert("owner".to_string(), "writer".to_string());
+        let metadata_location = write_metadata(&state.io, &metadata).await.unwrap();
+
+        let request = crate::tables::CommitMetadataRequest {
+            expected_metadata_location: events.metadata_location.clone(),
+            metadata_location: metadata_location.clone(),
+        };
+        crate::tables::commit_metadata_location(
+            State(state.clone()),
+            Path("db.events".to_string()),
+            Json(request),
+        )
+        .await
+        .unwrap();
+
+        let Json(loaded) = load_table(State(state.clone()), table_path("events"))
+            .await
+            .unwrap();
+        assert_eq!(loaded.metadata_location, Some(metadata_location.clone()));
+        assert_eq!(loaded.metadata, metadata);
+
+        let set_properties = json!({
+            "updates": [{"action": "set-properties", "updates": {"retention": "30d"}}]
+        });
+        let Json(committed) = commit_table(
+            State(state),
+            table_path("events"),
+            Json(commit_request(set_properties)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(committed.metadata.properties["owner"], "writer");
+        assert_eq!(
+            committed
+                .metadata
+                .metadata_log
+                .last()
+                .unwrap()
+                .metadata_file,
+            metadata_location
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_and_drop_table() {
+        let warehouse = tempfile::tempdir().unwrap();
+        let state = setup(warehouse.path()).await;
+
+        let Json(created) = create_table(
+            State(state.clone()),
+            Path("db".to_string()),
+            Json(create_request("events", false)),
+        )
+        .await
+        .unwrap();
+
+        let rename: RenameTableRequest = serde_json::from_value(json!({
+            "source": {"namespace": ["db"], "name": "events"},
+            "destination": {"namespace": ["db"], "name": "clicks"}
+        }))
+        .unwrap();
+        rename_table(State(state.clone()), Json(rename))
+            .await
+            .unwrap();
+
+        let Json(tables) = list_tables(State(state.clone()), Path("db".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(tables.identifiers.len(), 1);
+        assert_eq!(tables.identifiers[0].name, "clicks");
+
+        // Another table's file under the table's location
+        let other_file = warehouse.path().join("db/events/data/other.parquet");
+        std::fs::create_dir_all(other_file.parent().unwrap()).unwrap();
+        std::fs::write(&other_file, b"").unwrap();
+
+        let params = DropTableParams {
+            purge_requested: true,
+        };
+        drop_table(State(state.clone()), table_path("clicks"), Query(params))
+            .await
+            .unwrap();
+
+        // The table keeps its original location, and only its own files
+        // there are purged
+        assert!(created.metadata.location.ends_with("/db/events"));
+        let metadata_location = created.metadata_location.unwrap();
+        assert!(!std::path::Path::new(&metadata_location).exists());
+        assert!(other_file.exists());
+
+        let err = load_table(State(state), table_path("clicks"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, StatusCode::NOT_FOUND);
+    }
+}
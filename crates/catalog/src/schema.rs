@@ -0,0 +1,551 @@
+use std::collections::{HashMap, HashSet};
+
+use iceberg::types::FieldType;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Field {
+    /// Field id, assigned by the catalog.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u32>,
+    /// Field name. Elements, keys and values of lists and maps may omit it.
+    #[serde(default)]
+    pub field: String,
+    #[serde(rename = "type")]
+    pub field_type: String,
+    #[serde(default)]
+    pub required: bool,
+    /// Fields of a struct.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<Field>,
+    /// Element of a list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub element: Option<Box<Field>>,
+    /// Key of a map.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<Box<Field>>,
+    /// Value of a map.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Box<Field>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schema {
+    pub fields: Vec<Field>,
+}
+
+impl Field {
+    /// Fields nested in a struct, list or map.
+    fn children_mut(&mut self) -> Vec<&mut Field> {
+        let mut children: Vec<&mut Field> = self.fields.iter_mut().collect();
+        children.extend(self.element.as_deref_mut());
+        children.extend(self.key.as_deref_mut());
+        children.extend(self.value.as_deref_mut());
+        children
+    }
+
+    /// Whether the field holds values rather than other fields.
+    pub fn is_primitive(&self) -> bool {
+        self.children().is_empty()
+    }
+
+    fn children(&self) -> Vec<&Field> {
+        let mut children: Vec<&Field> = self.fields.iter().collect();
+        children.extend(self.element.as_deref());
+        children.extend(self.key.as_deref());
+        children.extend(self.value.as_deref());
+        children
+    }
+}
+
+impl Schema {
+    /// Finds a field by id, at any depth.
+    pub fn field_by_id(&self, id: u32) -> Option<&Field> {
+        let mut fields: Vec<&Field> = self.fields.iter().collect();
+
+        while let Some(field) = fields.pop() {
+            if field.id == Some(id) {
+                return Some(field);
+            }
+            fields.extend(field.children());
+        }
+
+        None
+    }
+
+    /// Validates field names and types, replaces legacy type names with
+    /// canonical ones and assigns field ids. Fields keep the ids they had in
+    /// `previous`, matched by path, so data files stay readable. Their types
+    /// may therefore only change as Iceberg allows, such as int to long. New
+    /// fields are numbered after `last_column_id`, the highest id the table
+    /// ever assigned, so ids of dropped fields aren't reused.
+    ///
+    /// Returns the schema and the table's new last column id.
+    pub fn normalize(
+        mut self,
+        previous: Option<&Schema>,
+        last_column_id: u32,
+    ) -> Result<(Schema, u32), String> {
+        if self.fields.is_empty() {
+            return Err("Schema has no fields".to_string());
+        }
+
+        validate_fields(&mut self.fields, "")?;
+
+        let mut previous_ids = HashMap::new();
+        if let Some(previous) = previous {
+            for field in &previous.fields {
+                collect_ids(field, "", &mut previous_ids);
+            }
+            for field in &self.fields {
+                check_type_change(field, "", previous)?;
+            }
+        }
+
+        // Tables stored before the last column id was tracked only have the
+        // ids of their current fields
+        let mut last_id = previous_ids
+            .values()
+            .copied()
+            .fold(last_column_id, u32::max);
+        let mut assign = |path: &str| {
+            previous_ids.get(path).copied().unwrap_or_else(|| {
+                last_id += 1;
+                last_id
+            })
+        };
+        assign_ids(self.fields.iter_mut().collect(), "", &mut assign);
+
+        Ok((self, last_id))
+    }
+}
+
+impl Schema {
+    /// The schema in Iceberg's JSON form, as kept in table metadata.
+    pub fn to_iceberg(&self, schema_id: i64) -> Value {
+        json!({
+            "type": "struct",
+            "schema-id": schema_id,
+            "fields": self.fields.iter().map(iceberg_field).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Reads a schema in Iceberg's JSON form, keeping its field ids. Types
+    /// are kept as named by Iceberg, whether or not the services can write
+    /// them.
+    pub fn from_iceberg(schema: &Value) -> Result<Schema, String> {
+        let fields = schema["fields"]
+            .as_array()
+            .ok_or_else(|| "Schema has no fields".to_string())?;
+
+        Ok(Schema {
+            fields: fields
+                .iter()
+                .map(field_from_iceberg)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+fn iceberg_field(field: &Field) -> Value {
+    json!({
+        "id": field.id,
+        "name": field.field,
+        "required": field.required,
+        "type": iceberg_type(field),
+    })
+}
+
+fn iceberg_type(field: &Field) -> Value {
+    let element = field.element.as_deref();
+    let (key, value) = (field.key.as_deref(), field.value.as_deref());
+
+    match (field.field_type.as_str(), element, key, value) {
+        ("struct", ..) => json!({
+            "type": "struct",
+            "fields": field.fields.iter().map(iceberg_field).collect::<Vec<_>>(),
+        }),
+        ("list", Some(element), ..) => json!({
+            "type": "list",
+            "element-id": element.id,
+            "element": iceberg_type(element),
+            "element-required": element.required,
+        }),
+        ("map", _, Some(key), Some(value)) => json!({
+            "type": "map",
+            "key-id": key.id,
+            "key": iceberg_type(key),
+            "value-id": value.id,
+            "value": iceberg_type(value),
+            "value-required": value.required,
+        }),
+        (field_type, ..) => Value::from(field_type),
+    }
+}
+
+fn field_from_iceberg(field: &Value) -> Result<Field, String> {
+    let name = field["name"]
+        .as_str()
+        .ok_or_else(|| format!("Schema field has no name: {}", field))?;
+
+    typed_field(
+        name,
+        &field["id"],
+        field["required"].as_bool().unwrap_or(false),
+        &field["type"],
+    )
+}
+
+/// A field of an Iceberg type, a name for primitives or an object for
+/// nested types.
+fn typed_field(
+    name: &str,
+    id: &Value,
+    required: bool,
+    field_type: &Value,
+) -> Result<Field, String> {
+    let mut field = Field {
+        id: id.as_u64().map(|id| id as u32),
+        field: name.to_string(),
+        field_type: String::new(),
+        required,
+        fields: Vec::new(),
+        element: None,
+        key: None,
+        value: None,
+    };
+
+    if let Some(primitive) = field_type.as_str() {
+        field.field_type = primitive.to_string();
+        return Ok(field);
+    }
+
+    let nested = |name: &str, prefix: &str| {
+        typed_field(
+            name,
+            &field_type[format!("{}-id", prefix)],
+            field_type[format!("{}-required", prefix)]
+                .as_bool()
+                .unwrap_or(prefix == "key"),
+            &field_type[prefix],
+        )
+        .map(Box::new)
+    };
+
+    match field_type["type"].as_str() {
+        Some("struct") => {
+            let schema = Schema::from_iceberg(field_type)?;
+            field.fields = schema.fields;
+        }
+        Some("list") => field.element = Some(nested("element", "element")?),
+        Some("map") => {
+            field.key = Some(nested("key", "key")?);
+            field.value = Some(nested("value", "value")?);
+        }
+        _ => return Err(format!("Invalid type of field {}: {}", name, field_type)),
+    }
+    field.field_type = field_type["type"].as_str().unwrap_or_default().to_string();
+
+    Ok(field)
+}
+
+/// Like Iceberg, numbers the fields of a struct consecutively before
+/// numbering their children.
+fn assign_ids(mut fields: Vec<&mut Field>, parent: &str, assign: &mut impl FnMut(&str) -> u32) {
+    let mut paths = Vec::with_capacity(fields.len());
+
+    for field in fields.iter_mut() {
+        let path = field_path(parent, &field.field);
+        field.id = Some(assign(&path));
+        paths.push(path);
+    }
+
+    for (field, path) in fields.into_iter().zip(paths) {
+        assign_ids(field.children_mut(), &path, assign);
+    }
+}
+
+fn field_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", parent, name)
+    }
+}
+
+fn collect_ids(field: &Field, parent: &str, ids: &mut HashMap<String, u32>) {
+    let path = field_path(parent, &field.field);
+
+    if let Some(id) = field.id {
+        ids.insert(path.clone(), id);
+    }
+
+    for child in field.children() {
+        collect_ids(child, &path, ids);
+    }
+}
+
+/// Fails if a field kept from `previous` changed its type in a way data
+/// files written with the old type can't be read as.
+fn check_type_change(field: &Field, parent: &str, previous: &Schema) -> Result<(), String> {
+    let path = field_path(parent, &field.field);
+
+    let previous_field = previous
+        .fields
+        .iter()
+        .find_map(|previous_field| find_by_path(previous_field, "", &path));
+
+    if let Some(previous_field) = previous_field {
+        // Types Iceberg clients wrote that the services don't know can't change
+        let promotable = match (
+            previous_field.field_type.parse::<FieldType>(),
+            field.field_type.parse::<FieldType>(),
+        ) {
+            (Ok(from), Ok(to)) => from.can_promote_to(&to),
+            _ => previous_field.field_type == field.field_type,
+        };
+
+        if !promotable {
+            return Err(format!(
+                "Field {} can't change type from {} to {}",
+                path, previous_field.field_type, field.field_type
+            ));
+        }
+    }
+
+    for child in field.children() {
+        check_type_change(child, &path, previous)?;
+    }
+
+    Ok(())
+}
+
+fn find_by_path<'a>(field: &'a Field, parent: &str, path: &str) -> Option<&'a Field> {
+    let field_path = field_path(parent, &field.field);
+
+    if field_path == path {
+        return Some(field);
+    }
+
+    field
+        .children()
+        .into_iter()
+        .find_map(|child| find_by_path(child, &field_path, path))
+}
+
+fn validate_fields(fields: &mut [Field], parent: &str) -> Result<(), String> {
+    let mut names = HashSet::new();
+
+    for field in fields {
+        let path = field_path(parent, &field.field);
+
+        if field.field.is_empty() {
+            return Err(format!("Field of {:?} has an empty name", parent));
+        }
+
+        if !names.insert(field.field.clone()) {
+            return Err(format!("Duplicate field name: {}", path));
+        }
+
+        validate_field(field, &path)?;
+    }
+
+    Ok(())
+}
+
+fn validate_field(field: &mut Field, path: &str) -> Result<(), String> {
+    let field_type: FieldType = field
+        .field_type
+        .parse()
+        .map_err(|e| format!("Field {}: {}", path, e))?;
+    field.field_type = field_type.to_string();
+
+    let has_element = field.element.is_some();
+    let has_map_entries = field.key.is_some() || field.value.is_some();
+
+    match field_type {
+        FieldType::Struct => {
+            if field.fields.is_empty() || has_element || has_map_entries {
+                return Err(format!("Struct field {} must only have fields", path));
+            }
+            validate_fields(&mut field.fields, path)?;
+        }
+        FieldType::List => match field.element.as_deref_mut() {
+            Some(element) if field.fields.is_empty() && !has_map_entries => {
+                element.field = "element".to_string();
+                validate_field(element, &format!("{}.element", path))?;
+            }
+            _ => return Err(format!("List field {} must only have an element", path)),
+        },
+        FieldType::Map => match (field.key.as_deref_mut(), field.value.as_deref_mut()) {
+            (Some(key), Some(value)) if field.fields.is_empty() && !has_element => {
+                key.field = "key".to_string();
+                value.field = "value".to_string();
+                // Map keys can't be null
+                key.required = true;
+                validate_field(key, &format!("{}.key", path))?;
+                validate_field(value, &format!("{}.value", path))?;
+            }
+            _ => {
+                return Err(format!(
+                    "Map field {} must only have a key and a value",
+                    path
+                ))
+            }
+        },
+        _ => {
+            if !field.fields.is_empty() || has_element || has_map_entries {
+                return Err(format!(
+                    "Field {} of primitive type {} can't have nested fields",
+                    path, field_type
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema(value: serde_json::Value) -> Schema {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_normalize_assigns_ids() {
+        let normalized = schema(json!({"fields": [
+            {"field": "user_id", "type": "UUID", "required": true},
+            {"field": "device", "type": "struct", "fields": [
+                {"field": "type", "type": "ENUM"},
+                {"field": "tags", "type": "list", "element": {"type": "string"}}
+            ]},
+            {"field": "impressions", "type": "UINT_16"}
+        ]}))
+        .normalize(None, 0)
+        .unwrap()
+        .0;
+
+        let device = &normalized.fields[1];
+        assert_eq!(normalized.fields[0].field_type, "uuid");
+        assert_eq!(normalized.fields[2].field_type, "int");
+        assert_eq!(device.fields[0].field_type, "string");
+
+        let ids: Vec<_> = normalized.fields.iter().map(|f| f.id.unwrap()).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(device.fields[0].id, Some(4));
+        assert_eq!(device.fields[1].id, Some(5));
+        assert_eq!(device.fields[1].element.as_ref().unwrap().id, Some(6));
+
+        // Evolving the schema keeps existing ids and numbers new fields after them
+        let evolved = schema(json!({"fields": [
+            {"field": "impressions", "type": "int"},
+            {"field": "clicks", "type": "long"}
+        ]}))
+        .normalize(Some(&normalized), 6)
+        .unwrap()
+        .0;
+        assert_eq!(evolved.fields[0].id, Some(3));
+        assert_eq!(evolved.fields[1].id, Some(7));
+    }
+
+    #[test]
+    fn test_normalize_never_reuses_dropped_ids() {
+        let (schema_v1, last_column_id) = schema(json!({"fields": [
+            {"field": "ts", "type": "timestamp"},
+            {"field": "clicks", "type": "long"}
+        ]}))
+        .normalize(None, 0)
+        .unwrap();
+        assert_eq!(last_column_id, 2);
+
+        // Dropping the highest-id field keeps its id assigned
+        let (schema_v2, last_column_id) = schema(json!({"fields": [
+            {"field": "ts", "type": "timestamp"}
+        ]}))
+        .normalize(Some(&schema_v1), last_column_id)
+        .unwrap();
+        assert_eq!(last_column_id, 2);
+
+        // Data files written with the dropped field must not be read as the new one
+        let (schema_v3, last_column_id) = schema(json!({"fields": [
+            {"field": "ts", "type": "timestamp"},
+            {"field": "views", "type": "long"}
+        ]}))
+        .normalize(Some(&schema_v2), last_column_id)
+        .unwrap();
+        assert_eq!(schema_v3.fields[1].id, Some(3));
+        assert_eq!(last_column_id, 3);
+    }
+
+    #[test]
+    fn test_normalize_only_promotes_types() {
+        let (previous, last_column_id) = schema(json!({"fields": [
+            {"field": "clicks", "type": "int"},
+            {"field": "price", "type": "decimal(10, 2)"},
+            {"field": "device", "type": "struct", "fields": [
+                {"field": "os", "type": "string"}
+            ]}
+        ]}))
+        .normalize(None, 0)
+        .unwrap();
+
+        let evolve = |value| schema(value).normalize(Some(&previous), last_column_id);
+
+        let (promoted, _) = evolve(json!({"fields": [
+            {"field": "clicks", "type": "long"},
+            {"field": "price", "type": "decimal(12, 2)"}
+        ]}))
+        .unwrap();
+        assert_eq!(promoted.fields[0].id, Some(1));
+
+        assert_eq!(
+            evolve(json!({"fields": [{"field": "clicks", "type": "string"}]})).unwrap_err(),
+            "Field clicks can't change type from int to string"
+        );
+        assert_eq!(
+            evolve(json!({"fields": [{"field": "price", "type": "decimal(12, 3)"}]})).unwrap_err(),
+            "Field price can't change type from decimal(10, 2) to decimal(12, 3)"
+        );
+        assert_eq!(
+            evolve(
+                json!({"fields": [{"field": "device", "type": "struct", "fields": [
+                    {"field": "os", "type": "int"}
+                ]}]})
+            )
+            .unwrap_err(),
+            "Field device.os can't change type from string to int"
+        );
+    }
+
+    #[test]
+    fn test_normalize_rejects_invalid_schemas() {
+        let error = |value| schema(value).normalize(None, 0).unwrap_err();
+
+        assert_eq!(error(json!({"fields": []})), "Schema has no fields");
+        assert_eq!(
+            error(json!({"fields": [{"field": "ts", "type": "TIMESTAMP_NANOS"}]})),
+            "Field ts: Unknown type: TIMESTAMP_NANOS"
+        );
+        assert_eq!(
+            error(
+                json!({"fields": [{"field": "a", "type": "struct", "fields": [
+                    {"field": "b", "type": "int"}, {"field": "b", "type": "long"}
+                ]}]})
+            ),
+            "Duplicate field name: a.b"
+        );
+        assert_eq!(
+            error(json!({"fields": [{"field": "tags", "type": "list"}]})),
+            "List field tags must only have an element"
+        );
+        assert_eq!(
+            error(json!({"fields": [{"field": "price", "type": "decimal(40, 2)"}]})),
+            "Field price: Invalid type decimal(40, 2): precision must be between 1 and 38"
+        );
+    }
+}
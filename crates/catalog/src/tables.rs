@@ -0,0 +1,1065 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use fileio::FileIOError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{sqlite::SqlitePool, Row};
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::{
+    files,
+    identifier::{TableIdentifier, DEFAULT_NAMESPACE},
+    properties, purge,
+    rest::{
+        metadata::{TableMetadata, TableUpdate, LAST_ADDED},
+        namespaces,
+        tables::{now_millis, RegistryColumns},
+    },
+    schema::Schema,
+    AppState,
+};
+
+const DEFAULT_PAGE_SIZE: u32 = 100;
+const MAX_PAGE_SIZE: u32 = 1000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableRequest {
+    /// Table identifier, `ns1.ns2.table` or a bare name for the default namespace.
+    pub name: String,
+    pub schema: Schema,
+    /// Table properties, such as `write.parquet.compression-codec`. Replace
+    /// those of an existing table if set, and are kept otherwise.
+    #[serde(default)]
+    pub properties: Option<HashMap<String, String>>,
+    /// Iceberg sort order new data files are sorted by. Replaces that of an
+    /// existing table if set, and is kept otherwise.
+    #[serde(default)]
+    pub sort_order: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableResponse {
+    /// Fully qualified table identifier.
+    pub name: String,
+    pub namespace: Vec<String>,
+    pub schema: Schema,
+    /// Directory data files of the table are written under.
+    pub location: String,
+    /// Location of the current metadata file, if the table has one.
+    pub metadata_location: Option<String>,
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+    /// Iceberg sort order of new data files, if the table is sorted.
+    #[serde(default)]
+    pub sort_order: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListTablesParams {
+    /// Only list tables directly in this namespace, written `ns1.ns2`.
+    pub namespace: Option<String>,
+    pub page_size: Option<u32>,
+    /// Identifier of the last table of the previous page.
+    pub page_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListTablesResponse {
+    /// Fully qualified table identifiers.
+    pub tables: Vec<String>,
+    /// Token to fetch the next page with, absent on the last page.
+    pub next_page_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommitMetadataRequest {
+    /// Location the committer based its changes on, `None` for the first commit.
+    pub expected_metadata_location: Option<String>,
+    pub metadata_location: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DropTableParams {
+    /// Also delete the table's data and metadata files.
+    #[serde(default)]
+    pub purge: bool,
+}
+
+/// Creates the table registry, shared by this API and the Iceberg REST
+/// catalog. Each table has a single metadata pointer, `metadata_location`,
+/// and the Iceberg metadata REST clients load in `metadata`.
+pub async fn create_metadata_table(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS table_metadata (
+            name TEXT PRIMARY KEY,
+            namespace TEXT NOT NULL,
+            schema TEXT NOT NULL,
+            metadata_location TEXT,
+            properties TEXT NOT NULL DEFAULT '{}',
+            sort_order TEXT,
+            last_column_id INTEGER NOT NULL DEFAULT 0,
+            previous_metadata_location TEXT,
+            metadata TEXT
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    // Databases created before metadata pointers, properties, sort orders,
+    // last column ids and Iceberg metadata were tracked lack their columns
+    for (column, definition) in [
+        ("metadata_location", "TEXT"),
+        ("properties", "TEXT NOT NULL DEFAULT '{}'"),
+        ("sort_order", "TEXT"),
+        ("last_column_id", "INTEGER NOT NULL DEFAULT 0"),
+        ("previous_metadata_location", "TEXT"),
+        ("metadata", "TEXT"),
+    ] {
+        let exists =
+            sqlx::query("SELECT 1 FROM pragma_table_info('table_metadata') WHERE name = ?")
+                .bind(column)
+                .fetch_optional(pool)
+                .await?
+                .is_some();
+
+        if !exists {
+            sqlx::query(&format!(
+                "ALTER TABLE table_metadata ADD COLUMN {} {}",
+                column, definition
+            ))
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Moves tables of databases created before namespaces existed into the
+/// default namespace. Tables and their data files are then keyed by fully
+/// qualified identifier.
+pub async fn migrate_to_namespaces(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let has_namespace =
+        sqlx::query("SELECT 1 FROM pragma_table_info('table_metadata') WHERE name = 'namespace'")
+            .fetch_optional(pool)
+            .await?
+            .is_some();
+
+    if has_namespace {
+        return Ok(());
+    }
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("ALTER TABLE table_metadata ADD COLUMN namespace TEXT NOT NULL DEFAULT ''")
+        .execute(&mut *tx)
+        .await?;
+
+    for query in [
+        "UPDATE table_metadata SET name = ?1 || '.' || name, namespace = ?1",
+        "UPDATE data_files SET table_name = ?1 || '.' || table_name",
+    ] {
+        sqlx::query(query)
+            .bind(DEFAULT_NAMESPACE)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Creates the Iceberg metadata of tables stored before the registry kept
+/// it, so REST clients can load them.
+pub async fn backfill_iceberg_metadata(
+    pool: &SqlitePool,
+    warehouse: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let rows = sqlx::query(
+        "SELECT name, schema, properties, sort_order FROM table_metadata WHERE metadata IS NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for row in rows {
+        let identifier: TableIdentifier = row.get::<String, _>("name").parse()?;
+        let schema: Schema = serde_json::from_str(row.get("schema"))?;
+        let properties = serde_json::from_str(row.get("properties"))?;
+        let sort_order = row
+            .get::<Option<String>, _>("sort_order")
+            .map(|sort_order| serde_json::from_str(&sort_order))
+            .transpose()?;
+
+        let location = identifier.location(warehouse);
+        let metadata = new_iceberg_metadata(location, &schema, properties, sort_order);
+
+        sqlx::query("UPDATE table_metadata SET metadata = ? WHERE name = ?")
+            .bind(serde_json::to_string(&metadata)?)
+            .bind(identifier.to_string())
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Iceberg metadata of a table created through this API.
+fn new_iceberg_metadata(
+    location: String,
+    schema: &Schema,
+    properties: HashMap<String, String>,
+    sort_order: Option<Value>,
+) -> TableMetadata {
+    TableMetadata::new(
+        Uuid::new_v4().to_string(),
+        location,
+        schema.to_iceberg(0),
+        None,
+        sort_order,
+        properties,
+        now_millis(),
+    )
+}
+
+/// Parses a table identifier from a request.
+pub fn parse_identifier(identifier: &str) -> Result<TableIdentifier, (StatusCode, String)> {
+    identifier.parse().map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+pub fn table_not_found(identifier: &TableIdentifier) -> (StatusCode, String) {
+    (
+        StatusCode::NOT_FOUND,
+        format!("Table not found: {}", identifier),
+    )
+}
+
+pub async fn store_table_metadata(
+    State(state): State<AppState>,
+    Json(payload): Json<TableRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let identifier = parse_identifier(&payload.name)?;
+
+    let namespace_exists = namespaces::load_properties(&state.pool, &identifier.namespace)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:?}", e)))?
+        .is_some();
+
+    if !namespace_exists {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("Namespace not found: {}", identifier.namespace_name()),
+        ));
+    }
+
+    let internal_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+
+    let previous = sqlx::query(
+        "SELECT schema, properties, sort_order, last_column_id, metadata
+         FROM table_metadata WHERE name = ?",
+    )
+    .bind(identifier.to_string())
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+    let last_column_id: u32 = previous.as_ref().map_or(0, |row| row.get("last_column_id"));
+    let previous_properties: HashMap<String, String> = previous
+        .as_ref()
+        .map(|row| serde_json::from_str(row.get("properties")))
+        .transpose()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .unwrap_or_default();
+    let previous_sort_order: Option<Value> = previous
+        .as_ref()
+        .and_then(|row| row.get::<Option<String>, _>("sort_order"))
+        .map(|sort_order| serde_json::from_str(&sort_order))
+        .transpose()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let previous_metadata: Option<TableMetadata> = previous
+        .as_ref()
+        .and_then(|row| row.get::<Option<String>, _>("metadata"))
+        .map(|metadata| serde_json::from_str(&metadata))
+        .transpose()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let previous: Option<Schema> = previous
+        .map(|row| serde_json::from_str(row.get("schema")))
+        .transpose()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let (schema, last_column_id) = payload
+        .schema
+        .normalize(previous.as_ref(), last_column_id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let schema_json = serde_json::to_string(&schema)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let properties_json = payload
+        .properties
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Kept properties are checked too, as they may name dropped columns
+    properties::validate(
+        payload.properties.as_ref().unwrap_or(&previous_properties),
+        &schema,
+    )
+    .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    // A kept sort order may sort by a column the update drops
+    if let Some(sort_order) = payload.sort_order.as_ref().or(previous_sort_order.as_ref()) {
+        validate_sort_order(sort_order, &schema)?;
+    }
+    let sort_order_json = payload.sort_order.as_ref().map(Value::to_string);
+
+    let metadata = match previous_metadata {
+        Some(mut metadata) => {
+            let updates = metadata_updates(
+                &metadata,
+                &schema,
+                last_column_id,
+                payload.properties.as_ref(),
+                payload.sort_order.as_ref(),
+            );
+            metadata
+                .apply(updates, now_millis())
+                .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+            metadata
+        }
+        None => new_iceberg_metadata(
+            identifier.location(&state.warehouse),
+            &schema,
+            payload.properties.clone().unwrap_or_default(),
+            payload.sort_order.clone(),
+        ),
+    };
+    let metadata_json = serde_json::to_string(&metadata)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    sqlx::query(
+        "INSERT INTO table_metadata
+             (name, namespace, schema, properties, sort_order, last_column_id, metadata)
+         VALUES (?1, ?2, ?3, COALESCE(?4, '{}'), ?5, ?6, ?7)
+         ON CONFLICT(name) DO UPDATE
+         SET schema = excluded.schema,
+             properties = COALESCE(?4, properties),
+             sort_order = COALESCE(?5, sort_order),
+             last_column_id = excluded.last_column_id,
+             metadata = excluded.metadata",
+    )
+    .bind(identifier.to_string())
+    .bind(identifier.namespace_name())
+    .bind(&schema_json)
+    .bind(&properties_json)
+    .bind(&sort_order_json)
+    .bind(last_column_id)
+    .bind(&metadata_json)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+
+    tx.commit().await.map_err(internal_error)?;
+
+    if previous.is_none() {
+        Ok(StatusCode::CREATED)
+    } else {
+        Ok(StatusCode::OK)
+    }
+}
+
+/// Changes to a table's Iceberg metadata that mirror a schema, properties
+/// and sort order update.
+fn metadata_updates(
+    metadata: &TableMetadata,
+    schema: &Schema,
+    last_column_id: u32,
+    properties: Option<&HashMap<String, String>>,
+    sort_order: Option<&Value>,
+) -> Vec<TableUpdate> {
+    let mut updates = vec![
+        TableUpdate::AddSchema {
+            schema: schema.to_iceberg(0),
+            last_column_id: Some(last_column_id.into()),
+        },
+        TableUpdate::SetCurrentSchema {
+            schema_id: LAST_ADDED,
+        },
+    ];
+
+    if let Some(properties) = properties {
+        let removals = metadata
+            .properties
+            .keys()
+            .filter(|key| !properties.contains_key(*key))
+            .cloned()
+            .collect();
+        updates.push(TableUpdate::RemoveProperties { removals });
+        updates.push(TableUpdate::SetProperties {
+            updates: properties.clone(),
+        });
+    }
+
+    if let Some(sort_order) = sort_order {
+        updates.push(TableUpdate::AddSortOrder {
+            sort_order: sort_order.clone(),
+        });
+        updates.push(TableUpdate::SetDefaultSortOrder {
+            sort_order_id: LAST_ADDED,
+        });
+    }
+
+    updates
+}
+
+/// Checks that a sort order parses and sorts by top-level primitive columns
+/// of the table, the only ones the write service can sort data files by.
+fn validate_sort_order(sort_order: &Value, schema: &Schema) -> Result<(), (StatusCode, String)> {
+    let sort_order = iceberg::parser::sort_order::from_json_value(sort_order).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid sort order: {}", e),
+        )
+    })?;
+
+    for field in sort_order.fields() {
+        let top_level = schema
+            .fields
+            .iter()
+            .any(|source| source.id == Some(field.source_id));
+
+        match schema.field_by_id(field.source_id) {
+            Some(source) if top_level && source.is_primitive() => {}
+            Some(source) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("Can't sort by nested field {}", source.field),
+                ))
+            }
+            None => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("Sort order references unknown field id {}", field.source_id),
+                ))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn list_tables(
+    State(state): State<AppState>,
+    Query(params): Query<ListTablesParams>,
+) -> Result<Json<ListTablesResponse>, (StatusCode, String)> {
+    let page_size = params
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    // Fetch one extra row to know whether there is another page
+    let rows = sqlx::query(
+        "SELECT name FROM table_metadata
+         WHERE name > ?1 AND (?2 IS NULL OR namespace = ?2)
+         ORDER BY name LIMIT ?3",
+    )
+    .bind(params.page_token.unwrap_or_default())
+    .bind(params.namespace)
+    .bind(page_size + 1)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut tables: Vec<String> = rows.iter().map(|row| row.get("name")).collect();
+
+    let next_page_token = if tables.len() > page_size as usize {
+        tables.truncate(page_size as usize);
+        tables.last().cloned()
+    } else {
+        None
+    };
+
+    Ok(Json(ListTablesResponse {
+        tables,
+        next_page_token,
+    }))
+}
+
+pub async fn get_table(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<TableResponse>, (StatusCode, String)> {
+    let identifier = parse_identifier(&name)?;
+
+    let row = sqlx::query(
+        "SELECT schema, metadata_location, properties, sort_order, metadata
+         FROM table_metadata WHERE name = ?",
+    )
+    .bind(identifier.to_string())
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or_else(|| table_not_found(&identifier))?;
+
+    let schema: String = row.get("schema");
+    let schema = serde_json::from_str(&schema)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let properties: String = row.get("properties");
+    let properties = serde_json::from_str(&properties)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let sort_order = row
+        .get::<Option<String>, _>("sort_order")
+        .map(|sort_order| serde_json::from_str(&sort_order))
+        .transpose()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    // Tables created through the REST catalog may be located anywhere
+    let metadata = row
+        .get::<Option<String>, _>("metadata")
+        .map(|metadata| serde_json::from_str::<TableMetadata>(&metadata))
+        .transpose()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let location = match metadata {
+        Some(metadata) => metadata.location,
+        None => identifier.location(&state.warehouse),
+    };
+
+    Ok(Json(TableResponse {
+        name: identifier.to_string(),
+        location,
+        namespace: identifier.namespace,
+        schema,
+        metadata_location: row.get("metadata_location"),
+        properties,
+        sort_order,
+    }))
+}
+
+pub async fn drop_table(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<DropTableParams>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let internal_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let identifier = parse_identifier(&name)?;
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+
+    let row = sqlx::query(
+        "DELETE FROM table_metadata WHERE name = ?
+         RETURNING metadata_location, previous_metadata_location, metadata",
+    )
+    .bind(identifier.to_string())
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(internal_error)?
+    .ok_or_else(|| table_not_found(&identifier))?;
+
+    let metadata = row
+        .get::<Option<String>, _>("metadata")
+        .map(|metadata| serde_json::from_str::<TableMetadata>(&metadata))
+        .transpose()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let paths = files::delete_data_files(&mut tx, &identifier)
+        .await
+        .map_err(internal_error)?;
+
+    tx.commit().await.map_err(internal_error)?;
+
+    // Files are only removed once the table is gone from the catalog, so a
+    // failed purge leaves orphaned files rather than a table with missing data
+    if params.purge {
+        let purge_error = |e: purge::PurgeError| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+        let files = purge::table_files(
+            &state.io,
+            paths,
+            row.get("metadata_location"),
+            row.get("previous_metadata_location"),
+            metadata.as_ref(),
+        )
+        .await
+        .map_err(purge_error)?;
+        purge::delete_files(&state.io, &files)
+            .await
+            .map_err(purge_error)?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Points a table at a new metadata file. The swap only happens if the table
+/// still points at the expected location, otherwise a concurrent commit won
+/// and 409 is returned so the committer can retry on top of it.
+///
+/// The metadata the file holds is stored along with the pointer, so REST
+/// clients load and commit on top of it rather than on older metadata.
+pub async fn commit_metadata_location(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(payload): Json<CommitMetadataRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let internal_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let identifier = parse_identifier(&name)?;
+
+    let metadata = read_metadata(&state, &payload.metadata_location).await?;
+    let columns = RegistryColumns::new(&metadata)?;
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+
+    // `IS` also matches when both sides are NULL, i.e. the first commit
+    let result = sqlx::query(
+        "UPDATE table_metadata SET
+            previous_metadata_location = metadata_location, metadata_location = ?,
+            metadata = ?, schema = ?, properties = ?, sort_order = ?, last_column_id = ?
+         WHERE name = ? AND metadata_location IS ?",
+    )
+    .bind(&payload.metadata_location)
+    .bind(&columns.metadata)
+    .bind(&columns.schema)
+    .bind(&columns.properties)
+    .bind(&columns.sort_order)
+    .bind(metadata.last_column_id)
+    .bind(identifier.to_string())
+    .bind(&payload.expected_metadata_location)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+
+    if result.rows_affected() == 0 {
+        let row = sqlx::query("SELECT metadata_location FROM table_metadata WHERE name = ?")
+            .bind(identifier.to_string())
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(internal_error)?
+            .ok_or_else(|| table_not_found(&identifier))?;
+
+        let current: Option<String> = row.get("metadata_location");
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "Metadata location of {} is {:?}, expected {:?}",
+                identifier, current, payload.expected_metadata_location
+            ),
+        ));
+    }
+
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Reads the metadata file a table is being pointed at.
+async fn read_metadata(
+    state: &AppState,
+    location: &str,
+) -> Result<TableMetadata, (StatusCode, String)> {
+    let data = state.io.read(location).await.map_err(|e| match e {
+        FileIOError::ObjectStore(_) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        _ => (StatusCode::BAD_REQUEST, e.to_string()),
+    })?;
+
+    serde_json::from_slice(&data).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid metadata file {}: {}", location, e),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_state;
+
+    async fn create_table(state: &AppState, name: &str) -> Result<(), (StatusCode, String)> {
+        let request = TableRequest {
+            name: name.to_string(),
+            schema: serde_json::from_value(serde_json::json!({
+                "fields": [{"field": "user_id", "type": "UUID"}]
+            }))
+            .unwrap(),
+            properties: None,
+            sort_order: None,
+        };
+
+        store_table_metadata(State(state.clone()), Json(request))
+            .await
+            .map(|_| ())
+    }
+
+    async fn list(
+        state: &AppState,
+        namespace: Option<&str>,
+        page_size: u32,
+        page_token: Option<String>,
+    ) -> ListTablesResponse {
+        let params = ListTablesParams {
+            namespace: namespace.map(str::to_string),
+            page_size: Some(page_size),
+            page_token,
+        };
+
+        let Json(response) = list_tables(State(state.clone()), Query(params))
+            .await
+            .unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_list_tables_paginates() {
+        let state = test_state("").await;
+        for name in ["clicks", "traffic", "events"] {
+            create_table(&state, name).await.unwrap();
+        }
+
+        let page = list(&state, None, 2, None).await;
+        assert_eq!(page.tables, vec!["default.clicks", "default.events"]);
+        assert_eq!(page.next_page_token.as_deref(), Some("default.events"));
+
+        let page = list(&state, None, 2, page.next_page_token).await;
+        assert_eq!(page.tables, vec!["default.traffic"]);
+        assert_eq!(page.next_page_token, None);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_schema() {
+        let state = test_state("").await;
+
+        let request = TableRequest {
+            name: "traffic".to_string(),
+            schema: serde_json::from_value(serde_json::json!({
+                "fields": [{"field": "user_id", "type": "UUID"}, {"field": "user_id", "type": "int"}]
+            }))
+            .unwrap(),
+            properties: None,
+            sort_order: None,
+        };
+        let result = store_table_metadata(State(state.clone()), Json(request)).await;
+        assert_eq!(
+            result,
+            Err((
+                StatusCode::BAD_REQUEST,
+                "Duplicate field name: user_id".to_string()
+            ))
+        );
+
+        let result = get_table(State(state), Path("traffic".to_string())).await;
+        assert!(matches!(result, Err((StatusCode::NOT_FOUND, _))));
+    }
+
+    #[tokio::test]
+    async fn test_namespaced_tables() {
+        let state = test_state("").await;
+
+        let result = create_table(&state, "sales.eu.orders").await;
+        assert!(matches!(result, Err((StatusCode::NOT_FOUND, _))));
+
+        let result = create_table(&state, "sales..orders").await;
+        assert!(matches!(result, Err((StatusCode::BAD_REQUEST, _))));
+
+        sqlx::query(
+            "INSERT INTO iceberg_namespaces (namespace, properties) VALUES ('sales.eu', '{}')",
+        )
+        .execute(&state.pool)
+        .await
+        .unwrap();
+        create_table(&state, "sales.eu.orders").await.unwrap();
+        create_table(&state, "orders").await.unwrap();
+
+        let page = list(&state, Some("sales.eu"), 10, None).await;
+        assert_eq!(page.tables, vec!["sales.eu.orders"]);
+
+        let Json(table) = get_table(State(state), Path("sales.eu.orders".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(table.name, "sales.eu.orders");
+        assert_eq!(table.namespace, vec!["sales", "eu"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_table() {
+        let state = test_state("/warehouse/").await;
+        create_table(&state, "traffic").await.unwrap();
+
+        let Json(table) = get_table(State(state.clone()), Path("traffic".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(table.name, "default.traffic");
+        assert_eq!(table.schema.fields[0].field, "user_id");
+        assert_eq!(table.schema.fields[0].field_type, "uuid");
+        assert_eq!(table.schema.fields[0].id, Some(1));
+        assert_eq!(table.location, "/warehouse/default/traffic");
+        assert_eq!(table.metadata_location, None);
+        assert!(table.properties.is_empty());
+
+        let result = get_table(State(state), Path("missing".to_string())).await;
+        assert!(matches!(result, Err((StatusCode::NOT_FOUND, _))));
+    }
+
+    #[tokio::test]
+    async fn test_table_properties() {
+        let state = test_state("").await;
+        let store = |properties: Option<serde_json::Value>| TableRequest {
+            name: "traffic".to_string(),
+            schema: serde_json::from_value(serde_json::json!({
+                "fields": [{"field": "user_id", "type": "UUID"}]
+            }))
+            .unwrap(),
+            properties: properties.map(|p| serde_json::from_value(p).unwrap()),
+            sort_order: None,
+        };
+        let properties = |state: &AppState| {
+            let state = state.clone();
+            async move {
+                let Json(table) = get_table(State(state), Path("traffic".to_string()))
+                    .await
+                    .unwrap();
+                table.properties
+            }
+        };
+
+        let codec = serde_json::json!({"write.parquet.compression-codec": "zstd"});
+        store_table_metadata(State(state.clone()), Json(store(Some(codec))))
+            .await
+            .unwrap();
+        assert_eq!(
+            properties(&state).await["write.parquet.compression-codec"],
+            "zstd"
+        );
+
+        // Schema updates without properties keep them
+        store_table_metadata(State(state.clone()), Json(store(None)))
+            .await
+            .unwrap();
+        assert_eq!(properties(&state).await.len(), 1);
+
+        // Values the write service couldn't build files with are refused
+        let invalid = serde_json::json!({"write.parquet.compression-codec": "zip"});
+        let result = store_table_metadata(State(state.clone()), Json(store(Some(invalid)))).await;
+        assert!(matches!(result, Err((StatusCode::BAD_REQUEST, _))));
+        assert_eq!(properties(&state).await.len(), 1);
+
+        let empty = serde_json::json!({});
+        store_table_metadata(State(state.clone()), Json(store(Some(empty))))
+            .await
+            .unwrap();
+        assert!(properties(&state).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_table_sort_order() {
+        let state = test_state("").await;
+        let store = |source_id: u32| TableRequest {
+            name: "traffic".to_string(),
+            schema: serde_json::from_value(serde_json::json!({
+                "fields": [
+                    {"field": "user_id", "type": "UUID"},
+                    {"field": "device", "type": "struct", "fields": [
+                        {"field": "os", "type": "string"}
+                    ]}
+                ]
+            }))
+            .unwrap(),
+            properties: None,
+            sort_order: Some(serde_json::json!({
+                "order-id": 1,
+                "fields": [{
+                    "source-id": source_id,
+                    "transform": "identity",
+                    "direction": "asc",
+                    "null-order": "nulls-first"
+                }]
+            })),
+        };
+
+        // Field 2 is the struct, 3 its string field, which data files can't
+        // be sorted by either
+        for source_id in [2, 3, 9] {
+            let result = store_table_metadata(State(state.clone()), Json(store(source_id))).await;
+            assert!(matches!(result, Err((StatusCode::BAD_REQUEST, _))));
+        }
+
+        store_table_metadata(State(state.clone()), Json(store(1)))
+            .await
+            .unwrap();
+        let Json(table) = get_table(State(state.clone()), Path("traffic".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(table.sort_order.unwrap()["fields"][0]["source-id"], 1);
+
+        // Nor can an update drop the column the kept order sorts by
+        let drop_user_id = TableRequest {
+            schema: serde_json::from_value(serde_json::json!({
+                "fields": [{"field": "device", "type": "struct", "fields": [
+                    {"field": "os", "type": "string"}
+                ]}]
+            }))
+            .unwrap(),
+            sort_order: None,
+            ..store(1)
+        };
+        let result = store_table_metadata(State(state), Json(drop_user_id)).await;
+        assert!(matches!(result, Err((StatusCode::BAD_REQUEST, _))));
+    }
+
+    /// Location of a version of the `traffic` table's metadata.
+    fn metadata_file(version: &str) -> String {
+        format!(
+            "memory://warehouse/default/traffic/metadata/{}.metadata.json",
+            version
+        )
+    }
+
+    /// Writes a metadata file with a `version` property, then points the
+    /// `traffic` table at it.
+    async fn commit(
+        state: &AppState,
+        expected: Option<&str>,
+        location: &str,
+    ) -> Result<StatusCode, (StatusCode, String)> {
+        let schema: Schema = serde_json::from_value(serde_json::json!({
+            "fields": [{"field": "user_id", "type": "UUID"}]
+        }))
+        .unwrap();
+        let properties = HashMap::from([("version".to_string(), location.to_string())]);
+        let metadata = new_iceberg_metadata(
+            "memory://warehouse/default/traffic".to_string(),
+            &schema,
+            properties,
+            None,
+        );
+        state
+            .io
+            .write(location, serde_json::to_vec(&metadata).unwrap().into())
+            .await
+            .unwrap();
+
+        let request = CommitMetadataRequest {
+            expected_metadata_location: expected.map(str::to_string),
+            metadata_location: location.to_string(),
+        };
+
+        commit_metadata_location(
+            State(state.clone()),
+            Path("traffic".to_string()),
+            Json(request),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_commit_metadata_location() {
+        let state = test_state("").await;
+
+        let (v1, v2) = (metadata_file("v1"), metadata_file("v2"));
+
+        let result = commit(&state, None, &v1).await;
+        assert!(matches!(result, Err((StatusCode::NOT_FOUND, _))));
+
+        create_table(&state, "traffic").await.unwrap();
+
+        let status = commit(&state, None, &v1).await.unwrap();
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        // A second committer based on the same version loses
+        let result = commit(&state, None, &metadata_file("v1-other")).await;
+        assert!(matches!(result, Err((StatusCode::CONFLICT, _))));
+
+        commit(&state, Some(&v1), &v2).await.unwrap();
+
+        let result = commit(&state, Some(&v1), &metadata_file("v3")).await;
+        assert!(matches!(result, Err((StatusCode::CONFLICT, _))));
+
+        // The table's metadata is that of the file it points at
+        let Json(table) = get_table(State(state.clone()), Path("traffic".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(table.metadata_location, Some(v2.clone()));
+        assert_eq!(table.properties["version"], v2);
+
+        // Pointing at a file that isn't table metadata fails
+        let request = CommitMetadataRequest {
+            expected_metadata_location: Some(v2.clone()),
+            metadata_location: metadata_file("missing"),
+        };
+        let result =
+            commit_metadata_location(State(state), Path("traffic".to_string()), Json(request))
+                .await;
+        assert!(matches!(result, Err((StatusCode::BAD_REQUEST, _))));
+    }
+
+    #[tokio::test]
+    async fn test_drop_table() {
+        let state = test_state("").await;
+        create_table(&state, "traffic").await.unwrap();
+
+        let params = DropTableParams { purge: false };
+        let status = drop_table(
+            State(state.clone()),
+            Path("traffic".to_string()),
+            Query(params),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let result = get_table(State(state.clone()), Path("traffic".to_string())).await;
+        assert!(matches!(result, Err((StatusCode::NOT_FOUND, _))));
+
+        let params = DropTableParams { purge: false };
+        let result = drop_table(State(state), Path("traffic".to_string()), Query(params)).await;
+        assert!(matches!(result, Err((StatusCode::NOT_FOUND, _))));
+    }
+
+    #[tokio::test]
+    async fn test_purge_table() {
+        let state = test_state("").await;
+        create_table(&state, "traffic").await.unwrap();
+
+        let data_file = "memory://warehouse/default/traffic/data/a.parquet";
+        // Another table's file in the same directory is left alone
+        let other_file = "memory://warehouse/default/traffic/data/other.parquet";
+        for file in [data_file, other_file] {
+            state.io.write(file, "{}".into()).await.unwrap();
+        }
+
+        let files = serde_json::from_value(serde_json::json!({
+            "files": [{
+                "path": data_file,
+                "record_count": 1,
+                "file_size_in_bytes": 2,
+                "written_at_ms": 1723320520000_i64
+            }]
+        }))
+        .unwrap();
+        files::register_data_files(
+            State(state.clone()),
+            Path("traffic".to_string()),
+            Json(files),
+        )
+        .await
+        .unwrap();
+        let metadata_file = metadata_file("v1");
+        commit(&state, None, &metadata_file).await.unwrap();
+
+        let params = DropTableParams { purge: true };
+        drop_table(
+            State(state.clone()),
+            Path("traffic".to_string()),
+            Query(params),
+        )
+        .await
+        .unwrap();
+
+        assert!(!state.io.exists(data_file).await.unwrap());
+        assert!(!state.io.exists(&metadata_file).await.unwrap());
+        assert!(state.io.exists(other_file).await.unwrap());
+    }
+}
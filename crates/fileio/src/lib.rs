@@ -0,0 +1,275 @@
+//! File access shared by the services and the iceberg crate, for local
+//! files, S3-compatible buckets and memory, selected by URI scheme.
+
+use std::{
+    collections::HashMap,
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+use bytes::Bytes;
+use futures::TryStreamExt;
+use object_store::{
+    aws::{AmazonS3, AmazonS3Builder},
+    local::LocalFileSystem,
+    memory::InMemory,
+    path::Path,
+    ObjectStore,
+};
+use thiserror::Error;
+use url::Url;
+
+#[derive(Error, Debug)]
+pub enum FileIOError {
+    #[error("Invalid location: {0}")]
+    InvalidLocation(String),
+    #[error("Unsupported scheme {0}, expected file, s3 or memory")]
+    UnsupportedScheme(String),
+    #[error("File not found: {0}")]
+    NotFound(String),
+    #[error("Storage request failed: {0}")]
+    ObjectStore(#[from] object_store::Error),
+}
+
+/// Connection settings of S3-compatible buckets.
+#[derive(Debug, Clone, Default)]
+pub struct S3Config {
+    /// Endpoint of S3-compatible services such as MinIO, AWS if not set.
+    pub endpoint: Option<String>,
+    pub bucket: String,
+    /// Key prefix of all data files.
+    pub prefix: String,
+    pub region: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+}
+
+impl S3Config {
+    /// Reads `S3_ENDPOINT`, `S3_BUCKET`, `S3_PREFIX`, `AWS_REGION`,
+    /// `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        Self {
+            endpoint: var("S3_ENDPOINT"),
+            bucket: var("S3_BUCKET").unwrap_or_default(),
+            prefix: var("S3_PREFIX").unwrap_or_default(),
+            region: var("AWS_REGION"),
+            access_key_id: var("AWS_ACCESS_KEY_ID"),
+            secret_access_key: var("AWS_SECRET_ACCESS_KEY"),
+        }
+    }
+
+    /// Client of `bucket`, with the endpoint and credentials of this config.
+    pub fn build(&self, bucket: &str) -> Result<AmazonS3, FileIOError> {
+        let mut builder = AmazonS3Builder::new().with_bucket_name(bucket);
+
+        if let Some(endpoint) = &self.endpoint {
+            builder = builder
+                .with_endpoint(endpoint)
+                .with_allow_http(endpoint.starts_with("http://"))
+                // Buckets of S3-compatible services are rarely DNS names
+                .with_virtual_hosted_style_request(false);
+        }
+        if let Some(region) = &self.region {
+            builder = builder.with_region(region);
+        }
+        if let (Some(key), Some(secret)) = (&self.access_key_id, &self.secret_access_key) {
+            builder = builder
+                .with_access_key_id(key)
+                .with_secret_access_key(secret);
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+/// Reads and writes files by location. Locations are `file://`, `s3://` or
+/// `memory://` URIs, or local paths.
+///
+/// Clones share their stores, so files written to `memory://` through one
+/// clone are visible to the others.
+#[derive(Debug, Clone)]
+pub struct FileIO {
+    s3: S3Config,
+    /// Stores by scheme and authority, e.g. `s3://bucket`.
+    stores: Arc<Mutex<HashMap<String, Arc<dyn ObjectStore>>>>,
+}
+
+impl Default for FileIO {
+    fn default() -> Self {
+        Self::new(S3Config::default())
+    }
+}
+
+impl FileIO {
+    pub fn new(s3: S3Config) -> Self {
+        Self {
+            s3,
+            stores: Arc::default(),
+        }
+    }
+
+    /// Parses a location, reading local paths as `file://` URIs.
+    pub fn parse_location(location: &str) -> Result<Url, FileIOError> {
+        let invalid = || FileIOError::InvalidLocation(location.to_string());
+
+        if location.contains("://") {
+            return Url::parse(location).map_err(|_| invalid());
+        }
+
+        let path = std::path::absolute(location).map_err(|_| invalid())?;
+        Url::from_file_path(path).map_err(|_| invalid())
+    }
+
+    /// Store holding a location, and the location's path in it. Stores are
+    /// keyed by scheme and bucket, as DataFusion registers them.
+    pub fn object_store(
+        &self,
+        location: &str,
+    ) -> Result<(Arc<dyn ObjectStore>, Path), FileIOError> {
+        let url = Self::parse_location(location)?;
+        let path = Path::from_url_path(url.path())
+            .map_err(|_| FileIOError::InvalidLocation(location.to_string()))?;
+
+        let key = format!("{}://{}", url.scheme(), url.authority());
+        let mut stores = self.stores.lock().unwrap();
+        if let Some(store) = stores.get(&key) {
+            return Ok((store.clone(), path));
+        }
+
+        let store: Arc<dyn ObjectStore> = match url.scheme() {
+            "file" => Arc::new(LocalFileSystem::new()),
+            "memory" => Arc::new(InMemory::new()),
+            "s3" => Arc::new(self.s3.build(url.authority())?),
+            scheme => return Err(FileIOError::UnsupportedScheme(scheme.to_string())),
+        };
+        stores.insert(key, store.clone());
+
+        Ok((store, path))
+    }
+
+    pub async fn read(&self, location: &str) -> Result<Bytes, FileIOError> {
+        let (store, path) = self.object_store(location)?;
+        let result = store.get(&path).await.map_err(not_found(location))?;
+        Ok(result.bytes().await?)
+    }
+
+    /// Reads the bytes of `range`, such as a Parquet footer.
+    pub async fn read_range(
+        &self,
+        location: &str,
+        range: Range<usize>,
+    ) -> Result<Bytes, FileIOError> {
+        let (store, path) = self.object_store(location)?;
+        store
+            .get_range(&path, range)
+            .await
+            .map_err(not_found(location))
+    }
+
+    /// Writes a file, replacing it if it exists.
+    pub async fn write(&self, location: &str, data: Bytes) -> Result<(), FileIOError> {
+        let (store, path) = self.object_store(location)?;
+        store.put(&path, data.into()).await?;
+        Ok(())
+    }
+
+    /// Locations of the files under a directory or prefix, recursively.
+    pub async fn list(&self, location: &str) -> Result<Vec<String>, FileIOError> {
+        let url = Self::parse_location(location)?;
+        let (store, path) = self.object_store(location)?;
+
+        let mut root = url.clone();
+        root.set_path("");
+
+        let mut locations: Vec<String> = store
+            .list(Some(&path))
+            .map_ok(|meta| format!("{}/{}", root.as_str().trim_end_matches('/'), meta.location))
+            .try_collect()
+            .await?;
+        locations.sort();
+        Ok(locations)
+    }
+
+    /// Deletes a file. Deleting a missing file is not an error.
+    pub async fn delete(&self, location: &str) -> Result<(), FileIOError> {
+        let (store, path) = self.object_store(location)?;
+
+        match store.delete(&path).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn exists(&self, location: &str) -> Result<bool, FileIOError> {
+        let (store, path) = self.object_store(location)?;
+
+        match store.head(&path).await {
+            Ok(_) => Ok(true),
+            Err(object_store::Error::NotFound { .. }) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+fn not_found(location: &str) -> impl FnOnce(object_store::Error) -> FileIOError + '_ {
+    move |e| match e {
+        object_store::Error::NotFound { .. } => FileIOError::NotFound(location.to_string()),
+        e => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_and_local_files() {
+        let io = FileIO::default();
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("t/metadata/v1.metadata.json");
+        let local = local.to_str().unwrap();
+
+        for location in ["memory://warehouse/t/metadata/v1.metadata.json", local] {
+            assert!(!io.exists(location).await.unwrap());
+            assert!(matches!(
+                io.read(location).await,
+                Err(FileIOError::NotFound(_))
+            ));
+
+            io.write(location, Bytes::from("{\"format-version\": 2}"))
+                .await
+                .unwrap();
+            assert!(io.exists(location).await.unwrap());
+            assert_eq!(
+                io.read_range(location, 2..16).await.unwrap(),
+                "format-version"
+            );
+
+            // Clones share the files they write
+            let read = io.clone().read(location).await.unwrap();
+            assert_eq!(read, "{\"format-version\": 2}");
+
+            io.delete(location).await.unwrap();
+            io.delete(location).await.unwrap();
+            assert!(!io.exists(location).await.unwrap());
+        }
+
+        io.write("memory://warehouse/t/data/a.parquet", Bytes::new())
+            .await
+            .unwrap();
+        io.write("memory://warehouse/u/data/b.parquet", Bytes::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            io.list("memory://warehouse/t").await.unwrap(),
+            vec!["memory://warehouse/t/data/a.parquet"]
+        );
+
+        assert!(matches!(
+            io.read("gs://bucket/a.parquet").await,
+            Err(FileIOError::UnsupportedScheme(_))
+        ));
+    }
+}
@@ -0,0 +1,545 @@
+//! Reader of Avro object container files, which Iceberg writes manifest lists
+//! and manifests as. Records are decoded with the schema the file was written
+//! with; callers look fields up by name.
+
+use std::{collections::HashMap, io::Read};
+
+use flate2::read::DeflateDecoder;
+use serde_json::Value as Json;
+use thiserror::Error;
+
+const MAGIC: &[u8] = b"Obj\x01";
+const SYNC_LENGTH: usize = 16;
+
+#[derive(Error, Debug)]
+pub enum AvroError {
+    #[error("Invalid Avro file: {0}")]
+    InvalidFile(String),
+    #[error("Invalid Avro schema: {0}")]
+    InvalidSchema(String),
+    #[error("Unsupported Avro codec: {0}")]
+    UnsupportedCodec(String),
+    #[error("Failed to decompress Avro block: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Writer schema of a file, with references to named types resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schema {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    Record(Vec<(String, Schema)>),
+    Enum(Vec<String>),
+    Array(Box<Schema>),
+    Map(Box<Schema>),
+    Union(Vec<Schema>),
+    Fixed(usize),
+}
+
+/// A decoded value. Unions decode to the value of their branch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Bytes(Vec<u8>),
+    String(String),
+    Record(Vec<(String, Value)>),
+    Enum(String),
+    Array(Vec<Value>),
+    Map(Vec<(String, Value)>),
+    Fixed(Vec<u8>),
+}
+
+impl Value {
+    /// Field of a record, `None` for other values.
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Record(fields) => fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Ints and longs, `None` for other values such as nulls.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n as i64),
+            Value::Long(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) | Value::Enum(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(bytes) | Value::Fixed(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+}
+
+impl Schema {
+    pub fn parse(json: &Json) -> Result<Schema, AvroError> {
+        Self::parse_named(json, &mut HashMap::new())
+    }
+
+    fn parse_named(json: &Json, names: &mut HashMap<String, Schema>) -> Result<Schema, AvroError> {
+        let invalid = || AvroError::InvalidSchema(json.to_string());
+
+        let type_name = match json {
+            Json::String(name) => name.as_str(),
+            Json::Array(branches) => {
+                let branches = branches
+                    .iter()
+                    .map(|branch| Self::parse_named(branch, names))
+                    .collect::<Result<_, _>>()?;
+                return Ok(Schema::Union(branches));
+            }
+            Json::Object(object) => match object.get("type") {
+                Some(Json::String(name)) => name.as_str(),
+                // A type given as a nested schema, rather than by name
+                Some(nested) => return Self::parse_named(nested, names),
+                None => return Err(invalid()),
+            },
+            _ => return Err(invalid()),
+        };
+
+        let schema = match type_name {
+            "null" => Schema::Null,
+            "boolean" => Schema::Boolean,
+            "int" => Schema::Int,
+            "long" => Schema::Long,
+            "float" => Schema::Float,
+            "double" => Schema::Double,
+            "bytes" => Schema::Bytes,
+            "string" => Schema::String,
+            "record" | "error" => {
+                let fields = json["fields"].as_array().ok_or_else(invalid)?;
+                let fields = fields
+                    .iter()
+                    .map(|field| {
+                        let name = field["name"].as_str().ok_or_else(invalid)?;
+                        Ok((name.to_string(), Self::parse_named(&field["type"], names)?))
+                    })
+                    .collect::<Result<_, AvroError>>()?;
+                Schema::Record(fields)
+            }
+            "enum" => {
+                let symbols = json["symbols"].as_array().ok_or_else(invalid)?;
+                let symbols = symbols
+                    .iter()
+                    .map(|symbol| symbol.as_str().map(str::to_string).ok_or_else(invalid))
+                    .collect::<Result<_, _>>()?;
+                Schema::Enum(symbols)
+            }
+            "array" => Schema::Array(Box::new(Self::parse_named(&json["items"], names)?)),
+            "map" => Schema::Map(Box::new(Self::parse_named(&json["values"], names)?)),
+            "fixed" => Schema::Fixed(json["size"].as_u64().ok_or_else(invalid)? as usize),
+            name => {
+                return names
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| AvroError::InvalidSchema(format!("Unknown type {}", name)))
+            }
+        };
+
+        // Named types can be referred to by later fields, by name or full name
+        if let Some(name) = json.get("name").and_then(Json::as_str) {
+            if let Some(namespace) = json.get("namespace").and_then(Json::as_str) {
+                names.insert(format!("{}.{}", namespace, name), schema.clone());
+            }
+            names.insert(name.to_string(), schema.clone());
+        }
+
+        Ok(schema)
+    }
+}
+
+/// Decodes the records of an object container file.
+pub fn read_container(data: &[u8]) -> Result<Vec<Value>, AvroError> {
+    let mut reader = Reader::new(data);
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(AvroError::InvalidFile("missing magic bytes".to_string()));
+    }
+
+    let metadata = reader.read_value(&Schema::Map(Box::new(Schema::Bytes)))?;
+    let Value::Map(metadata) = metadata else {
+        unreachable!("maps decode to maps")
+    };
+    let metadata: HashMap<String, Vec<u8>> = metadata
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value.as_bytes()?.to_vec())))
+        .collect();
+
+    let schema = metadata
+        .get("avro.schema")
+        .ok_or_else(|| AvroError::InvalidFile("missing avro.schema".to_string()))?;
+    let schema: Json =
+        serde_json::from_slice(schema).map_err(|e| AvroError::InvalidSchema(e.to_string()))?;
+    let schema = Schema::parse(&schema)?;
+    let codec = metadata
+        .get("avro.codec")
+        .map(|codec| String::from_utf8_lossy(codec).into_owned())
+        .unwrap_or_else(|| "null".to_string());
+
+    let sync = reader.take(SYNC_LENGTH)?;
+    let mut records = Vec::new();
+    while !reader.is_empty() {
+        let count = reader.read_long()?;
+        let size = reader.read_long()?;
+        let block = reader.take(usize::try_from(size).map_err(|_| invalid_length(size))?)?;
+
+        let block = match codec.as_str() {
+            "null" => block.to_vec(),
+            "deflate" => {
+                let mut decoded = Vec::new();
+                DeflateDecoder::new(block).read_to_end(&mut decoded)?;
+                decoded
+            }
+            codec => return Err(AvroError::UnsupportedCodec(codec.to_string())),
+        };
+
+        let mut block_reader = Reader::new(&block);
+        for _ in 0..count {
+            records.push(block_reader.read_value(&schema)?);
+        }
+
+        if reader.take(SYNC_LENGTH)? != sync {
+            return Err(AvroError::InvalidFile("sync marker mismatch".to_string()));
+        }
+    }
+
+    Ok(records)
+}
+
+fn invalid_length(length: i64) -> AvroError {
+    AvroError::InvalidFile(format!("invalid length {}", length))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, position: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], AvroError> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| AvroError::InvalidFile("unexpected end of data".to_string()))?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    /// Zig-zag encoded variable length integer, as ints and longs are written.
+    fn read_long(&mut self) -> Result<i64, AvroError> {
+        let mut value: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+            }
+        }
+        Err(AvroError::InvalidFile("integer too long".to_string()))
+    }
+
+    fn read_length(&mut self) -> Result<usize, AvroError> {
+        let length = self.read_long()?;
+        usize::try_from(length).map_err(|_| invalid_length(length))
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>, AvroError> {
+        let length = self.read_length()?;
+        Ok(self.take(length)?.to_vec())
+    }
+
+    fn read_string(&mut self) -> Result<String, AvroError> {
+        String::from_utf8(self.read_bytes()?).map_err(|e| AvroError::InvalidFile(e.to_string()))
+    }
+
+    /// Items of an array or map, written in blocks that end with an empty one.
+    /// Blocks with a negative count are followed by their size in bytes.
+    fn read_blocks(
+        &mut self,
+        mut read_item: impl FnMut(&mut Self) -> Result<(), AvroError>,
+    ) -> Result<(), AvroError> {
+        loop {
+            let count = self.read_long()?;
+            if count == 0 {
+                return Ok(());
+            }
+            if count < 0 {
+                self.read_long()?;
+            }
+            for _ in 0..count.unsigned_abs() {
+                read_item(self)?;
+            }
+        }
+    }
+
+    fn read_value(&mut self, schema: &Schema) -> Result<Value, AvroError> {
+        let value = match schema {
+            Schema::Null => Value::Null,
+            Schema::Boolean => Value::Boolean(self.take(1)?[0] != 0),
+            Schema::Int => {
+                let n = self.read_long()?;
+                Value::Int(i32::try_from(n).map_err(|_| invalid_length(n))?)
+            }
+            Schema::Long => Value::Long(self.read_long()?),
+            Schema::Float => Value::Float(f32::from_le_bytes(self.take(4)?.try_into().unwrap())),
+            Schema::Double => Value::Double(f64::from_le_bytes(self.take(8)?.try_into().unwrap())),
+            Schema::Bytes => Value::Bytes(self.read_bytes()?),
+            Schema::String => Value::String(self.read_string()?),
+            Schema::Record(fields) => Value::Record(
+                fields
+                    .iter()
+                    .map(|(name, schema)| Ok((name.clone(), self.read_value(schema)?)))
+                    .collect::<Result<_, AvroError>>()?,
+            ),
+            Schema::Enum(symbols) => {
+                let index = self.read_length()?;
+                let symbol = symbols
+                    .get(index)
+                    .ok_or_else(|| invalid_length(index as i64))?;
+                Value::Enum(symbol.clone())
+            }
+            Schema::Array(items) => {
+                let mut values = Vec::new();
+                self.read_blocks(|reader| {
+                    values.push(reader.read_value(items)?);
+                    Ok(())
+                })?;
+                Value::Array(values)
+            }
+            Schema::Map(values) => {
+                let mut entries = Vec::new();
+                self.read_blocks(|reader| {
+                    let key = reader.read_string()?;
+                    entries.push((key, reader.read_value(values)?));
+                    Ok(())
+                })?;
+                Value::Map(entries)
+            }
+            Schema::Union(branches) => {
+                let index = self.read_length()?;
+                let branch = branches
+                    .get(index)
+                    .ok_or_else(|| invalid_length(index as i64))?;
+                self.read_value(branch)?
+            }
+            Schema::Fixed(size) => Value::Fixed(self.take(*size)?.to_vec()),
+        };
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::Write;
+
+    use flate2::{write::DeflateEncoder, Compression};
+    use serde_json::json;
+
+    use super::*;
+
+    fn write_long(out: &mut Vec<u8>, n: i64) {
+        let mut n = ((n << 1) ^ (n >> 63)) as u64;
+        while n >= 0x80 {
+            out.push((n as u8 & 0x7f) | 0x80);
+            n >>= 7;
+        }
+        out.push(n as u8);
+    }
+
+    fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+        write_long(out, bytes.len() as i64);
+        out.extend_from_slice(bytes);
+    }
+
+    fn write_value(out: &mut Vec<u8>, schema: &Schema, value: &Value) {
+        match (schema, value) {
+            (Schema::Union(branches), value) => {
+                // Nulls take the null branch, other values the first other one
+                let index = branches
+                    .iter()
+                    .position(|branch| (*branch == Schema::Null) == (*value == Value::Null))
+                    .unwrap();
+                write_long(out, index as i64);
+                write_value(out, &branches[index], value);
+            }
+            (Schema::Null, Value::Null) => {}
+            (Schema::Boolean, Value::Boolean(b)) => out.push(*b as u8),
+            (Schema::Int, Value::Int(n)) => write_long(out, *n as i64),
+            (Schema::Long, Value::Long(n)) => write_long(out, *n),
+            (Schema::Float, Value::Float(n)) => out.extend_from_slice(&n.to_le_bytes()),
+            (Schema::Double, Value::Double(n)) => out.extend_from_slice(&n.to_le_bytes()),
+            (Schema::Bytes, Value::Bytes(bytes)) => write_bytes(out, bytes),
+            (Schema::String, Value::String(s)) => write_bytes(out, s.as_bytes()),
+            (Schema::Record(fields), Value::Record(values)) => {
+                for ((_, schema), (_, value)) in fields.iter().zip(values) {
+                    write_value(out, schema, value);
+                }
+            }
+            (Schema::Enum(symbols), Value::Enum(symbol)) => write_long(
+                out,
+                symbols.iter().position(|s| s == symbol).unwrap() as i64,
+            ),
+            (Schema::Array(items), Value::Array(values)) => {
+                if !values.is_empty() {
+                    write_long(out, values.len() as i64);
+                    for value in values {
+                        write_value(out, items, value);
+                    }
+                }
+                write_long(out, 0);
+            }
+            (Schema::Map(schema), Value::Map(entries)) => {
+                if !entries.is_empty() {
+                    write_long(out, entries.len() as i64);
+                    for (key, value) in entries {
+                        write_bytes(out, key.as_bytes());
+                        write_value(out, schema, value);
+                    }
+                }
+                write_long(out, 0);
+            }
+            (Schema::Fixed(_), Value::Fixed(bytes)) => out.extend_from_slice(bytes),
+            (schema, value) => panic!("{:?} doesn't match {:?}", value, schema),
+        }
+    }
+
+    /// Writes records as an object container file of one block.
+    pub(crate) fn write_container(schema: &Json, codec: &str, records: &[Value]) -> Vec<u8> {
+        let parsed = Schema::parse(schema).unwrap();
+
+        let mut out = MAGIC.to_vec();
+        let metadata = Value::Map(vec![
+            (
+                "avro.schema".to_string(),
+                Value::Bytes(schema.to_string().into_bytes()),
+            ),
+            (
+                "avro.codec".to_string(),
+                Value::Bytes(codec.as_bytes().to_vec()),
+            ),
+        ]);
+        write_value(&mut out, &Schema::Map(Box::new(Schema::Bytes)), &metadata);
+        let sync = [7u8; SYNC_LENGTH];
+        out.extend_from_slice(&sync);
+
+        let mut block = Vec::new();
+        for record in records {
+            write_value(&mut block, &parsed, record);
+        }
+        let block = match codec {
+            "deflate" => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&block).unwrap();
+                encoder.finish().unwrap()
+            }
+            _ => block,
+        };
+
+        write_long(&mut out, records.len() as i64);
+        write_bytes(&mut out, &block);
+        out.extend_from_slice(&sync);
+        out
+    }
+
+    #[test]
+    fn test_read_container() {
+        let schema = json!({
+            "type": "record",
+            "name": "entry",
+            "fields": [
+                {"name": "id", "type": "long"},
+                {"name": "name", "type": ["null", "string"]},
+                {"name": "counts", "type": {"type": "array", "items": {
+                    "type": "record",
+                    "name": "k_v",
+                    "fields": [
+                        {"name": "key", "type": "int"},
+                        {"name": "value", "type": "long"}
+                    ]
+                }}},
+                {"name": "last", "type": ["null", "k_v"]},
+                {"name": "kind", "type": {"type": "enum", "name": "kind", "symbols": ["A", "B"]}}
+            ]
+        });
+        let records: Vec<Value> = (0..3)
+            .map(|i| {
+                Value::Record(vec![
+                    ("id".to_string(), Value::Long(-i * 1_000_000_007)),
+                    (
+                        "name".to_string(),
+                        if i == 1 {
+                            Value::Null
+                        } else {
+                            Value::String(format!("r{}", i))
+                        },
+                    ),
+                    (
+                        "counts".to_string(),
+                        Value::Array(
+                            (0..i as i32)
+                                .map(|key| {
+                                    Value::Record(vec![
+                                        ("key".to_string(), Value::Int(key)),
+                                        ("value".to_string(), Value::Long(key as i64 * 10)),
+                                    ])
+                                })
+                                .collect(),
+                        ),
+                    ),
+                    ("last".to_string(), Value::Null),
+                    ("kind".to_string(), Value::Enum("B".to_string())),
+                ])
+            })
+            .collect();
+
+        for codec in ["null", "deflate"] {
+            let data = write_container(&schema, codec, &records);
+            assert_eq!(read_container(&data).unwrap(), records);
+        }
+
+        let data = write_container(&schema, "snappy", &records);
+        assert!(matches!(
+            read_container(&data),
+            Err(AvroError::UnsupportedCodec(_))
+        ));
+        assert!(matches!(
+            read_container(b"Obj"),
+            Err(AvroError::InvalidFile(_))
+        ));
+    }
+}
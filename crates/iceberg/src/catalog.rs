@@ -0,0 +1,195 @@
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use tokio::runtime::Handle;
+
+use crate::{operations::MetadataCatalog, table::TableError};
+
+/// The catalog service's metadata pointers, reached over HTTP.
+///
+/// Like [`FileIoMetadata`](crate::io::FileIoMetadata), requests run on
+/// `runtime` and must not be made from one of its tasks.
+#[derive(Debug, Clone)]
+pub struct HttpCatalog {
+    base_url: String,
+    client: Client,
+    runtime: Handle,
+}
+
+impl HttpCatalog {
+    pub fn new(base_url: &str, runtime: Handle) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: Client::new(),
+            runtime,
+        }
+    }
+
+    async fn get_metadata_location(&self, identifier: &str) -> Result<Option<String>, TableError> {
+        let response = self
+            .client
+            .get(format!("{}/tables/{}", self.base_url, identifier))
+            .send()
+            .await
+            .map_err(io_error)?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(TableError::NotFound(identifier.to_string()));
+        }
+
+        let table: Value = response
+            .error_for_status()
+            .map_err(io_error)?
+            .json()
+            .await
+            .map_err(io_error)?;
+
+        Ok(table["metadata_location"].as_str().map(str::to_string))
+    }
+
+    async fn post_metadata_location(
+        &self,
+        identifier: &str,
+        expected: Option<&str>,
+        new: &str,
+    ) -> Result<(), TableError> {
+        let response = self
+            .client
+            .post(format!("{}/tables/{}/metadata", self.base_url, identifier))
+            .json(&json!({
+                "expected_metadata_location": expected,
+                "metadata_location": new,
+            }))
+            .send()
+            .await
+            .map_err(io_error)?;
+
+        match response.status() {
+            StatusCode::CONFLICT => {
+                let message = response.text().await.map_err(io_error)?;
+                Err(TableError::CommitFailed(message))
+            }
+            StatusCode::NOT_FOUND => Err(TableError::NotFound(identifier.to_string())),
+            _ => {
+                response.error_for_status().map_err(io_error)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl MetadataCatalog for HttpCatalog {
+    fn metadata_location(&self, identifier: &str) -> Result<Option<String>, TableError> {
+        self.runtime
+            .block_on(self.get_metadata_location(identifier))
+    }
+
+    fn swap_metadata_location(
+        &self,
+        identifier: &str,
+        expected: Option<&str>,
+        new: &str,
+    ) -> Result<(), TableError> {
+        self.runtime
+            .block_on(self.post_metadata_location(identifier, expected, new))
+    }
+}
+
+fn io_error(e: reqwest::Error) -> TableError {
+    TableError::Io(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{
+        extract::{Path, State},
+        http::StatusCode,
+        routing::{get, post},
+        Json, Router,
+    };
+
+    use super::*;
+
+    type Pointers = Arc<Mutex<HashMap<String, Option<String>>>>;
+
+    async fn get_table(
+        State(pointers): State<Pointers>,
+        Path(name): Path<String>,
+    ) -> Result<Json<Value>, StatusCode> {
+        let pointers = pointers.lock().unwrap();
+        let location = pointers.get(&name).ok_or(StatusCode::NOT_FOUND)?;
+        Ok(Json(json!({ "name": name, "metadata_location": location })))
+    }
+
+    async fn commit(
+        State(pointers): State<Pointers>,
+        Path(name): Path<String>,
+        Json(request): Json<Value>,
+    ) -> Result<StatusCode, (StatusCode, String)> {
+        let mut pointers = pointers.lock().unwrap();
+        let location = pointers
+            .get_mut(&name)
+            .ok_or((StatusCode::NOT_FOUND, String::new()))?;
+
+        if location.as_deref() != request["expected_metadata_location"].as_str() {
+            return Err((StatusCode::CONFLICT, format!("{} changed", name)));
+        }
+
+        *location = request["metadata_location"].as_str().map(str::to_string);
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// Serves the catalog's metadata pointer endpoints for `db.events`.
+    fn serve(runtime: &tokio::runtime::Runtime) -> String {
+        let pointers = Pointers::default();
+        pointers
+            .lock()
+            .unwrap()
+            .insert("db.events".to_string(), None);
+
+        let app = Router::new()
+            .route("/tables/:name", get(get_table))
+            .route("/tables/:name/metadata", post(commit))
+            .with_state(pointers);
+
+        let listener = runtime
+            .block_on(tokio::net::TcpListener::bind("127.0.0.1:0"))
+            .unwrap();
+        let address = listener.local_addr().unwrap();
+        runtime.spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        format!("http://{}", address)
+    }
+
+    #[test]
+    fn test_swap_metadata_location() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let catalog = HttpCatalog::new(&serve(&runtime), runtime.handle().clone());
+
+        assert_eq!(catalog.metadata_location("db.events").unwrap(), None);
+        catalog
+            .swap_metadata_location("db.events", None, "v1.metadata.json")
+            .unwrap();
+
+        // A second committer based on the same version loses
+        let result = catalog.swap_metadata_location("db.events", None, "v1-other.metadata.json");
+        assert!(matches!(result, Err(TableError::CommitFailed(_))));
+        assert_eq!(
+            catalog.metadata_location("db.events").unwrap().as_deref(),
+            Some("v1.metadata.json")
+        );
+
+        assert!(matches!(
+            catalog.metadata_location("db.missing"),
+            Err(TableError::NotFound(_))
+        ));
+        assert!(matches!(
+            catalog.swap_metadata_location("db.missing", None, "v1.metadata.json"),
+            Err(TableError::NotFound(_))
+        ));
+    }
+}
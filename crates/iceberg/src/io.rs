@@ -0,0 +1,91 @@
+use fileio::FileIO;
+use tokio::runtime::Handle;
+
+use crate::{
+    operations::MetadataIo,
+    parser,
+    table::{TableError, TableMetadata},
+};
+
+/// Metadata files read and written through [`FileIO`], wherever the table
+/// lives.
+///
+/// Table operations are blocking, so they run the I/O on `runtime`. They must
+/// not be called from one of its tasks, but e.g. from `spawn_blocking`.
+#[derive(Debug, Clone)]
+pub struct FileIoMetadata {
+    io: FileIO,
+    runtime: Handle,
+}
+
+impl FileIoMetadata {
+    pub fn new(io: FileIO, runtime: Handle) -> Self {
+        Self { io, runtime }
+    }
+}
+
+impl MetadataIo for FileIoMetadata {
+    fn read(&self, location: &str) -> Result<TableMetadata, TableError> {
+        let bytes = self
+            .runtime
+            .block_on(self.io.read(location))
+            .map_err(|e| TableError::Io(e.to_string()))?;
+
+        let json = std::str::from_utf8(&bytes)
+            .map_err(|e| TableError::Io(format!("{}: {}", location, e)))?;
+        parser::metadata::from_json(json)
+            .map_err(|e| TableError::Io(format!("{}: {}", location, e)))
+    }
+
+    fn write(&self, location: &str, metadata: &TableMetadata) -> Result<(), TableError> {
+        let json = parser::metadata::to_json(metadata);
+
+        self.runtime
+            .block_on(self.io.write(location, json.into()))
+            .map_err(|e| TableError::Io(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        schema::{NestedField, Schema},
+        snapshot::Snapshot,
+    };
+
+    #[test]
+    fn test_read_and_write_metadata() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let io = FileIoMetadata::new(FileIO::default(), runtime.handle().clone());
+
+        let mut metadata = TableMetadata::new(
+            "memory://warehouse/db/events",
+            HashMap::from([("owner".to_string(), "a".to_string())]),
+        );
+        metadata.last_column_id = 1;
+        metadata.schema = Schema::new(vec![NestedField {
+            id: 1,
+            name: "id".to_string(),
+            field_type: "integer".to_string(),
+            required: true,
+        }]);
+        metadata.current_snapshot_id = Some(3);
+        metadata.snapshots = vec![Snapshot::new(
+            3,
+            1723320520000,
+            vec!["memory://warehouse/db/events/metadata/m1.avro".to_string()],
+        )];
+
+        let location = "memory://warehouse/db/events/metadata/v1.metadata.json";
+        io.write(location, &metadata).unwrap();
+        assert_eq!(io.read(location).unwrap(), metadata);
+
+        assert!(matches!(
+            io.read("memory://warehouse/db/events/metadata/v2.metadata.json"),
+            Err(TableError::Io(_))
+        ));
+    }
+}
@@ -1,10 +1,16 @@
+pub mod avro;
+pub mod catalog;
+pub mod io;
+pub mod manifest;
 pub mod metadata;
+pub mod operations;
 pub mod parser;
 pub mod partition;
 pub mod rollback;
 pub mod scan;
 pub mod schema;
 pub mod snapshot;
+pub mod sort;
 pub mod table;
 pub mod types;
 pub mod updates;
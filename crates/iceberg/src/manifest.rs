@@ -0,0 +1,394 @@
+//! Manifest lists and manifests, the Avro files a snapshot's data files are
+//! listed in. Format v1 and v2 files are read alike, fields v1 lacks taking
+//! their v2 defaults.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::Value as Json;
+use thiserror::Error;
+
+use crate::avro::{self, AvroError, Value};
+
+/// Content of manifests and data files listing data rather than deletes.
+pub const DATA_CONTENT: i32 = 0;
+
+#[derive(Error, Debug)]
+pub enum ManifestError {
+    #[error(transparent)]
+    Avro(#[from] AvroError),
+    #[error("Missing required manifest field: {0}")]
+    MissingField(String),
+}
+
+/// Status of a manifest entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryStatus {
+    Existing,
+    Added,
+    Deleted,
+}
+
+/// A manifest, as the manifest list of a snapshot lists it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManifestFile {
+    pub manifest_path: String,
+    pub manifest_length: i64,
+    pub partition_spec_id: i32,
+    pub content: i32,
+    pub sequence_number: i64,
+    pub added_snapshot_id: i64,
+    pub added_files_count: i32,
+    pub existing_files_count: i32,
+    pub deleted_files_count: i32,
+    pub added_rows_count: i64,
+    pub existing_rows_count: i64,
+    pub deleted_rows_count: i64,
+}
+
+/// A data file of a manifest, and the snapshot that added or deleted it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub status: EntryStatus,
+    /// Snapshot that added or deleted the file, `None` if inherited from the
+    /// manifest.
+    pub snapshot_id: Option<i64>,
+    /// `None` if inherited from the manifest.
+    pub sequence_number: Option<i64>,
+    pub data_file: DataFile,
+}
+
+/// A data file with its column statistics, keyed by field id. Bounds are
+/// kept in Iceberg's single-value binary form.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DataFile {
+    pub content: i32,
+    pub file_path: String,
+    pub file_format: String,
+    /// Partition values by partition field name, as JSON.
+    pub partition: BTreeMap<String, Json>,
+    pub record_count: i64,
+    pub file_size_in_bytes: i64,
+    pub value_counts: HashMap<i32, i64>,
+    pub null_value_counts: HashMap<i32, i64>,
+    pub nan_value_counts: HashMap<i32, i64>,
+    pub lower_bounds: HashMap<i32, Vec<u8>>,
+    pub upper_bounds: HashMap<i32, Vec<u8>>,
+}
+
+impl ManifestFile {
+    /// Describes a manifest from its entries, for v1 snapshots that list
+    /// manifests rather than a manifest list.
+    pub fn from_entries(
+        manifest_path: &str,
+        manifest_length: i64,
+        snapshot_id: i64,
+        entries: &[ManifestEntry],
+    ) -> ManifestFile {
+        let count = |status: EntryStatus| {
+            let entries = entries.iter().filter(|entry| entry.status == status);
+            let rows = entries
+                .clone()
+                .map(|entry| entry.data_file.record_count)
+                .sum();
+            (entries.count() as i32, rows)
+        };
+        let (added_files_count, added_rows_count) = count(EntryStatus::Added);
+        let (existing_files_count, existing_rows_count) = count(EntryStatus::Existing);
+        let (deleted_files_count, deleted_rows_count) = count(EntryStatus::Deleted);
+
+        ManifestFile {
+            manifest_path: manifest_path.to_string(),
+            manifest_length,
+            partition_spec_id: 0,
+            content: DATA_CONTENT,
+            sequence_number: 0,
+            added_snapshot_id: snapshot_id,
+            added_files_count,
+            existing_files_count,
+            deleted_files_count,
+            added_rows_count,
+            existing_rows_count,
+            deleted_rows_count,
+        }
+    }
+}
+
+/// Reads the manifests a manifest list lists.
+pub fn read_manifest_list(data: &[u8]) -> Result<Vec<ManifestFile>, ManifestError> {
+    avro::read_container(data)?
+        .iter()
+        .map(|record| {
+            // v1 lists name the file counts after data files
+            let count = |v2: &str, v1: &str| {
+                optional_i64(record, v2)
+                    .or_else(|| optional_i64(record, v1))
+                    .unwrap_or_default() as i32
+            };
+
+            Ok(ManifestFile {
+                manifest_path: string(record, "manifest_path")?,
+                manifest_length: required_i64(record, "manifest_length")?,
+                partition_spec_id: required_i64(record, "partition_spec_id")? as i32,
+                content: optional_i64(record, "content").unwrap_or_default() as i32,
+                sequence_number: optional_i64(record, "sequence_number").unwrap_or_default(),
+                added_snapshot_id: required_i64(record, "added_snapshot_id")?,
+                added_files_count: count("added_files_count", "added_data_files_count"),
+                existing_files_count: count("existing_files_count", "existing_data_files_count"),
+                deleted_files_count: count("deleted_files_count", "deleted_data_files_count"),
+                added_rows_count: optional_i64(record, "added_rows_count").unwrap_or_default(),
+                existing_rows_count: optional_i64(record, "existing_rows_count")
+                    .unwrap_or_default(),
+                deleted_rows_count: optional_i64(record, "deleted_rows_count").unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Reads the entries of a manifest.
+pub fn read_manifest(data: &[u8]) -> Result<Vec<ManifestEntry>, ManifestError> {
+    avro::read_container(data)?
+        .iter()
+        .map(|record| {
+            let status = match required_i64(record, "status")? {
+                0 => EntryStatus::Existing,
+                1 => EntryStatus::Added,
+                _ => EntryStatus::Deleted,
+            };
+            let file = record
+                .field("data_file")
+                .ok_or_else(|| ManifestError::MissingField("data_file".to_string()))?;
+
+            Ok(ManifestEntry {
+                status,
+                snapshot_id: optional_i64(record, "snapshot_id"),
+                sequence_number: optional_i64(record, "sequence_number"),
+                data_file: DataFile {
+                    content: optional_i64(file, "content").unwrap_or_default() as i32,
+                    file_path: string(file, "file_path")?,
+                    file_format: string(file, "file_format")?,
+                    partition: partition(file.field("partition")),
+                    record_count: required_i64(file, "record_count")?,
+                    file_size_in_bytes: required_i64(file, "file_size_in_bytes")?,
+                    value_counts: id_map(file, "value_counts", Value::as_i64),
+                    null_value_counts: id_map(file, "null_value_counts", Value::as_i64),
+                    nan_value_counts: id_map(file, "nan_value_counts", Value::as_i64),
+                    lower_bounds: id_map(file, "lower_bounds", |v| {
+                        v.as_bytes().map(<[u8]>::to_vec)
+                    }),
+                    upper_bounds: id_map(file, "upper_bounds", |v| {
+                        v.as_bytes().map(<[u8]>::to_vec)
+                    }),
+                },
+            })
+        })
+        .collect()
+}
+
+fn optional_i64(record: &Value, field: &str) -> Option<i64> {
+    record.field(field).and_then(Value::as_i64)
+}
+
+fn required_i64(record: &Value, field: &str) -> Result<i64, ManifestError> {
+    optional_i64(record, field).ok_or_else(|| ManifestError::MissingField(field.to_string()))
+}
+
+fn string(record: &Value, field: &str) -> Result<String, ManifestError> {
+    record
+        .field(field)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| ManifestError::MissingField(field.to_string()))
+}
+
+/// Maps keyed by field id, which are written as arrays of key-value records.
+fn id_map<T>(record: &Value, field: &str, value: impl Fn(&Value) -> Option<T>) -> HashMap<i32, T> {
+    let Some(Value::Array(entries)) = record.field(field) else {
+        return HashMap::new();
+    };
+
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let key = entry.field("key")?.as_i64()? as i32;
+            Some((key, value(entry.field("value")?)?))
+        })
+        .collect()
+}
+
+fn partition(record: Option<&Value>) -> BTreeMap<String, Json> {
+    let Some(Value::Record(fields)) = record else {
+        return BTreeMap::new();
+    };
+
+    fields
+        .iter()
+        .map(|(name, value)| (name.clone(), to_json(value)))
+        .collect()
+}
+
+/// Partition values as JSON, binary values as hex.
+fn to_json(value: &Value) -> Json {
+    match value {
+        Value::Null => Json::Null,
+        Value::Boolean(b) => Json::from(*b),
+        Value::Int(n) => Json::from(*n),
+        Value::Long(n) => Json::from(*n),
+        Value::Float(n) => Json::from(*n),
+        Value::Double(n) => Json::from(*n),
+        Value::String(s) | Value::Enum(s) => Json::from(s.as_str()),
+        Value::Bytes(bytes) | Value::Fixed(bytes) => Json::from(
+            bytes
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>(),
+        ),
+        Value::Record(fields) | Value::Map(fields) => Json::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), to_json(value)))
+                .collect(),
+        ),
+        Value::Array(values) => Json::Array(values.iter().map(to_json).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::avro::tests::write_container;
+
+    fn record(fields: Vec<(&str, Value)>) -> Value {
+        Value::Record(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_read_manifest_list_and_manifest() {
+        // A v1 manifest list, without content or sequence numbers
+        let schema = json!({
+            "type": "record",
+            "name": "manifest_file",
+            "fields": [
+                {"name": "manifest_path", "type": "string"},
+                {"name": "manifest_length", "type": "long"},
+                {"name": "partition_spec_id", "type": "int"},
+                {"name": "added_snapshot_id", "type": ["null", "long"]},
+                {"name": "added_data_files_count", "type": ["null", "int"]},
+                {"name": "added_rows_count", "type": ["null", "long"]}
+            ]
+        });
+        let list = write_container(
+            &schema,
+            "deflate",
+            &[record(vec![
+                (
+                    "manifest_path",
+                    Value::String("s3://b/t/metadata/m1.avro".to_string()),
+                ),
+                ("manifest_length", Value::Long(4096)),
+                ("partition_spec_id", Value::Int(0)),
+                ("added_snapshot_id", Value::Long(3055729675574597004)),
+                ("added_data_files_count", Value::Int(2)),
+                ("added_rows_count", Value::Long(10)),
+            ])],
+        );
+        let manifests = read_manifest_list(&list).unwrap();
+        assert_eq!(manifests.len(), 1);
+        assert_eq!(manifests[0].manifest_path, "s3://b/t/metadata/m1.avro");
+        assert_eq!(manifests[0].added_snapshot_id, 3055729675574597004);
+        assert_eq!(manifests[0].added_files_count, 2);
+        assert_eq!(manifests[0].content, DATA_CONTENT);
+
+        let schema = json!({
+            "type": "record",
+            "name": "manifest_entry",
+            "fields": [
+                {"name": "status", "type": "int"},
+                {"name": "snapshot_id", "type": ["null", "long"]},
+                {"name": "sequence_number", "type": ["null", "long"]},
+                {"name": "data_file", "type": {
+                    "type": "record",
+                    "name": "r2",
+                    "fields": [
+                        {"name": "file_path", "type": "string"},
+                        {"name": "file_format", "type": "string"},
+                        {"name": "partition", "type": {
+                            "type": "record",
+                            "name": "r102",
+                            "fields": [{"name": "day", "type": ["null", {"type": "int", "logicalType": "date"}]}]
+                        }},
+                        {"name": "record_count", "type": "long"},
+                        {"name": "file_size_in_bytes", "type": "long"},
+                        {"name": "value_counts", "type": ["null", {"type": "array", "logicalType": "map", "items": {
+                            "type": "record",
+                            "name": "k117_v118",
+                            "fields": [{"name": "key", "type": "int"}, {"name": "value", "type": "long"}]
+                        }}]},
+                        {"name": "lower_bounds", "type": ["null", {"type": "array", "logicalType": "map", "items": {
+                            "type": "record",
+                            "name": "k126_v127",
+                            "fields": [{"name": "key", "type": "int"}, {"name": "value", "type": "bytes"}]
+                        }}]}
+                    ]
+                }}
+            ]
+        });
+        let entry = |status: i32, path: &str| {
+            record(vec![
+                ("status", Value::Int(status)),
+                ("snapshot_id", Value::Null),
+                ("sequence_number", Value::Null),
+                (
+                    "data_file",
+                    record(vec![
+                        ("file_path", Value::String(path.to_string())),
+                        ("file_format", Value::String("PARQUET".to_string())),
+                        ("partition", record(vec![("day", Value::Int(19723))])),
+                        ("record_count", Value::Long(5)),
+                        ("file_size_in_bytes", Value::Long(1024)),
+                        (
+                            "value_counts",
+                            Value::Array(vec![record(vec![
+                                ("key", Value::Int(1)),
+                                ("value", Value::Long(5)),
+                            ])]),
+                        ),
+                        (
+                            "lower_bounds",
+                            Value::Array(vec![record(vec![
+                                ("key", Value::Int(1)),
+                                ("value", Value::Bytes(7i64.to_le_bytes().to_vec())),
+                            ])]),
+                        ),
+                    ]),
+                ),
+            ])
+        };
+        let manifest = write_container(
+            &schema,
+            "null",
+            &[entry(1, "a.parquet"), entry(2, "b.parquet")],
+        );
+
+        let entries = read_manifest(&manifest).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].status, EntryStatus::Added);
+        assert_eq!(entries[0].snapshot_id, None);
+        let file = &entries[0].data_file;
+        assert_eq!(file.file_path, "a.parquet");
+        assert_eq!(file.partition["day"], json!(19723));
+        assert_eq!(file.value_counts[&1], 5);
+        assert_eq!(file.lower_bounds[&1], 7i64.to_le_bytes());
+        assert!(file.null_value_counts.is_empty());
+
+        let summary = ManifestFile::from_entries("m.avro", 10, 1, &entries);
+        assert_eq!(summary.added_files_count, 1);
+        assert_eq!(summary.deleted_rows_count, 5);
+    }
+}
@@ -1,15 +1,70 @@
 use std::collections::HashMap;
 
-use crate::{partition::PartitionSpec, schema::Schema, snapshot::Snapshot};
+use serde_json::{Map, Value};
 
-#[derive(Debug)]
+use crate::{
+    partition::PartitionSpec,
+    schema::Schema,
+    snapshot::Snapshot,
+    sort::{order::UNSORTED_ORDER_ID, SortOrder},
+};
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct TableMetadata {
+    /// 1 or 2, written back as read.
+    pub format_version: u32,
+    /// Base location of the table's data and metadata files.
     pub location: String,
+    /// Metadata file this version was read from, `None` if never committed.
+    /// Not part of the file itself.
+    pub metadata_location: Option<String>,
     pub last_updated_millis: u64,
     pub last_column_id: u32,
-    pub current_snapshot_id: u32,
+    /// `None` if the table has no snapshots.
+    pub current_snapshot_id: Option<u64>,
     pub schema: Schema,
     pub partition_spec: PartitionSpec,
+    pub sort_orders: Vec<SortOrder>,
+    pub default_sort_order_id: u32,
     pub properties: HashMap<String, String>,
-    pub snapshots: Vec<Snapshot>
+    pub snapshots: Vec<Snapshot>,
+    /// Fields of the metadata file not modeled above, such as v2's
+    /// `table-uuid`, `schemas` and `refs`, written back as they were read.
+    pub other: Map<String, Value>
+}
+
+impl TableMetadata {
+    /// Metadata of a new table without columns, partitions or snapshots.
+    pub fn new(location: &str, properties: HashMap<String, String>) -> Self {
+        Self {
+            format_version: 1,
+            location: location.to_string(),
+            metadata_location: None,
+            last_updated_millis: 0,
+            last_column_id: 0,
+            current_snapshot_id: None,
+            schema: Schema::new(Vec::new()),
+            partition_spec: PartitionSpec::new(Vec::new()),
+            sort_orders: vec![SortOrder::unsorted()],
+            default_sort_order_id: UNSORTED_ORDER_ID,
+            properties,
+            snapshots: Vec::new(),
+            other: Map::new(),
+        }
+    }
+
+    pub fn replace_properties(&self, properties: HashMap<String, String>) -> TableMetadata {
+        TableMetadata {
+            properties,
+            ..self.clone()
+        }
+    }
+
+    /// Order new data files are sorted in, `None` if the table's default
+    /// order isn't among its orders.
+    pub fn default_sort_order(&self) -> Option<&SortOrder> {
+        self.sort_orders
+            .iter()
+            .find(|order| order.order_id() == self.default_sort_order_id)
+    }
 }
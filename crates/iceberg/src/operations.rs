@@ -0,0 +1,229 @@
+use std::{cell::RefCell, fmt::Debug};
+
+use crate::table::{TableError, TableMetadata, TableOperations};
+
+/// Catalog keeping the pointer to each table's current metadata file.
+pub trait MetadataCatalog: Debug {
+    /// Location of the table's current metadata file, `None` if never committed.
+    fn metadata_location(&self, identifier: &str) -> Result<Option<String>, TableError>;
+
+    /// Points the table at `new` if it still points at `expected`, failing with
+    /// [`TableError::CommitFailed`] otherwise.
+    fn swap_metadata_location(
+        &self,
+        identifier: &str,
+        expected: Option<&str>,
+        new: &str,
+    ) -> Result<(), TableError>;
+}
+
+/// Reads and writes metadata files.
+pub trait MetadataIo: Debug {
+    fn read(&self, location: &str) -> Result<TableMetadata, TableError>;
+    fn write(&self, location: &str, metadata: &TableMetadata) -> Result<(), TableError>;
+}
+
+/// Table operations backed by a catalog's atomic metadata pointer swap.
+///
+/// Every commit writes a new metadata file, then swaps the catalog pointer from
+/// the base's file to it. Metadata files are never overwritten, so a commit
+/// that loses the swap leaves the winner's metadata intact.
+#[derive(Debug)]
+pub struct CatalogTableOperations<C, I> {
+    identifier: String,
+    catalog: C,
+    io: I,
+    current: RefCell<TableMetadata>,
+}
+
+impl<C: MetadataCatalog, I: MetadataIo> CatalogTableOperations<C, I> {
+    /// Loads the table's current metadata.
+    pub fn load(identifier: &str, catalog: C, io: I) -> Result<Self, TableError> {
+        let current = Self::read_current(identifier, &catalog, &io)?;
+
+        Ok(Self {
+            identifier: identifier.to_string(),
+            catalog,
+            io,
+            current: RefCell::new(current),
+        })
+    }
+
+    fn read_current(identifier: &str, catalog: &C, io: &I) -> Result<TableMetadata, TableError> {
+        let location = catalog
+            .metadata_location(identifier)?
+            .ok_or_else(|| TableError::NotFound(identifier.to_string()))?;
+
+        let mut metadata = io.read(&location)?;
+        metadata.metadata_location = Some(location);
+        Ok(metadata)
+    }
+
+    fn new_metadata_location(metadata: &TableMetadata) -> String {
+        format!(
+            "{}/metadata/{}.metadata.json",
+            metadata.location.trim_end_matches('/'),
+            uuid::Uuid::new_v4()
+        )
+    }
+}
+
+impl<C: MetadataCatalog, I: MetadataIo> TableOperations for CatalogTableOperations<C, I> {
+    fn current(&self) -> TableMetadata {
+        self.current.borrow().clone()
+    }
+
+    fn refresh(&self) -> Result<TableMetadata, TableError> {
+        let metadata = Self::read_current(&self.identifier, &self.catalog, &self.io)?;
+        *self.current.borrow_mut() = metadata.clone();
+        Ok(metadata)
+    }
+
+    fn commit(&self, base: &TableMetadata, updated: &TableMetadata) -> Result<(), TableError> {
+        let location = Self::new_metadata_location(updated);
+        self.io.write(&location, updated)?;
+
+        self.catalog.swap_metadata_location(
+            &self.identifier,
+            base.metadata_location.as_deref(),
+            &location,
+        )?;
+
+        *self.current.borrow_mut() = TableMetadata {
+            metadata_location: Some(location),
+            ..updated.clone()
+        };
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+    use super::*;
+    use crate::updates::{PendingUpdate, UpdateProperties};
+
+    /// Catalog and metadata files kept in memory, shared between clones to
+    /// simulate concurrent committers.
+    #[derive(Debug, Clone, Default)]
+    struct MemoryCatalog {
+        pointers: Rc<RefCell<HashMap<String, String>>>,
+        files: Rc<RefCell<HashMap<String, TableMetadata>>>,
+    }
+
+    impl MemoryCatalog {
+        fn create(&self, identifier: &str, metadata: TableMetadata) {
+            let location = format!("{}/metadata/initial.metadata.json", metadata.location);
+            self.files.borrow_mut().insert(location.clone(), metadata);
+            self.pointers
+                .borrow_mut()
+                .insert(identifier.to_string(), location);
+        }
+    }
+
+    impl MetadataCatalog for MemoryCatalog {
+        fn metadata_location(&self, identifier: &str) -> Result<Option<String>, TableError> {
+            Ok(self.pointers.borrow().get(identifier).cloned())
+        }
+
+        fn swap_metadata_location(
+            &self,
+            identifier: &str,
+            expected: Option<&str>,
+            new: &str,
+        ) -> Result<(), TableError> {
+            let mut pointers = self.pointers.borrow_mut();
+
+            if pointers.get(identifier).map(String::as_str) != expected {
+                return Err(TableError::CommitFailed(format!(
+                    "{} no longer points at {:?}",
+                    identifier, expected
+                )));
+            }
+
+            pointers.insert(identifier.to_string(), new.to_string());
+            Ok(())
+        }
+    }
+
+    impl MetadataIo for MemoryCatalog {
+        fn read(&self, location: &str) -> Result<TableMetadata, TableError> {
+            self.files
+                .borrow()
+                .get(location)
+                .cloned()
+                .ok_or_else(|| TableError::Io(format!("No such file: {}", location)))
+        }
+
+        fn write(&self, location: &str, metadata: &TableMetadata) -> Result<(), TableError> {
+            self.files
+                .borrow_mut()
+                .insert(location.to_string(), metadata.clone());
+            Ok(())
+        }
+    }
+
+    fn load(catalog: &MemoryCatalog) -> CatalogTableOperations<MemoryCatalog, MemoryCatalog> {
+        CatalogTableOperations::load("db.events", catalog.clone(), catalog.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_concurrent_commits() {
+        let catalog = MemoryCatalog::default();
+        catalog.create(
+            "db.events",
+            TableMetadata::new("/warehouse/db/events", HashMap::new()),
+        );
+
+        let first = load(&catalog);
+        let second = load(&catalog);
+
+        let base = first.current();
+        let updated =
+            base.replace_properties(HashMap::from([("owner".to_string(), "a".to_string())]));
+        first.commit(&base, &updated).unwrap();
+
+        // The second committer still holds the metadata the first one replaced
+        let base = second.current();
+        let updated =
+            base.replace_properties(HashMap::from([("owner".to_string(), "b".to_string())]));
+        let result = second.commit(&base, &updated);
+        assert!(matches!(result, Err(TableError::CommitFailed(_))));
+
+        let refreshed = second.refresh().unwrap();
+        assert_eq!(refreshed.properties["owner"], "a");
+        assert_eq!(refreshed, first.current());
+
+        assert!(matches!(
+            CatalogTableOperations::load("db.missing", catalog.clone(), catalog),
+            Err(TableError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_update_properties_keeps_concurrent_changes() {
+        let catalog = MemoryCatalog::default();
+        catalog.create(
+            "db.events",
+            TableMetadata::new("/warehouse/db/events", HashMap::new()),
+        );
+
+        let mut update = UpdateProperties::new(Box::new(load(&catalog)));
+        update.set("retention", "30d");
+
+        // Committed after the update was started, but before it commits
+        let other = load(&catalog);
+        let base = other.current();
+        let updated =
+            base.replace_properties(HashMap::from([("owner".to_string(), "a".to_string())]));
+        other.commit(&base, &updated).unwrap();
+
+        update.commit().unwrap();
+
+        let current = load(&catalog).current();
+        assert_eq!(current.properties["owner"], "a");
+        assert_eq!(current.properties["retention"], "30d");
+    }
+}
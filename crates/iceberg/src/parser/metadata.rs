@@ -1,24 +1,71 @@
 use std::collections::HashMap;
 
-use serde_json::Value;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
 
 use crate::{
-    metadata::TableMetadata, partition::PartitionSpec, schema::Schema, snapshot::Snapshot,
+    metadata::TableMetadata,
+    partition::PartitionSpec,
+    schema::Schema,
+    snapshot::Snapshot,
+    sort::{order::UNSORTED_ORDER_ID, SortOrder},
 };
 
-use super::{partition_spec, schema, snapshot, util, ParserError};
+use super::{partition_spec, schema, snapshot, sort_order, util, ParserError};
 
 static TABLE_FORMAT_VERSION: u32 = 1;
-
-static FORMAT_VERSION: &'static str = "format-version";
-static LOCATION: &'static str = "location";
-static LAST_UPDATED_MILLIS: &'static str = "last-updated-ms";
-static LAST_COLUMN_ID: &'static str = "last-column-id";
-static SCHEMA: &'static str = "schema";
-static PARTITION_SPEC: &'static str = "partition-spec";
-static PROPERTIES: &'static str = "properties";
-static CURRENT_SNAPSHOT_ID: &'static str = "current-snapshot-id";
-static SNAPSHOTS: &'static str = "snapshots";
+/// v2 metadata, as Spark and REST catalogs write it, is read too.
+static MAX_FORMAT_VERSION: u32 = 2;
+
+static FORMAT_VERSION: &str = "format-version";
+static LOCATION: &str = "location";
+static LAST_UPDATED_MILLIS: &str = "last-updated-ms";
+static LAST_COLUMN_ID: &str = "last-column-id";
+static SCHEMA: &str = "schema";
+static SCHEMAS: &str = "schemas";
+static CURRENT_SCHEMA_ID: &str = "current-schema-id";
+static SCHEMA_ID: &str = "schema-id";
+static PARTITION_SPEC: &str = "partition-spec";
+static PARTITION_SPECS: &str = "partition-specs";
+static DEFAULT_SPEC_ID: &str = "default-spec-id";
+static SPEC_ID: &str = "spec-id";
+static FIELDS: &str = "fields";
+static PROPERTIES: &str = "properties";
+static CURRENT_SNAPSHOT_ID: &str = "current-snapshot-id";
+static SNAPSHOTS: &str = "snapshots";
+static SORT_ORDERS: &str = "sort-orders";
+static DEFAULT_SORT_ORDER_ID: &str = "default-sort-order-id";
+static TABLE_UUID: &str = "table-uuid";
+static LAST_SEQUENCE_NUMBER: &str = "last-sequence-number";
+static LAST_PARTITION_ID: &str = "last-partition-id";
+static FIELD_ID: &str = "field-id";
+static SOURCE_ID: &str = "source-id";
+static TRANSFORM: &str = "transform";
+static NAME: &str = "name";
+static TYPE: &str = "type";
+static REFS: &str = "refs";
+static MAIN_BRANCH: &str = "main";
+static SNAPSHOT_LOG: &str = "snapshot-log";
+static SNAPSHOT_ID: &str = "snapshot-id";
+static TIMESTAMP_MS: &str = "timestamp-ms";
+
+/// Fields [`TableMetadata`] models. Any others are kept as they were read.
+static MODELED_FIELDS: [&str; 11] = [
+    FORMAT_VERSION,
+    LOCATION,
+    LAST_UPDATED_MILLIS,
+    LAST_COLUMN_ID,
+    CURRENT_SNAPSHOT_ID,
+    SCHEMA,
+    PARTITION_SPEC,
+    SORT_ORDERS,
+    DEFAULT_SORT_ORDER_ID,
+    PROPERTIES,
+    SNAPSHOTS,
+];
+
+/// Partition field ids start after those of data columns, as in Java.
+static PARTITION_DATA_ID_START: u64 = 1000;
 
 pub fn from_json(json: &str) -> Result<TableMetadata, ParserError> {
     let value: Value = serde_json::from_str(json)?;
@@ -28,47 +75,352 @@ pub fn from_json(json: &str) -> Result<TableMetadata, ParserError> {
 pub fn from_json_value(value: &Value) -> Result<TableMetadata, ParserError> {
     let format_version = util::get_u32!(&value, FORMAT_VERSION)?;
 
-    if format_version != TABLE_FORMAT_VERSION {
+    if format_version < TABLE_FORMAT_VERSION || format_version > MAX_FORMAT_VERSION {
         return Err(ParserError::UnsupportedFormatVersion(format_version));
     }
 
     let location = util::get_string!(&value, LOCATION)?;
     let last_column_id = util::get_u32!(&value, LAST_COLUMN_ID)?;
-    let current_snapshot_id = util::get_u32!(&value, CURRENT_SNAPSHOT_ID)?;
+    let current_snapshot_id = get_current_snapshot_id(value)?;
     let last_updated_millis = util::get_u64!(&value, LAST_UPDATED_MILLIS)?;
 
+    let other = value
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(field, _)| !MODELED_FIELDS.contains(&field.as_str()))
+        .map(|(field, value)| (field.clone(), value.clone()))
+        .collect();
+
     Ok(TableMetadata {
+        format_version,
         location,
+        metadata_location: None,
         last_column_id,
         current_snapshot_id,
         last_updated_millis,
         schema: get_schema(value)?,
         partition_spec: get_partition_spec(value)?,
+        sort_orders: get_sort_orders(value)?,
+        default_sort_order_id: get_default_sort_order_id(value)?,
         properties: get_properties(value)?,
         snapshots: get_snapshots(value)?,
+        other,
     })
 }
 
+pub fn to_json(metadata: &TableMetadata) -> String {
+    to_json_value(metadata).to_string()
+}
+
+/// Writes metadata in the format version it was read in, with the fields
+/// the model doesn't interpret as they were read, so metadata survives a
+/// round trip. v2 schemas, partition specs, refs and snapshot log are kept
+/// in step with the modeled schema, spec and current snapshot.
+pub fn to_json_value(metadata: &TableMetadata) -> Value {
+    let sort_orders: Vec<Value> = metadata
+        .sort_orders
+        .iter()
+        .map(sort_order::to_json_value)
+        .collect();
+    let snapshots: Vec<Value> = metadata
+        .snapshots
+        .iter()
+        .map(snapshot::to_json_value)
+        .collect();
+
+    let mut object = metadata.other.clone();
+    let fields = json!({
+        FORMAT_VERSION: metadata.format_version,
+        LOCATION: metadata.location,
+        LAST_UPDATED_MILLIS: metadata.last_updated_millis,
+        LAST_COLUMN_ID: metadata.last_column_id,
+        SORT_ORDERS: sort_orders,
+        DEFAULT_SORT_ORDER_ID: metadata.default_sort_order_id,
+        PROPERTIES: metadata.properties,
+        SNAPSHOTS: snapshots,
+    });
+    object.extend(fields.as_object().unwrap().clone());
+
+    let v1 = metadata.format_version == TABLE_FORMAT_VERSION;
+    match metadata.current_snapshot_id {
+        Some(id) => {
+            object.insert(CURRENT_SNAPSHOT_ID.to_string(), json!(id));
+        }
+        // -1 marks a table without snapshots in v1, v2 leaves the field out
+        None if v1 => {
+            object.insert(CURRENT_SNAPSHOT_ID.to_string(), json!(-1));
+        }
+        None => {}
+    }
+
+    if v1 {
+        object.insert(SCHEMA.to_string(), schema::to_json_value(&metadata.schema));
+        object.insert(
+            PARTITION_SPEC.to_string(),
+            partition_spec::to_json_value(&metadata.partition_spec),
+        );
+    }
+
+    // v1 metadata of newer writers has the v2 fields too
+    if !v1 || object.contains_key(SCHEMAS) {
+        put_schema(&mut object, &metadata.schema);
+    }
+    if !v1 || object.contains_key(PARTITION_SPECS) {
+        put_partition_spec(&mut object, &metadata.partition_spec);
+    }
+    if !v1 || object.contains_key(REFS) {
+        put_main_branch(&mut object, metadata.current_snapshot_id);
+    }
+    if !v1 || object.contains_key(SNAPSHOT_LOG) {
+        put_snapshot_log(&mut object, metadata);
+    }
+
+    if !v1 {
+        object
+            .entry(TABLE_UUID)
+            .or_insert_with(|| json!(Uuid::new_v4().to_string()));
+
+        let last_sequence_number = metadata
+            .snapshots
+            .iter()
+            .map(Snapshot::sequence_number)
+            .chain(object.get(LAST_SEQUENCE_NUMBER).and_then(Value::as_u64))
+            .max()
+            .unwrap_or_default();
+        object.insert(
+            LAST_SEQUENCE_NUMBER.to_string(),
+            json!(last_sequence_number),
+        );
+    }
+
+    Value::Object(object)
+}
+
+/// Makes the schema the current one of `schemas`, adding it under a new id
+/// if it isn't the current one already.
+fn put_schema(object: &mut Map<String, Value>, schema: &Schema) {
+    let current_schema_id = object.get(CURRENT_SCHEMA_ID).and_then(Value::as_u64);
+    let schemas = array_mut(object, SCHEMAS);
+
+    let current = schemas
+        .iter()
+        .find(|item| item.get(SCHEMA_ID).and_then(Value::as_u64) == current_schema_id);
+    if current
+        .and_then(|item| schema::from_json_value(item).ok())
+        .as_ref()
+        == Some(schema)
+    {
+        return;
+    }
+
+    let schema_id = next_id(schemas, SCHEMA_ID);
+    let mut value = schema::to_json_value(schema);
+    value[TYPE] = json!("struct");
+    value[SCHEMA_ID] = json!(schema_id);
+    schemas.push(value);
+
+    object.insert(CURRENT_SCHEMA_ID.to_string(), json!(schema_id));
+}
+
+/// Makes the spec the default of `partition-specs`, adding it under a new id
+/// if it isn't the default already. Fields keep the ids they have in earlier
+/// specs, new ones are numbered on from `last-partition-id`.
+fn put_partition_spec(object: &mut Map<String, Value>, spec: &PartitionSpec) {
+    let default_spec_id = object.get(DEFAULT_SPEC_ID).and_then(Value::as_u64);
+    let mut last_partition_id = object
+        .get(LAST_PARTITION_ID)
+        .and_then(Value::as_u64)
+        .unwrap_or(PARTITION_DATA_ID_START - 1);
+    let specs = array_mut(object, PARTITION_SPECS);
+
+    let default = specs
+        .iter()
+        .find(|item| item.get(SPEC_ID).and_then(Value::as_u64) == default_spec_id);
+    let default_fields = default.and_then(|item| item.get(FIELDS));
+    if default_fields
+        .and_then(|fields| partition_spec::from_json_value(fields).ok())
+        .as_ref()
+        == Some(spec)
+    {
+        return;
+    }
+
+    let earlier_fields: Vec<Value> = specs
+        .iter()
+        .filter_map(|item| item.get(FIELDS)?.as_array())
+        .flatten()
+        .cloned()
+        .collect();
+    let same_field = |earlier: &Value, field: &Value| {
+        [SOURCE_ID, TRANSFORM, NAME]
+            .iter()
+            .all(|key| earlier.get(key) == field.get(key))
+    };
+
+    let mut fields = partition_spec::to_json_value(spec);
+    for field in fields.as_array_mut().unwrap() {
+        let field_id = earlier_fields
+            .iter()
+            .find(|earlier| same_field(earlier, field))
+            .and_then(|earlier| earlier.get(FIELD_ID)?.as_u64())
+            .unwrap_or_else(|| {
+                last_partition_id += 1;
+                last_partition_id
+            });
+        field[FIELD_ID] = json!(field_id);
+    }
+
+    let spec_id = next_id(specs, SPEC_ID);
+    specs.push(json!({ SPEC_ID: spec_id, FIELDS: fields }));
+
+    object.insert(DEFAULT_SPEC_ID.to_string(), json!(spec_id));
+    object.insert(LAST_PARTITION_ID.to_string(), json!(last_partition_id));
+}
+
+/// Points the `main` branch at the current snapshot, which v2 readers take
+/// as the current one as much as `current-snapshot-id`.
+fn put_main_branch(object: &mut Map<String, Value>, current_snapshot_id: Option<u64>) {
+    let refs = object.entry(REFS).or_insert_with(|| json!({}));
+    if !refs.is_object() {
+        *refs = json!({});
+    }
+    let refs = refs.as_object_mut().unwrap();
+
+    match current_snapshot_id {
+        Some(snapshot_id) => {
+            let main = refs
+                .entry(MAIN_BRANCH)
+                .or_insert_with(|| json!({ TYPE: "branch" }));
+            main[SNAPSHOT_ID] = json!(snapshot_id);
+        }
+        None => {
+            refs.remove(MAIN_BRANCH);
+        }
+    }
+}
+
+/// Logs the current snapshot becoming current, unless it's the last logged.
+fn put_snapshot_log(object: &mut Map<String, Value>, metadata: &TableMetadata) {
+    let log = array_mut(object, SNAPSHOT_LOG);
+    let Some(snapshot_id) = metadata.current_snapshot_id else {
+        return;
+    };
+
+    let last = log
+        .last()
+        .and_then(|entry| entry.get(SNAPSHOT_ID)?.as_u64());
+    if last != Some(snapshot_id) {
+        log.push(json!({
+            SNAPSHOT_ID: snapshot_id,
+            TIMESTAMP_MS: metadata.last_updated_millis,
+        }));
+    }
+}
+
+/// The array under a key, replacing anything else there.
+fn array_mut<'a>(object: &'a mut Map<String, Value>, key: &str) -> &'a mut Vec<Value> {
+    let value = object.entry(key).or_insert_with(|| json!([]));
+    if !value.is_array() {
+        *value = json!([]);
+    }
+    value.as_array_mut().unwrap()
+}
+
+/// An id after those of the items of a list.
+fn next_id(items: &[Value], id_field: &str) -> u64 {
+    items
+        .iter()
+        .filter_map(|item| item.get(id_field)?.as_u64())
+        .max()
+        .map_or(0, |id| id + 1)
+}
+
+/// The current snapshot, `None` if the table has none, which v1 metadata
+/// writes as -1 and v2 metadata may leave out.
+fn get_current_snapshot_id(value: &Value) -> Result<Option<u64>, ParserError> {
+    match value.get(CURRENT_SNAPSHOT_ID) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(id)) if id.as_i64() == Some(-1) => Ok(None),
+        Some(Value::Number(id)) => id.as_u64().map(Some).ok_or_else(|| {
+            ParserError::InvalidFieldType(format!("Invalid {}: {}", CURRENT_SNAPSHOT_ID, id))
+        }),
+        Some(_) => Err(ParserError::InvalidFieldType(format!(
+            "{} must be a number",
+            CURRENT_SNAPSHOT_ID
+        ))),
+    }
+}
+
+/// The v1 `schema`, or the current one of the v2 `schemas`.
 fn get_schema(value: &Value) -> Result<Schema, ParserError> {
-    let value = value
-        .get(SCHEMA)
-        .ok_or_else(|| ParserError::MissingRequiredField(SCHEMA.to_owned()))?;
+    if let Some(schema) = value.get(SCHEMA) {
+        return schema::from_json_value(schema);
+    }
 
-    schema::from_json_value(&value)
+    let current_schema_id = util::get_u32!(value, CURRENT_SCHEMA_ID)?;
+    let schema = find_by_id(value, SCHEMAS, SCHEMA_ID, current_schema_id)?;
+    schema::from_json_value(schema)
 }
 
+/// The v1 `partition-spec`, or the fields of the default v2 spec.
 fn get_partition_spec(value: &Value) -> Result<PartitionSpec, ParserError> {
-    let value = value
-        .get(PARTITION_SPEC)
-        .ok_or_else(|| ParserError::MissingRequiredField(PARTITION_SPEC.to_owned()))?;
+    if let Some(spec) = value.get(PARTITION_SPEC) {
+        return partition_spec::from_json_value(spec);
+    }
+
+    let default_spec_id = util::get_u32!(value, DEFAULT_SPEC_ID)?;
+    let spec = find_by_id(value, PARTITION_SPECS, SPEC_ID, default_spec_id)?;
+    let fields = spec.get(FIELDS).ok_or_else(|| {
+        ParserError::MissingRequiredField(format!("{}.{}", PARTITION_SPECS, FIELDS))
+    })?;
+    partition_spec::from_json_value(fields)
+}
+
+/// The item of a list, such as `schemas`, with the given id.
+fn find_by_id<'a>(
+    value: &'a Value,
+    list: &str,
+    id_field: &str,
+    id: u32,
+) -> Result<&'a Value, ParserError> {
+    let items = value
+        .get(list)
+        .and_then(Value::as_array)
+        .ok_or_else(|| ParserError::MissingRequiredField(list.to_owned()))?;
+
+    items
+        .iter()
+        .find(|item| item.get(id_field).and_then(Value::as_u64) == Some(id as u64))
+        .ok_or_else(|| {
+            ParserError::InvalidFieldType(format!("No {} with {} {}", list, id_field, id))
+        })
+}
+
+/// Sort orders are optional in v1 metadata, tables without any are unsorted.
+fn get_sort_orders(value: &Value) -> Result<Vec<SortOrder>, ParserError> {
+    let Some(value) = value.get(SORT_ORDERS) else {
+        return Ok(vec![SortOrder::unsorted()]);
+    };
 
-    partition_spec::from_json_value(&value)
+    let value = value.as_array().ok_or_else(|| {
+        ParserError::InvalidFieldType(format!("{} must be an array", SORT_ORDERS))
+    })?;
+
+    value.iter().map(sort_order::from_json_value).collect()
+}
+
+fn get_default_sort_order_id(value: &Value) -> Result<u32, ParserError> {
+    match value.get(DEFAULT_SORT_ORDER_ID) {
+        Some(_) => util::get_u32!(value, DEFAULT_SORT_ORDER_ID),
+        None => Ok(UNSORTED_ORDER_ID),
+    }
 }
 
 fn get_properties(value: &Value) -> Result<HashMap<String, String>, ParserError> {
-    let value = value
-        .get(PROPERTIES)
-        .ok_or_else(|| ParserError::MissingRequiredField(PROPERTIES.to_owned()))?;
+    let Some(value) = value.get(PROPERTIES) else {
+        return Ok(HashMap::new());
+    };
 
     let value = value.as_object().unwrap();
 
@@ -85,9 +437,9 @@ fn get_properties(value: &Value) -> Result<HashMap<String, String>, ParserError>
 }
 
 fn get_snapshots(value: &Value) -> Result<Vec<Snapshot>, ParserError> {
-    let value = value
-        .get(SNAPSHOTS)
-        .ok_or_else(|| ParserError::MissingRequiredField(SNAPSHOTS.to_owned()))?;
+    let Some(value) = value.get(SNAPSHOTS) else {
+        return Ok(Vec::new());
+    };
 
     let value = value.as_array().unwrap();
 
@@ -129,6 +481,16 @@ mod tests {
                     "name": "id_bucket"
                 }
             ],
+            "sort-orders": [
+                {"order-id": 0, "fields": []},
+                {
+                    "order-id": 1,
+                    "fields": [
+                        {"source-id": 3, "transform": "identity", "direction": "desc", "null-order": "nulls-last"}
+                    ]
+                }
+            ],
+            "default-sort-order-id": 1,
             "properties": {
                 "property1": "value1",
                 "property2": "value2"
@@ -149,8 +511,12 @@ mod tests {
 
         let metadata = result.unwrap();
         assert_eq!(metadata.location, "s3://test-location/metadata.json");
+        assert_eq!(metadata.sort_orders.len(), 2);
+        let sort_order = metadata.default_sort_order().unwrap();
+        assert_eq!(sort_order.order_id(), 1);
+        assert_eq!(sort_order.fields()[0].source_id, 3);
         assert_eq!(metadata.last_column_id, 100);
-        assert_eq!(metadata.current_snapshot_id, 1);
+        assert_eq!(metadata.current_snapshot_id, Some(1));
         assert_eq!(metadata.last_updated_millis, 1723320520000);
 
         assert_eq!(metadata.schema.fields.len(), 3);
@@ -184,6 +550,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_to_json_round_trip() {
+        let json = r#"{
+            "format-version": 1,
+            "location": "s3://test-location",
+            "last-column-id": 2,
+            "last-updated-ms": 1723320520000,
+            "current-snapshot-id": 1,
+            "schema": {
+                "fields": [
+                    {"id": 1, "name": "id", "type": "integer", "required": true},
+                    {"id": 2, "name": "name", "type": "string", "required": false}
+                ]
+            },
+            "partition-spec": [{"source-id": 1, "transform": "bucket", "name": "id_bucket"}],
+            "sort-orders": [
+                {
+                    "order-id": 1,
+                    "fields": [
+                        {"source-id": 2, "transform": "identity", "direction": "asc", "null-order": "nulls-first"}
+                    ]
+                }
+            ],
+            "default-sort-order-id": 1,
+            "properties": {"owner": "a"},
+            "snapshots": [
+                {"snapshot-id": 1, "timestamp-ms": 1723320520000, "manifests": ["s3://test-location/m1.avro"]}
+            ]
+        }"#;
+
+        let metadata = from_json(json).unwrap();
+        let written = to_json(&metadata);
+
+        assert_eq!(from_json(&written).unwrap(), metadata);
+        assert_eq!(
+            serde_json::from_str::<Value>(&written).unwrap(),
+            serde_json::from_str::<Value>(json).unwrap()
+        );
+    }
+
     #[test]
     fn test_from_json_invalid_json() {
         let json = r#"{
@@ -213,9 +619,125 @@ mod tests {
     }
 
     #[test]
-    fn test_from_json_unsupported_format_version() {
+    fn test_from_json_v2() {
+        // As Spark writes it: current schema and spec by id, manifest lists
         let json = r#"{
             "format-version": 2,
+            "table-uuid": "9c12d441-03fe-4693-9a96-a0705ddf69c1",
+            "location": "s3://bucket/db/events",
+            "last-sequence-number": 2,
+            "last-updated-ms": 1723320520000,
+            "last-column-id": 3,
+            "current-schema-id": 1,
+            "schemas": [
+                {"type": "struct", "schema-id": 0, "fields": [
+                    {"id": 1, "name": "id", "required": true, "type": "long"}
+                ]},
+                {"type": "struct", "schema-id": 1, "fields": [
+                    {"id": 1, "name": "id", "required": true, "type": "long"},
+                    {"id": 2, "name": "tags", "required": false, "type": {
+                        "type": "list", "element-id": 3, "element": "string", "element-required": false
+                    }}
+                ]}
+            ],
+            "default-spec-id": 0,
+            "partition-specs": [
+                {"spec-id": 0, "fields": [
+                    {"source-id": 1, "field-id": 1000, "name": "id_bucket", "transform": "bucket[16]"}
+                ]}
+            ],
+            "last-partition-id": 1000,
+            "default-sort-order-id": 0,
+            "sort-orders": [{"order-id": 0, "fields": []}],
+            "properties": {"write.format.default": "parquet"},
+            "current-snapshot-id": 3051729675574597004,
+            "refs": {"main": {"snapshot-id": 3051729675574597004, "type": "branch"}},
+            "snapshot-log": [
+                {"snapshot-id": 3051729675574597003, "timestamp-ms": 1723320510000},
+                {"snapshot-id": 3051729675574597004, "timestamp-ms": 1723320520000}
+            ],
+            "metadata-log": [
+                {"metadata-file": "s3://bucket/db/events/metadata/v1.metadata.json", "timestamp-ms": 1723320510000}
+            ],
+            "snapshots": [
+                {
+                    "sequence-number": 1,
+                    "snapshot-id": 3051729675574597003,
+                    "timestamp-ms": 1723320510000,
+                    "summary": {"operation": "append", "added-data-files": "1"},
+                    "manifest-list": "s3://bucket/db/events/metadata/snap-3.avro",
+                    "schema-id": 0
+                },
+                {
+                    "sequence-number": 2,
+                    "snapshot-id": 3051729675574597004,
+                    "parent-snapshot-id": 3051729675574597003,
+                    "timestamp-ms": 1723320520000,
+                    "summary": {"operation": "overwrite"},
+                    "manifest-list": "s3://bucket/db/events/metadata/snap-4.avro",
+                    "schema-id": 1
+                }
+            ]
+        }"#;
+
+        let metadata = from_json(json).unwrap();
+        assert_eq!(metadata.current_snapshot_id, Some(3051729675574597004));
+        assert_eq!(metadata.schema.fields.len(), 2);
+        assert!(metadata.schema.fields[1]
+            .field_type
+            .contains("\"element-id\":3"));
+        assert_eq!(
+            metadata.partition_spec.fields()[0].transform.name(),
+            "bucket[16]"
+        );
+
+        let snapshot = &metadata.snapshots[1];
+        assert_eq!(snapshot.parent_snapshot_id(), Some(3051729675574597003));
+        assert_eq!(snapshot.sequence_number(), 2);
+        assert_eq!(snapshot.operation(), Some("overwrite"));
+        assert_eq!(
+            snapshot.manifest_list(),
+            Some("s3://bucket/db/events/metadata/snap-4.avro")
+        );
+
+        // Written back as v2, as it was read
+        let written = to_json_value(&metadata);
+        assert_eq!(from_json_value(&written).unwrap(), metadata);
+        assert_eq!(written, serde_json::from_str::<Value>(json).unwrap());
+
+        // A new schema, spec and snapshot are added, not written over the old
+        let mut updated = metadata.clone();
+        updated.schema.fields.pop();
+        updated.partition_spec = PartitionSpec::new(Vec::new());
+        updated.current_snapshot_id = Some(3051729675574597003);
+        updated.last_updated_millis = 1723320530000;
+        let written = to_json_value(&updated);
+        let read = from_json_value(&written).unwrap();
+        assert_eq!(read.schema, updated.schema);
+        assert_eq!(read.partition_spec, updated.partition_spec);
+        assert_eq!(to_json_value(&read), written);
+        assert_eq!(written[FORMAT_VERSION], 2);
+        assert_eq!(written[CURRENT_SCHEMA_ID], 2);
+        assert_eq!(written[SCHEMAS].as_array().unwrap().len(), 3);
+        assert_eq!(written[DEFAULT_SPEC_ID], 1);
+        assert_eq!(
+            written[PARTITION_SPECS][0],
+            metadata.other[PARTITION_SPECS][0]
+        );
+        assert_eq!(
+            written[REFS][MAIN_BRANCH][SNAPSHOT_ID],
+            3051729675574597003u64
+        );
+        assert_eq!(written[SNAPSHOT_LOG][2][TIMESTAMP_MS], 1723320530000u64);
+        assert_eq!(written[TABLE_UUID], "9c12d441-03fe-4693-9a96-a0705ddf69c1");
+        assert_eq!(written[LAST_SEQUENCE_NUMBER], 2);
+        assert!(written.get(SCHEMA).is_none());
+    }
+
+    #[test]
+    fn test_from_json_unsupported_format_version() {
+        let json = r#"{
+            "format-version": 3,
             "location": "s3://test-location/metadata.json",
             "last-column-id": 100,
             "last-updated-ms": 1723320520000,
@@ -226,7 +748,7 @@ mod tests {
         assert!(result.is_err());
         assert!(matches!(
             result,
-            Err(ParserError::UnsupportedFormatVersion(2))
+            Err(ParserError::UnsupportedFormatVersion(3))
         ));
     }
 }
@@ -4,6 +4,7 @@ pub mod metadata;
 pub mod schema;
 pub mod partition_spec;
 pub mod snapshot;
+pub mod sort_order;
 mod util;
 
 #[derive(Error, Debug)]
@@ -16,6 +17,8 @@ pub enum ParserError {
     InvalidFieldType(String),
     #[error("{0}")]
     InvalidPartitionTransform(String),
+    #[error("{0}")]
+    InvalidSortOrder(String),
     #[error("Unsupported format version: {0}")]
     UnsupportedFormatVersion(u32),
 }
@@ -1,4 +1,4 @@
-use serde_json::Value;
+use serde_json::{json, Value};
 
 use crate::{
     parser::util,
@@ -7,9 +7,9 @@ use crate::{
 
 use super::ParserError;
 
-static SOURCE_ID: &'static str = "source-id";
-static TRANSFORM: &'static str = "transform";
-static NAME: &'static str = "name";
+static SOURCE_ID: &str = "source-id";
+static TRANSFORM: &str = "transform";
+static NAME: &str = "name";
 
 pub fn from_json(json: &str) -> Result<PartitionSpec, ParserError> {
     let value: Value = serde_json::from_str(json)?;
@@ -32,10 +32,23 @@ pub fn from_json_value(value: &Value) -> Result<PartitionSpec, ParserError> {
         let name = util::get_string!(field_value, NAME)?;
 
         let transform = transform::get_transform(&transform_name)
-            .ok_or_else(|| ParserError::InvalidPartitionTransform(transform_name))?;
+            .ok_or(ParserError::InvalidPartitionTransform(transform_name))?;
 
         partition_fields.push(PartitionField::new(source_id, name, transform));
     }
 
     Ok(PartitionSpec::new(partition_fields))
 }
+
+pub fn to_json_value(spec: &PartitionSpec) -> Value {
+    spec.fields()
+        .iter()
+        .map(|field| {
+            json!({
+                SOURCE_ID: field.source_id,
+                TRANSFORM: field.transform.name(),
+                NAME: field.name,
+            })
+        })
+        .collect()
+}
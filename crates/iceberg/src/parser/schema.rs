@@ -1,4 +1,4 @@
-use serde_json::Value;
+use serde_json::{json, Value};
 
 use crate::{
     parser::util,
@@ -7,11 +7,11 @@ use crate::{
 
 use super::ParserError;
 
-static TYPE: &'static str = "type";
-static FIELDS: &'static str = "fields";
-static NAME: &'static str = "name";
-static ID: &'static str = "id";
-static REQUIRED: &'static str = "required";
+static TYPE: &str = "type";
+static FIELDS: &str = "fields";
+static NAME: &str = "name";
+static ID: &str = "id";
+static REQUIRED: &str = "required";
 
 pub fn from_json(json: &str) -> Result<Schema, ParserError> {
     let value: Value = serde_json::from_str(json)?;
@@ -39,9 +39,37 @@ pub fn from_json_value(value: &Value) -> Result<Schema, ParserError> {
     Ok(Schema::new(nested_fields))
 }
 
+pub fn to_json_value(schema: &Schema) -> Value {
+    let fields: Vec<Value> = schema
+        .fields
+        .iter()
+        .map(|field| {
+            json!({
+                ID: field.id,
+                NAME: field.name,
+                TYPE: field_type(field),
+                REQUIRED: field.required,
+            })
+        })
+        .collect();
+
+    json!({ FIELDS: fields })
+}
+
+fn field_type(field: &NestedField) -> Value {
+    match serde_json::from_str(&field.field_type) {
+        Ok(nested @ Value::Object(_)) => nested,
+        _ => Value::from(field.field_type.as_str()),
+    }
+}
+
 fn as_field(value: &Value) -> Result<NestedField, ParserError> {
     let name = util::get_string!(value, NAME, "schema.fields.name")?;
-    let field_type = util::get_string!(value, TYPE, "schema.fields.type")?;
+    // Nested types, written as objects, are kept as their JSON
+    let field_type = match value.get(TYPE) {
+        Some(nested @ Value::Object(_)) => nested.to_string(),
+        _ => util::get_string!(value, TYPE, "schema.fields.type")?,
+    };
     let id = util::get_u32!(value, ID, "schema.fields.id")?;
     let required = util::get_bool!(value, REQUIRED, "schema.fields.required")?;
 
@@ -1,12 +1,19 @@
-use serde_json::Value;
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value};
 
 use crate::snapshot::Snapshot;
 
 use super::{util, ParserError};
 
-static SNAPSHOT_ID: &'static str = "snapshot-id";
-static TIMESTAMP_MS: &'static str = "timestamp-ms";
-static MANIFESTS: &'static str = "manifests";
+static SNAPSHOT_ID: &str = "snapshot-id";
+static TIMESTAMP_MS: &str = "timestamp-ms";
+static MANIFESTS: &str = "manifests";
+static PARENT_SNAPSHOT_ID: &str = "parent-snapshot-id";
+static SEQUENCE_NUMBER: &str = "sequence-number";
+static MANIFEST_LIST: &str = "manifest-list";
+static SUMMARY: &str = "summary";
+static SCHEMA_ID: &str = "schema-id";
 
 pub fn from_json(json: &str) -> Result<Snapshot, ParserError> {
     let value: Value = serde_json::from_str(json)?;
@@ -22,7 +29,89 @@ pub fn from_json_value(value: &Value) -> Result<Snapshot, ParserError> {
 
     let snapshot_id = util::get_u64!(value, SNAPSHOT_ID)?;
     let timestamp_ms = util::get_u64!(value, TIMESTAMP_MS)?;
-    let manifests = util::get_string_array!(value, MANIFESTS)?;
 
-    return Ok(Snapshot::new(snapshot_id, timestamp_ms, manifests));
-}
\ No newline at end of file
+    // Snapshots list their manifests in a manifest list, or in v1 directly
+    let manifest_list = match value.get(MANIFEST_LIST) {
+        Some(_) => Some(util::get_string!(value, MANIFEST_LIST)?),
+        None => None,
+    };
+    let manifests = match (value.get(MANIFESTS), &manifest_list) {
+        (None, Some(_)) => Vec::new(),
+        _ => util::get_string_array!(value, MANIFESTS)?,
+    };
+
+    let parent_snapshot_id = match value.get(PARENT_SNAPSHOT_ID) {
+        Some(Value::Null) | None => None,
+        Some(_) => Some(util::get_u64!(value, PARENT_SNAPSHOT_ID)?),
+    };
+    let sequence_number = match value.get(SEQUENCE_NUMBER) {
+        Some(_) => util::get_u64!(value, SEQUENCE_NUMBER)?,
+        None => 0,
+    };
+    let schema_id = match value.get(SCHEMA_ID) {
+        Some(_) => Some(util::get_u32!(value, SCHEMA_ID)?),
+        None => None,
+    };
+
+    Ok(Snapshot::new(snapshot_id, timestamp_ms, manifests)
+        .with_parent(parent_snapshot_id)
+        .with_sequence_number(sequence_number)
+        .with_manifest_list(manifest_list)
+        .with_schema_id(schema_id)
+        .with_summary(get_summary(value)?))
+}
+
+fn get_summary(value: &Value) -> Result<HashMap<String, String>, ParserError> {
+    let Some(summary) = value.get(SUMMARY) else {
+        return Ok(HashMap::new());
+    };
+
+    let summary = summary.as_object().ok_or_else(|| {
+        ParserError::InvalidFieldType(format!("snapshot {} must be an object", SUMMARY))
+    })?;
+
+    summary
+        .iter()
+        .map(|(key, value)| match value {
+            Value::String(value) => Ok((key.clone(), value.clone())),
+            _ => Err(ParserError::InvalidFieldType(format!(
+                "{}.{}",
+                SUMMARY, key
+            ))),
+        })
+        .collect()
+}
+
+pub fn to_json_value(snapshot: &Snapshot) -> Value {
+    let mut value = json!({
+        SNAPSHOT_ID: snapshot.snapshot_id(),
+        TIMESTAMP_MS: snapshot.timestamp_ms(),
+    });
+
+    let object: &mut Map<String, Value> = value.as_object_mut().unwrap();
+    // Snapshots with a manifest list have no manifests of their own, which v2
+    // metadata doesn't allow
+    if snapshot.manifest_list().is_none() || !snapshot.manifests().is_empty() {
+        object.insert(MANIFESTS.to_string(), json!(snapshot.manifests()));
+    }
+    if let Some(parent_snapshot_id) = snapshot.parent_snapshot_id() {
+        object.insert(PARENT_SNAPSHOT_ID.to_string(), json!(parent_snapshot_id));
+    }
+    if snapshot.sequence_number() > 0 {
+        object.insert(
+            SEQUENCE_NUMBER.to_string(),
+            json!(snapshot.sequence_number()),
+        );
+    }
+    if let Some(manifest_list) = snapshot.manifest_list() {
+        object.insert(MANIFEST_LIST.to_string(), json!(manifest_list));
+    }
+    if let Some(schema_id) = snapshot.schema_id() {
+        object.insert(SCHEMA_ID.to_string(), json!(schema_id));
+    }
+    if !snapshot.summary().is_empty() {
+        object.insert(SUMMARY.to_string(), json!(snapshot.summary()));
+    }
+
+    value
+}
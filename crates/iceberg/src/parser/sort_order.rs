@@ -0,0 +1,126 @@
+use serde_json::{json, Value};
+
+use crate::{
+    parser::util,
+    sort::{NullOrder, SortDirection, SortField, SortOrder},
+};
+
+use super::ParserError;
+
+static ORDER_ID: &str = "order-id";
+static FIELDS: &str = "fields";
+static SOURCE_ID: &str = "source-id";
+static TRANSFORM: &str = "transform";
+static DIRECTION: &str = "direction";
+static NULL_ORDER: &str = "null-order";
+
+pub fn from_json(json: &str) -> Result<SortOrder, ParserError> {
+    let value: Value = serde_json::from_str(json)?;
+    from_json_value(&value)
+}
+
+pub fn from_json_value(value: &Value) -> Result<SortOrder, ParserError> {
+    let order_id = util::get_u32!(value, ORDER_ID, "sort-order.order-id")?;
+    let field_values = value
+        .get(FIELDS)
+        .and_then(Value::as_array)
+        .ok_or_else(|| ParserError::MissingRequiredField("sort-order.fields".to_owned()))?;
+
+    let mut fields = Vec::with_capacity(field_values.len());
+
+    for field_value in field_values {
+        let source_id = util::get_u32!(field_value, SOURCE_ID, "sort-order.fields.source-id")?;
+        let transform = util::get_string!(field_value, TRANSFORM, "sort-order.fields.transform")?;
+
+        let direction = match util::get_string!(field_value, DIRECTION)?.as_str() {
+            "asc" => SortDirection::Ascending,
+            "desc" => SortDirection::Descending,
+            other => {
+                return Err(ParserError::InvalidSortOrder(format!(
+                    "Invalid sort direction: {}",
+                    other
+                )))
+            }
+        };
+
+        let null_order = match util::get_string!(field_value, NULL_ORDER)?.as_str() {
+            "nulls-first" => NullOrder::NullsFirst,
+            "nulls-last" => NullOrder::NullsLast,
+            other => {
+                return Err(ParserError::InvalidSortOrder(format!(
+                    "Invalid null order: {}",
+                    other
+                )))
+            }
+        };
+
+        fields.push(SortField {
+            source_id,
+            transform,
+            direction,
+            null_order,
+        });
+    }
+
+    Ok(SortOrder::new(order_id, fields))
+}
+
+pub fn to_json_value(order: &SortOrder) -> Value {
+    let fields: Vec<Value> = order
+        .fields()
+        .iter()
+        .map(|field| {
+            let direction = match field.direction {
+                SortDirection::Ascending => "asc",
+                SortDirection::Descending => "desc",
+            };
+            let null_order = match field.null_order {
+                NullOrder::NullsFirst => "nulls-first",
+                NullOrder::NullsLast => "nulls-last",
+            };
+            json!({
+                SOURCE_ID: field.source_id,
+                TRANSFORM: field.transform,
+                DIRECTION: direction,
+                NULL_ORDER: null_order,
+            })
+        })
+        .collect();
+
+    json!({ ORDER_ID: order.order_id(), FIELDS: fields })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json() {
+        let order = from_json(
+            r#"{
+                "order-id": 1,
+                "fields": [
+                    {"source-id": 2, "transform": "identity", "direction": "asc", "null-order": "nulls-first"},
+                    {"source-id": 3, "transform": "bucket[4]", "direction": "desc", "null-order": "nulls-last"}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(order.order_id(), 1);
+        assert_eq!(order.fields().len(), 2);
+        assert_eq!(order.fields()[0].source_id, 2);
+        assert_eq!(order.fields()[0].direction, SortDirection::Ascending);
+        assert_eq!(order.fields()[0].null_order, NullOrder::NullsFirst);
+        assert!(order.fields()[0].preserves_order());
+        assert_eq!(order.fields()[1].direction, SortDirection::Descending);
+        assert!(!order.fields()[1].preserves_order());
+
+        let result = from_json(
+            r#"{"order-id": 1, "fields": [
+                {"source-id": 2, "transform": "identity", "direction": "up", "null-order": "nulls-first"}
+            ]}"#,
+        );
+        assert!(matches!(result, Err(ParserError::InvalidSortOrder(_))));
+    }
+}
@@ -1,10 +1,12 @@
+use std::rc::Rc;
+
 use super::Transform;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PartitionField {
     pub source_id: u32,
     pub name: String,
-    pub transform: Box<dyn Transform>,
+    pub transform: Rc<dyn Transform>,
 }
 
 impl PartitionField {
@@ -12,7 +14,16 @@ impl PartitionField {
         Self {
             source_id,
             name,
-            transform,
+            transform: transform.into(),
         }
     }
 }
+
+/// Fields are equal if they apply the same transform to the same source.
+impl PartialEq for PartitionField {
+    fn eq(&self, other: &Self) -> bool {
+        self.source_id == other.source_id
+            && self.name == other.name
+            && self.transform.name() == other.transform.name()
+    }
+}
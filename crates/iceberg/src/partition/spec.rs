@@ -2,7 +2,7 @@ use std::rc::Rc;
 
 use super::PartitionField;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PartitionSpec {
     partition_fields: Vec<PartitionField>,
 }
@@ -11,6 +11,10 @@ impl PartitionSpec {
     pub fn new(partition_fields: Vec<PartitionField>) -> PartitionSpec {
         PartitionSpec { partition_fields }
     }
+
+    pub fn fields(&self) -> &[PartitionField] {
+        &self.partition_fields
+    }
 }
 
 pub type PartitionSpecRef = Rc<PartitionSpec>;
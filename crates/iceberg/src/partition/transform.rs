@@ -16,9 +16,27 @@ pub trait TransformOutput {
     fn as_boolean(&self) -> bool;
 }
 
+impl TransformInput for i32 {
+    fn as_integer(&self) -> i32 {
+        *self
+    }
+
+    fn as_float(&self) -> f32 {
+        *self as f32
+    }
+
+    fn as_string(&self) -> String {
+        format!("{}", *self)
+    }
+
+    fn as_boolean(&self) -> bool {
+        *self != 0
+    }
+}
+
 impl TransformOutput for i32 {
     fn as_integer(&self) -> i32 {
-        *self as i32
+        *self
     }
 
     fn as_float(&self) -> f32 {
@@ -38,7 +56,9 @@ pub trait Transform : fmt::Debug {
     // TODO: Remove Boxing
     fn apply(&self, input: &dyn TransformInput) -> Box<dyn TransformOutput>;
     fn can_transform(&self, field_type: FieldType) -> bool;
-    fn get_result_type(&self) -> FieldType;
+    fn get_result_type(&self, source_type: FieldType) -> FieldType;
+    /// Name the transform is written as in metadata files.
+    fn name(&self) -> &str;
 }
 
 pub struct TransformFactory {}
@@ -46,32 +66,211 @@ pub struct TransformFactory {}
 #[derive(Debug)]
 pub struct Bucket {
     pub n: i32,
+    name: String,
 }
 
 impl Bucket {
     pub fn new(n: i32) -> Bucket {
-        Bucket { n }
+        Bucket {
+            n,
+            name: format!("bucket[{}]", n),
+        }
     }
 }
 
 impl Transform for Bucket {
     fn apply(&self, input: &dyn TransformInput) -> Box<dyn TransformOutput> {
-        return Box::new(input.as_integer() / self.n);
+        Box::new(input.as_integer() / self.n)
     }
 
     fn can_transform(&self, field_type: FieldType) -> bool {
         field_type == FieldType::Integer
     }
 
-    fn get_result_type(&self) -> FieldType {
+    fn get_result_type(&self, _source_type: FieldType) -> FieldType {
         FieldType::Integer
     }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
 }
 
+/// Partitions by the source value itself.
+#[derive(Debug)]
+pub struct Identity {}
+
+impl Transform for Identity {
+    fn apply(&self, input: &dyn TransformInput) -> Box<dyn TransformOutput> {
+        Box::new(input.as_integer())
+    }
+
+    fn can_transform(&self, field_type: FieldType) -> bool {
+        field_type.is_primitive()
+    }
+
+    fn get_result_type(&self, source_type: FieldType) -> FieldType {
+        source_type
+    }
+
+    fn name(&self) -> &str {
+        "identity"
+    }
+}
+
+/// Partitions integers by the multiple of `width` at or below them.
+#[derive(Debug)]
+pub struct Truncate {
+    pub width: i32,
+    name: String,
+}
+
+impl Truncate {
+    pub fn new(width: i32) -> Truncate {
+        Truncate {
+            width,
+            name: format!("truncate[{}]", width),
+        }
+    }
+}
 
+impl Transform for Truncate {
+    fn apply(&self, input: &dyn TransformInput) -> Box<dyn TransformOutput> {
+        let value = input.as_integer();
+        Box::new(value - value.rem_euclid(self.width))
+    }
+
+    fn can_transform(&self, field_type: FieldType) -> bool {
+        matches!(
+            field_type,
+            FieldType::Integer | FieldType::Long | FieldType::Decimal { .. } | FieldType::String | FieldType::Binary
+        )
+    }
+
+    fn get_result_type(&self, source_type: FieldType) -> FieldType {
+        source_type
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    Year,
+    Month,
+    Day,
+    Hour,
+}
+
+/// Partitions dates and timestamps by the years, months, days or hours since
+/// the epoch. Inputs are dates, as days since the epoch.
+#[derive(Debug)]
+pub struct Temporal {
+    pub unit: TimeUnit,
+}
+
+impl Transform for Temporal {
+    fn apply(&self, input: &dyn TransformInput) -> Box<dyn TransformOutput> {
+        let days = input.as_integer();
+        let (year, month) = year_month(days);
+        let value = match self.unit {
+            TimeUnit::Year => year - 1970,
+            TimeUnit::Month => (year - 1970) * 12 + month - 1,
+            TimeUnit::Day => days,
+            TimeUnit::Hour => days * 24,
+        };
+        Box::new(value)
+    }
+
+    fn can_transform(&self, field_type: FieldType) -> bool {
+        match self.unit {
+            TimeUnit::Hour => field_type == FieldType::Timestamp,
+            _ => matches!(field_type, FieldType::Date | FieldType::Timestamp),
+        }
+    }
+
+    fn get_result_type(&self, _source_type: FieldType) -> FieldType {
+        match self.unit {
+            TimeUnit::Day => FieldType::Date,
+            _ => FieldType::Integer,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self.unit {
+            TimeUnit::Year => "year",
+            TimeUnit::Month => "month",
+            TimeUnit::Day => "day",
+            TimeUnit::Hour => "hour",
+        }
+    }
+}
+
+/// Year and month, from 1, of a date given as days since 1970-01-01.
+fn year_month(days: i32) -> (i32, i32) {
+    // Counts from 0000-03-01, so leap days end each 4, 100 and 400 year era
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month)
+}
+
+/// Parses a transform as metadata files write it, such as `bucket[16]`.
 pub fn get_transform(transform_name: &str) -> Option<Box<dyn Transform>> {
+    let argument = |prefix: &str| {
+        transform_name
+            .strip_prefix(prefix)?
+            .strip_prefix('[')?
+            .strip_suffix(']')?
+            .parse::<i32>()
+            .ok()
+            .filter(|n| *n > 0)
+    };
+
     match transform_name {
-        "bucket" => Some(Box::new(Bucket::new(5))),
-        _ => None,
+        // Written without a count by earlier versions of this crate
+        "bucket" => Some(Box::new(Bucket {
+            n: 5,
+            name: "bucket".to_string(),
+        })),
+        "identity" => Some(Box::new(Identity {})),
+        "year" => Some(Box::new(Temporal { unit: TimeUnit::Year })),
+        "month" => Some(Box::new(Temporal { unit: TimeUnit::Month })),
+        "day" => Some(Box::new(Temporal { unit: TimeUnit::Day })),
+        "hour" => Some(Box::new(Temporal { unit: TimeUnit::Hour })),
+        _ => {
+            if let Some(n) = argument("bucket") {
+                Some(Box::new(Bucket::new(n)))
+            } else if let Some(width) = argument("truncate") {
+                Some(Box::new(Truncate::new(width)))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_transform() {
+        assert_eq!(get_transform("bucket[16]").unwrap().name(), "bucket[16]");
+        assert_eq!(get_transform("truncate[10]").unwrap().apply(&-3).as_integer(), -10);
+        assert!(get_transform("bucket[0]").is_none());
+        assert!(get_transform("spiral").is_none());
+
+        // 2024-02-29
+        let month = get_transform("month").unwrap();
+        assert_eq!(month.apply(&19782).as_integer(), 54 * 12 + 1);
+        assert_eq!(get_transform("year").unwrap().apply(&-1).as_integer(), -1);
     }
 }
\ No newline at end of file
@@ -1,5 +1,8 @@
+#[derive(Default)]
 pub struct Rollback {}
 
+// Stubbed out until rollbacks are implemented
+#[allow(unused_variables)]
 impl Rollback {
     pub fn new() -> Self {
         Rollback {}
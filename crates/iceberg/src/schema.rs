@@ -1,14 +1,15 @@
 use std::rc::Rc;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NestedField {
     pub id: u32,
     pub name: String,
+    /// Type name, such as `long`, or the JSON of a nested type.
     pub field_type: String,
     pub required: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Schema {
     pub fields: Vec<NestedField>,
 }
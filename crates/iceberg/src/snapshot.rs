@@ -1,11 +1,22 @@
-use std::rc::Rc;
+use std::{collections::HashMap, rc::Rc};
+
+pub use crate::manifest::DataFile;
 
 /// A snapshot of the data in a table at a point in time.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Snapshot {
     snapshot_id: u64,
+    parent_snapshot_id: Option<u64>,
+    sequence_number: u64,
     timestamp_ms: u64,
+    /// Manifests of v1 snapshots written without a manifest list.
     manifests: Vec<String>,
+    manifest_list: Option<String>,
+    /// Schema current when the snapshot was taken, which v2 metadata records.
+    schema_id: Option<u32>,
+    /// Operation and counts of the change, as `operation`, `added-data-files`
+    /// and so on.
+    summary: HashMap<String, String>,
     added_files: Vec<DataFile>,
     deleted_files: Vec<DataFile>,
 }
@@ -18,17 +29,90 @@ impl Snapshot {
     ) -> Self {
         Snapshot {
             snapshot_id,
+            parent_snapshot_id: None,
+            sequence_number: 0,
             timestamp_ms,
             manifests,
+            manifest_list: None,
+            schema_id: None,
+            summary: HashMap::new(),
             added_files: Vec::new(),
             deleted_files: Vec::new(),
         }
     }
+
+    pub fn with_parent(mut self, parent_snapshot_id: Option<u64>) -> Self {
+        self.parent_snapshot_id = parent_snapshot_id;
+        self
+    }
+
+    pub fn with_sequence_number(mut self, sequence_number: u64) -> Self {
+        self.sequence_number = sequence_number;
+        self
+    }
+
+    pub fn with_manifest_list(mut self, manifest_list: Option<String>) -> Self {
+        self.manifest_list = manifest_list;
+        self
+    }
+
+    pub fn with_schema_id(mut self, schema_id: Option<u32>) -> Self {
+        self.schema_id = schema_id;
+        self
+    }
+
+    pub fn with_summary(mut self, summary: HashMap<String, String>) -> Self {
+        self.summary = summary;
+        self
+    }
+
+    pub fn snapshot_id(&self) -> u64 {
+        self.snapshot_id
+    }
+
+    pub fn parent_snapshot_id(&self) -> Option<u64> {
+        self.parent_snapshot_id
+    }
+
+    /// Sequence number of the snapshot, 0 in v1 tables.
+    pub fn sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
+
+    pub fn timestamp_ms(&self) -> u64 {
+        self.timestamp_ms
+    }
+
+    pub fn manifests(&self) -> &[String] {
+        &self.manifests
+    }
+
+    pub fn manifest_list(&self) -> Option<&str> {
+        self.manifest_list.as_deref()
+    }
+
+    pub fn schema_id(&self) -> Option<u32> {
+        self.schema_id
+    }
+
+    pub fn summary(&self) -> &HashMap<String, String> {
+        &self.summary
+    }
+
+    /// Operation of the snapshot, such as `append` or `overwrite`.
+    pub fn operation(&self) -> Option<&str> {
+        self.summary.get("operation").map(String::as_str)
+    }
+
+    pub fn added_files(&self) -> &[DataFile] {
+        &self.added_files
+    }
+
+    pub fn deleted_files(&self) -> &[DataFile] {
+        &self.deleted_files
+    }
 }
 
 pub type SnapshotRef = Rc<Snapshot>;
 
 pub struct ExpireSnapshots {}
-
-#[derive(Debug)]
-pub struct DataFile {}
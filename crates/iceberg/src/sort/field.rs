@@ -0,0 +1,32 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NullOrder {
+    NullsFirst,
+    NullsLast,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortField {
+    pub source_id: u32,
+    /// Transform applied to the source column, such as `identity` or
+    /// `bucket[16]`.
+    pub transform: String,
+    pub direction: SortDirection,
+    pub null_order: NullOrder,
+}
+
+impl SortField {
+    /// Whether sorting by the source column also sorts by the transformed
+    /// values. Hashing and dropping values don't keep the order.
+    pub fn preserves_order(&self) -> bool {
+        matches!(
+            self.transform.as_str(),
+            "identity" | "year" | "month" | "day" | "hour"
+        ) || self.transform.starts_with("truncate[")
+    }
+}
@@ -0,0 +1,5 @@
+pub mod field;
+pub mod order;
+
+pub use field::{NullOrder, SortDirection, SortField};
+pub use order::{SortOrder, SortOrderRef};
@@ -0,0 +1,36 @@
+use std::rc::Rc;
+
+use super::SortField;
+
+/// Order id reserved for tables that aren't sorted.
+pub const UNSORTED_ORDER_ID: u32 = 0;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortOrder {
+    order_id: u32,
+    fields: Vec<SortField>,
+}
+
+impl SortOrder {
+    pub fn new(order_id: u32, fields: Vec<SortField>) -> SortOrder {
+        SortOrder { order_id, fields }
+    }
+
+    pub fn unsorted() -> SortOrder {
+        SortOrder::new(UNSORTED_ORDER_ID, Vec::new())
+    }
+
+    pub fn order_id(&self) -> u32 {
+        self.order_id
+    }
+
+    pub fn fields(&self) -> &[SortField] {
+        &self.fields
+    }
+
+    pub fn is_unsorted(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+pub type SortOrderRef = Rc<SortOrder>;
@@ -1,5 +1,8 @@
 use std::{collections::HashMap, rc::Rc};
 
+use thiserror::Error;
+
+pub use crate::metadata::TableMetadata;
 use crate::{
     partition::{PartitionSpec, PartitionSpecRef},
     rollback::Rollback,
@@ -9,27 +12,28 @@ use crate::{
     updates::{RewriteFiles, UpdateProperties, UpdateSchema},
 };
 
-#[derive(Debug)]
-pub struct TableMetadata {
-    pub properties: HashMap<String, String>,
-}
-
-impl TableMetadata {
-    pub fn new(properties: HashMap<String, String>) -> Self {
-        Self { properties }
-    }
-
-    pub fn replace_properties(&self, properties: HashMap<String, String>) -> TableMetadata {
-        TableMetadata { properties }
-    }
+#[derive(Error, Debug)]
+pub enum TableError {
+    /// Another commit changed the table since the base metadata was read. The
+    /// commit can be retried on top of the refreshed metadata.
+    #[error("Commit failed: {0}")]
+    CommitFailed(String),
+    #[error("Table not found: {0}")]
+    NotFound(String),
+    #[error("IO error: {0}")]
+    Io(String),
 }
 
 pub trait TableOperations: std::fmt::Debug {
+    /// Metadata as of the last refresh or commit.
     fn current(&self) -> TableMetadata;
-    fn refresh(&self) -> TableMetadata;
-    fn commit(&self, base: &TableMetadata, updated: &TableMetadata);
+    /// Reloads the metadata the table currently points at.
+    fn refresh(&self) -> Result<TableMetadata, TableError>;
+    /// Replaces `base` with `updated`, failing if `base` is no longer current.
+    fn commit(&self, base: &TableMetadata, updated: &TableMetadata) -> Result<(), TableError>;
 }
 
+#[derive(Default)]
 pub struct Table {}
 
 impl Table {
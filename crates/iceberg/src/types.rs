@@ -1,4 +1,8 @@
-#[derive(PartialEq, Eq)]
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FieldType {
     Boolean,
     Integer,
@@ -10,16 +14,210 @@ pub enum FieldType {
     Timestamp,
     String,
     Uuid,
-    Fixed,
+    Fixed(u32),
     Binary,
-    Decimal,
+    Decimal { precision: u32, scale: u32 },
     Struct,
     List,
     Map,
 }
 
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum TypeError {
+    #[error("Unknown type: {0}")]
+    Unknown(String),
+    #[error("Invalid type {0}: {1}")]
+    Invalid(String, String),
+}
+
+/// Largest decimal precision that fits in 16 bytes.
+const MAX_DECIMAL_PRECISION: u32 = 38;
+
+impl FieldType {
+    pub fn is_primitive(&self) -> bool {
+        !matches!(self, FieldType::Struct | FieldType::List | FieldType::Map)
+    }
+
+    /// Whether values of this type can be read as `to`: the same type, or
+    /// one of Iceberg's type promotions, int to long, float to double and
+    /// widening a decimal's precision.
+    pub fn can_promote_to(&self, to: &FieldType) -> bool {
+        match (self, to) {
+            (FieldType::Integer, FieldType::Long) | (FieldType::Float, FieldType::Double) => true,
+            (
+                FieldType::Decimal { precision, scale },
+                FieldType::Decimal {
+                    precision: to_precision,
+                    scale: to_scale,
+                },
+            ) => scale == to_scale && precision <= to_precision,
+            (from, to) => from == to,
+        }
+    }
+
+    /// Maps Parquet logical and physical type names, as older clients send
+    /// them, to the type storing the same values.
+    pub fn from_legacy_name(name: &str) -> Option<FieldType> {
+        let field_type = match name {
+            "BOOLEAN" => FieldType::Boolean,
+            "INT_8" | "INT_16" | "INT_32" | "UINT_8" | "UINT_16" | "INT32" => FieldType::Integer,
+            "INT_64" | "UINT_32" | "INT64" => FieldType::Long,
+            // Unsigned 64 bit values don't fit in a long
+            "UINT_64" => FieldType::Decimal {
+                precision: 20,
+                scale: 0,
+            },
+            "FLOAT" => FieldType::Float,
+            "DOUBLE" => FieldType::Double,
+            "DATE" => FieldType::Date,
+            "TIME_MILLIS" | "TIME_MICROS" => FieldType::Time,
+            "TIMESTAMP_MILLIS" | "TIMESTAMP_MICROS" => FieldType::Timestamp,
+            "UTF8" | "STRING" | "ENUM" | "JSON" => FieldType::String,
+            "UUID" => FieldType::Uuid,
+            "BSON" | "BYTE_ARRAY" => FieldType::Binary,
+            _ => return None,
+        };
+
+        Some(field_type)
+    }
+}
+
+impl FromStr for FieldType {
+    type Err = TypeError;
+
+    /// Parses a type by its Iceberg name, falling back to legacy Parquet names.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let field_type = match s {
+            "boolean" => FieldType::Boolean,
+            "int" => FieldType::Integer,
+            "long" => FieldType::Long,
+            "float" => FieldType::Float,
+            "double" => FieldType::Double,
+            "date" => FieldType::Date,
+            "time" => FieldType::Time,
+            "timestamp" => FieldType::Timestamp,
+            "string" => FieldType::String,
+            "uuid" => FieldType::Uuid,
+            "binary" => FieldType::Binary,
+            "struct" => FieldType::Struct,
+            "list" => FieldType::List,
+            "map" => FieldType::Map,
+            _ => {
+                if let Some(length) = s.strip_prefix("fixed[").and_then(|s| s.strip_suffix(']')) {
+                    return parse_fixed(s, length);
+                }
+                if let Some(args) = s.strip_prefix("decimal(").and_then(|s| s.strip_suffix(')')) {
+                    return parse_decimal(s, args);
+                }
+                return FieldType::from_legacy_name(s)
+                    .ok_or_else(|| TypeError::Unknown(s.to_string()));
+            }
+        };
+
+        Ok(field_type)
+    }
+}
+
+fn parse_fixed(s: &str, length: &str) -> Result<FieldType, TypeError> {
+    match length.trim().parse::<u32>() {
+        Ok(length) if length > 0 => Ok(FieldType::Fixed(length)),
+        _ => Err(TypeError::Invalid(
+            s.to_string(),
+            "length must be a positive integer".to_string(),
+        )),
+    }
+}
+
+fn parse_decimal(s: &str, args: &str) -> Result<FieldType, TypeError> {
+    let invalid = |reason: &str| TypeError::Invalid(s.to_string(), reason.to_string());
+
+    let (precision, scale) = args
+        .split_once(',')
+        .ok_or_else(|| invalid("expected decimal(precision, scale)"))?;
+    let precision: u32 = precision
+        .trim()
+        .parse()
+        .map_err(|_| invalid("precision must be an integer"))?;
+    let scale: u32 = scale
+        .trim()
+        .parse()
+        .map_err(|_| invalid("scale must be an integer"))?;
+
+    if precision == 0 || precision > MAX_DECIMAL_PRECISION {
+        return Err(invalid("precision must be between 1 and 38"));
+    }
+    if scale > precision {
+        return Err(invalid("scale must not exceed precision"));
+    }
+
+    Ok(FieldType::Decimal { precision, scale })
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::Boolean => write!(f, "boolean"),
+            FieldType::Integer => write!(f, "int"),
+            FieldType::Long => write!(f, "long"),
+            FieldType::Float => write!(f, "float"),
+            FieldType::Double => write!(f, "double"),
+            FieldType::Date => write!(f, "date"),
+            FieldType::Time => write!(f, "time"),
+            FieldType::Timestamp => write!(f, "timestamp"),
+            FieldType::String => write!(f, "string"),
+            FieldType::Uuid => write!(f, "uuid"),
+            FieldType::Fixed(length) => write!(f, "fixed[{}]", length),
+            FieldType::Binary => write!(f, "binary"),
+            FieldType::Decimal { precision, scale } => {
+                write!(f, "decimal({}, {})", precision, scale)
+            }
+            FieldType::Struct => write!(f, "struct"),
+            FieldType::List => write!(f, "list"),
+            FieldType::Map => write!(f, "map"),
+        }
+    }
+}
+
 pub type Boolean = bool;
 pub type Integer = i32;
 pub type Long = i64;
 pub type Float = f32;
 pub type Double = f64;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_types() {
+        assert_eq!("long".parse(), Ok(FieldType::Long));
+        assert_eq!("fixed[16]".parse(), Ok(FieldType::Fixed(16)));
+        assert_eq!(
+            "decimal(10, 2)".parse(),
+            Ok(FieldType::Decimal {
+                precision: 10,
+                scale: 2
+            })
+        );
+        assert_eq!("UINT_16".parse(), Ok(FieldType::Integer));
+        assert_eq!("TIMESTAMP_MILLIS".parse(), Ok(FieldType::Timestamp));
+        assert_eq!("ENUM".parse(), Ok(FieldType::String));
+
+        assert_eq!(
+            "varchar".parse::<FieldType>(),
+            Err(TypeError::Unknown("varchar".to_string()))
+        );
+        assert!(matches!(
+            "decimal(39, 2)".parse::<FieldType>(),
+            Err(TypeError::Invalid(..))
+        ));
+        assert!(matches!(
+            "fixed[0]".parse::<FieldType>(),
+            Err(TypeError::Invalid(..))
+        ));
+
+        for name in ["int", "fixed[16]", "decimal(10, 2)", "map"] {
+            assert_eq!(name.parse::<FieldType>().unwrap().to_string(), name);
+        }
+    }
+}
@@ -1,12 +1,26 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    thread,
+    time::Duration,
+};
+
+use thiserror::Error;
 
 use crate::{
     schema::Schema,
     snapshot::{DataFile, SnapshotRef},
-    table::{TableMetadata, TableOperations},
+    table::{TableError, TableMetadata, TableOperations},
 };
 
-pub enum UpdateError {}
+/// Times a commit is retried after losing to a concurrent commit.
+const COMMIT_RETRIES: u32 = 4;
+const COMMIT_BACKOFF: Duration = Duration::from_millis(10);
+
+#[derive(Error, Debug)]
+pub enum UpdateError {
+    #[error(transparent)]
+    Table(#[from] TableError),
+}
 
 /// An update that can be committed
 pub trait PendingUpdate {
@@ -54,7 +68,7 @@ impl PendingUpdate for UpdateProperties {
     type Changes = HashMap<String, String>;
 
     fn apply(&mut self) -> Result<Self::Changes, UpdateError> {
-        self.base = self.ops.refresh();
+        self.base = self.ops.refresh()?;
 
         let mut changes = HashMap::new();
 
@@ -73,17 +87,31 @@ impl PendingUpdate for UpdateProperties {
         Ok(changes)
     }
 
-    // TODO: Add retry and backoff
+    /// Commits, re-applying the updates on top of concurrent commits that
+    /// won the race.
     fn commit(&mut self) -> Result<(), UpdateError> {
-        let changes = self.apply()?;
-        let updated = self.base.replace_properties(changes);
-        self.ops.commit(&self.base, &updated);
-        Ok(())
+        let mut attempt = 0;
+
+        loop {
+            let changes = self.apply()?;
+            let updated = self.base.replace_properties(changes);
+
+            match self.ops.commit(&self.base, &updated) {
+                Err(TableError::CommitFailed(_)) if attempt < COMMIT_RETRIES => {
+                    thread::sleep(COMMIT_BACKOFF * 2u32.pow(attempt));
+                    attempt += 1;
+                }
+                result => return Ok(result?),
+            }
+        }
     }
 }
 
+#[derive(Default)]
 pub struct UpdateSchema {}
 
+// Stubbed out until schema updates are implemented
+#[allow(unused_variables)]
 impl UpdateSchema {
     pub fn new() -> Self {
         Self {}
@@ -124,6 +152,8 @@ impl PendingUpdate for UpdateSchema {
 
 pub struct RewriteFiles {}
 
+// Stubbed out until rewrites are implemented
+#[allow(unused_variables)]
 impl RewriteFiles {
     pub fn new(files_to_delete: Vec<DataFile>, files_to_add: Vec<DataFile>) -> Self {
         Self {}
@@ -0,0 +1,868 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::Arc,
+};
+
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use async_trait::async_trait;
+use datafusion::{
+    datasource::{MemTable, TableProvider},
+    execution::context::SessionState,
+};
+use fileio::FileIO;
+use iceberg::{
+    manifest::{self, EntryStatus, ManifestEntry, ManifestFile, DATA_CONTENT},
+    schema::Schema as IcebergSchema,
+    snapshot::Snapshot as IcebergSnapshot,
+};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    engine::QueryError,
+    scan::{DataFilesTable, PartitionField},
+};
+
+/// Namespace of tables referenced without one.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Identifies a table by its namespace levels and name, written as
+/// `ns1.ns2.table`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableIdentifier {
+    pub namespace: Vec<String>,
+    pub name: String,
+}
+
+impl TableIdentifier {
+    /// Parses a fully qualified `ns1.ns2.table` identifier.
+    pub fn parse(name: &str) -> TableIdentifier {
+        match name.rsplit_once('.') {
+            Some((namespace, name)) => TableIdentifier {
+                namespace: namespace.split('.').map(str::to_string).collect(),
+                name: name.to_string(),
+            },
+            None => TableIdentifier {
+                namespace: vec![DEFAULT_NAMESPACE.to_string()],
+                name: name.to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for TableIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.namespace.join("."), self.name)
+    }
+}
+
+/// A registration of data files a table can be read as of.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Snapshot {
+    pub snapshot_id: i64,
+    #[serde(default)]
+    pub parent_snapshot_id: Option<i64>,
+    #[serde(default)]
+    pub sequence_number: i64,
+    /// Milliseconds since the epoch.
+    pub timestamp_ms: i64,
+    #[serde(default)]
+    pub added_files_count: i64,
+    #[serde(default)]
+    pub added_records_count: i64,
+    #[serde(default)]
+    pub removed_files_count: i64,
+    #[serde(default)]
+    pub removed_records_count: i64,
+    /// Operation and counts of the change, as Iceberg snapshot summaries
+    /// give them.
+    #[serde(default)]
+    pub summary: HashMap<String, String>,
+}
+
+/// A snapshot becoming the current one of a table, as Iceberg's snapshot log
+/// records it. Rollbacks make an earlier snapshot current again.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotLogEntry {
+    pub snapshot_id: i64,
+    /// Milliseconds since the epoch.
+    pub timestamp_ms: i64,
+}
+
+/// A data file of a table, with the statistics the write service collected
+/// per column, keyed by field id.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DataFile {
+    pub path: String,
+    #[serde(default)]
+    pub record_count: i64,
+    #[serde(default)]
+    pub file_size_in_bytes: i64,
+    #[serde(default)]
+    pub value_counts: HashMap<String, i64>,
+    #[serde(default)]
+    pub null_value_counts: HashMap<String, i64>,
+    #[serde(default)]
+    pub nan_value_counts: HashMap<String, i64>,
+    #[serde(default)]
+    pub lower_bounds: HashMap<String, Value>,
+    #[serde(default)]
+    pub upper_bounds: HashMap<String, Value>,
+    /// `PARQUET` if not given.
+    #[serde(default)]
+    pub file_format: Option<String>,
+    /// Partition values by partition field name, empty if the table isn't
+    /// partitioned.
+    #[serde(default)]
+    pub partition: BTreeMap<String, Value>,
+}
+
+/// What is known of a table besides its data, which its metadata tables
+/// show. Tables with an Iceberg metadata file are described by it and the
+/// manifests of their current snapshot, tables the write service registers
+/// files for by the catalog.
+#[derive(Debug, Clone, Default)]
+pub struct TableMetadata {
+    pub properties: HashMap<String, String>,
+    /// Snapshots, oldest first.
+    pub snapshots: Vec<Snapshot>,
+    pub current_snapshot_id: Option<i64>,
+    /// Current data files.
+    pub files: Vec<DataFile>,
+    /// Manifests of the current snapshot. The catalog keeps the files of the
+    /// tables it tracks in its database, which have none.
+    pub manifests: Vec<ManifestFile>,
+}
+
+/// Resolves table identifiers of a query to the tables to scan.
+#[async_trait]
+pub trait TableSource: Send + Sync {
+    /// Loads a table, as of a snapshot if one is given, or `None` if it
+    /// doesn't exist.
+    async fn load_table(
+        &self,
+        state: &SessionState,
+        identifier: &TableIdentifier,
+        snapshot_id: Option<i64>,
+    ) -> Result<Option<Arc<dyn TableProvider>>, QueryError>;
+
+    /// Snapshots of a table, oldest first, or `None` if it doesn't exist.
+    async fn snapshots(
+        &self,
+        identifier: &TableIdentifier,
+    ) -> Result<Option<Vec<Snapshot>>, QueryError>;
+
+    /// When each snapshot of a table became current, oldest first, or `None`
+    /// if it doesn't exist. Tables never rolled back made each snapshot
+    /// current as it was taken.
+    async fn snapshot_log(
+        &self,
+        identifier: &TableIdentifier,
+    ) -> Result<Option<Vec<SnapshotLogEntry>>, QueryError> {
+        let snapshots = self.snapshots(identifier).await?;
+        Ok(snapshots.map(|snapshots| creation_log(&snapshots)))
+    }
+
+    /// Metadata of a table, or `None` if it doesn't exist.
+    async fn metadata(
+        &self,
+        identifier: &TableIdentifier,
+    ) -> Result<Option<TableMetadata>, QueryError>;
+
+    /// Lists the tables of every namespace.
+    async fn list_tables(&self) -> Result<Vec<TableIdentifier>, QueryError>;
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogField {
+    #[serde(default)]
+    id: Option<i32>,
+    field: String,
+    #[serde(rename = "type")]
+    field_type: String,
+    #[serde(default)]
+    required: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogSchema {
+    fields: Vec<CatalogField>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogTable {
+    schema: CatalogSchema,
+    #[serde(default)]
+    properties: HashMap<String, String>,
+    /// Iceberg metadata file of tables written by other engines, such as
+    /// Spark through the REST catalog.
+    #[serde(default)]
+    metadata_location: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogTables {
+    tables: Vec<String>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogDataFiles {
+    files: Vec<DataFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogSnapshots {
+    snapshots: Vec<Snapshot>,
+}
+
+/// Client for the catalog service, serving the Parquet files of each table,
+/// scanned as [`DataFilesTable`]s. Tables with an Iceberg metadata file are
+/// planned from the manifests of their snapshots, others from the files the
+/// catalog registered for them.
+#[derive(Debug, Clone)]
+pub struct CatalogClient {
+    base_url: String,
+    client: reqwest::Client,
+    /// Stores of data files, registered with DataFusion by scheme and bucket.
+    io: FileIO,
+}
+
+impl CatalogClient {
+    pub fn new(base_url: &str, io: FileIO) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            io,
+        }
+    }
+
+    /// Makes the stores of data files outside the local file system, such
+    /// as `s3://` buckets, available to the scan.
+    fn register_object_stores(
+        &self,
+        state: &SessionState,
+        files: &[DataFile],
+    ) -> Result<(), QueryError> {
+        let io_error = |e: fileio::FileIOError| QueryError::InternalError(e.to_string());
+
+        for file in files {
+            let mut url = FileIO::parse_location(&file.path).map_err(io_error)?;
+            if url.scheme() == "file" {
+                continue;
+            }
+
+            let (store, _) = self.io.object_store(&file.path).map_err(io_error)?;
+            url.set_path("");
+            state.runtime_env().register_object_store(&url, store);
+        }
+
+        Ok(())
+    }
+
+    async fn table(
+        &self,
+        identifier: &TableIdentifier,
+    ) -> Result<Option<CatalogTable>, QueryError> {
+        self.get::<CatalogTable>(&format!("/tables/{}", identifier), &[])
+            .await
+            .map_err(|e| QueryError::InternalError(e.to_string()))
+    }
+
+    /// Snapshots the catalog took of a table it registers files for.
+    async fn catalog_snapshots(
+        &self,
+        identifier: &TableIdentifier,
+    ) -> Result<Vec<Snapshot>, QueryError> {
+        let snapshots = self
+            .get::<CatalogSnapshots>(&format!("/tables/{}/snapshots", identifier), &[])
+            .await
+            .map_err(|e| QueryError::InternalError(e.to_string()))?;
+        Ok(snapshots
+            .map(|snapshots| snapshots.snapshots)
+            .unwrap_or_default())
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Option<T>, reqwest::Error> {
+        let response = self
+            .client
+            .get(format!("{}{}", self.base_url, path))
+            .query(query)
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        Ok(Some(response.error_for_status()?.json().await?))
+    }
+}
+
+#[async_trait]
+impl TableSource for CatalogClient {
+    async fn load_table(
+        &self,
+        state: &SessionState,
+        identifier: &TableIdentifier,
+        snapshot_id: Option<i64>,
+    ) -> Result<Option<Arc<dyn TableProvider>>, QueryError> {
+        let Some(table) = self.table(identifier).await? else {
+            return Ok(None);
+        };
+
+        let (files, field_ids, partition_fields) = match &table.metadata_location {
+            Some(location) => {
+                let file = read_metadata_file(&self.io, location).await?;
+                let files = match snapshot_id.map(|id| id as u64).or(file.current_snapshot_id) {
+                    Some(snapshot_id) => {
+                        let snapshot = file
+                            .snapshots
+                            .iter()
+                            .find(|snapshot| snapshot.snapshot_id() == snapshot_id)
+                            .ok_or_else(|| {
+                                QueryError::SnapshotNotFound(format!(
+                                    "{} snapshot {}",
+                                    identifier, snapshot_id
+                                ))
+                            })?;
+                        read_snapshot_files(&self.io, snapshot, &file.schema)
+                            .await?
+                            .1
+                    }
+                    None => Vec::new(),
+                };
+                let field_ids = file
+                    .schema
+                    .fields
+                    .iter()
+                    .map(|field| (field.name.clone(), field.id as i32))
+                    .collect();
+                (files, field_ids, file.partition_fields)
+            }
+            None => {
+                let snapshot_id = snapshot_id.map(|id| id.to_string());
+                let query: Vec<(&str, &str)> = snapshot_id
+                    .iter()
+                    .map(|id| ("snapshot_id", id.as_str()))
+                    .collect();
+                let files = self
+                    .get::<CatalogDataFiles>(&format!("/tables/{}/files", identifier), &query)
+                    .await
+                    .map_err(|e| QueryError::InternalError(e.to_string()))?
+                    .map(|files| files.files)
+                    .unwrap_or_default();
+                // Bounds of the files are keyed by the field ids of their columns
+                let field_ids = table
+                    .schema
+                    .fields
+                    .iter()
+                    .filter_map(|field| Some((field.field.clone(), field.id?)))
+                    .collect();
+                (files, field_ids, Vec::new())
+            }
+        };
+
+        // A table nothing was written to yet has no files to infer a schema from
+        if files.is_empty() {
+            let schema = arrow_schema(&table.schema);
+            let table = MemTable::try_new(schema, vec![vec![]])?;
+            return Ok(Some(Arc::new(table)));
+        }
+
+        self.register_object_stores(state, &files)?;
+
+        let table = DataFilesTable::try_new(state, files, field_ids)
+            .await?
+            .with_partition_fields(partition_fields);
+
+        Ok(Some(Arc::new(table)))
+    }
+
+    /// Snapshots of tables with an Iceberg metadata file are the ones it
+    /// lists, those of other tables the ones the catalog took.
+    async fn snapshots(
+        &self,
+        identifier: &TableIdentifier,
+    ) -> Result<Option<Vec<Snapshot>>, QueryError> {
+        let Some(table) = self.table(identifier).await? else {
+            return Ok(None);
+        };
+        let snapshots = match &table.metadata_location {
+            Some(location) => {
+                let file = read_metadata_file(&self.io, location).await?;
+                sorted_snapshots(&file.snapshots)
+            }
+            None => self.catalog_snapshots(identifier).await?,
+        };
+        Ok(Some(snapshots))
+    }
+
+    /// Metadata files without a snapshot log, as written by early v1
+    /// writers, are taken to have made each snapshot current as it was taken.
+    async fn snapshot_log(
+        &self,
+        identifier: &TableIdentifier,
+    ) -> Result<Option<Vec<SnapshotLogEntry>>, QueryError> {
+        let Some(table) = self.table(identifier).await? else {
+            return Ok(None);
+        };
+        let log = match &table.metadata_location {
+            Some(location) => {
+                let file = read_metadata_file(&self.io, location).await?;
+                match file.snapshot_log.is_empty() {
+                    true => creation_log(&sorted_snapshots(&file.snapshots)),
+                    false => file.snapshot_log,
+                }
+            }
+            None => creation_log(&self.catalog_snapshots(identifier).await?),
+        };
+        Ok(Some(log))
+    }
+
+    async fn metadata(
+        &self,
+        identifier: &TableIdentifier,
+    ) -> Result<Option<TableMetadata>, QueryError> {
+        let catalog_error = |e: reqwest::Error| QueryError::InternalError(e.to_string());
+
+        let Some(table) = self
+            .get::<CatalogTable>(&format!("/tables/{}", identifier), &[])
+            .await
+            .map_err(catalog_error)?
+        else {
+            return Ok(None);
+        };
+        if let Some(location) = &table.metadata_location {
+            return read_iceberg_metadata(&self.io, location).await.map(Some);
+        }
+
+        let mut snapshots = self.catalog_snapshots(identifier).await?;
+        for snapshot in &mut snapshots {
+            snapshot.summary = catalog_summary(snapshot);
+        }
+        let files = self
+            .get::<CatalogDataFiles>(&format!("/tables/{}/files", identifier), &[])
+            .await
+            .map_err(catalog_error)?
+            .map(|files| files.files)
+            .unwrap_or_default();
+
+        Ok(Some(TableMetadata {
+            properties: table.properties,
+            current_snapshot_id: snapshots.last().map(|s| s.snapshot_id),
+            snapshots,
+            files,
+            manifests: Vec::new(),
+        }))
+    }
+
+    async fn list_tables(&self) -> Result<Vec<TableIdentifier>, QueryError> {
+        let mut tables = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let mut query = vec![("page_size", "1000")];
+            if let Some(token) = &page_token {
+                query.push(("page_token", token));
+            }
+            let page = self
+                .get::<CatalogTables>("/tables", &query)
+                .await
+                .map_err(|e| QueryError::InternalError(e.to_string()))?
+                .ok_or_else(|| QueryError::InternalError("Catalog has no tables".to_string()))?;
+
+            tables.extend(page.tables.iter().map(|name| TableIdentifier::parse(name)));
+            match page.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => return Ok(tables),
+            }
+        }
+    }
+}
+
+/// Summary of a snapshot the catalog took, which only adds files or replaces
+/// them with their compaction.
+fn catalog_summary(snapshot: &Snapshot) -> HashMap<String, String> {
+    let mut summary = HashMap::from([
+        (
+            "added-data-files".to_string(),
+            snapshot.added_files_count.to_string(),
+        ),
+        (
+            "added-records".to_string(),
+            snapshot.added_records_count.to_string(),
+        ),
+    ]);
+
+    let operation = if snapshot.removed_files_count > 0 {
+        summary.insert(
+            "deleted-data-files".to_string(),
+            snapshot.removed_files_count.to_string(),
+        );
+        summary.insert(
+            "deleted-records".to_string(),
+            snapshot.removed_records_count.to_string(),
+        );
+        "replace"
+    } else {
+        "append"
+    };
+    summary.insert("operation".to_string(), operation.to_string());
+
+    summary
+}
+
+/// Snapshots becoming current as they were taken.
+fn creation_log(snapshots: &[Snapshot]) -> Vec<SnapshotLogEntry> {
+    snapshots
+        .iter()
+        .map(|snapshot| SnapshotLogEntry {
+            snapshot_id: snapshot.snapshot_id,
+            timestamp_ms: snapshot.timestamp_ms,
+        })
+        .collect()
+}
+
+/// Reads and parses the metadata file of a table.
+async fn read_metadata_file(io: &FileIO, location: &str) -> Result<MetadataFile, QueryError> {
+    let data = io
+        .read(location)
+        .await
+        .map_err(|e| QueryError::InternalError(e.to_string()))?;
+    parse_metadata(location, &data)
+}
+
+/// Reads the metadata file of a table, and the manifest list and manifests
+/// of its current snapshot.
+async fn read_iceberg_metadata(io: &FileIO, location: &str) -> Result<TableMetadata, QueryError> {
+    let MetadataFile {
+        schema,
+        properties,
+        snapshots,
+        current_snapshot_id,
+        ..
+    } = read_metadata_file(io, location).await?;
+    let current = snapshots
+        .iter()
+        .find(|snapshot| Some(snapshot.snapshot_id()) == current_snapshot_id);
+
+    let (manifests, files) = match current {
+        Some(snapshot) => read_snapshot_files(io, snapshot, &schema).await?,
+        None => (Vec::new(), Vec::new()),
+    };
+
+    Ok(TableMetadata {
+        properties,
+        snapshots: sorted_snapshots(&snapshots),
+        current_snapshot_id: current_snapshot_id.map(|id| id as i64),
+        files,
+        manifests,
+    })
+}
+
+/// Reads the manifests of a snapshot, from its manifest list or, for v1
+/// snapshots without one, the snapshot itself, and the data files they list.
+async fn read_snapshot_files(
+    io: &FileIO,
+    snapshot: &IcebergSnapshot,
+    schema: &IcebergSchema,
+) -> Result<(Vec<ManifestFile>, Vec<DataFile>), QueryError> {
+    let read = |location: String| async move {
+        io.read(&location)
+            .await
+            .map_err(|e| QueryError::InternalError(e.to_string()))
+    };
+    let manifest_error = |location: &str, e: manifest::ManifestError| {
+        QueryError::InternalError(format!("{}: {}", location, e))
+    };
+
+    // Manifests come with their entries, which v1 snapshots without a
+    // manifest list are summarized from
+    let mut manifests: Vec<(ManifestFile, Option<Vec<ManifestEntry>>)> = Vec::new();
+    match snapshot.manifest_list() {
+        Some(list) => {
+            let data = read(list.to_string()).await?;
+            let list_files =
+                manifest::read_manifest_list(&data).map_err(|e| manifest_error(list, e))?;
+            manifests.extend(list_files.into_iter().map(|file| (file, None)));
+        }
+        None => {
+            for path in snapshot.manifests() {
+                let data = read(path.clone()).await?;
+                let entries =
+                    manifest::read_manifest(&data).map_err(|e| manifest_error(path, e))?;
+                let file = ManifestFile::from_entries(
+                    path,
+                    data.len() as i64,
+                    snapshot.snapshot_id() as i64,
+                    &entries,
+                );
+                manifests.push((file, Some(entries)));
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    for (manifest, entries) in &manifests {
+        if manifest.content != DATA_CONTENT {
+            continue;
+        }
+        let entries = match entries {
+            Some(entries) => entries.clone(),
+            None => {
+                let path = &manifest.manifest_path;
+                let data = read(path.clone()).await?;
+                manifest::read_manifest(&data).map_err(|e| manifest_error(path, e))?
+            }
+        };
+
+        files.extend(
+            entries
+                .into_iter()
+                .filter(|entry| entry.status != EntryStatus::Deleted)
+                .map(|entry| data_file(entry, schema)),
+        );
+    }
+
+    Ok((manifests.into_iter().map(|(file, _)| file).collect(), files))
+}
+
+/// Parts of a metadata file the metadata tables show and scans are planned
+/// from.
+struct MetadataFile {
+    schema: IcebergSchema,
+    partition_fields: Vec<PartitionField>,
+    properties: HashMap<String, String>,
+    snapshots: Vec<IcebergSnapshot>,
+    current_snapshot_id: Option<u64>,
+    snapshot_log: Vec<SnapshotLogEntry>,
+}
+
+/// The snapshot log of a metadata file, which the iceberg crate doesn't
+/// model.
+#[derive(Deserialize)]
+struct SnapshotLog {
+    #[serde(default, rename = "snapshot-log")]
+    snapshot_log: Vec<SnapshotLogEntry>,
+}
+
+/// Parses a metadata file. The parsed metadata itself isn't `Send`, so it
+/// doesn't outlive this function.
+fn parse_metadata(location: &str, data: &[u8]) -> Result<MetadataFile, QueryError> {
+    let invalid = |e: &dyn fmt::Display| QueryError::InternalError(format!("{}: {}", location, e));
+
+    let json = std::str::from_utf8(data).map_err(|e| invalid(&e))?;
+    let metadata = iceberg::parser::metadata::from_json(json).map_err(|e| invalid(&e))?;
+    let log: SnapshotLog = serde_json::from_str(json).map_err(|e| invalid(&e))?;
+
+    let partition_fields = metadata
+        .partition_spec
+        .fields()
+        .iter()
+        .map(|field| PartitionField {
+            name: field.name.clone(),
+            source_id: field.source_id as i32,
+            transform: field.transform.name().to_string(),
+        })
+        .collect();
+
+    Ok(MetadataFile {
+        schema: metadata.schema,
+        partition_fields,
+        properties: metadata.properties,
+        snapshots: metadata.snapshots,
+        current_snapshot_id: metadata.current_snapshot_id,
+        snapshot_log: log.snapshot_log,
+    })
+}
+
+/// Snapshots of a metadata file, oldest first.
+fn sorted_snapshots(snapshots: &[IcebergSnapshot]) -> Vec<Snapshot> {
+    let mut snapshots: Vec<Snapshot> = snapshots.iter().map(snapshot).collect();
+    snapshots.sort_by_key(|snapshot| (snapshot.timestamp_ms, snapshot.sequence_number));
+    snapshots
+}
+
+fn snapshot(snapshot: &IcebergSnapshot) -> Snapshot {
+    let count = |key: &str| {
+        snapshot
+            .summary()
+            .get(key)
+            .and_then(|count| count.parse().ok())
+            .unwrap_or_default()
+    };
+
+    Snapshot {
+        snapshot_id: snapshot.snapshot_id() as i64,
+        parent_snapshot_id: snapshot.parent_snapshot_id().map(|id| id as i64),
+        sequence_number: snapshot.sequence_number() as i64,
+        timestamp_ms: snapshot.timestamp_ms() as i64,
+        added_files_count: count("added-data-files"),
+        added_records_count: count("added-records"),
+        removed_files_count: count("deleted-data-files"),
+        removed_records_count: count("deleted-records"),
+        summary: snapshot.summary().clone(),
+    }
+}
+
+/// A data file of a manifest, with its bounds as JSON as the write service
+/// collects them.
+fn data_file(entry: ManifestEntry, schema: &IcebergSchema) -> DataFile {
+    let file = entry.data_file;
+    let counts = |counts: HashMap<i32, i64>| {
+        counts
+            .into_iter()
+            .map(|(id, count)| (id.to_string(), count))
+            .collect()
+    };
+    let bounds = |bounds: HashMap<i32, Vec<u8>>| {
+        bounds
+            .into_iter()
+            .filter_map(|(id, bound)| {
+                let field = schema.fields.iter().find(|field| field.id == id as u32)?;
+                Some((id.to_string(), bound_value(&bound, &field.field_type)?))
+            })
+            .collect()
+    };
+
+    DataFile {
+        path: file.file_path,
+        record_count: file.record_count,
+        file_size_in_bytes: file.file_size_in_bytes,
+        value_counts: counts(file.value_counts),
+        null_value_counts: counts(file.null_value_counts),
+        nan_value_counts: counts(file.nan_value_counts),
+        lower_bounds: bounds(file.lower_bounds),
+        upper_bounds: bounds(file.upper_bounds),
+        file_format: Some(file.file_format),
+        partition: file.partition,
+    }
+}
+
+/// Decodes a bound from Iceberg's single-value binary form. Binary, decimal
+/// and other bounds the write service has no JSON for are left out.
+fn bound_value(bound: &[u8], field_type: &str) -> Option<Value> {
+    let value = match field_type {
+        "boolean" => Value::from(*bound.first()? != 0),
+        "int" | "date" => Value::from(i32::from_le_bytes(bound.try_into().ok()?)),
+        "long" | "time" | "timestamp" | "timestamptz" => {
+            Value::from(i64::from_le_bytes(bound.try_into().ok()?))
+        }
+        "float" => Value::from(f32::from_le_bytes(bound.try_into().ok()?) as f64),
+        "double" => Value::from(f64::from_le_bytes(bound.try_into().ok()?)),
+        "string" => Value::from(std::str::from_utf8(bound).ok()?),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Maps the column types of the catalog to Arrow. Nested and unknown types
+/// are read as strings.
+fn arrow_schema(schema: &CatalogSchema) -> SchemaRef {
+    let fields: Vec<Field> = schema
+        .fields
+        .iter()
+        .map(|field| {
+            let data_type = match field.field_type.as_str() {
+                "boolean" => DataType::Boolean,
+                "int" => DataType::Int32,
+                "long" => DataType::Int64,
+                "float" => DataType::Float32,
+                "double" => DataType::Float64,
+                "date" => DataType::Date32,
+                "time" => DataType::Time64(TimeUnit::Microsecond),
+                "timestamp" => DataType::Timestamp(TimeUnit::Microsecond, None),
+                "binary" => DataType::Binary,
+                _ => DataType::Utf8,
+            };
+            Field::new(&field.field, data_type, !field.required)
+        })
+        .collect();
+
+    Arc::new(Schema::new(fields))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_tables::MetadataTable;
+    use arrow::array::BooleanArray;
+    use axum::body::Bytes;
+
+    #[tokio::test]
+    async fn test_read_iceberg_metadata() {
+        // Rolled back to the first snapshot, whose manifests are all gone
+        let json = r#"{
+            "format-version": 2,
+            "table-uuid": "9c12d441-03fe-4693-9a96-a0705ddf69c1",
+            "location": "memory://warehouse/db/events",
+            "last-updated-ms": 1723320520000,
+            "last-column-id": 1,
+            "current-schema-id": 0,
+            "schemas": [{"type": "struct", "schema-id": 0, "fields": [
+                {"id": 1, "name": "id", "required": true, "type": "long"}
+            ]}],
+            "default-spec-id": 0,
+            "partition-specs": [{"spec-id": 0, "fields": []}],
+            "properties": {"owner": "spark"},
+            "current-snapshot-id": 1,
+            "snapshot-log": [
+                {"snapshot-id": 1, "timestamp-ms": 1723320510000},
+                {"snapshot-id": 2, "timestamp-ms": 1723320520000},
+                {"snapshot-id": 1, "timestamp-ms": 1723320530000}
+            ],
+            "snapshots": [
+                {
+                    "sequence-number": 2,
+                    "snapshot-id": 2,
+                    "parent-snapshot-id": 1,
+                    "timestamp-ms": 1723320520000,
+                    "summary": {"operation": "overwrite", "deleted-data-files": "1"},
+                    "manifests": []
+                },
+                {
+                    "sequence-number": 1,
+                    "snapshot-id": 1,
+                    "timestamp-ms": 1723320510000,
+                    "summary": {"operation": "append", "added-data-files": "1"},
+                    "manifests": []
+                }
+            ]
+        }"#;
+        let location = "memory://warehouse/db/events/metadata/v3.metadata.json";
+        let io = FileIO::default();
+        io.write(location, Bytes::from(json)).await.unwrap();
+
+        let file = read_metadata_file(&io, location).await.unwrap();
+        assert_eq!(
+            file.snapshot_log.last(),
+            Some(&SnapshotLogEntry {
+                snapshot_id: 1,
+                timestamp_ms: 1723320530000
+            })
+        );
+
+        let metadata = read_iceberg_metadata(&io, location).await.unwrap();
+        assert_eq!(metadata.properties["owner"], "spark");
+        assert_eq!(metadata.current_snapshot_id, Some(1));
+        assert_eq!(metadata.snapshots[0].added_files_count, 1);
+        assert_eq!(metadata.snapshots[1].removed_files_count, 1);
+        assert!(metadata.files.is_empty() && metadata.manifests.is_empty());
+
+        let history = MetadataTable::History.build(&metadata).unwrap();
+        let current = history.column(3).as_any().downcast_ref::<BooleanArray>();
+        assert_eq!(current.unwrap(), &BooleanArray::from(vec![true, false]));
+
+        assert_eq!(bound_value(&7i32.to_le_bytes(), "date"), Some(7.into()));
+        assert_eq!(bound_value(b"eu", "string"), Some("eu".into()));
+        assert_eq!(bound_value(&[0; 16], "decimal(38, 2)"), None);
+    }
+}
@@ -1,77 +1,600 @@
-use arrow::record_batch::RecordBatch;
-use arrow::array::{Int64Array, StringArray};
-use arrow::datatypes::{DataType, Field, Schema};
+use arrow::datatypes::SchemaRef;
+use datafusion::{
+    catalog::{
+        schema::{MemorySchemaProvider, SchemaProvider},
+        MemoryCatalogProvider,
+    },
+    datasource::MemTable,
+    error::DataFusionError,
+    execution::SendableRecordBatchStream,
+    logical_expr::LogicalPlan,
+    physical_plan::stream::RecordBatchStreamAdapter,
+    prelude::{SessionConfig, SessionContext},
+    sql::{ResolvedTableReference, TableReference},
+};
+use futures::{stream, StreamExt};
 use std::sync::Arc;
 use thiserror::Error;
 
+use crate::{
+    catalog::{TableIdentifier, TableSource, DEFAULT_NAMESPACE},
+    metadata_tables::MetadataTable,
+    time_travel::{self, TableVersion},
+};
+
+/// Catalog that unqualified and `db.table` references resolve in.
+const DEFAULT_CATALOG: &str = "datafusion";
+
+/// Schema of the views DataFusion describes the registered tables with.
+const INFORMATION_SCHEMA: &str = "information_schema";
+
 #[derive(Error, Debug)]
 pub enum QueryError {
     #[error("Failed to parse query: {0}")]
     ParseError(String),
+    #[error("Table not found: {0}")]
+    TableNotFound(String),
+    #[error("Snapshot not found: {0}")]
+    SnapshotNotFound(String),
     #[error("Execution error: {0}")]
     ExecutionError(String),
     #[error("Internal error: {0}")]
     InternalError(String),
 }
 
-pub struct QueryEngine;
+impl From<DataFusionError> for QueryError {
+    fn from(e: DataFusionError) -> Self {
+        match e {
+            DataFusionError::SQL(..)
+            | DataFusionError::Plan(_)
+            | DataFusionError::SchemaError(..) => QueryError::ParseError(e.to_string()),
+            e => QueryError::ExecutionError(e.to_string()),
+        }
+    }
+}
+
+pub struct QueryEngine {
+    tables: Arc<dyn TableSource>,
+}
 
 impl QueryEngine {
-    pub fn new() -> Self {
-        QueryEngine
+    pub fn new(tables: Arc<dyn TableSource>) -> Self {
+        QueryEngine { tables }
+    }
+
+    /// Runs a query, returning its batches as they are produced. Nothing more
+    /// is computed than the stream is polled for, and dropping it cancels the
+    /// query.
+    pub async fn execute_query(
+        &self,
+        query: &str,
+    ) -> Result<SendableRecordBatchStream, QueryError> {
+        let (ctx, plan) = self.plan(query).await?;
+        let mut batches = ctx
+            .execute_logical_plan(plan)
+            .await?
+            .execute_stream()
+            .await?;
+
+        // Most failures surface with the first batch, which is awaited so that
+        // they are still returned as errors rather than in the stream
+        let first = batches.next().await.transpose()?;
+        let schema = batches.schema();
+        let batches = stream::iter(first.map(Ok)).chain(batches);
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(schema, batches)))
     }
 
-    pub async fn execute_query(&self, query: &str) -> Result<Vec<RecordBatch>, QueryError> {
-        // This is a dummy implementation.
-        // In real steps could do be:
-        // 1. Parse the query
-        // 2. Create a logical plan
-        // 3. Optimize the plan
-        // 4. Create a physical plan
-        // 5. Execute the plan and collect results
+    /// Schema of the result of a query, without running it.
+    pub async fn query_schema(&self, query: &str) -> Result<SchemaRef, QueryError> {
+        let (_, plan) = self.plan(query).await?;
+        Ok(Arc::new(plan.schema().as_arrow().clone()))
+    }
+
+    /// Tables of the catalog.
+    pub async fn list_tables(&self) -> Result<Vec<TableIdentifier>, QueryError> {
+        self.tables.list_tables().await
+    }
 
-        // For now, we'll just create a dummy RecordBatch based on the query length
-        if query.is_empty() {
+    /// Schema of a table, or `None` if it doesn't exist.
+    pub async fn table_schema(
+        &self,
+        identifier: &TableIdentifier,
+    ) -> Result<Option<SchemaRef>, QueryError> {
+        let ctx = context();
+        let table = self
+            .tables
+            .load_table(&ctx.state(), identifier, None)
+            .await?;
+        Ok(table.map(|table| table.schema()))
+    }
+
+    async fn plan(&self, query: &str) -> Result<(SessionContext, LogicalPlan), QueryError> {
+        if query.trim().is_empty() {
             return Err(QueryError::ParseError("Empty query".to_string()));
         }
+        let ctx = context();
+
+        // Only the tables the query references are loaded from the catalog,
+        // but catalog queries such as `SHOW TABLES` see all of them
+        let rewritten = time_travel::rewrite(query)?;
+        let statement = ctx.state().sql_to_statement(&rewritten.query, "generic")?;
+        let (catalog_tables, references): (Vec<_>, Vec<_>) = ctx
+            .state()
+            .resolve_table_references(&statement)?
+            .into_iter()
+            .partition(|reference| reference.schema() == Some(INFORMATION_SCHEMA));
+
+        if !catalog_tables.is_empty() {
+            for table in self.tables.list_tables().await? {
+                if let Some(reference) = table_reference(&table) {
+                    self.register_table(&ctx, reference, table, None).await?;
+                }
+            }
+        }
+        for reference in references {
+            if let Some((table, metadata_table)) = MetadataTable::parse(reference.table()) {
+                let identifier = TableIdentifier {
+                    name: table.to_string(),
+                    ..table_identifier(&reference)
+                };
+                self.register_metadata_table(&ctx, reference, &identifier, metadata_table)
+                    .await?;
+                continue;
+            }
+
+            // Names time travel clauses were replaced with are bare
+            let versioned = match &reference {
+                TableReference::Bare { table } => rewritten.tables.get(table.as_ref()),
+                _ => None,
+            };
+            let (identifier, snapshot_id) = match versioned {
+                Some(versioned) => {
+                    let identifier = table_identifier(&versioned.reference);
+                    let snapshot_id = self.snapshot_id(&identifier, versioned.version).await?;
+                    (identifier, Some(snapshot_id))
+                }
+                None => (table_identifier(&reference), None),
+            };
+            self.register_table(&ctx, reference, identifier, snapshot_id)
+                .await?;
+        }
+
+        let plan = ctx.state().statement_to_plan(statement).await?;
+        Ok((ctx, plan))
+    }
+
+    /// Resolves a version of a table to a snapshot: ids through the table's
+    /// snapshots, times through its snapshot log, so that a time after a
+    /// rollback reads the snapshot rolled back to.
+    async fn snapshot_id(
+        &self,
+        identifier: &TableIdentifier,
+        version: TableVersion,
+    ) -> Result<i64, QueryError> {
+        let not_found = || QueryError::TableNotFound(identifier.to_string());
+
+        let snapshot_id = match version {
+            TableVersion::Snapshot(id) => {
+                let snapshots = self.tables.snapshots(identifier).await?;
+                let snapshots = snapshots.ok_or_else(not_found)?;
+                snapshots.iter().any(|s| s.snapshot_id == id).then_some(id)
+            }
+            TableVersion::AsOfTime(ms) => {
+                let log = self.tables.snapshot_log(identifier).await?;
+                let log = log.ok_or_else(not_found)?;
+                log.iter()
+                    .rev()
+                    .find(|entry| entry.timestamp_ms <= ms)
+                    .map(|entry| entry.snapshot_id)
+            }
+        };
+        snapshot_id
+            .ok_or_else(|| QueryError::SnapshotNotFound(format!("{} {}", identifier, version)))
+    }
+
+    /// Registers a referenced table under the catalog and schema DataFusion
+    /// resolves the reference to: `table` and `db.table` live in the default
+    /// catalog, `ns1.ns2.table` in catalog `ns1`. The table is read at a
+    /// snapshot if one is given.
+    async fn register_table(
+        &self,
+        ctx: &SessionContext,
+        reference: TableReference,
+        identifier: TableIdentifier,
+        snapshot_id: Option<i64>,
+    ) -> Result<(), QueryError> {
+        let resolved = reference.resolve(DEFAULT_CATALOG, DEFAULT_NAMESPACE);
+        let schema = schema_provider(ctx, &resolved)?;
+        if schema.table_exist(&resolved.table) {
+            return Ok(());
+        }
+
+        let table = self
+            .tables
+            .load_table(&ctx.state(), &identifier, snapshot_id)
+            .await?
+            .ok_or_else(|| QueryError::TableNotFound(identifier.to_string()))?;
+
+        schema.register_table(resolved.table.to_string(), table)?;
+
+        Ok(())
+    }
+
+    /// Registers a metadata table, such as `orders$files`, of the table with
+    /// the identifier.
+    async fn register_metadata_table(
+        &self,
+        ctx: &SessionContext,
+        reference: TableReference,
+        identifier: &TableIdentifier,
+        metadata_table: MetadataTable,
+    ) -> Result<(), QueryError> {
+        let resolved = reference.resolve(DEFAULT_CATALOG, DEFAULT_NAMESPACE);
+        let schema = schema_provider(ctx, &resolved)?;
+        if schema.table_exist(&resolved.table) {
+            return Ok(());
+        }
+
+        let metadata = self
+            .tables
+            .metadata(identifier)
+            .await?
+            .ok_or_else(|| QueryError::TableNotFound(identifier.to_string()))?;
+        let batch = metadata_table
+            .build(&metadata)
+            .map_err(DataFusionError::from)?;
+        let table = MemTable::try_new(batch.schema(), vec![vec![batch]])?;
+        schema.register_table(resolved.table.to_string(), Arc::new(table))?;
+
+        Ok(())
+    }
+}
+
+/// Schema a resolved reference lives in, registered if it isn't yet.
+fn schema_provider(
+    ctx: &SessionContext,
+    resolved: &ResolvedTableReference,
+) -> Result<Arc<dyn SchemaProvider>, QueryError> {
+    let catalog = match ctx.catalog(&resolved.catalog) {
+        Some(catalog) => catalog,
+        None => {
+            let catalog = Arc::new(MemoryCatalogProvider::new());
+            ctx.register_catalog(resolved.catalog.as_ref(), catalog.clone());
+            catalog
+        }
+    };
 
-        let schema = Arc::new(Schema::new(vec![
-            Field::new("id", DataType::Int64, false),
-            Field::new("value", DataType::Utf8, false),
-        ]));
+    match catalog.schema(&resolved.schema) {
+        Some(schema) => Ok(schema),
+        None => {
+            let schema: Arc<dyn SchemaProvider> = Arc::new(MemorySchemaProvider::new());
+            catalog.register_schema(&resolved.schema, schema.clone())?;
+            Ok(schema)
+        }
+    }
+}
 
-        let id_array = Int64Array::from_iter_values(0..query.len() as i64);
-        let value_array = StringArray::from_iter_values(std::iter::repeat("value").take(query.len()));
+fn context() -> SessionContext {
+    let config = SessionConfig::new()
+        .with_default_catalog_and_schema(DEFAULT_CATALOG, DEFAULT_NAMESPACE)
+        .with_information_schema(true);
+    SessionContext::new_with_config(config)
+}
 
-        let batch = RecordBatch::try_new(schema, vec![
-            Arc::new(id_array),
-            Arc::new(value_array),
-        ])
-        .map_err(|e| QueryError::InternalError(e.to_string()))?;
+/// Catalog, schema and name a table is referenced by in SQL, the inverse of
+/// [`table_identifier`]. Tables more than two namespaces deep can't be.
+pub fn sql_reference(identifier: &TableIdentifier) -> Option<ResolvedTableReference> {
+    table_reference(identifier)
+        .map(|reference| reference.resolve(DEFAULT_CATALOG, DEFAULT_NAMESPACE))
+}
 
-        Ok(vec![batch])
+fn table_reference(identifier: &TableIdentifier) -> Option<TableReference> {
+    let table = identifier.name.as_str();
+    match identifier.namespace.as_slice() {
+        [schema] => Some(TableReference::partial(schema.as_str(), table)),
+        [catalog, schema] => Some(TableReference::full(
+            catalog.as_str(),
+            schema.as_str(),
+            table,
+        )),
+        _ => None,
+    }
+}
+
+/// Maps a SQL table reference to the catalog identifier of the table.
+fn table_identifier(reference: &TableReference) -> TableIdentifier {
+    let namespace = match reference {
+        TableReference::Bare { .. } => vec![DEFAULT_NAMESPACE.to_string()],
+        TableReference::Partial { schema, .. } => vec![schema.to_string()],
+        TableReference::Full {
+            catalog, schema, ..
+        } => vec![catalog.to_string(), schema.to_string()],
+    };
+
+    TableIdentifier {
+        namespace,
+        name: reference.table().to_string(),
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use crate::catalog::{DataFile, Snapshot, SnapshotLogEntry, TableMetadata};
+    use arrow::array::{Int64Array, RecordBatch, StringArray};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use async_trait::async_trait;
+    use datafusion::{
+        datasource::{MemTable, TableProvider},
+        execution::context::SessionState,
+    };
+    use futures::TryStreamExt;
+    use iceberg::manifest::ManifestFile;
+    use std::collections::HashMap;
+
+    /// Serves in-memory tables with `id` and `value` columns. A table of `n`
+    /// rows has a snapshot `100 + i` of `i` rows, taken at `i` seconds, per
+    /// row. `sales.eu.orders` was rolled back to its first snapshot at 10s.
+    struct MemoryTables {
+        row_counts: HashMap<String, usize>,
+    }
+
+    #[async_trait]
+    impl TableSource for MemoryTables {
+        async fn load_table(
+            &self,
+            _state: &SessionState,
+            identifier: &TableIdentifier,
+            snapshot_id: Option<i64>,
+        ) -> Result<Option<Arc<dyn TableProvider>>, QueryError> {
+            let Some(&rows) = self.row_counts.get(&identifier.to_string()) else {
+                return Ok(None);
+            };
+            let rows = snapshot_id.map_or(rows, |id| (id - 100) as usize);
+
+            let schema = Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int64, false),
+                Field::new("value", DataType::Utf8, false),
+            ]));
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int64Array::from_iter_values(0..rows as i64)),
+                    Arc::new(StringArray::from_iter_values(std::iter::repeat_n(
+                        "value", rows,
+                    ))),
+                ],
+            )
+            .unwrap();
+
+            Ok(Some(Arc::new(MemTable::try_new(
+                schema,
+                vec![vec![batch]],
+            )?)))
+        }
+
+        async fn snapshots(
+            &self,
+            identifier: &TableIdentifier,
+        ) -> Result<Option<Vec<Snapshot>>, QueryError> {
+            let rows = self.row_counts.get(&identifier.to_string());
+            Ok(rows.map(|&rows| {
+                (1..=rows as i64)
+                    .map(|i| Snapshot {
+                        snapshot_id: 100 + i,
+                        parent_snapshot_id: (i > 1).then_some(99 + i),
+                        sequence_number: i,
+                        timestamp_ms: i * 1000,
+                        added_files_count: 1,
+                        added_records_count: 1,
+                        ..Default::default()
+                    })
+                    .collect()
+            }))
+        }
+
+        async fn snapshot_log(
+            &self,
+            identifier: &TableIdentifier,
+        ) -> Result<Option<Vec<SnapshotLogEntry>>, QueryError> {
+            let Some(snapshots) = self.snapshots(identifier).await? else {
+                return Ok(None);
+            };
+            let mut log: Vec<SnapshotLogEntry> = snapshots
+                .iter()
+                .map(|snapshot| SnapshotLogEntry {
+                    snapshot_id: snapshot.snapshot_id,
+                    timestamp_ms: snapshot.timestamp_ms,
+                })
+                .collect();
+            if identifier.to_string() == "sales.eu.orders" {
+                log.push(SnapshotLogEntry {
+                    snapshot_id: 101,
+                    timestamp_ms: 10_000,
+                });
+            }
+            Ok(Some(log))
+        }
+
+        async fn metadata(
+            &self,
+            identifier: &TableIdentifier,
+        ) -> Result<Option<TableMetadata>, QueryError> {
+            let Some(snapshots) = self.snapshots(identifier).await? else {
+                return Ok(None);
+            };
+            let files = snapshots
+                .iter()
+                .map(|snapshot| DataFile {
+                    path: format!("{}/{}.parquet", identifier, snapshot.sequence_number),
+                    record_count: 1,
+                    ..Default::default()
+                })
+                .collect();
+            // A manifest per snapshot, as if it had been written by Spark
+            let manifests = snapshots
+                .iter()
+                .map(|snapshot| ManifestFile {
+                    manifest_path: format!(
+                        "{}/metadata/{}-m0.avro",
+                        identifier, snapshot.snapshot_id
+                    ),
+                    added_snapshot_id: snapshot.snapshot_id,
+                    sequence_number: snapshot.sequence_number,
+                    added_files_count: 1,
+                    added_rows_count: 1,
+                    ..Default::default()
+                })
+                .collect();
+            let properties = HashMap::from([("owner".to_string(), "test".to_string())]);
+
+            Ok(Some(TableMetadata {
+                properties,
+                current_snapshot_id: snapshots.last().map(|s| s.snapshot_id),
+                snapshots,
+                files,
+                manifests,
+            }))
+        }
+
+        async fn list_tables(&self) -> Result<Vec<TableIdentifier>, QueryError> {
+            let mut names: Vec<&String> = self.row_counts.keys().collect();
+            names.sort();
+            Ok(names
+                .into_iter()
+                .map(|name| TableIdentifier::parse(name))
+                .collect())
+        }
+    }
+
+    pub(crate) fn engine() -> QueryEngine {
+        let row_counts = HashMap::from([
+            ("default.dummy".to_string(), 19),
+            ("sales.orders".to_string(), 3),
+            ("sales.eu.orders".to_string(), 2),
+        ]);
+
+        QueryEngine::new(Arc::new(MemoryTables { row_counts }))
+    }
+
+    async fn query(engine: &QueryEngine, query: &str) -> Result<Vec<RecordBatch>, QueryError> {
+        let batches = engine.execute_query(query).await?;
+        Ok(batches.try_collect().await?)
+    }
+
+    fn row_count(batches: &[RecordBatch]) -> usize {
+        batches.iter().map(|b| b.num_rows()).sum()
+    }
 
     #[tokio::test]
     async fn test_execute_query() {
-        let engine = QueryEngine::new();
-        let result = engine.execute_query("SELECT * FROM dummy").await;
+        let engine = engine();
+        let result = query(&engine, "SELECT * FROM dummy").await;
         assert!(result.is_ok());
         let batches = result.unwrap();
         assert_eq!(batches.len(), 1);
         assert_eq!(batches[0].num_columns(), 2);
-        assert_eq!(batches[0].num_rows(), 19); // Length of "SELECT * FROM dummy"
+        assert_eq!(batches[0].num_rows(), 19);
     }
 
     #[tokio::test]
     async fn test_empty_query() {
-        let engine = QueryEngine::new();
-        let result = engine.execute_query("").await;
+        let engine = engine();
+        let result = query(&engine, "").await;
         assert!(matches!(result, Err(QueryError::ParseError(_))));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_namespaced_tables() {
+        let engine = engine();
+
+        let batches = query(&engine, "SELECT id FROM sales.orders").await.unwrap();
+        assert_eq!(row_count(&batches), 3);
+
+        let batches = query(
+            &engine,
+            "SELECT a.id FROM sales.eu.orders a JOIN default.dummy b ON a.id = b.id",
+        )
+        .await
+        .unwrap();
+        assert_eq!(row_count(&batches), 2);
+
+        let result = query(&engine, "SELECT * FROM sales.missing").await;
+        assert!(matches!(result, Err(QueryError::TableNotFound(t)) if t == "sales.missing"));
+    }
+
+    #[tokio::test]
+    async fn test_time_travel() {
+        let engine = engine();
+
+        let batches = query(&engine, "SELECT id FROM sales.orders FOR VERSION AS OF 102")
+            .await
+            .unwrap();
+        assert_eq!(row_count(&batches), 2);
+
+        // The snapshot taken at 1s is the last one at 1.5s
+        let batches = query(
+            &engine,
+            "SELECT orders.id, o.id FROM sales.orders FOR SYSTEM_TIME AS OF '1970-01-01T00:00:01.5Z'
+             JOIN sales.orders o ON orders.id = o.id",
+        )
+        .await
+        .unwrap();
+        assert_eq!(row_count(&batches), 1);
+
+        // Times after a rollback read the snapshot rolled back to
+        let batches = query(
+            &engine,
+            "SELECT id FROM sales.eu.orders FOR SYSTEM_TIME AS OF '1970-01-01T00:00:10Z'",
+        )
+        .await
+        .unwrap();
+        assert_eq!(row_count(&batches), 1);
+
+        let result = query(&engine, "SELECT * FROM sales.orders FOR VERSION AS OF 7").await;
+        assert!(matches!(result, Err(QueryError::SnapshotNotFound(_))));
+        let result = query(
+            &engine,
+            "SELECT * FROM dummy FOR SYSTEM_TIME AS OF '1970-01-01T00:00:00Z'",
+        )
+        .await;
+        assert!(matches!(result, Err(QueryError::SnapshotNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_metadata_tables() {
+        let engine = engine();
+
+        let batches = query(
+            &engine,
+            "SELECT snapshot_id, parent_id FROM sales.orders$snapshots WHERE parent_id = 101",
+        )
+        .await
+        .unwrap();
+        assert_eq!(row_count(&batches), 1);
+
+        for (table, rows) in [
+            ("sales.orders$history", 3),
+            ("sales.orders$files", 3),
+            ("sales.orders$manifests", 3),
+            ("sales.orders$partitions", 1),
+            ("sales.orders$properties", 1),
+            ("sales.eu.orders$files", 2),
+        ] {
+            let batches = query(&engine, &format!("SELECT * FROM {}", table))
+                .await
+                .unwrap();
+            assert_eq!(row_count(&batches), rows, "{}", table);
+        }
+
+        let batches = query(&engine, "SELECT record_count FROM dummy$partitions")
+            .await
+            .unwrap();
+        let counts = batches[0].column(0).as_any().downcast_ref::<Int64Array>();
+        assert_eq!(counts.unwrap().value(0), 19);
+
+        let result = query(&engine, "SELECT * FROM missing$files").await;
+        assert!(matches!(result, Err(QueryError::TableNotFound(t)) if t == "default.missing"));
+    }
+}
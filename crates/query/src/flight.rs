@@ -0,0 +1,542 @@
+//! Arrow Flight SQL endpoint of the query service, for ADBC, JDBC and ODBC
+//! drivers. Statements run through the [`QueryEngine`] like those of
+//! `/query`, their batches streamed as Flight data as they are produced.
+//!
+//! Prepared statements are kept until closed or idle for
+//! [`STATEMENT_IDLE_TIMEOUT`], at most [`MAX_STATEMENTS`] of them, and take
+//! no parameters. Tables
+//! are listed in the catalog and schema SQL references them by, which tables
+//! more than two namespaces deep have none of.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use arrow::{
+    datatypes::Schema, error::ArrowError, ipc::writer::IpcWriteOptions, record_batch::RecordBatch,
+};
+use arrow_flight::{
+    encode::FlightDataEncoderBuilder,
+    error::FlightError,
+    flight_service_server::FlightService,
+    sql::{
+        metadata::{SqlInfoData, SqlInfoDataBuilder},
+        server::FlightSqlService,
+        ActionClosePreparedStatementRequest, ActionCreatePreparedStatementRequest,
+        ActionCreatePreparedStatementResult, CommandGetCatalogs, CommandGetDbSchemas,
+        CommandGetSqlInfo, CommandGetTables, CommandPreparedStatementQuery, CommandStatementQuery,
+        ProstMessageExt, SqlInfo, TicketStatementQuery,
+    },
+    Action, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo, IpcMessage, SchemaAsIpc,
+    Ticket,
+};
+use axum::body::Bytes;
+use datafusion::{execution::SendableRecordBatchStream, sql::ResolvedTableReference};
+use futures::{stream, Stream, TryStreamExt};
+use prost::Message;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use crate::{
+    catalog::TableIdentifier,
+    engine::{sql_reference, QueryEngine, QueryError},
+};
+
+/// Prepared statements unused for this long are closed.
+const STATEMENT_IDLE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Open prepared statements, beyond which the least recently used is closed.
+const MAX_STATEMENTS: usize = 1024;
+
+type FlightDataStream = Pin<Box<dyn Stream<Item = Result<FlightData, Status>> + Send>>;
+
+pub struct FlightSqlServer {
+    engine: Arc<QueryEngine>,
+    statements: Mutex<Statements>,
+    sql_info: SqlInfoData,
+}
+
+/// Queries of open prepared statements, by handle, with when they were last
+/// used. Clients that never close theirs can't keep them open forever.
+struct Statements {
+    queries: HashMap<Bytes, (String, Instant)>,
+    idle_timeout: Duration,
+    capacity: usize,
+}
+
+impl Statements {
+    fn new(idle_timeout: Duration, capacity: usize) -> Self {
+        Statements {
+            queries: HashMap::new(),
+            idle_timeout,
+            capacity,
+        }
+    }
+
+    fn insert(&mut self, handle: Bytes, query: String) {
+        let now = Instant::now();
+        self.queries
+            .retain(|_, (_, used)| now.duration_since(*used) < self.idle_timeout);
+
+        if self.queries.len() >= self.capacity {
+            let oldest = self
+                .queries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(handle, _)| handle.clone());
+            if let Some(oldest) = oldest {
+                self.queries.remove(&oldest);
+            }
+        }
+
+        self.queries.insert(handle, (query, now));
+    }
+
+    fn get(&mut self, handle: &Bytes) -> Option<String> {
+        let now = Instant::now();
+        match self.queries.get_mut(handle) {
+            Some((query, used)) if now.duration_since(*used) < self.idle_timeout => {
+                *used = now;
+                Some(query.clone())
+            }
+            Some(_) => {
+                self.queries.remove(handle);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn remove(&mut self, handle: &Bytes) {
+        self.queries.remove(handle);
+    }
+}
+
+impl FlightSqlServer {
+    pub fn new(engine: Arc<QueryEngine>) -> Self {
+        let mut sql_info = SqlInfoDataBuilder::new();
+        sql_info.append(SqlInfo::FlightSqlServerName, "crate");
+        sql_info.append(SqlInfo::FlightSqlServerVersion, env!("CARGO_PKG_VERSION"));
+        sql_info.append(SqlInfo::FlightSqlServerArrowVersion, "1.3");
+        sql_info.append(SqlInfo::FlightSqlServerReadOnly, true);
+
+        FlightSqlServer {
+            engine,
+            statements: Mutex::new(Statements::new(STATEMENT_IDLE_TIMEOUT, MAX_STATEMENTS)),
+            sql_info: sql_info.build().expect("server info is valid"),
+        }
+    }
+
+    fn prepared_query(&self, handle: &Bytes) -> Option<String> {
+        self.statements.lock().unwrap().get(handle)
+    }
+
+    /// Tables with the SQL reference they are listed under.
+    async fn tables(&self) -> Result<Vec<(TableIdentifier, ResolvedTableReference)>, Status> {
+        let tables = self.engine.list_tables().await.map_err(status)?;
+        Ok(tables
+            .into_iter()
+            .filter_map(|table| sql_reference(&table).map(|reference| (table, reference)))
+            .collect())
+    }
+
+    async fn execute(&self, query: &str) -> Result<Response<FlightDataStream>, Status> {
+        let batches = self.engine.execute_query(query).await.map_err(status)?;
+        Ok(Response::new(flight_data(batches)))
+    }
+}
+
+fn status(error: QueryError) -> Status {
+    match error {
+        QueryError::ParseError(_) => Status::invalid_argument(error.to_string()),
+        QueryError::TableNotFound(_) | QueryError::SnapshotNotFound(_) => {
+            Status::not_found(error.to_string())
+        }
+        QueryError::ExecutionError(_) | QueryError::InternalError(_) => {
+            Status::internal(error.to_string())
+        }
+    }
+}
+
+fn unknown_statement() -> Status {
+    Status::not_found("Unknown prepared statement")
+}
+
+fn internal(error: ArrowError) -> Status {
+    Status::internal(error.to_string())
+}
+
+/// Describes a result that the ticket of its only endpoint fetches.
+fn flight_info(
+    schema: &Schema,
+    ticket: impl ProstMessageExt,
+    request: Request<FlightDescriptor>,
+) -> Result<Response<FlightInfo>, ArrowError> {
+    let ticket = Ticket::new(ticket.as_any().encode_to_vec());
+    let info = FlightInfo::new()
+        .try_with_schema(schema)?
+        .with_endpoint(FlightEndpoint::new().with_ticket(ticket))
+        .with_descriptor(request.into_inner());
+
+    Ok(Response::new(info))
+}
+
+fn flight_data(batches: SendableRecordBatchStream) -> FlightDataStream {
+    let schema = batches.schema();
+    let batches = batches.map_err(|e| FlightError::ExternalError(Box::new(e)));
+    let data = FlightDataEncoderBuilder::new()
+        .with_schema(schema)
+        .build(batches)
+        .map_err(Status::from);
+    Box::pin(data)
+}
+
+/// Streams the single batch of a metadata call.
+fn metadata(
+    schema: Arc<Schema>,
+    batch: Result<RecordBatch, FlightError>,
+) -> Response<FlightDataStream> {
+    let data = FlightDataEncoderBuilder::new()
+        .with_schema(schema)
+        .build(stream::once(async { batch }))
+        .map_err(Status::from);
+    Response::new(Box::pin(data))
+}
+
+#[tonic::async_trait]
+impl FlightSqlService for FlightSqlServer {
+    type FlightService = FlightSqlServer;
+
+    async fn get_flight_info_statement(
+        &self,
+        query: CommandStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = self
+            .engine
+            .query_schema(&query.query)
+            .await
+            .map_err(status)?;
+
+        // The ticket carries the query, statements being run once fetched
+        let ticket = TicketStatementQuery {
+            statement_handle: query.query.into_bytes().into(),
+        };
+        flight_info(&schema, ticket, request).map_err(internal)
+    }
+
+    async fn do_get_statement(
+        &self,
+        ticket: TicketStatementQuery,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let query = String::from_utf8(ticket.statement_handle.to_vec())
+            .map_err(|_| Status::invalid_argument("Statement handle isn't a query"))?;
+        self.execute(&query).await
+    }
+
+    async fn do_action_create_prepared_statement(
+        &self,
+        query: ActionCreatePreparedStatementRequest,
+        _request: Request<Action>,
+    ) -> Result<ActionCreatePreparedStatementResult, Status> {
+        let schema = self
+            .engine
+            .query_schema(&query.query)
+            .await
+            .map_err(status)?;
+        let IpcMessage(dataset_schema) = SchemaAsIpc::new(&schema, &IpcWriteOptions::default())
+            .try_into()
+            .map_err(internal)?;
+
+        let handle = Bytes::copy_from_slice(Uuid::new_v4().as_bytes());
+        self.statements
+            .lock()
+            .unwrap()
+            .insert(handle.clone(), query.query);
+
+        Ok(ActionCreatePreparedStatementResult {
+            prepared_statement_handle: handle,
+            dataset_schema,
+            parameter_schema: Bytes::new(),
+        })
+    }
+
+    async fn get_flight_info_prepared_statement(
+        &self,
+        query: CommandPreparedStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let sql = self
+            .prepared_query(&query.prepared_statement_handle)
+            .ok_or_else(unknown_statement)?;
+        let schema = self.engine.query_schema(&sql).await.map_err(status)?;
+        flight_info(&schema, query, request).map_err(internal)
+    }
+
+    async fn do_get_prepared_statement(
+        &self,
+        query: CommandPreparedStatementQuery,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let sql = self
+            .prepared_query(&query.prepared_statement_handle)
+            .ok_or_else(unknown_statement)?;
+        self.execute(&sql).await
+    }
+
+    async fn do_action_close_prepared_statement(
+        &self,
+        query: ActionClosePreparedStatementRequest,
+        _request: Request<Action>,
+    ) -> Result<(), Status> {
+        self.statements
+            .lock()
+            .unwrap()
+            .remove(&query.prepared_statement_handle);
+        Ok(())
+    }
+
+    async fn get_flight_info_catalogs(
+        &self,
+        query: CommandGetCatalogs,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = query.clone().into_builder().schema();
+        flight_info(&schema, query, request).map_err(internal)
+    }
+
+    async fn do_get_catalogs(
+        &self,
+        query: CommandGetCatalogs,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let catalogs: BTreeSet<String> = self
+            .tables()
+            .await?
+            .into_iter()
+            .map(|(_, reference)| reference.catalog.to_string())
+            .collect();
+
+        let mut builder = query.into_builder();
+        for catalog in catalogs {
+            builder.append(catalog);
+        }
+        Ok(metadata(builder.schema(), builder.build()))
+    }
+
+    async fn get_flight_info_schemas(
+        &self,
+        query: CommandGetDbSchemas,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = query.clone().into_builder().schema();
+        flight_info(&schema, query, request).map_err(internal)
+    }
+
+    async fn do_get_schemas(
+        &self,
+        query: CommandGetDbSchemas,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let schemas: BTreeSet<(String, String)> = self
+            .tables()
+            .await?
+            .into_iter()
+            .map(|(_, reference)| (reference.catalog.to_string(), reference.schema.to_string()))
+            .collect();
+
+        let mut builder = query.into_builder();
+        for (catalog, schema) in schemas {
+            builder.append(catalog, schema);
+        }
+        Ok(metadata(builder.schema(), builder.build()))
+    }
+
+    async fn get_flight_info_tables(
+        &self,
+        query: CommandGetTables,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = query.clone().into_builder().schema();
+        flight_info(&schema, query, request).map_err(internal)
+    }
+
+    async fn do_get_tables(
+        &self,
+        query: CommandGetTables,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let mut builder = query.into_builder();
+        let empty = Schema::empty();
+
+        for (table, reference) in self.tables().await? {
+            // Schemas are only loaded when asked for, which takes reading
+            // the footer of a data file of each table
+            let schema = match builder.include_schema() {
+                true => self.engine.table_schema(&table).await.map_err(status)?,
+                false => None,
+            };
+            builder.append(
+                &reference.catalog,
+                &reference.schema,
+                &reference.table,
+                "TABLE",
+                schema.as_deref().unwrap_or(&empty),
+            )?;
+        }
+        Ok(metadata(builder.schema(), builder.build()))
+    }
+
+    async fn get_flight_info_sql_info(
+        &self,
+        query: CommandGetSqlInfo,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = query.clone().into_builder(&self.sql_info).schema();
+        flight_info(&schema, query, request).map_err(internal)
+    }
+
+    async fn do_get_sql_info(
+        &self,
+        query: CommandGetSqlInfo,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let builder = query.into_builder(&self.sql_info);
+        Ok(metadata(builder.schema(), builder.build()))
+    }
+
+    async fn register_sql_info(&self, _id: i32, _result: &SqlInfo) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::AsArray;
+    use arrow_flight::decode::FlightRecordBatchStream;
+
+    use super::*;
+    use crate::engine::tests::engine;
+
+    fn server() -> FlightSqlServer {
+        FlightSqlServer::new(Arc::new(engine()))
+    }
+
+    /// Gets the flight info of a command and fetches its only endpoint.
+    async fn fetch(server: &FlightSqlServer, command: impl ProstMessageExt) -> Vec<RecordBatch> {
+        let descriptor = FlightDescriptor::new_cmd(command.as_any().encode_to_vec());
+        let info = server
+            .get_flight_info(Request::new(descriptor))
+            .await
+            .unwrap()
+            .into_inner();
+        let ticket = info.endpoint[0].ticket.clone().unwrap();
+
+        let data = server
+            .do_get(Request::new(ticket))
+            .await
+            .unwrap()
+            .into_inner();
+        FlightRecordBatchStream::new_from_flight_data(data.map_err(FlightError::from))
+            .try_collect()
+            .await
+            .unwrap()
+    }
+
+    fn rows(batches: &[RecordBatch]) -> usize {
+        batches.iter().map(RecordBatch::num_rows).sum()
+    }
+
+    #[tokio::test]
+    async fn test_statements_and_metadata() {
+        let server = server();
+
+        let query = CommandStatementQuery {
+            query: "SELECT id FROM sales.orders".to_string(),
+            transaction_id: None,
+        };
+        assert_eq!(rows(&fetch(&server, query).await), 3);
+
+        let prepared = server
+            .do_action_create_prepared_statement(
+                ActionCreatePreparedStatementRequest {
+                    query: "SELECT * FROM dummy WHERE id < 5".to_string(),
+                    transaction_id: None,
+                },
+                Request::new(Action::new("", "")),
+            )
+            .await
+            .unwrap();
+        let query = CommandPreparedStatementQuery {
+            prepared_statement_handle: prepared.prepared_statement_handle.clone(),
+        };
+        assert_eq!(rows(&fetch(&server, query.clone()).await), 5);
+
+        server
+            .do_action_close_prepared_statement(
+                ActionClosePreparedStatementRequest {
+                    prepared_statement_handle: prepared.prepared_statement_handle,
+                },
+                Request::new(Action::new("", "")),
+            )
+            .await
+            .unwrap();
+        let descriptor = FlightDescriptor::new_cmd(query.as_any().encode_to_vec());
+        let closed = server.get_flight_info(Request::new(descriptor)).await;
+        assert_eq!(closed.unwrap_err().code(), tonic::Code::NotFound);
+
+        // sales.eu.orders is in catalog sales, the other tables in the default one
+        let tables = CommandGetTables {
+            catalog: None,
+            db_schema_filter_pattern: None,
+            table_name_filter_pattern: Some("orders".to_string()),
+            table_types: vec![],
+            include_schema: true,
+        };
+        let batches = fetch(&server, tables).await;
+        assert_eq!(rows(&batches), 2);
+        let catalogs: Vec<_> = batches[0].column(0).as_string::<i32>().iter().collect();
+        assert_eq!(catalogs, [Some("datafusion"), Some("sales")]);
+
+        let schemas = CommandGetDbSchemas {
+            catalog: Some("datafusion".to_string()),
+            db_schema_filter_pattern: None,
+        };
+        assert_eq!(rows(&fetch(&server, schemas).await), 2);
+
+        let error = server
+            .get_flight_info_statement(
+                CommandStatementQuery {
+                    query: "SELECT * FROM missing".to_string(),
+                    transaction_id: None,
+                },
+                Request::new(FlightDescriptor::new_cmd(Vec::<u8>::new())),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::NotFound);
+    }
+
+    #[test]
+    fn test_statements_expire() {
+        let handle = |i: u8| Bytes::from(vec![i]);
+
+        // The least recently used statement is closed beyond capacity
+        let mut statements = Statements::new(STATEMENT_IDLE_TIMEOUT, 2);
+        statements.insert(handle(1), "SELECT 1".to_string());
+        statements.insert(handle(2), "SELECT 2".to_string());
+        assert_eq!(statements.get(&handle(1)).as_deref(), Some("SELECT 1"));
+        statements.insert(handle(3), "SELECT 3".to_string());
+        assert_eq!(statements.get(&handle(2)), None);
+        assert_eq!(statements.get(&handle(1)).as_deref(), Some("SELECT 1"));
+        assert_eq!(statements.get(&handle(3)).as_deref(), Some("SELECT 3"));
+
+        // Idle statements are closed
+        let mut statements = Statements::new(Duration::ZERO, 2);
+        statements.insert(handle(1), "SELECT 1".to_string());
+        assert_eq!(statements.get(&handle(1)), None);
+        assert!(statements.queries.is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+//! Formats query results are returned in, named by the `format` field of a
+//! query or else negotiated with its `Accept` header.
+//!
+//! | `format` | Media type | Body |
+//! |---|---|---|
+//! | `json` | `application/json` | `{"schema": ..., "rows": [{"column": value}]}` |
+//! | `json-columnar` | | `{"schema": ..., "columns": [[value]]}`, a column per schema field |
+//! | `ndjson` | `application/x-ndjson` | A JSON object per row and line |
+//! | `csv` | `text/csv` | Rows with a header line |
+//! | `arrow` | `application/vnd.apache.arrow.stream` | Arrow IPC stream |
+//! | `parquet` | `application/vnd.apache.parquet` | Parquet file |
+//!
+//! The schema of JSON results lists the `name`, Arrow `type` and `nullable`
+//! of each column. JSON rows leave out null values. JSON is the default.
+
+use axum::http::{header::ACCEPT, HeaderMap};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResultFormat {
+    Json,
+    JsonColumnar,
+    Ndjson,
+    Csv,
+    ArrowStream,
+    Parquet,
+}
+
+const FORMATS: [ResultFormat; 6] = [
+    ResultFormat::Json,
+    ResultFormat::JsonColumnar,
+    ResultFormat::Ndjson,
+    ResultFormat::Csv,
+    ResultFormat::ArrowStream,
+    ResultFormat::Parquet,
+];
+
+#[derive(Error, Debug, PartialEq)]
+pub enum FormatError {
+    #[error("Unknown result format {0}, expected one of {}", names(|f| Some(f.name())))]
+    Unknown(String),
+    #[error("Results can be returned as {}", names(ResultFormat::media_type))]
+    NotAcceptable,
+}
+
+fn names(name: fn(&ResultFormat) -> Option<&'static str>) -> String {
+    FORMATS
+        .iter()
+        .filter_map(name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl ResultFormat {
+    /// The format a query names, or else the first one its `Accept` header
+    /// lists that results can be written in.
+    pub fn negotiate(
+        format: Option<&str>,
+        headers: &HeaderMap,
+    ) -> Result<ResultFormat, FormatError> {
+        if let Some(name) = format {
+            let name = name.trim().to_lowercase();
+            return FORMATS
+                .into_iter()
+                .find(|format| format.name() == name)
+                .ok_or(FormatError::Unknown(name));
+        }
+
+        let Some(accept) = headers.get(ACCEPT) else {
+            return Ok(ResultFormat::Json);
+        };
+
+        accept
+            .to_str()
+            .map_err(|_| FormatError::NotAcceptable)?
+            .split(',')
+            .filter_map(|media| media.split(';').next())
+            .find_map(|media| match media.trim() {
+                "application/*" | "*/*" => Some(ResultFormat::Json),
+                "application/jsonl" => Some(ResultFormat::Ndjson),
+                media => FORMATS
+                    .into_iter()
+                    .find(|format| format.media_type() == Some(media)),
+            })
+            .ok_or(FormatError::NotAcceptable)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResultFormat::Json => "json",
+            ResultFormat::JsonColumnar => "json-columnar",
+            ResultFormat::Ndjson => "ndjson",
+            ResultFormat::Csv => "csv",
+            ResultFormat::ArrowStream => "arrow",
+            ResultFormat::Parquet => "parquet",
+        }
+    }
+
+    /// Media type `Accept` headers ask for the format with. Columnar JSON
+    /// has none of its own, and is only returned when named.
+    fn media_type(&self) -> Option<&'static str> {
+        match self {
+            ResultFormat::Json => Some("application/json"),
+            ResultFormat::JsonColumnar => None,
+            ResultFormat::Ndjson => Some("application/x-ndjson"),
+            ResultFormat::Csv => Some("text/csv"),
+            ResultFormat::ArrowStream => Some("application/vnd.apache.arrow.stream"),
+            ResultFormat::Parquet => Some("application/vnd.apache.parquet"),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        self.media_type().unwrap_or("application/json")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        let mut headers = HeaderMap::new();
+        let negotiate = |format, headers: &HeaderMap| ResultFormat::negotiate(format, headers);
+
+        assert_eq!(negotiate(None, &headers), Ok(ResultFormat::Json));
+        headers.insert(ACCEPT, "text/html, text/csv;q=0.9".parse().unwrap());
+        assert_eq!(negotiate(None, &headers), Ok(ResultFormat::Csv));
+        headers.insert(ACCEPT, "text/html".parse().unwrap());
+        assert_eq!(negotiate(None, &headers), Err(FormatError::NotAcceptable));
+
+        // The format field wins over the header
+        assert_eq!(
+            negotiate(Some("JSON-Columnar"), &headers),
+            Ok(ResultFormat::JsonColumnar)
+        );
+        assert_eq!(
+            negotiate(Some("xml"), &headers),
+            Err(FormatError::Unknown("xml".to_string()))
+        );
+    }
+}
@@ -1,16 +1,30 @@
-use arrow::array::RecordBatch;
 use axum::{
+    body::Body,
     extract::State,
-    http::StatusCode,
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+mod catalog;
 mod engine;
-
+mod flight;
+mod format;
+mod metadata_tables;
+mod postgres;
+mod scan;
+mod stream;
+mod time_travel;
+
+use arrow_flight::flight_service_server::FlightServiceServer;
+use catalog::CatalogClient;
 use engine::{QueryEngine, QueryError};
+use fileio::{FileIO, S3Config};
+use flight::FlightSqlServer;
+use format::{FormatError, ResultFormat};
 
 #[derive(Clone)]
 struct AppState {
@@ -20,19 +34,12 @@ struct AppState {
 #[derive(Deserialize)]
 struct QueryRequest {
     query: String,
+    /// Result format, see [`format`]. The `Accept` header picks it if unset.
+    #[serde(default)]
+    format: Option<String>,
 }
 
-#[derive(Serialize)]
-enum QueryResponse {
-    Success(SuccessResponse),
-    Error(ErrorResponse),
-}
-
-#[derive(Serialize)]
-struct SuccessResponse {
-    results: Vec<serde_json::Value>,
-}
-
+/// Body of failed queries, whatever result format they asked for.
 #[derive(Serialize)]
 struct ErrorResponse {
     error: String,
@@ -43,60 +50,49 @@ struct StatusResponse {
     status: String,
 }
 
+/// Runs a query, streaming its result in the format it asks for. Errors found
+/// before the first batch are returned as an [`ErrorResponse`].
 async fn execute_query(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<QueryRequest>,
-) -> (StatusCode, Json<QueryResponse>) {
-    let result = state.query_engine.execute_query(&payload.query).await;
+) -> Response {
+    let format = match ResultFormat::negotiate(payload.format.as_deref(), &headers) {
+        Ok(format) => format,
+        Err(e @ FormatError::Unknown(_)) => {
+            return error_response(StatusCode::BAD_REQUEST, e.to_string())
+        }
+        Err(e @ FormatError::NotAcceptable) => {
+            return error_response(StatusCode::NOT_ACCEPTABLE, e.to_string())
+        }
+    };
 
-    match result {
+    let batches = match state.query_engine.execute_query(&payload.query).await {
+        Ok(batches) => batches,
         Err(e) => {
             let status = match e {
                 QueryError::ParseError(_) => StatusCode::BAD_REQUEST,
+                QueryError::TableNotFound(_) => StatusCode::NOT_FOUND,
+                QueryError::SnapshotNotFound(_) => StatusCode::NOT_FOUND,
                 QueryError::ExecutionError(_) => StatusCode::INTERNAL_SERVER_ERROR,
                 QueryError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             };
-    
-            let response = QueryResponse::Error(ErrorResponse {
-                error: e.to_string(),
-            });
-            return (status, Json(response));
-        },
-        Ok(r) => {
-            let results = arrow_to_json(&r);
-
-            match results {
-                Err(e) => {
-                    let status = StatusCode::INTERNAL_SERVER_ERROR;
-
-                    let response = QueryResponse::Error(ErrorResponse {
-                        error: e.to_string(),
-                    });
-
-                    return (status, Json(response));
-                },
-                Ok(results) => {
-                    let response = QueryResponse::Success(SuccessResponse { results });
-
-                    return (StatusCode::OK, Json(response))
-                }
-            }
+            return error_response(status, e.to_string());
         }
+    };
+
+    match stream::encode(batches, format) {
+        Ok(body) => (
+            [(CONTENT_TYPE, format.content_type())],
+            Body::from_stream(body),
+        )
+            .into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
     }
 }
 
-fn arrow_to_json(
-    batches: &[RecordBatch],
-) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error>> {
-    let mut writer = arrow_json::ArrayWriter::new(Vec::new());
-    for batch in batches {
-        writer.write(batch)?;
-    }
-    writer.finish()?;
-
-    let json_values: Vec<serde_json::Value> = serde_json::from_slice(&writer.into_inner())?;
-
-    Ok(json_values)
+fn error_response(status: StatusCode, error: String) -> Response {
+    (status, Json(ErrorResponse { error })).into_response()
 }
 
 async fn get_status() -> Json<StatusResponse> {
@@ -107,8 +103,14 @@ async fn get_status() -> Json<StatusResponse> {
 
 #[tokio::main]
 async fn main() {
-    let query_engine = Arc::new(QueryEngine::new());
-
+    let catalog = Arc::new(CatalogClient::new(
+        "http://localhost:3002",
+        FileIO::new(S3Config::from_env()),
+    ));
+    let query_engine = Arc::new(QueryEngine::new(catalog));
+
+    let flight = FlightServiceServer::new(FlightSqlServer::new(query_engine.clone()));
+    let postgres = query_engine.clone();
     let app_state = AppState { query_engine };
 
     let app = Router::new()
@@ -116,6 +118,20 @@ async fn main() {
         .route("/status", get(get_status))
         .with_state(app_state);
 
+    // Flight SQL clients connect to grpc://localhost:50051
+    let flight = tonic::transport::Server::builder()
+        .add_service(flight)
+        .serve("0.0.0.0:50051".parse().unwrap());
+    tokio::spawn(async move { flight.await.unwrap() });
+
+    // Postgres clients connect to the address POSTGRES_LISTEN names, such
+    // as 0.0.0.0:5432, when it is set
+    if let Ok(address) = std::env::var("POSTGRES_LISTEN") {
+        let listener = tokio::net::TcpListener::bind(&address).await.unwrap();
+        println!("Postgres server running on {}", address);
+        tokio::spawn(async move { postgres::serve(listener, postgres).await.unwrap() });
+    }
+
     println!("Server running on http://localhost:3000");
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
     axum::serve(listener, app).await.unwrap();
@@ -0,0 +1,333 @@
+//! Metadata tables, read like Iceberg's by appending their name to that of
+//! the table they describe, as in `SELECT * FROM sales.orders$snapshots`.
+//!
+//! | Table | Rows |
+//! |---|---|
+//! | `$snapshots` | The snapshots of the table, with their summary as JSON |
+//! | `$history` | When each snapshot became current, and whether it's an ancestor of the current one |
+//! | `$files` | The current data files, with their column statistics as JSON keyed by field id |
+//! | `$manifests` | The manifests of the current snapshot |
+//! | `$partitions` | Record and file counts per partition, with its values as JSON |
+//! | `$properties` | Table properties |
+//!
+//! Tables with an Iceberg metadata file are described by it and their
+//! manifests. The catalog keeps the snapshots and data files of the tables
+//! the write service writes in its database rather than in manifest files,
+//! so they have no manifests.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+use arrow::{
+    array::{
+        ArrayRef, BooleanArray, Int32Array, Int64Array, StringArray, TimestampMillisecondArray,
+    },
+    datatypes::{DataType, Field, Schema, TimeUnit},
+    error::ArrowError,
+    record_batch::RecordBatch,
+};
+use iceberg::manifest::DATA_CONTENT;
+use serde::Serialize;
+
+use crate::catalog::{DataFile, Snapshot, TableMetadata};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetadataTable {
+    Snapshots,
+    History,
+    Files,
+    Manifests,
+    Partitions,
+    Properties,
+}
+
+impl MetadataTable {
+    /// Splits a name such as `orders$files` into the table and the metadata
+    /// table, or returns `None` if it doesn't name one.
+    pub fn parse(name: &str) -> Option<(&str, MetadataTable)> {
+        let (table, suffix) = name.rsplit_once('$')?;
+        let metadata_table = match suffix {
+            "snapshots" => MetadataTable::Snapshots,
+            "history" => MetadataTable::History,
+            "files" => MetadataTable::Files,
+            "manifests" => MetadataTable::Manifests,
+            "partitions" => MetadataTable::Partitions,
+            "properties" => MetadataTable::Properties,
+            _ => return None,
+        };
+        (!table.is_empty()).then_some((table, metadata_table))
+    }
+
+    /// Rows of the metadata table of a table.
+    pub fn build(&self, metadata: &TableMetadata) -> Result<RecordBatch, ArrowError> {
+        match self {
+            MetadataTable::Snapshots => snapshots(&metadata.snapshots),
+            MetadataTable::History => history(metadata),
+            MetadataTable::Files => files(metadata),
+            MetadataTable::Manifests => manifests(metadata),
+            MetadataTable::Partitions => partitions(metadata),
+            MetadataTable::Properties => properties(metadata),
+        }
+    }
+}
+
+fn timestamp_field(name: &str) -> Field {
+    Field::new(
+        name,
+        DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+        false,
+    )
+}
+
+fn timestamps(snapshots: &[Snapshot]) -> ArrayRef {
+    let times = snapshots.iter().map(|s| s.timestamp_ms);
+    Arc::new(TimestampMillisecondArray::from_iter_values(times).with_timezone("UTC"))
+}
+
+fn batch(fields: Vec<Field>, columns: Vec<ArrayRef>) -> Result<RecordBatch, ArrowError> {
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+}
+
+fn json<T: Serialize>(value: &T) -> Result<String, ArrowError> {
+    serde_json::to_string(value).map_err(|e| ArrowError::JsonError(e.to_string()))
+}
+
+/// A statistic of each file as JSON, with its keys in order.
+fn stats<V: Serialize>(
+    files: &[DataFile],
+    values: impl Fn(&DataFile) -> &HashMap<String, V>,
+) -> Result<ArrayRef, ArrowError> {
+    let values = files
+        .iter()
+        .map(|file| {
+            let values: BTreeMap<_, _> = values(file).iter().collect();
+            json(&values)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Arc::new(StringArray::from(values)))
+}
+
+fn snapshots(snapshots: &[Snapshot]) -> Result<RecordBatch, ArrowError> {
+    let summaries = snapshots
+        .iter()
+        .map(|snapshot| {
+            let summary: BTreeMap<_, _> = snapshot
+                .summary
+                .iter()
+                .filter(|(key, _)| *key != "operation")
+                .collect();
+            json(&summary)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    batch(
+        vec![
+            timestamp_field("committed_at"),
+            Field::new("snapshot_id", DataType::Int64, false),
+            Field::new("parent_id", DataType::Int64, true),
+            Field::new("operation", DataType::Utf8, false),
+            Field::new("summary", DataType::Utf8, false),
+        ],
+        vec![
+            timestamps(snapshots),
+            Arc::new(Int64Array::from_iter_values(
+                snapshots.iter().map(|s| s.snapshot_id),
+            )),
+            Arc::new(Int64Array::from_iter(
+                snapshots.iter().map(|s| s.parent_snapshot_id),
+            )),
+            Arc::new(StringArray::from_iter_values(snapshots.iter().map(|s| {
+                s.summary.get("operation").map_or("append", String::as_str)
+            }))),
+            Arc::new(StringArray::from_iter_values(summaries)),
+        ],
+    )
+}
+
+fn history(metadata: &TableMetadata) -> Result<RecordBatch, ArrowError> {
+    let snapshots = &metadata.snapshots;
+
+    // Snapshots rolled back from, or written to other branches, aren't
+    // ancestors of the current one
+    let mut ancestors = HashSet::new();
+    let mut ancestor = metadata.current_snapshot_id;
+    while let Some(id) = ancestor.filter(|id| ancestors.insert(*id)) {
+        ancestor = snapshots
+            .iter()
+            .find(|s| s.snapshot_id == id)
+            .and_then(|s| s.parent_snapshot_id);
+    }
+
+    batch(
+        vec![
+            timestamp_field("made_current_at"),
+            Field::new("snapshot_id", DataType::Int64, false),
+            Field::new("parent_id", DataType::Int64, true),
+            Field::new("is_current_ancestor", DataType::Boolean, false),
+        ],
+        vec![
+            timestamps(snapshots),
+            Arc::new(Int64Array::from_iter_values(
+                snapshots.iter().map(|s| s.snapshot_id),
+            )),
+            Arc::new(Int64Array::from_iter(
+                snapshots.iter().map(|s| s.parent_snapshot_id),
+            )),
+            Arc::new(BooleanArray::from_iter(
+                snapshots
+                    .iter()
+                    .map(|s| Some(ancestors.contains(&s.snapshot_id))),
+            )),
+        ],
+    )
+}
+
+fn files(metadata: &TableMetadata) -> Result<RecordBatch, ArrowError> {
+    let files = &metadata.files;
+
+    batch(
+        vec![
+            Field::new("content", DataType::Int32, false),
+            Field::new("file_path", DataType::Utf8, false),
+            Field::new("file_format", DataType::Utf8, false),
+            Field::new("record_count", DataType::Int64, false),
+            Field::new("file_size_in_bytes", DataType::Int64, false),
+            Field::new("value_counts", DataType::Utf8, false),
+            Field::new("null_value_counts", DataType::Utf8, false),
+            Field::new("nan_value_counts", DataType::Utf8, false),
+            Field::new("lower_bounds", DataType::Utf8, false),
+            Field::new("upper_bounds", DataType::Utf8, false),
+        ],
+        vec![
+            Arc::new(Int32Array::from(vec![DATA_CONTENT; files.len()])),
+            Arc::new(StringArray::from_iter_values(
+                files.iter().map(|f| f.path.as_str()),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                files
+                    .iter()
+                    .map(|f| f.file_format.as_deref().unwrap_or("PARQUET")),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                files.iter().map(|f| f.record_count),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                files.iter().map(|f| f.file_size_in_bytes),
+            )),
+            stats(files, |f| &f.value_counts)?,
+            stats(files, |f| &f.null_value_counts)?,
+            stats(files, |f| &f.nan_value_counts)?,
+            stats(files, |f| &f.lower_bounds)?,
+            stats(files, |f| &f.upper_bounds)?,
+        ],
+    )
+}
+
+fn manifests(metadata: &TableMetadata) -> Result<RecordBatch, ArrowError> {
+    let manifests = &metadata.manifests;
+
+    batch(
+        vec![
+            Field::new("content", DataType::Int32, false),
+            Field::new("path", DataType::Utf8, false),
+            Field::new("length", DataType::Int64, false),
+            Field::new("partition_spec_id", DataType::Int32, false),
+            Field::new("added_snapshot_id", DataType::Int64, false),
+            Field::new("sequence_number", DataType::Int64, false),
+            Field::new("added_data_files_count", DataType::Int32, false),
+            Field::new("existing_data_files_count", DataType::Int32, false),
+            Field::new("deleted_data_files_count", DataType::Int32, false),
+            Field::new("added_rows_count", DataType::Int64, false),
+            Field::new("existing_rows_count", DataType::Int64, false),
+            Field::new("deleted_rows_count", DataType::Int64, false),
+        ],
+        vec![
+            Arc::new(Int32Array::from_iter_values(
+                manifests.iter().map(|m| m.content),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                manifests.iter().map(|m| m.manifest_path.as_str()),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                manifests.iter().map(|m| m.manifest_length),
+            )),
+            Arc::new(Int32Array::from_iter_values(
+                manifests.iter().map(|m| m.partition_spec_id),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                manifests.iter().map(|m| m.added_snapshot_id),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                manifests.iter().map(|m| m.sequence_number),
+            )),
+            Arc::new(Int32Array::from_iter_values(
+                manifests.iter().map(|m| m.added_files_count),
+            )),
+            Arc::new(Int32Array::from_iter_values(
+                manifests.iter().map(|m| m.existing_files_count),
+            )),
+            Arc::new(Int32Array::from_iter_values(
+                manifests.iter().map(|m| m.deleted_files_count),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                manifests.iter().map(|m| m.added_rows_count),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                manifests.iter().map(|m| m.existing_rows_count),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                manifests.iter().map(|m| m.deleted_rows_count),
+            )),
+        ],
+    )
+}
+
+fn partitions(metadata: &TableMetadata) -> Result<RecordBatch, ArrowError> {
+    // Record, file counts and sizes by partition values, a single `{}`
+    // partition if the table isn't partitioned
+    let mut partitions: BTreeMap<String, (i64, i32, i64)> = BTreeMap::new();
+    for file in &metadata.files {
+        let (records, files, size) = partitions.entry(json(&file.partition)?).or_default();
+        *records += file.record_count;
+        *files += 1;
+        *size += file.file_size_in_bytes;
+    }
+
+    batch(
+        vec![
+            Field::new("partition", DataType::Utf8, false),
+            Field::new("record_count", DataType::Int64, false),
+            Field::new("file_count", DataType::Int32, false),
+            Field::new("total_data_file_size_in_bytes", DataType::Int64, false),
+        ],
+        vec![
+            Arc::new(StringArray::from_iter_values(partitions.keys())),
+            Arc::new(Int64Array::from_iter_values(
+                partitions.values().map(|(records, _, _)| *records),
+            )),
+            Arc::new(Int32Array::from_iter_values(
+                partitions.values().map(|(_, files, _)| *files),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                partitions.values().map(|(_, _, size)| *size),
+            )),
+        ],
+    )
+}
+
+fn properties(metadata: &TableMetadata) -> Result<RecordBatch, ArrowError> {
+    let properties: BTreeMap<_, _> = metadata.properties.iter().collect();
+
+    batch(
+        vec![
+            Field::new("key", DataType::Utf8, false),
+            Field::new("value", DataType::Utf8, false),
+        ],
+        vec![
+            Arc::new(StringArray::from_iter_values(properties.keys())),
+            Arc::new(StringArray::from_iter_values(properties.values())),
+        ],
+    )
+}
@@ -0,0 +1,461 @@
+//! PostgreSQL wire protocol front-end of the query service, for psql,
+//! DBeaver and Grafana. Queries of the simple and extended flows run through
+//! the [`QueryEngine`], their rows sent as batches are produced.
+//!
+//! Catalog queries read `information_schema`. Session statements such as
+//! `SET` and `BEGIN` are acknowledged but change nothing, queries being
+//! read-only. Statements take no parameters, and clients aren't
+//! authenticated.
+
+use std::{fmt::Debug, io, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, StringArray},
+    compute::cast,
+    datatypes::{
+        DataType, Date32Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Schema,
+        TimeUnit, TimestampMicrosecondType,
+    },
+    error::ArrowError,
+    record_batch::RecordBatch,
+    util::display::{ArrayFormatter, FormatOptions},
+};
+use async_trait::async_trait;
+use datafusion::{
+    error::DataFusionError,
+    execution::SendableRecordBatchStream,
+    sql::sqlparser::{
+        dialect::GenericDialect,
+        tokenizer::{Token, Tokenizer},
+    },
+};
+use futures::{stream, Sink, Stream, StreamExt};
+use pgwire::{
+    api::{
+        portal::{Format, Portal},
+        query::{ExtendedQueryHandler, SimpleQueryHandler},
+        results::{DataRowEncoder, FieldInfo, QueryResponse, Response, Tag},
+        stmt::QueryParser,
+        store::PortalStore,
+        ClientInfo, ClientPortalStore, PgWireServerHandlers, Type,
+    },
+    error::{ErrorInfo, PgWireError, PgWireResult},
+    messages::{data::DataRow, PgWireBackendMessage},
+    tokio::process_socket,
+};
+use tokio::net::TcpListener;
+
+use crate::engine::{QueryEngine, QueryError};
+
+/// Accepts Postgres connections until the listener fails.
+pub async fn serve(listener: TcpListener, engine: Arc<QueryEngine>) -> io::Result<()> {
+    let handlers = Arc::new(Handlers {
+        backend: Arc::new(Backend {
+            engine: engine.clone(),
+            parser: Arc::new(StatementParser { engine }),
+        }),
+    });
+
+    loop {
+        let (socket, _) = listener.accept().await?;
+        let handlers = handlers.clone();
+        tokio::spawn(async move { process_socket(socket, None, handlers).await });
+    }
+}
+
+struct Handlers {
+    backend: Arc<Backend>,
+}
+
+impl PgWireServerHandlers for Handlers {
+    fn simple_query_handler(&self) -> Arc<impl SimpleQueryHandler> {
+        self.backend.clone()
+    }
+
+    fn extended_query_handler(&self) -> Arc<impl ExtendedQueryHandler> {
+        self.backend.clone()
+    }
+}
+
+struct Backend {
+    engine: Arc<QueryEngine>,
+    parser: Arc<StatementParser>,
+}
+
+impl Backend {
+    async fn execute(&self, query: &str, format: &Format) -> PgWireResult<Response> {
+        if let Some(response) = session_statement(query) {
+            return Ok(response);
+        }
+
+        let batches = self.engine.execute_query(query).await.map_err(error)?;
+        let fields = Arc::new(fields(&batches.schema(), format));
+        Ok(Response::Query(QueryResponse::new(
+            fields.clone(),
+            rows(batches, fields),
+        )))
+    }
+}
+
+#[async_trait]
+impl SimpleQueryHandler for Backend {
+    async fn do_query<C>(&self, _client: &mut C, query: &str) -> PgWireResult<Vec<Response>>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        // A simple query may hold several statements, each getting a response
+        let statements = split_statements(query)?;
+        let mut responses = Vec::with_capacity(statements.len());
+        for statement in statements {
+            responses.push(self.execute(&statement, &Format::UnifiedText).await?);
+        }
+        Ok(responses)
+    }
+}
+
+#[async_trait]
+impl ExtendedQueryHandler for Backend {
+    type Statement = Statement;
+    type QueryParser = StatementParser;
+
+    fn query_parser(&self) -> Arc<Self::QueryParser> {
+        self.parser.clone()
+    }
+
+    async fn do_query<C>(
+        &self,
+        _client: &mut C,
+        portal: &Portal<Self::Statement>,
+        _max_rows: usize,
+    ) -> PgWireResult<Response>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = Self::Statement>,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        if portal.parameter_len() > 0 {
+            return Err(user_error("0A000", "Query parameters aren't supported"));
+        }
+        let query = &portal.statement.statement.query;
+        self.execute(query, &portal.result_column_format).await
+    }
+}
+
+/// A query, with the schema of its result if it has one.
+#[derive(Debug, Clone)]
+pub struct Statement {
+    query: String,
+    schema: Option<Arc<Schema>>,
+}
+
+/// Plans statements as they are parsed, so that they can be described.
+struct StatementParser {
+    engine: Arc<QueryEngine>,
+}
+
+#[async_trait]
+impl QueryParser for StatementParser {
+    type Statement = Statement;
+
+    async fn parse_sql<C>(
+        &self,
+        _client: &C,
+        sql: &str,
+        _types: &[Option<Type>],
+    ) -> PgWireResult<Option<Self::Statement>>
+    where
+        C: ClientInfo + Unpin + Send + Sync,
+    {
+        let schema = match session_statement(sql) {
+            Some(_) => None,
+            None => Some(self.engine.query_schema(sql).await.map_err(error)?),
+        };
+        Ok(Some(Statement {
+            query: sql.to_string(),
+            schema,
+        }))
+    }
+
+    fn get_parameter_types(&self, _stmt: &Self::Statement) -> PgWireResult<Vec<Type>> {
+        Ok(Vec::new())
+    }
+
+    fn get_result_schema(
+        &self,
+        stmt: &Self::Statement,
+        column_format: Option<&Format>,
+    ) -> PgWireResult<Vec<FieldInfo>> {
+        let format = column_format.unwrap_or(&Format::UnifiedBinary);
+        Ok(stmt
+            .schema
+            .as_ref()
+            .map(|schema| fields(schema, format))
+            .unwrap_or_default())
+    }
+}
+
+/// Splits a query into its statements, leaving their text as it is for the
+/// engine to parse. Statements end at semicolons outside of literals.
+fn split_statements(query: &str) -> PgWireResult<Vec<String>> {
+    let tokens = Tokenizer::new(&GenericDialect {}, query)
+        .with_unescape(false)
+        .tokenize()
+        .map_err(|e| user_error("42601", &e.to_string()))?;
+
+    Ok(tokens
+        .split(|token| *token == Token::SemiColon)
+        .map(|tokens| tokens.iter().map(Token::to_string).collect::<String>())
+        .filter(|statement| !statement.trim().is_empty())
+        .collect())
+}
+
+/// Acknowledges statements clients set up sessions with, which have nothing
+/// to do here.
+fn session_statement(query: &str) -> Option<Response> {
+    let keyword = query.split_whitespace().next()?.trim_end_matches(';');
+    match keyword.to_uppercase().as_str() {
+        "SET" | "RESET" | "DISCARD" | "DEALLOCATE" => {
+            Some(Response::Execution(Tag::new(&keyword.to_uppercase())))
+        }
+        "BEGIN" | "START" => Some(Response::TransactionStart(Tag::new("BEGIN"))),
+        "COMMIT" | "END" => Some(Response::TransactionEnd(Tag::new("COMMIT"))),
+        "ROLLBACK" | "ABORT" => Some(Response::TransactionEnd(Tag::new("ROLLBACK"))),
+        _ => None,
+    }
+}
+
+fn user_error(code: &str, message: &str) -> PgWireError {
+    PgWireError::UserError(Box::new(ErrorInfo::new(
+        "ERROR".to_string(),
+        code.to_string(),
+        message.to_string(),
+    )))
+}
+
+fn error(error: QueryError) -> PgWireError {
+    let code = match error {
+        QueryError::ParseError(_) => "42601",
+        QueryError::TableNotFound(_) => "42P01",
+        QueryError::SnapshotNotFound(_) => "42704",
+        QueryError::ExecutionError(_) | QueryError::InternalError(_) => "XX000",
+    };
+    user_error(code, &error.to_string())
+}
+
+/// Postgres type columns are sent as. Types without a counterpart are sent
+/// as text.
+fn pg_type(data_type: &DataType) -> Type {
+    match data_type {
+        DataType::Boolean => Type::BOOL,
+        DataType::Int8 | DataType::Int16 | DataType::UInt8 => Type::INT2,
+        DataType::Int32 | DataType::UInt16 => Type::INT4,
+        DataType::Int64 | DataType::UInt32 | DataType::UInt64 => Type::INT8,
+        DataType::Float16 | DataType::Float32 => Type::FLOAT4,
+        DataType::Float64 => Type::FLOAT8,
+        DataType::Binary | DataType::LargeBinary => Type::BYTEA,
+        DataType::Date32 | DataType::Date64 => Type::DATE,
+        DataType::Timestamp(_, None) => Type::TIMESTAMP,
+        DataType::Timestamp(_, Some(_)) => Type::TIMESTAMPTZ,
+        _ => Type::TEXT,
+    }
+}
+
+fn fields(schema: &Schema, format: &Format) -> Vec<FieldInfo> {
+    schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let datatype = pg_type(field.data_type());
+            FieldInfo::new(
+                field.name().clone(),
+                None,
+                None,
+                datatype,
+                format.format_for(i),
+            )
+        })
+        .collect()
+}
+
+fn rows(
+    batches: SendableRecordBatchStream,
+    fields: Arc<Vec<FieldInfo>>,
+) -> impl Stream<Item = PgWireResult<DataRow>> + Send {
+    batches.flat_map(move |batch| {
+        let rows = match batch.map_err(|e| error(e.into())) {
+            Ok(batch) => encode(&batch, &fields),
+            Err(e) => Err(e),
+        };
+        match rows {
+            Ok(rows) => stream::iter(rows.into_iter().map(Ok).collect::<Vec<_>>()),
+            Err(e) => stream::iter(vec![Err(e)]),
+        }
+    })
+}
+
+/// Casts a column to the Arrow type its Postgres type is encoded from.
+fn pg_column(column: &ArrayRef, datatype: &Type) -> Result<ArrayRef, ArrowError> {
+    let data_type = match *datatype {
+        Type::BOOL => DataType::Boolean,
+        Type::INT2 => DataType::Int16,
+        Type::INT4 => DataType::Int32,
+        Type::INT8 => DataType::Int64,
+        Type::FLOAT4 => DataType::Float32,
+        Type::FLOAT8 => DataType::Float64,
+        Type::BYTEA => DataType::Binary,
+        Type::DATE => DataType::Date32,
+        Type::TIMESTAMP => DataType::Timestamp(TimeUnit::Microsecond, None),
+        Type::TIMESTAMPTZ => DataType::Timestamp(TimeUnit::Microsecond, Some("+00:00".into())),
+        _ => {
+            let formatter = ArrayFormatter::try_new(column, &FormatOptions::default())?;
+            let text: StringArray = (0..column.len())
+                .map(|i| column.is_valid(i).then(|| formatter.value(i).to_string()))
+                .collect();
+            return Ok(Arc::new(text));
+        }
+    };
+    cast(column, &data_type)
+}
+
+fn encode(batch: &RecordBatch, fields: &Arc<Vec<FieldInfo>>) -> PgWireResult<Vec<DataRow>> {
+    let columns = batch
+        .columns()
+        .iter()
+        .zip(fields.iter())
+        .map(|(column, field)| pg_column(column, field.datatype()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error(DataFusionError::from(e).into()))?;
+
+    let mut encoder = DataRowEncoder::new(fields.clone());
+    let mut rows = Vec::with_capacity(batch.num_rows());
+    for row in 0..batch.num_rows() {
+        for (column, field) in columns.iter().zip(fields.iter()) {
+            let valid = column.is_valid(row);
+            match *field.datatype() {
+                Type::BOOL => {
+                    encoder.encode_field(&valid.then(|| column.as_boolean().value(row)))?
+                }
+                Type::INT2 => encoder
+                    .encode_field(&valid.then(|| column.as_primitive::<Int16Type>().value(row)))?,
+                Type::INT4 => encoder
+                    .encode_field(&valid.then(|| column.as_primitive::<Int32Type>().value(row)))?,
+                Type::INT8 => encoder
+                    .encode_field(&valid.then(|| column.as_primitive::<Int64Type>().value(row)))?,
+                Type::FLOAT4 => encoder.encode_field(
+                    &valid.then(|| column.as_primitive::<Float32Type>().value(row)),
+                )?,
+                Type::FLOAT8 => encoder.encode_field(
+                    &valid.then(|| column.as_primitive::<Float64Type>().value(row)),
+                )?,
+                Type::BYTEA => {
+                    encoder.encode_field(&valid.then(|| column.as_binary::<i32>().value(row)))?
+                }
+                Type::DATE => {
+                    let dates = column.as_primitive::<Date32Type>();
+                    encoder.encode_field(&dates.value_as_date(row).filter(|_| valid))?
+                }
+                Type::TIMESTAMP => {
+                    let times = column.as_primitive::<TimestampMicrosecondType>();
+                    encoder.encode_field(&times.value_as_datetime(row).filter(|_| valid))?
+                }
+                Type::TIMESTAMPTZ => {
+                    let times = column.as_primitive::<TimestampMicrosecondType>();
+                    let time = times.value_as_datetime(row).filter(|_| valid);
+                    encoder.encode_field(&time.map(|time| time.and_utc()))?
+                }
+                _ => encoder.encode_field(&valid.then(|| column.as_string::<i32>().value(row)))?,
+            }
+        }
+        rows.push(encoder.take_row());
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_postgres::{NoTls, SimpleQueryMessage};
+
+    use super::*;
+    use crate::engine::tests::engine;
+
+    #[tokio::test]
+    async fn test_postgres_clients() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, Arc::new(engine())));
+
+        let config = format!("host={} port={} user=test", address.ip(), address.port());
+        let (client, connection) = tokio_postgres::connect(&config, NoTls).await.unwrap();
+        tokio::spawn(connection);
+
+        // Simple flow, as psql queries
+        client
+            .batch_execute("SET application_name = 'test'")
+            .await
+            .unwrap();
+        let first_column = |messages: Vec<SimpleQueryMessage>| -> Vec<String> {
+            messages
+                .iter()
+                .filter_map(|message| match message {
+                    SimpleQueryMessage::Row(row) => Some(row.get(0).unwrap().to_string()),
+                    _ => None,
+                })
+                .collect()
+        };
+        let messages = client
+            .simple_query("SELECT id, value FROM sales.orders ORDER BY id")
+            .await
+            .unwrap();
+        assert_eq!(first_column(messages), ["0", "1", "2"]);
+
+        // Several statements, the last one querying a snapshot
+        let messages = client
+            .simple_query(
+                "SET search_path = sales;
+                 SELECT id FROM sales.orders FOR VERSION AS OF 103 ORDER BY id",
+            )
+            .await
+            .unwrap();
+        assert_eq!(first_column(messages), ["0", "1", "2"]);
+
+        // Extended flow, with binary results
+        let rows = client
+            .query("SELECT id, value FROM dummy WHERE id < 2 ORDER BY id", &[])
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].get::<_, i64>("id"), 1);
+        assert_eq!(rows[1].get::<_, &str>("value"), "value");
+
+        let tables = client
+            .query(
+                "SELECT table_catalog, table_schema, table_name FROM information_schema.tables
+                 WHERE table_schema <> 'information_schema' ORDER BY table_catalog, table_schema",
+                &[],
+            )
+            .await
+            .unwrap();
+        let tables: Vec<(String, String)> =
+            tables.iter().map(|row| (row.get(0), row.get(1))).collect();
+        assert_eq!(
+            tables,
+            [
+                ("datafusion".to_string(), "default".to_string()),
+                ("datafusion".to_string(), "sales".to_string()),
+                ("sales".to_string(), "eu".to_string()),
+            ]
+        );
+
+        let error = client
+            .query("SELECT * FROM missing", &[])
+            .await
+            .unwrap_err();
+        assert_eq!(error.code().unwrap().code(), "42P01");
+    }
+}
@@ -0,0 +1,640 @@
+//! Scans of the data files of a table, reading only what a query's filters
+//! may match. Filters are pushed down to
+//!
+//! | Level | Pruned by |
+//! |---|---|
+//! | Files | The column bounds, null and NaN counts the write service collected for each, keyed by field id, and the ranges of values their partition values stand for |
+//! | Row groups | Their column statistics, and bloom filters where written (`write.parquet.bloom-filter-enabled.column.<col>`) |
+//! | Pages | The page index, with the rows left filtered as they're decoded |
+//!
+//! `EXPLAIN` shows the files scanned and skipped on the `DataFilesExec` of
+//! each table. The row groups skipped are only known once the files are
+//! read, and `EXPLAIN ANALYZE` shows them on the `ParquetExec` below it as
+//! `row_groups_pruned_statistics` and `row_groups_pruned_bloom_filter`.
+//!
+//! Partition values bound the column they're a transform of: an `identity`
+//! value is the column's only one, a `day` value any time of the day, and
+//! so on. `bucket` values bound nothing.
+
+use std::{
+    any::Any,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    sync::Arc,
+};
+
+use arrow::{
+    array::{ArrayRef, BooleanArray, UInt64Array},
+    datatypes::{DataType, Schema, SchemaRef, TimeUnit},
+};
+use async_trait::async_trait;
+use datafusion::{
+    common::{Column, DFSchema, Result, ScalarValue, Statistics},
+    datasource::{
+        file_format::{parquet::ParquetFormat, FileFormat},
+        listing::{ListingTableUrl, PartitionedFile},
+        physical_plan::{FileScanConfig, ParquetExec},
+        TableProvider, TableType,
+    },
+    execution::{context::SessionState, object_store::ObjectStoreUrl, TaskContext},
+    logical_expr::{utils::conjunction, Expr, TableProviderFilterPushDown},
+    physical_optimizer::pruning::{PruningPredicate, PruningStatistics},
+    physical_plan::{
+        empty::EmptyExec, union::UnionExec, DisplayAs, DisplayFormatType, ExecutionPlan,
+        PlanProperties, SendableRecordBatchStream,
+    },
+};
+use object_store::ObjectMeta;
+use serde_json::{json, Value};
+
+use crate::catalog::DataFile;
+
+const HOUR_MICROS: i64 = 60 * 60 * 1_000_000;
+const DAY_MICROS: i64 = 24 * HOUR_MICROS;
+
+/// A partition field of a table: the partition values of files under its
+/// name are its transform of the column with the source id.
+#[derive(Debug, Clone)]
+pub struct PartitionField {
+    pub name: String,
+    pub source_id: i32,
+    /// Iceberg transform, such as `identity`, `day` or `truncate[10]`.
+    pub transform: String,
+}
+
+/// A table read from its data files.
+#[derive(Debug)]
+pub struct DataFilesTable {
+    schema: SchemaRef,
+    /// Field ids of the columns, by name.
+    field_ids: HashMap<String, i32>,
+    files: Vec<DataFile>,
+    /// Store and object of each file.
+    objects: Vec<(ObjectStoreUrl, ObjectMeta)>,
+    partition_fields: Vec<PartitionField>,
+}
+
+impl DataFilesTable {
+    /// Reads the schema of a table from the footers of its files. The stores
+    /// they're in must be registered with the session.
+    pub async fn try_new(
+        state: &SessionState,
+        files: Vec<DataFile>,
+        field_ids: HashMap<String, i32>,
+    ) -> Result<Self> {
+        let mut objects = Vec::with_capacity(files.len());
+        for file in &files {
+            let url = ListingTableUrl::parse(&file.path)?;
+            let store_url = url.object_store();
+            let location = url.prefix().clone();
+
+            // Files registered without their size are looked up
+            let object = match file.file_size_in_bytes {
+                size if size > 0 => ObjectMeta {
+                    location,
+                    last_modified: Default::default(),
+                    size: size as usize,
+                    e_tag: None,
+                    version: None,
+                },
+                _ => {
+                    let store = state.runtime_env().object_store(&store_url)?;
+                    store.head(&location).await?
+                }
+            };
+            objects.push((store_url, object));
+        }
+
+        let format = ParquetFormat::default();
+        let mut schemas = Vec::new();
+        for (store_url, objects) in by_store(&objects) {
+            let store = state.runtime_env().object_store(&store_url)?;
+            let schema = format.infer_schema(state, &store, &objects).await?;
+            schemas.push(schema.as_ref().clone());
+        }
+
+        Ok(DataFilesTable {
+            schema: Arc::new(Schema::try_merge(schemas)?),
+            field_ids,
+            files,
+            objects,
+            partition_fields: Vec::new(),
+        })
+    }
+
+    /// Prunes files by their partition values too, as partitioned by the
+    /// fields.
+    pub fn with_partition_fields(mut self, partition_fields: Vec<PartitionField>) -> Self {
+        self.partition_fields = partition_fields;
+        self
+    }
+
+    /// The bound on a column the partition values of a file imply, if any.
+    fn partition_bound(
+        &self,
+        file: &DataFile,
+        field_id: i32,
+        data_type: &DataType,
+        upper: bool,
+    ) -> Option<ScalarValue> {
+        self.partition_fields
+            .iter()
+            .filter(|field| field.source_id == field_id)
+            .find_map(|field| {
+                let value = file.partition.get(&field.name)?;
+                partition_bound(&field.transform, value, data_type, upper)
+            })
+    }
+}
+
+/// Groups objects by the store they're in.
+fn by_store<'a>(
+    objects: impl IntoIterator<Item = &'a (ObjectStoreUrl, ObjectMeta)>,
+) -> Vec<(ObjectStoreUrl, Vec<ObjectMeta>)> {
+    let mut stores: BTreeMap<String, (ObjectStoreUrl, Vec<ObjectMeta>)> = BTreeMap::new();
+    for (store_url, object) in objects {
+        stores
+            .entry(store_url.as_str().to_string())
+            .or_insert_with(|| (store_url.clone(), Vec::new()))
+            .1
+            .push(object.clone());
+    }
+    stores.into_values().collect()
+}
+
+#[async_trait]
+impl TableProvider for DataFilesTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    async fn scan(
+        &self,
+        state: &SessionState,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let predicate = match conjunction(filters.to_vec()) {
+            Some(filter) => {
+                let schema = DFSchema::try_from(self.schema.as_ref().clone())?;
+                Some(state.create_physical_expr(filter, &schema)?)
+            }
+            None => None,
+        };
+
+        let scanned = match &predicate {
+            Some(predicate) => PruningPredicate::try_new(predicate.clone(), self.schema.clone())?
+                .prune(&FileStatistics(self))?,
+            None => vec![true; self.files.len()],
+        };
+        let files_scanned = scanned.iter().filter(|scanned| **scanned).count();
+        let objects = self
+            .objects
+            .iter()
+            .zip(&scanned)
+            .filter_map(|(object, scanned)| scanned.then_some(object));
+
+        let mut options = state.table_options().parquet.clone();
+        options.global.pruning = true;
+        options.global.enable_page_index = true;
+        options.global.bloom_filter_on_read = true;
+        options.global.pushdown_filters = true;
+        options.global.reorder_filters = true;
+
+        let target_partitions = state.config().target_partitions().max(1);
+        let mut scans: Vec<Arc<dyn ExecutionPlan>> = Vec::new();
+        for (store_url, objects) in by_store(objects) {
+            let mut groups = vec![Vec::new(); target_partitions.min(objects.len())];
+            let count = groups.len();
+            for (i, object) in objects.into_iter().enumerate() {
+                groups[i % count].push(PartitionedFile::from(object));
+            }
+
+            let config = FileScanConfig::new(store_url, self.schema.clone())
+                .with_file_groups(groups)
+                .with_projection(projection.cloned())
+                .with_limit(limit);
+            let mut scan = ParquetExec::builder(config).with_table_parquet_options(options.clone());
+            if let Some(predicate) = &predicate {
+                scan = scan.with_predicate(predicate.clone());
+            }
+            scans.push(scan.build_arc());
+        }
+
+        let input: Arc<dyn ExecutionPlan> = match scans.len() {
+            0 => {
+                let schema = match projection {
+                    Some(projection) => Arc::new(self.schema.project(projection)?),
+                    None => self.schema.clone(),
+                };
+                Arc::new(EmptyExec::new(schema))
+            }
+            1 => scans.remove(0),
+            _ => Arc::new(UnionExec::new(scans)),
+        };
+
+        Ok(Arc::new(DataFilesExec {
+            input,
+            files_scanned,
+            files_skipped: self.files.len() - files_scanned,
+        }))
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> Result<Vec<TableProviderFilterPushDown>> {
+        // Pruning skips files and rows that can't match, not all that don't
+        Ok(vec![TableProviderFilterPushDown::Inexact; filters.len()])
+    }
+}
+
+/// Statistics of the data files of a table, one container per file.
+struct FileStatistics<'a>(&'a DataFilesTable);
+
+impl FileStatistics<'_> {
+    /// A lower or upper bound of each file on a column, null where unknown:
+    /// the tighter of its column bound and the one its partition values
+    /// imply.
+    fn bounds(&self, column: &Column, upper: bool) -> Option<ArrayRef> {
+        let table = self.0;
+        let data_type = table.schema.field_with_name(&column.name).ok()?.data_type();
+        let field_id = *table.field_ids.get(&column.name)?;
+        let id = field_id.to_string();
+        let unknown = ScalarValue::try_from(data_type).ok()?;
+
+        let values = table.files.iter().map(|file| {
+            // Bounds leave NaN out, which compares greater than any number
+            if file.nan_value_counts.get(&id).is_some_and(|&nans| nans > 0) {
+                return unknown.clone();
+            }
+            let bounds = match upper {
+                true => &file.upper_bounds,
+                false => &file.lower_bounds,
+            };
+            let bound = bounds.get(&id).and_then(|bound| scalar(bound, data_type));
+            let partition = table.partition_bound(file, field_id, data_type, upper);
+
+            match (bound, partition) {
+                (Some(bound), Some(partition)) => match (bound.partial_cmp(&partition), upper) {
+                    (Some(Ordering::Less), true) | (Some(Ordering::Greater), false) => bound,
+                    (Some(_), _) => partition,
+                    (None, _) => bound,
+                },
+                (bound, partition) => bound.or(partition).unwrap_or_else(|| unknown.clone()),
+            }
+        });
+        ScalarValue::iter_to_array(values).ok()
+    }
+}
+
+impl PruningStatistics for FileStatistics<'_> {
+    fn min_values(&self, column: &Column) -> Option<ArrayRef> {
+        self.bounds(column, false)
+    }
+
+    fn max_values(&self, column: &Column) -> Option<ArrayRef> {
+        self.bounds(column, true)
+    }
+
+    fn num_containers(&self) -> usize {
+        self.0.files.len()
+    }
+
+    fn null_counts(&self, column: &Column) -> Option<ArrayRef> {
+        let id = self.0.field_ids.get(&column.name)?.to_string();
+        let counts = self.0.files.iter().map(|file| {
+            let nulls = file.null_value_counts.get(&id);
+            nulls.map(|&nulls| nulls as u64)
+        });
+        Some(Arc::new(UInt64Array::from_iter(counts)))
+    }
+
+    fn row_counts(&self, _column: &Column) -> Option<ArrayRef> {
+        let counts = self.0.files.iter().map(|file| file.record_count as u64);
+        Some(Arc::new(UInt64Array::from_iter_values(counts)))
+    }
+
+    fn contained(&self, _column: &Column, _values: &HashSet<ScalarValue>) -> Option<BooleanArray> {
+        None
+    }
+}
+
+/// A bound of a column, as the write service records those of its physical
+/// type, or `None` if it can't be compared as one.
+fn scalar(bound: &Value, data_type: &DataType) -> Option<ScalarValue> {
+    let scalar = match data_type {
+        DataType::Boolean => ScalarValue::Boolean(Some(bound.as_bool()?)),
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => {
+            ScalarValue::Int64(Some(bound.as_i64()?))
+                .cast_to(data_type)
+                .ok()?
+        }
+        DataType::Date32 | DataType::Time32(TimeUnit::Millisecond) => {
+            ScalarValue::Int32(Some(i32::try_from(bound.as_i64()?).ok()?))
+                .cast_to(data_type)
+                .ok()?
+        }
+        DataType::Time64(_)
+        | DataType::Timestamp(TimeUnit::Millisecond, _)
+        | DataType::Timestamp(TimeUnit::Microsecond, _)
+        | DataType::Timestamp(TimeUnit::Nanosecond, _) => ScalarValue::Int64(Some(bound.as_i64()?))
+            .cast_to(data_type)
+            .ok()?,
+        DataType::Decimal128(precision, scale) => {
+            ScalarValue::Decimal128(Some(bound.as_i64()? as i128), *precision, *scale)
+        }
+        DataType::Float32 => ScalarValue::Float32(Some(bound.as_f64()? as f32)),
+        DataType::Float64 => ScalarValue::Float64(Some(bound.as_f64()?)),
+        DataType::Utf8 => ScalarValue::Utf8(Some(bound.as_str()?.to_string())),
+        DataType::LargeUtf8 => ScalarValue::LargeUtf8(Some(bound.as_str()?.to_string())),
+        // Unsigned integers are bounded as signed ones, which order them
+        // differently, and seconds and Date64 are stored in other units
+        _ => return None,
+    };
+    Some(scalar)
+}
+
+/// The lower or upper bound of the values of a column that a partition value
+/// of its transform stands for, or `None` if it bounds nothing.
+fn partition_bound(
+    transform: &str,
+    value: &Value,
+    data_type: &DataType,
+    upper: bool,
+) -> Option<ScalarValue> {
+    if transform == "identity" {
+        return scalar(value, data_type);
+    }
+
+    if let Some(width) = transform
+        .strip_prefix("truncate[")
+        .and_then(|width| width.strip_suffix(']'))
+    {
+        return match data_type {
+            // Strings start with their truncation, which is all that's known
+            DataType::Utf8 | DataType::LargeUtf8 if !upper => scalar(value, data_type),
+            DataType::Int32 | DataType::Int64 => {
+                let start = value.as_i64()?;
+                let bound = match upper {
+                    true => start.checked_add(width.parse::<i64>().ok()? - 1)?,
+                    false => start,
+                };
+                scalar(&json!(bound), data_type)
+            }
+            _ => None,
+        };
+    }
+
+    // Temporal values count years, months, days or hours since the epoch
+    let ordinal = value.as_i64()?;
+    let days = |days: i64| days.checked_mul(DAY_MICROS);
+    let (start, end) = match transform {
+        "year" => (
+            days(month_start(ordinal.checked_mul(12)?))?,
+            days(month_start(ordinal.checked_add(1)?.checked_mul(12)?))?,
+        ),
+        "month" => (
+            days(month_start(ordinal))?,
+            days(month_start(ordinal.checked_add(1)?))?,
+        ),
+        "day" => (days(ordinal)?, days(ordinal.checked_add(1)?)?),
+        "hour" => (
+            ordinal.checked_mul(HOUR_MICROS)?,
+            ordinal.checked_add(1)?.checked_mul(HOUR_MICROS)?,
+        ),
+        _ => return None,
+    };
+    let micros = match upper {
+        true => end - 1,
+        false => start,
+    };
+
+    match data_type {
+        DataType::Date32 => scalar(&json!(micros.div_euclid(DAY_MICROS)), data_type),
+        DataType::Timestamp(TimeUnit::Microsecond, _) => scalar(&json!(micros), data_type),
+        _ => None,
+    }
+}
+
+/// Days since the epoch of the first day of the month `months` after January
+/// 1970, counting years from March so that leap days come last.
+fn month_start(months: i64) -> i64 {
+    let month = months.rem_euclid(12) + 1;
+    let year = 1970 + months.div_euclid(12) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Scans the data files of a table left after pruning, showing in plans how
+/// many were skipped.
+#[derive(Debug)]
+pub struct DataFilesExec {
+    input: Arc<dyn ExecutionPlan>,
+    files_scanned: usize,
+    files_skipped: usize,
+}
+
+impl DisplayAs for DataFilesExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DataFilesExec: files_scanned={}, files_skipped={}",
+            self.files_scanned, self.files_skipped
+        )
+    }
+}
+
+impl ExecutionPlan for DataFilesExec {
+    fn name(&self) -> &str {
+        "DataFilesExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        self.input.properties()
+    }
+
+    fn maintains_input_order(&self) -> Vec<bool> {
+        vec![true]
+    }
+
+    fn benefits_from_input_partitioning(&self) -> Vec<bool> {
+        vec![false]
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(DataFilesExec {
+            input: children.remove(0),
+            files_scanned: self.files_scanned,
+            files_skipped: self.files_skipped,
+        }))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        self.input.execute(partition, context)
+    }
+
+    fn statistics(&self) -> Result<Statistics> {
+        self.input.statistics()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::{
+        array::{Int64Array, RecordBatch},
+        datatypes::Field,
+        util::pretty::pretty_format_batches,
+    };
+    use datafusion::{
+        parquet::{arrow::ArrowWriter, file::properties::WriterProperties},
+        prelude::SessionContext,
+    };
+    use serde_json::json;
+    use std::fs::File;
+
+    async fn explain(ctx: &SessionContext, query: &str) -> String {
+        let batches = ctx.sql(query).await.unwrap().collect().await.unwrap();
+        pretty_format_batches(&batches).unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_pruning() {
+        let dir = tempfile::tempdir().unwrap();
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+
+        // Files of ids 0-99, 100-199 and 200-299, in row groups of 10
+        let files = (0..3)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.parquet", i));
+                let ids = Int64Array::from_iter_values(i * 100..(i + 1) * 100);
+                let batch = RecordBatch::try_new(schema.clone(), vec![Arc::new(ids)]).unwrap();
+                let properties = WriterProperties::builder()
+                    .set_max_row_group_size(10)
+                    .build();
+                let file = File::create(&path).unwrap();
+                let mut writer =
+                    ArrowWriter::try_new(file, schema.clone(), Some(properties)).unwrap();
+                writer.write(&batch).unwrap();
+                writer.close().unwrap();
+
+                DataFile {
+                    path: path.to_str().unwrap().to_string(),
+                    record_count: 100,
+                    file_size_in_bytes: path.metadata().unwrap().len() as i64,
+                    lower_bounds: HashMap::from([("1".to_string(), json!(i * 100))]),
+                    upper_bounds: HashMap::from([("1".to_string(), json!(i * 100 + 99))]),
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        let ctx = SessionContext::new();
+        let field_ids = HashMap::from([("id".to_string(), 1)]);
+        let table = DataFilesTable::try_new(&ctx.state(), files, field_ids)
+            .await
+            .unwrap();
+        ctx.register_table("t", Arc::new(table)).unwrap();
+
+        let query = "SELECT id FROM t WHERE id >= 250";
+        let plan = explain(&ctx, &format!("EXPLAIN {}", query)).await;
+        assert!(
+            plan.contains("files_scanned=1, files_skipped=2"),
+            "{}",
+            plan
+        );
+
+        let batches = ctx.sql(query).await.unwrap().collect().await.unwrap();
+        let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(rows, 50);
+
+        let plan = explain(&ctx, &format!("EXPLAIN ANALYZE {}", query)).await;
+        assert!(plan.contains("row_groups_pruned_statistics=5"), "{}", plan);
+
+        let plan = explain(&ctx, "EXPLAIN SELECT id FROM t WHERE id < 0").await;
+        assert!(
+            plan.contains("files_scanned=0, files_skipped=3"),
+            "{}",
+            plan
+        );
+
+        // Files without bounds are pruned by their partition values
+        let table = ctx.table_provider("t").await.unwrap();
+        let table = table.as_any().downcast_ref::<DataFilesTable>().unwrap();
+        let files = table
+            .files
+            .iter()
+            .map(|file| DataFile {
+                lower_bounds: HashMap::new(),
+                upper_bounds: HashMap::new(),
+                partition: BTreeMap::from([(
+                    "id_trunc".to_string(),
+                    file.lower_bounds["1"].clone(),
+                )]),
+                ..file.clone()
+            })
+            .collect();
+        let partition_fields = vec![PartitionField {
+            name: "id_trunc".to_string(),
+            source_id: 1,
+            transform: "truncate[100]".to_string(),
+        }];
+        let field_ids = HashMap::from([("id".to_string(), 1)]);
+        let table = DataFilesTable::try_new(&ctx.state(), files, field_ids)
+            .await
+            .unwrap()
+            .with_partition_fields(partition_fields);
+        ctx.register_table("p", Arc::new(table)).unwrap();
+
+        let plan = explain(&ctx, "EXPLAIN SELECT id FROM p WHERE id >= 250").await;
+        assert!(
+            plan.contains("files_scanned=1, files_skipped=2"),
+            "{}",
+            plan
+        );
+
+        // Months and years span their days, leap days included
+        let bound = |transform, ordinal: i64, upper| {
+            partition_bound(transform, &json!(ordinal), &DataType::Date32, upper)
+        };
+        assert_eq!(
+            bound("month", 1, false),
+            Some(ScalarValue::Date32(Some(31)))
+        );
+        assert_eq!(
+            bound("month", 25, true),
+            Some(ScalarValue::Date32(Some(789)))
+        );
+        assert_eq!(bound("year", 1, true), Some(ScalarValue::Date32(Some(729))));
+        assert_eq!(bound("bucket[16]", 1, false), None);
+    }
+}
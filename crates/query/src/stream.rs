@@ -0,0 +1,301 @@
+//! Query results written to the response body batch by batch, so that no
+//! more than a batch of a result is held in memory. Columnar JSON, whose
+//! columns span the whole result, and the row group a Parquet file is
+//! writing are the exceptions. The body is only polled as fast as the client
+//! reads it, and a client that disconnects drops the stream, cancelling the
+//! query.
+//!
+//! Once the first batch is sent the status can't change anymore: an error
+//! after it ends the body early, which clients see as a truncated response.
+
+use std::mem;
+
+use arrow::{
+    csv::WriterBuilder, datatypes::Schema, error::ArrowError, ipc::writer::StreamWriter,
+    record_batch::RecordBatch,
+};
+use arrow_json::LineDelimitedWriter;
+use axum::body::Bytes;
+use datafusion::{
+    error::DataFusionError, execution::SendableRecordBatchStream, parquet::arrow::ArrowWriter,
+};
+use futures::{stream, Stream, StreamExt};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::format::ResultFormat;
+
+/// Columns of a JSON result.
+#[derive(Serialize)]
+struct ResultSchema {
+    fields: Vec<ResultField>,
+}
+
+#[derive(Serialize)]
+struct ResultField {
+    name: String,
+    #[serde(rename = "type")]
+    data_type: String,
+    nullable: bool,
+}
+
+impl From<&Schema> for ResultSchema {
+    fn from(schema: &Schema) -> Self {
+        let fields = schema
+            .fields()
+            .iter()
+            .map(|field| ResultField {
+                name: field.name().clone(),
+                data_type: field.data_type().to_string(),
+                nullable: field.is_nullable(),
+            })
+            .collect();
+        ResultSchema { fields }
+    }
+}
+
+/// Writes batches in a format, a chunk at a time.
+enum Encoder {
+    Json { rows: usize },
+    JsonColumnar { columns: Vec<(String, Vec<Value>)> },
+    Ndjson,
+    Csv,
+    ArrowStream(StreamWriter<Vec<u8>>),
+    Parquet(ArrowWriter<Vec<u8>>),
+}
+
+impl Encoder {
+    /// Starts a result, returning the encoder and what precedes the rows.
+    fn new(format: ResultFormat, schema: &Schema) -> Result<(Encoder, Bytes), ArrowError> {
+        let json_head = |key: &str| {
+            let schema = serde_json::to_string(&ResultSchema::from(schema))
+                .map_err(|e| ArrowError::JsonError(e.to_string()))?;
+            Ok::<_, ArrowError>(format!(r#"{{"schema":{},"{}":"#, schema, key))
+        };
+
+        match format {
+            ResultFormat::Json => {
+                let head = json_head("rows")? + "[";
+                Ok((Encoder::Json { rows: 0 }, head.into()))
+            }
+            ResultFormat::JsonColumnar => {
+                let columns = schema
+                    .fields()
+                    .iter()
+                    .map(|field| (field.name().clone(), Vec::new()))
+                    .collect();
+                Ok((
+                    Encoder::JsonColumnar { columns },
+                    json_head("columns")?.into(),
+                ))
+            }
+            ResultFormat::Ndjson => Ok((Encoder::Ndjson, Bytes::new())),
+            ResultFormat::Csv => {
+                // The header, which CSV writers write with their first batch
+                let mut writer = WriterBuilder::new().with_header(true).build(Vec::new());
+                writer.write(&RecordBatch::new_empty(schema.clone().into()))?;
+                Ok((Encoder::Csv, writer.into_inner().into()))
+            }
+            ResultFormat::ArrowStream => {
+                let mut writer = StreamWriter::try_new(Vec::new(), schema)?;
+                let head = mem::take(writer.get_mut());
+                Ok((Encoder::ArrowStream(writer), head.into()))
+            }
+            ResultFormat::Parquet => {
+                let mut writer = ArrowWriter::try_new(Vec::new(), schema.clone().into(), None)?;
+                let head = mem::take(writer.inner_mut());
+                Ok((Encoder::Parquet(writer), head.into()))
+            }
+        }
+    }
+
+    fn batch(&mut self, batch: &RecordBatch) -> Result<Bytes, ArrowError> {
+        match self {
+            Encoder::Json { rows } => {
+                // Rows are written one per line, which JSON strings can't
+                // span, so that turning the line ends into commas makes them
+                // array elements
+                let mut lines = ndjson(batch)?;
+                lines.pop();
+                for byte in lines.iter_mut().filter(|byte| **byte == b'\n') {
+                    *byte = b',';
+                }
+                if *rows > 0 && !lines.is_empty() {
+                    lines.insert(0, b',');
+                }
+                *rows += batch.num_rows();
+                Ok(lines.into())
+            }
+            Encoder::JsonColumnar { columns } => {
+                let lines = ndjson(batch)?;
+                for line in lines.split(|byte| *byte == b'\n').filter(|l| !l.is_empty()) {
+                    let mut row: Map<String, Value> = serde_json::from_slice(line)
+                        .map_err(|e| ArrowError::JsonError(e.to_string()))?;
+                    for (name, values) in columns.iter_mut() {
+                        values.push(row.remove(name.as_str()).unwrap_or(Value::Null));
+                    }
+                }
+                Ok(Bytes::new())
+            }
+            Encoder::Ndjson => Ok(ndjson(batch)?.into()),
+            Encoder::Csv => {
+                let mut writer = WriterBuilder::new().with_header(false).build(Vec::new());
+                writer.write(batch)?;
+                Ok(writer.into_inner().into())
+            }
+            Encoder::ArrowStream(writer) => {
+                writer.write(batch)?;
+                Ok(mem::take(writer.get_mut()).into())
+            }
+            Encoder::Parquet(writer) => {
+                // Only completed row groups reach the buffer
+                writer.write(batch)?;
+                Ok(mem::take(writer.inner_mut()).into())
+            }
+        }
+    }
+
+    /// Ends the result, returning what follows the rows.
+    fn finish(self) -> Result<Bytes, ArrowError> {
+        match self {
+            Encoder::Json { .. } => Ok(Bytes::from_static(b"]}")),
+            Encoder::JsonColumnar { columns } => {
+                let columns: Vec<Vec<Value>> =
+                    columns.into_iter().map(|(_, values)| values).collect();
+                let mut tail = serde_json::to_vec(&columns)
+                    .map_err(|e| ArrowError::JsonError(e.to_string()))?;
+                tail.push(b'}');
+                Ok(tail.into())
+            }
+            Encoder::Ndjson | Encoder::Csv => Ok(Bytes::new()),
+            Encoder::ArrowStream(writer) => Ok(writer.into_inner()?.into()),
+            Encoder::Parquet(writer) => Ok(writer.into_inner()?.into()),
+        }
+    }
+}
+
+fn ndjson(batch: &RecordBatch) -> Result<Vec<u8>, ArrowError> {
+    let mut writer = LineDelimitedWriter::new(Vec::new());
+    writer.write(batch)?;
+    writer.finish()?;
+    Ok(writer.into_inner())
+}
+
+/// Encodes a result as chunks of a response body, one per batch.
+pub fn encode(
+    batches: SendableRecordBatchStream,
+    format: ResultFormat,
+) -> Result<impl Stream<Item = Result<Bytes, DataFusionError>> + Send, ArrowError> {
+    let (encoder, head) = Encoder::new(format, &batches.schema())?;
+
+    let rows = stream::try_unfold(Some((batches, encoder)), |state| async move {
+        let Some((mut batches, mut encoder)) = state else {
+            return Ok(None);
+        };
+        match batches.next().await {
+            Some(batch) => {
+                let chunk = encoder.batch(&batch?)?;
+                Ok(Some((chunk, Some((batches, encoder)))))
+            }
+            None => Ok(Some((encoder.finish()?, None))),
+        }
+    });
+
+    Ok(stream::once(async { Ok(head) }).chain(rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::{
+        array::{Int64Array, StringArray},
+        datatypes::{DataType, Field},
+        ipc::reader::StreamReader,
+    };
+    use datafusion::{
+        parquet::arrow::arrow_reader::ParquetRecordBatchReader,
+        physical_plan::stream::RecordBatchStreamAdapter,
+    };
+    use futures::TryStreamExt;
+    use serde_json::json;
+
+    use super::*;
+
+    fn batches() -> SendableRecordBatchStream {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let batch = |ids: Vec<i64>, names: Vec<Option<&str>>| {
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int64Array::from(ids)),
+                    Arc::new(StringArray::from(names)),
+                ],
+            )
+        };
+        let batches = vec![
+            batch(vec![1, 2], vec![Some("a"), Some("b\nc")]),
+            batch(vec![], vec![]),
+            batch(vec![3], vec![None]),
+        ];
+
+        Box::pin(RecordBatchStreamAdapter::new(
+            schema.clone(),
+            stream::iter(batches.into_iter().map(|b| b.map_err(Into::into))),
+        ))
+    }
+
+    async fn body(format: ResultFormat) -> Bytes {
+        let chunks: Vec<Bytes> = encode(batches(), format)
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        chunks.concat().into()
+    }
+
+    #[tokio::test]
+    async fn test_encode() {
+        let schema = json!({"fields": [
+            {"name": "id", "type": "Int64", "nullable": false},
+            {"name": "name", "type": "Utf8", "nullable": true},
+        ]});
+
+        let rows: Value = serde_json::from_slice(&body(ResultFormat::Json).await).unwrap();
+        assert_eq!(
+            rows,
+            json!({"schema": schema, "rows": [
+                {"id": 1, "name": "a"},
+                {"id": 2, "name": "b\nc"},
+                {"id": 3},
+            ]})
+        );
+
+        let columns = body(ResultFormat::JsonColumnar).await;
+        let columns: Value = serde_json::from_slice(&columns).unwrap();
+        assert_eq!(
+            columns,
+            json!({"schema": schema, "columns": [[1, 2, 3], ["a", "b\nc", null]]})
+        );
+
+        let ndjson = String::from_utf8(body(ResultFormat::Ndjson).await.to_vec()).unwrap();
+        assert_eq!(ndjson.lines().count(), 3);
+        assert_eq!(ndjson.lines().last(), Some(r#"{"id":3}"#));
+
+        let csv = String::from_utf8(body(ResultFormat::Csv).await.to_vec()).unwrap();
+        assert_eq!(csv, "id,name\n1,a\n2,\"b\nc\"\n3,\n");
+
+        let ipc = body(ResultFormat::ArrowStream).await;
+        let reader = StreamReader::try_new(ipc.as_ref(), None).unwrap();
+        let rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+        assert_eq!(rows, 3);
+
+        let parquet = body(ResultFormat::Parquet).await;
+        let reader = ParquetRecordBatchReader::try_new(parquet, 1024).unwrap();
+        let rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+        assert_eq!(rows, 3);
+    }
+}
@@ -0,0 +1,251 @@
+//! Time travel: reading a table as of an earlier snapshot, with
+//!
+//! ```sql
+//! SELECT * FROM t FOR SYSTEM_TIME AS OF '2026-01-01T00:00:00Z'
+//! SELECT * FROM t FOR VERSION AS OF 4601734216305484326
+//! ```
+//!
+//! `FOR SYSTEM_TIME AS OF` reads the snapshot that was current at the time
+//! by the table's snapshot log, `FOR VERSION AS OF` the snapshot with the id,
+//! as Iceberg's `TableScan::as_of_time` and `use_snapshot` do. Timestamps
+//! without an offset are UTC.
+//!
+//! The SQL parser knows neither clause, so queries are rewritten before they
+//! are parsed: each versioned table becomes a quoted table name standing for
+//! the table at that version, aliased to the table's own name.
+
+use std::{collections::HashMap, fmt};
+
+use arrow::{
+    compute::kernels::cast_utils::string_to_timestamp_nanos,
+    temporal_conversions::timestamp_ms_to_datetime,
+};
+use datafusion::sql::{
+    sqlparser::{
+        dialect::GenericDialect,
+        keywords::Keyword,
+        tokenizer::{Token, Tokenizer, Word},
+    },
+    TableReference,
+};
+
+use crate::engine::QueryError;
+
+/// Version of a table a query reads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TableVersion {
+    Snapshot(i64),
+    /// Milliseconds since the epoch.
+    AsOfTime(i64),
+}
+
+impl fmt::Display for TableVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableVersion::Snapshot(id) => write!(f, "snapshot {}", id),
+            TableVersion::AsOfTime(ms) => match timestamp_ms_to_datetime(*ms) {
+                Some(time) => write!(f, "as of {} UTC", time),
+                None => write!(f, "as of {} ms", ms),
+            },
+        }
+    }
+}
+
+/// A table a rewritten query reads at an earlier version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionedTable {
+    pub reference: TableReference,
+    pub version: TableVersion,
+}
+
+/// A query with its time travel clauses rewritten, and the tables the names
+/// they were replaced with stand for.
+#[derive(Debug)]
+pub struct Rewritten {
+    pub query: String,
+    pub tables: HashMap<String, VersionedTable>,
+}
+
+fn is_word(token: Option<&Token>, keyword: &str) -> bool {
+    matches!(token, Some(Token::Word(word)) if word.quote_style.is_none()
+        && word.value.eq_ignore_ascii_case(keyword))
+}
+
+/// Rewrites the time travel clauses of a query. Queries without any are
+/// returned as they are.
+pub fn rewrite(query: &str) -> Result<Rewritten, QueryError> {
+    let parse_error = |message: String| QueryError::ParseError(message);
+
+    let tokens = Tokenizer::new(&GenericDialect {}, query)
+        .with_unescape(false)
+        .tokenize()
+        .map_err(|e| parse_error(e.to_string()))?;
+
+    // Positions of the tokens that aren't whitespace or comments
+    let significant: Vec<usize> = (0..tokens.len())
+        .filter(|&i| !matches!(tokens[i], Token::Whitespace(_)))
+        .collect();
+    let at = |k: usize| significant.get(k).map(|&i| &tokens[i]);
+
+    let mut tables = HashMap::new();
+    // Token ranges to replace, in order
+    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+
+    for k in 0..significant.len() {
+        if !is_word(at(k), "FOR") || !is_word(at(k + 2), "AS") || !is_word(at(k + 3), "OF") {
+            continue;
+        }
+        let version = match (at(k + 1), at(k + 4)) {
+            (clause, Some(Token::SingleQuotedString(time))) if is_word(clause, "SYSTEM_TIME") => {
+                let nanos = string_to_timestamp_nanos(time)
+                    .map_err(|_| parse_error(format!("Invalid timestamp '{}'", time)))?;
+                TableVersion::AsOfTime(nanos.div_euclid(1_000_000))
+            }
+            (clause, Some(Token::Number(id, _))) if is_word(clause, "VERSION") => {
+                let id = id
+                    .parse()
+                    .map_err(|_| parse_error(format!("Invalid snapshot id {}", id)))?;
+                TableVersion::Snapshot(id)
+            }
+            (clause, _) if is_word(clause, "SYSTEM_TIME") || is_word(clause, "VERSION") => {
+                return Err(parse_error(
+                    "Expected FOR SYSTEM_TIME AS OF '<timestamp>' or FOR VERSION AS OF <snapshot id>"
+                        .to_string(),
+                ))
+            }
+            _ => continue,
+        };
+
+        // The table name before the clause, `t`, `db.t` or `ns1.ns2.t`
+        let mut parts: Vec<&Word> = Vec::new();
+        let mut start = k;
+        while let Some(Token::Word(word)) = start.checked_sub(1).and_then(at) {
+            parts.insert(0, word);
+            start -= 1;
+            if start == 0 || at(start - 1) != Some(&Token::Period) {
+                break;
+            }
+            start -= 1;
+        }
+        // Identifiers are matched lowercase unless quoted, as DataFusion does
+        let parts: Vec<String> = parts
+            .iter()
+            .map(|word| match word.quote_style {
+                Some(_) => word.value.clone(),
+                None => word.value.to_lowercase(),
+            })
+            .collect();
+        let reference = match parts.as_slice() {
+            [table] => TableReference::bare(table.as_str()),
+            [schema, table] => TableReference::partial(schema.as_str(), table.as_str()),
+            [catalog, schema, table] => {
+                TableReference::full(catalog.as_str(), schema.as_str(), table.as_str())
+            }
+            _ => {
+                return Err(parse_error(
+                    "FOR SYSTEM_TIME and FOR VERSION must follow a table name".to_string(),
+                ))
+            }
+        };
+
+        let name = match version {
+            TableVersion::Snapshot(id) => format!("{}@v{}", parts.join("."), id),
+            TableVersion::AsOfTime(ms) => format!("{}@t{}", parts.join("."), ms),
+        };
+        let mut replacement = Token::Word(Word {
+            value: name.clone(),
+            quote_style: Some('"'),
+            keyword: Keyword::NoKeyword,
+        })
+        .to_string();
+
+        // Keep the columns qualified by the table's name resolving, unless
+        // the query gives the table an alias of its own
+        let aliased = match at(k + 5) {
+            Some(Token::Word(word)) => {
+                word.quote_style.is_some()
+                    || matches!(word.keyword, Keyword::AS | Keyword::NoKeyword)
+            }
+            _ => false,
+        };
+        if !aliased {
+            let table = Token::Word(Word {
+                value: reference.table().to_string(),
+                quote_style: Some('"'),
+                keyword: Keyword::NoKeyword,
+            });
+            replacement = format!("{} AS {}", replacement, table);
+        }
+
+        tables.insert(name, VersionedTable { reference, version });
+        replacements.push((significant[start], significant[k + 4], replacement));
+    }
+
+    if replacements.is_empty() {
+        return Ok(Rewritten {
+            query: query.to_string(),
+            tables,
+        });
+    }
+
+    let mut rewritten = String::with_capacity(query.len());
+    let mut replacements = replacements.into_iter().peekable();
+    let mut i = 0;
+    while i < tokens.len() {
+        match replacements.next_if(|(start, _, _)| *start == i) {
+            Some((_, end, replacement)) => {
+                rewritten.push_str(&replacement);
+                i = end + 1;
+            }
+            None => {
+                rewritten.push_str(&tokens[i].to_string());
+                i += 1;
+            }
+        }
+    }
+
+    Ok(Rewritten {
+        query: rewritten,
+        tables,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite() {
+        let rewritten = rewrite(
+            "SELECT o.id, orders.id FROM Sales.orders FOR VERSION AS OF 42 o
+             JOIN orders FOR SYSTEM_TIME AS OF '2026-01-01T00:00:01Z' ON o.id = orders.id
+             WHERE o.note = 'FOR VERSION AS OF 1'",
+        )
+        .unwrap();
+
+        assert_eq!(
+            rewritten.query,
+            r#"SELECT o.id, orders.id FROM "sales.orders@v42" o
+             JOIN "orders@t1767225601000" AS "orders" ON o.id = orders.id
+             WHERE o.note = 'FOR VERSION AS OF 1'"#
+        );
+        assert_eq!(
+            rewritten.tables["sales.orders@v42"],
+            VersionedTable {
+                reference: TableReference::partial("sales", "orders"),
+                version: TableVersion::Snapshot(42),
+            }
+        );
+        assert_eq!(
+            rewritten.tables["orders@t1767225601000"].version,
+            TableVersion::AsOfTime(1_767_225_601_000)
+        );
+
+        let query = "SELECT 'it''s' FROM t";
+        assert_eq!(rewrite(query).unwrap().query, query);
+        assert!(matches!(
+            rewrite("SELECT * FROM t FOR SYSTEM_TIME AS OF 'yesterday'"),
+            Err(QueryError::ParseError(_))
+        ));
+    }
+}
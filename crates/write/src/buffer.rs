@@ -0,0 +1,393 @@
+//! Buffers small writes per table so they land in reasonably sized data
+//! files instead of one file per request.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use arrow::{compute::concat_batches, datatypes::SchemaRef, record_batch::RecordBatch};
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+use crate::{
+    properties::FileOptions,
+    store::{DataFilePaths, Store},
+    wal::{Segment, Wal},
+};
+
+/// When buffered rows of a table are written out.
+#[derive(Debug, Clone)]
+pub struct FlushPolicy {
+    pub max_rows: usize,
+    /// In-memory size of the buffered batches.
+    pub max_bytes: usize,
+    /// Longest a write waits for other writes to join its file.
+    pub max_latency: Duration,
+}
+
+impl Default for FlushPolicy {
+    fn default() -> Self {
+        Self {
+            max_rows: 500_000,
+            max_bytes: 64 * 1024 * 1024,
+            max_latency: Duration::from_secs(1),
+        }
+    }
+}
+
+impl FlushPolicy {
+    /// Reads `WRITE_FLUSH_ROWS`, `WRITE_FLUSH_BYTES` and
+    /// `WRITE_FLUSH_LATENCY_MS`, keeping defaults for those not set.
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok().and_then(|v| v.parse().ok())
+        }
+        let default = Self::default();
+
+        Self {
+            max_rows: var("WRITE_FLUSH_ROWS").unwrap_or(default.max_rows),
+            max_bytes: var("WRITE_FLUSH_BYTES").unwrap_or(default.max_bytes),
+            max_latency: var("WRITE_FLUSH_LATENCY_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(default.max_latency),
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum FlushError {
+    #[error("Failed to flush buffered rows: {0}")]
+    Store(String),
+    #[error("Failed to log rows: {0}")]
+    Wal(String),
+    #[error("Rows were committed, but may be committed again on restart: {0}")]
+    Replayable(String),
+    #[error("Buffered rows were dropped before flushing")]
+    Dropped,
+}
+
+/// Paths of the data files holding a write, once they are registered.
+type FlushResult = Result<Vec<String>, FlushError>;
+
+/// Rows of one table waiting to be flushed.
+struct TableBuffer {
+    /// Tells the latency timer whether the buffer it was started for is still
+    /// the one buffered.
+    id: u64,
+    location: String,
+    /// Settings and sort order the file is written with.
+    options: FileOptions,
+    schema: SchemaRef,
+    batches: Vec<RecordBatch>,
+    rows: usize,
+    bytes: usize,
+    /// Writes acknowledged once the buffer is flushed.
+    waiters: Vec<oneshot::Sender<FlushResult>>,
+    /// Log of the buffered batches, if the buffer has a WAL.
+    segment: Option<Segment>,
+}
+
+/// The buffer of one table, if it has one. Writes to the table hold its
+/// lock while their batch is logged, so batches are logged in the order they
+/// are buffered without holding up other tables.
+type TableSlot = Arc<tokio::sync::Mutex<Option<TableBuffer>>>;
+
+/// Buffers writes per table and flushes them by row count, size or age.
+#[derive(Clone)]
+pub struct WriteBuffer {
+    store: Arc<Box<dyn Store>>,
+    policy: FlushPolicy,
+    tables: Arc<Mutex<HashMap<String, TableSlot>>>,
+    next_id: Arc<AtomicU64>,
+    wal: Option<Wal>,
+}
+
+impl WriteBuffer {
+    pub fn new(store: Arc<Box<dyn Store>>, policy: FlushPolicy) -> Self {
+        Self {
+            store,
+            policy,
+            tables: Arc::default(),
+            next_id: Arc::default(),
+            wal: None,
+        }
+    }
+
+    /// Logs batches to `wal` before buffering them.
+    pub fn with_wal(mut self, wal: Wal) -> Self {
+        self.wal = Some(wal);
+        self
+    }
+
+    /// Buffers a batch for a table, returning once it is written and
+    /// registered with the catalog.
+    pub async fn write(
+        &self,
+        table: &str,
+        location: &str,
+        options: &FileOptions,
+        batch: RecordBatch,
+    ) -> FlushResult {
+        let (sender, receiver) = oneshot::channel();
+
+        let slot = self.slot(table);
+        let mut current = slot.lock().await;
+
+        // Batches of a file must share a schema and settings, so a table
+        // change flushes what was buffered before it
+        if let Some(buffer) = current.as_ref() {
+            if buffer.schema != batch.schema()
+                || buffer.location != location
+                || &buffer.options != options
+            {
+                self.spawn_flush(table, current.take());
+            }
+        }
+
+        if current.is_none() {
+            let segment = match &self.wal {
+                Some(wal) => Some(create_segment(wal, table, location, options, &batch).await?),
+                None => None,
+            };
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+            *current = Some(TableBuffer {
+                id,
+                location: location.to_string(),
+                options: options.clone(),
+                schema: batch.schema(),
+                batches: Vec::new(),
+                rows: 0,
+                bytes: 0,
+                waiters: Vec::new(),
+                segment,
+            });
+            self.spawn_timer(table, slot.clone(), id);
+        }
+
+        let buffer = current.as_mut().expect("buffer was just inserted");
+
+        // The batch is only acknowledged once it is logged. A failed append
+        // may leave part of the batch in the segment, and batches logged after
+        // it couldn't be replayed, so the buffer is flushed and the next write
+        // starts a new segment.
+        if let Some(segment) = buffer.segment.take() {
+            let (segment, logged) = append(segment, &batch).await;
+            buffer.segment = segment;
+            if let Err(e) = logged {
+                self.spawn_flush(table, current.take());
+                return Err(e);
+            }
+        }
+
+        buffer.rows += batch.num_rows();
+        buffer.bytes += batch.get_array_memory_size();
+        buffer.batches.push(batch);
+        buffer.waiters.push(sender);
+
+        if buffer.rows >= self.policy.max_rows || buffer.bytes >= self.policy.max_bytes {
+            self.spawn_flush(table, current.take());
+        }
+        drop(current);
+
+        receiver.await.unwrap_or(Err(FlushError::Dropped))
+    }
+
+    fn slot(&self, table: &str) -> TableSlot {
+        let mut tables = self.tables.lock().unwrap();
+        tables.entry(table.to_string()).or_default().clone()
+    }
+
+    /// Flushes a buffer on its own task, so a client going away doesn't
+    /// cancel the flush of other clients' rows.
+    fn spawn_flush(&self, table: &str, buffer: Option<TableBuffer>) {
+        if let Some(buffer) = buffer {
+            tokio::spawn(self.clone().flush(table.to_string(), buffer));
+        }
+    }
+
+    /// Flushes the buffer `id` once it is as old as the policy allows, if it
+    /// wasn't flushed before.
+    fn spawn_timer(&self, table: &str, slot: TableSlot, id: u64) {
+        let this = self.clone();
+        let table = table.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(this.policy.max_latency).await;
+            let buffer = {
+                let mut current = slot.lock().await;
+                match current.as_ref() {
+                    Some(buffer) if buffer.id == id => current.take(),
+                    _ => None,
+                }
+            };
+            if let Some(buffer) = buffer {
+                this.flush(table, buffer).await;
+            }
+        });
+    }
+
+    /// Writes out a buffer and acknowledges its writes. Its log segment is
+    /// deleted even if the flush failed, as the writers were told so and
+    /// replaying it would commit rows they may retry. A segment that can't be
+    /// deleted would be replayed, so its writers are told their rows may be
+    /// committed twice.
+    async fn flush(self, table: String, mut buffer: TableBuffer) {
+        let mut result = self.write_file(&table, &buffer).await;
+
+        if let Some(segment) = buffer.segment.take() {
+            let removed = tokio::task::spawn_blocking(move || segment.remove())
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|removed| removed.map_err(|e| e.to_string()));
+            if let (Ok(_), Err(e)) = (&result, removed) {
+                result = Err(FlushError::Replayable(e));
+            }
+        }
+
+        for waiter in buffer.waiters {
+            let _ = waiter.send(result.clone());
+        }
+    }
+
+    async fn write_file(&self, table: &str, buffer: &TableBuffer) -> FlushResult {
+        let store_error = |e: &dyn std::fmt::Display| FlushError::Store(e.to_string());
+
+        // A buffer whose only write failed to be logged
+        if buffer.rows == 0 {
+            return Ok(Vec::new());
+        }
+
+        let data = concat_batches(&buffer.schema, &buffer.batches)
+            .and_then(|data| buffer.options.arrange(data))
+            .map_err(|e| store_error(&e))?;
+        let path = DataFilePaths::new(&buffer.location).path(0);
+        let properties = buffer
+            .options
+            .writer_properties()
+            .map_err(|e| store_error(&e))?;
+
+        let file = self
+            .store
+            .write(data, &path, properties)
+            .await
+            .map_err(|e| store_error(&e))?;
+        self.store
+            .notify_catalog(table, std::slice::from_ref(&file))
+            .await
+            .map_err(|e| store_error(&e))?;
+
+        Ok(vec![file.path])
+    }
+}
+
+/// Starts the log segment of a new buffer, off the async runtime.
+async fn create_segment(
+    wal: &Wal,
+    table: &str,
+    location: &str,
+    options: &FileOptions,
+    batch: &RecordBatch,
+) -> Result<Segment, FlushError> {
+    let wal = wal.clone();
+    let (table, location, options) = (table.to_string(), location.to_string(), options.clone());
+    let schema = batch.schema();
+
+    tokio::task::spawn_blocking(move || wal.create_segment(&table, &location, &options, &schema))
+        .await
+        .map_err(|e| FlushError::Wal(e.to_string()))?
+        .map_err(|e| FlushError::Wal(e.to_string()))
+}
+
+/// Logs a batch off the async runtime, handing the segment back with the
+/// result of the append.
+async fn append(
+    mut segment: Segment,
+    batch: &RecordBatch,
+) -> (Option<Segment>, Result<(), FlushError>) {
+    let batch = batch.clone();
+
+    let appended = tokio::task::spawn_blocking(move || {
+        let logged = segment.append(&batch);
+        (segment, logged)
+    })
+    .await;
+
+    match appended {
+        Ok((segment, logged)) => (
+            Some(segment),
+            logged.map_err(|e| FlushError::Wal(e.to_string())),
+        ),
+        Err(e) => (None, Err(FlushError::Wal(e.to_string()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::tests::{MemoryStore, WrittenFiles};
+    use arrow::{
+        array::Int32Array,
+        datatypes::{DataType, Field, Schema},
+    };
+
+    fn batch(values: Vec<i32>) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(Int32Array::from(values))]).unwrap()
+    }
+
+    fn buffer(policy: FlushPolicy) -> (WriteBuffer, WrittenFiles) {
+        let store = MemoryStore::default();
+        let files = store.files.clone();
+        (WriteBuffer::new(Arc::new(Box::new(store)), policy), files)
+    }
+
+    #[tokio::test]
+    async fn test_flush_by_rows() {
+        let policy = FlushPolicy {
+            max_rows: 4,
+            max_latency: Duration::from_secs(60),
+            ..FlushPolicy::default()
+        };
+        let (buffer, written) = buffer(policy);
+        let options = FileOptions::default();
+
+        let (first, second) = tokio::join!(
+            buffer.write("db.t", "/warehouse/db/t", &options, batch(vec![1, 2])),
+            buffer.write("db.t", "/warehouse/db/t", &options, batch(vec![3, 4])),
+        );
+
+        // Both writes are acknowledged with the one file holding them
+        let files = first.unwrap();
+        assert_eq!(files, second.unwrap());
+        assert!(files[0].starts_with("/warehouse/db/t/data/"));
+        assert_eq!(written.lock().unwrap()[0].1, 4);
+    }
+
+    #[tokio::test]
+    async fn test_flush_by_latency() {
+        let policy = FlushPolicy {
+            max_latency: Duration::from_millis(10),
+            ..FlushPolicy::default()
+        };
+        let (buffer, files) = buffer(policy);
+        let options = FileOptions::default();
+
+        buffer
+            .write("db.t", "/warehouse/db/t", &options, batch(vec![1]))
+            .await
+            .unwrap();
+        buffer
+            .write("db.t", "/warehouse/db/t", &options, batch(vec![2]))
+            .await
+            .unwrap();
+
+        let files = files.lock().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_ne!(files[0].0, files[1].0);
+    }
+}
@@ -0,0 +1,182 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{schema::CatalogSchema, store::DataFile};
+
+/// Namespace of tables written without one.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Identifies a table by its namespace levels and name, written as
+/// `ns1.ns2.table`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableIdentifier {
+    pub namespace: Vec<String>,
+    pub name: String,
+}
+
+impl FromStr for TableIdentifier {
+    type Err = String;
+
+    /// Parses `ns1.ns2.table`. A bare table name is in the default namespace.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut levels: Vec<String> = s.split('.').map(str::to_string).collect();
+        let name = levels.pop().unwrap_or_default();
+
+        if levels.is_empty() {
+            levels.push(DEFAULT_NAMESPACE.to_string());
+        }
+
+        // Levels are part of catalog URLs, and name directories
+        if levels
+            .iter()
+            .chain([&name])
+            .any(|level| level.is_empty() || level.contains(['/', '\\']))
+        {
+            return Err(format!("invalid table identifier: {:?}", s));
+        }
+
+        Ok(TableIdentifier {
+            namespace: levels,
+            name,
+        })
+    }
+}
+
+impl fmt::Display for TableIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.namespace.join("."), self.name)
+    }
+}
+
+/// A table as the write service needs it.
+#[derive(Debug, Deserialize)]
+pub struct CatalogTable {
+    pub schema: CatalogSchema,
+    /// Directory data files of the table are written under.
+    pub location: String,
+    /// Table properties, such as the Parquet settings of its data files.
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+    /// Iceberg sort order of new data files, if the table is sorted.
+    #[serde(default)]
+    pub sort_order: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct RegisterDataFiles<'a> {
+    files: &'a [DataFile],
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    removed: &'a [String],
+}
+
+/// A data file registered with the catalog, as a rewrite needs it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegisteredFile {
+    pub path: String,
+    pub file_size_in_bytes: i64,
+}
+
+#[derive(Deserialize)]
+struct RegisteredFiles {
+    files: Vec<RegisteredFile>,
+}
+
+/// Client for the catalog service.
+#[derive(Debug, Clone)]
+pub struct CatalogClient {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl CatalogClient {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Fetches a table, or `None` if it doesn't exist.
+    pub async fn get_table(&self, table: &str) -> Result<Option<CatalogTable>, reqwest::Error> {
+        let response = self
+            .client
+            .get(format!("{}/tables/{}", self.base_url, table))
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        Ok(Some(response.error_for_status()?.json().await?))
+    }
+
+    /// Registers data files written for a table, so that readers can find
+    /// them without listing directories.
+    pub async fn register_data_files(
+        &self,
+        table: &str,
+        files: &[DataFile],
+    ) -> Result<(), reqwest::Error> {
+        self.replace_data_files(table, files, &[]).await
+    }
+
+    /// Registers data files in place of current ones, in one snapshot. Fails
+    /// with a conflict status if one of `removed` is no longer current.
+    pub async fn replace_data_files(
+        &self,
+        table: &str,
+        files: &[DataFile],
+        removed: &[String],
+    ) -> Result<(), reqwest::Error> {
+        self.client
+            .post(format!("{}/tables/{}/files", self.base_url, table))
+            .json(&RegisterDataFiles { files, removed })
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Lists the current data files of a table, oldest first.
+    pub async fn list_data_files(
+        &self,
+        table: &str,
+    ) -> Result<Vec<RegisteredFile>, reqwest::Error> {
+        let files: RegisteredFiles = self
+            .client
+            .get(format!("{}/tables/{}/files", self.base_url, table))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(files.files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_identifier() {
+        let identifier: TableIdentifier = "sales.eu.orders".parse().unwrap();
+        assert_eq!(identifier.namespace, vec!["sales", "eu"]);
+        assert_eq!(identifier.name, "orders");
+        assert_eq!(identifier.to_string(), "sales.eu.orders");
+
+        // Bare and qualified names of a table share buffers and WAL segments
+        let identifier: TableIdentifier = "traffic".parse().unwrap();
+        assert_eq!(identifier, "default.traffic".parse().unwrap());
+        assert_eq!(identifier.to_string(), "default.traffic");
+
+        assert!("".parse::<TableIdentifier>().is_err());
+        assert!("sales..orders".parse::<TableIdentifier>().is_err());
+        assert!("sales/eu.orders".parse::<TableIdentifier>().is_err());
+    }
+}
@@ -0,0 +1,289 @@
+//! Multi-dimensional clustering of data files. A linear sort only keeps the
+//! bounds of its first column tight; ordering rows along a space-filling
+//! curve instead keeps those of every clustered column reasonably tight.
+//!
+//! Tables are clustered by setting `write.clustering` to `zorder(a, b)` or
+//! `hilbert(a, b)`, naming top-level columns. Hilbert curves cluster a
+//! little better, z-order curves are cheaper to compute. Clustering replaces
+//! the table's sort order for the files it is used for.
+//!
+//! Writes order rows within each file they write; `POST /rewrite` orders the
+//! rows of many files together, so that files split along the curve.
+
+use arrow::{
+    array::{Array, UInt64Array},
+    compute::{cast, kernels::rank::rank, sort_to_indices, take_record_batch, SortOptions},
+    datatypes::DataType,
+    error::ArrowError,
+    record_batch::RecordBatch,
+};
+use parquet::arrow::PARQUET_FIELD_ID_META_KEY;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::CatalogSchema;
+
+/// Table property naming the clustering curve and columns.
+pub const CLUSTERING_PROPERTY: &str = "write.clustering";
+
+/// More columns would leave too few bits of each in the curve position.
+const MAX_COLUMNS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Curve {
+    ZOrder,
+    Hilbert,
+}
+
+/// Curve the rows of data files are ordered along, and the columns it
+/// interleaves.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Clustering {
+    pub curve: Curve,
+    pub field_ids: Vec<u32>,
+}
+
+impl Clustering {
+    /// Parses `zorder(a, b)` or `hilbert(a, b)`, resolving the columns to
+    /// field ids of the table's schema.
+    pub fn parse(value: &str, schema: &CatalogSchema) -> Result<Clustering, String> {
+        let invalid = || format!("invalid {}: {}", CLUSTERING_PROPERTY, value);
+
+        let (curve, columns) = value
+            .trim()
+            .strip_suffix(')')
+            .and_then(|value| value.split_once('('))
+            .ok_or_else(invalid)?;
+
+        let curve = match curve.trim().to_lowercase().as_str() {
+            "zorder" => Curve::ZOrder,
+            "hilbert" => Curve::Hilbert,
+            _ => return Err(invalid()),
+        };
+
+        let field_ids = columns
+            .split(',')
+            .map(|name| {
+                let name = name.trim();
+                let field = schema
+                    .fields
+                    .iter()
+                    .find(|field| field.field == name)
+                    .ok_or_else(|| format!("can't cluster by unknown column {}", name))?;
+
+                let nested =
+                    !field.fields.is_empty() || field.element.is_some() || field.key.is_some();
+                match field.id {
+                    Some(id) if !nested => Ok(id),
+                    _ => Err(format!("can't cluster by column {}", name)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if field_ids.is_empty() || field_ids.len() > MAX_COLUMNS {
+            return Err(format!(
+                "{} takes 1 to {} columns",
+                CLUSTERING_PROPERTY, MAX_COLUMNS
+            ));
+        }
+
+        Ok(Clustering { curve, field_ids })
+    }
+
+    /// Orders the rows of a batch along the curve. Columns the batch doesn't
+    /// have are left out of the curve.
+    pub fn cluster(&self, batch: RecordBatch) -> Result<RecordBatch, ArrowError> {
+        let schema = batch.schema();
+        let columns: Vec<&dyn Array> =
+            self.field_ids
+                .iter()
+                .filter_map(|id| {
+                    let id = id.to_string();
+                    schema.fields().iter().position(|field| {
+                        field.metadata().get(PARQUET_FIELD_ID_META_KEY) == Some(&id)
+                    })
+                })
+                .map(|index| batch.column(index).as_ref())
+                .collect();
+
+        if columns.is_empty() || batch.num_rows() < 2 {
+            return Ok(batch);
+        }
+
+        // Each column gets an equal share of the 64 bit position
+        let bits = (u64::BITS / columns.len() as u32).min(u32::BITS);
+        let coordinates = columns
+            .iter()
+            .map(|column| normalize(*column, bits))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut point = vec![0; columns.len()];
+        let positions: UInt64Array = (0..batch.num_rows())
+            .map(|row| {
+                for (axis, values) in point.iter_mut().zip(&coordinates) {
+                    *axis = values[row];
+                }
+                match self.curve {
+                    Curve::ZOrder => interleave(&point, bits),
+                    Curve::Hilbert => hilbert_index(&mut point, bits),
+                }
+            })
+            .collect();
+
+        let indices = sort_to_indices(&positions, None, None)?;
+        take_record_batch(&batch, &indices)
+    }
+}
+
+/// Maps the values of a column to `bits` bit coordinates by rank, so that
+/// skewed columns still spread over the whole axis. Nulls come first.
+fn normalize(column: &dyn Array, bits: u32) -> Result<Vec<u64>, ArrowError> {
+    let options = SortOptions {
+        descending: false,
+        nulls_first: true,
+    };
+    let ranks = match column.data_type() {
+        DataType::Boolean => rank(&cast(column, &DataType::UInt8)?, Some(options))?,
+        _ => rank(column, Some(options))?,
+    };
+
+    let rows = column.len() as u128;
+    Ok(ranks
+        .into_iter()
+        .map(|rank| ((rank.saturating_sub(1) as u128) << bits) / rows)
+        .map(|coordinate| coordinate as u64)
+        .collect())
+}
+
+/// Z-order position: the bits of the coordinates, interleaved from the most
+/// significant.
+fn interleave(point: &[u64], bits: u32) -> u64 {
+    let mut position = 0;
+    for bit in (0..bits).rev() {
+        for axis in point {
+            position = (position << 1) | ((axis >> bit) & 1);
+        }
+    }
+    position
+}
+
+/// Hilbert curve position, by transposing the coordinates in place as
+/// Skilling's "Programming the Hilbert curve" (2004) does, then interleaving
+/// the transposed bits.
+fn hilbert_index(point: &mut [u64], bits: u32) -> u64 {
+    let n = point.len();
+    let top = 1u64 << (bits - 1);
+
+    // Inverse undo excess work
+    let mut q = top;
+    while q > 1 {
+        let p = q - 1;
+        for i in 0..n {
+            if point[i] & q != 0 {
+                point[0] ^= p;
+            } else {
+                let t = (point[0] ^ point[i]) & p;
+                point[0] ^= t;
+                point[i] ^= t;
+            }
+        }
+        q >>= 1;
+    }
+
+    // Gray encode
+    for i in 1..n {
+        point[i] ^= point[i - 1];
+    }
+    let mut t = 0;
+    let mut q = top;
+    while q > 1 {
+        if point[n - 1] & q != 0 {
+            t ^= q - 1;
+        }
+        q >>= 1;
+    }
+    for axis in point.iter_mut() {
+        *axis ^= t;
+    }
+
+    interleave(point, bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use arrow::{
+        array::{ArrayRef, Int32Array},
+        datatypes::{Field, Schema},
+    };
+
+    use super::*;
+
+    /// The 16 points of a 4x4 grid, in row order.
+    fn grid() -> RecordBatch {
+        let field = |name: &str, id: u32| {
+            Field::new(name, DataType::Int32, false).with_metadata(HashMap::from([(
+                PARQUET_FIELD_ID_META_KEY.to_string(),
+                id.to_string(),
+            )]))
+        };
+        let schema = Arc::new(Schema::new(vec![field("x", 1), field("y", 2)]));
+        let x: Vec<i32> = (0..16).map(|i| i / 4).collect();
+        let y: Vec<i32> = (0..16).map(|i| i % 4).collect();
+
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(x)) as ArrayRef,
+                Arc::new(Int32Array::from(y)) as ArrayRef,
+            ],
+        )
+        .unwrap()
+    }
+
+    fn points(batch: &RecordBatch) -> Vec<(i32, i32)> {
+        let column = |i: usize| {
+            batch
+                .column(i)
+                .as_any()
+                .downcast_ref::<Int32Array>()
+                .unwrap()
+                .values()
+                .to_vec()
+        };
+        column(0).into_iter().zip(column(1)).collect()
+    }
+
+    #[test]
+    fn test_zorder() {
+        let clustering = Clustering {
+            curve: Curve::ZOrder,
+            field_ids: vec![1, 2],
+        };
+        let points = points(&clustering.cluster(grid()).unwrap());
+
+        // Each quadrant is written before the next one starts
+        assert_eq!(&points[..4], &[(0, 0), (0, 1), (1, 0), (1, 1)]);
+        assert_eq!(&points[4..8], &[(0, 2), (0, 3), (1, 2), (1, 3)]);
+        assert_eq!(points[15], (3, 3));
+    }
+
+    #[test]
+    fn test_hilbert() {
+        let clustering = Clustering {
+            curve: Curve::Hilbert,
+            field_ids: vec![1, 2],
+        };
+        let points = points(&clustering.cluster(grid()).unwrap());
+
+        // Consecutive points of a Hilbert curve are neighbours
+        assert_eq!(points.len(), 16);
+        for pair in points.windows(2) {
+            let distance = (pair[0].0 - pair[1].0).abs() + (pair[0].1 - pair[1].1).abs();
+            assert_eq!(distance, 1, "{:?}", pair);
+        }
+        let quadrant: Vec<_> = points[..4].iter().map(|(x, y)| (x / 2, y / 2)).collect();
+        assert!(quadrant.iter().all(|q| *q == quadrant[0]));
+    }
+}
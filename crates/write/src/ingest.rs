@@ -0,0 +1,422 @@
+//! Incremental decoding of NDJSON and CSV request bodies, so large uploads
+//! are converted to record batches as they arrive instead of being buffered.
+
+use std::sync::Arc;
+
+use arrow::csv::{reader::Decoder, ReaderBuilder};
+use arrow::datatypes::{DataType, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::util::{self, DecodeError, RowError};
+
+/// Rows per decoded batch.
+const BATCH_ROWS: usize = 8192;
+
+/// Longest NDJSON line or CSV header held while waiting for its end.
+const MAX_LINE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Decodes a request body chunk by chunk.
+pub trait ChunkDecoder: Send {
+    /// Decodes the next chunk of the body, returning the batches completed.
+    fn decode(&mut self, chunk: &[u8]) -> Result<Vec<RecordBatch>, DecodeError>;
+
+    /// Decodes what is left once the body ended.
+    fn finish(&mut self) -> Result<Vec<RecordBatch>, DecodeError>;
+}
+
+/// Decodes newline-delimited JSON objects, skipping blank lines.
+pub struct NdjsonDecoder {
+    schema: SchemaRef,
+    /// Bytes of the line that is not complete yet.
+    partial_line: Vec<u8>,
+    rows: Vec<Value>,
+    /// Index of the first row in `rows`.
+    first_row: usize,
+}
+
+impl NdjsonDecoder {
+    pub fn new(schema: SchemaRef) -> Self {
+        Self {
+            schema,
+            partial_line: Vec::new(),
+            rows: Vec::new(),
+            first_row: 0,
+        }
+    }
+
+    fn push_line(&mut self, line: &[u8]) -> Result<Option<RecordBatch>, DecodeError> {
+        if line.len() > MAX_LINE_BYTES {
+            return Err(DecodeError::LineTooLong(MAX_LINE_BYTES));
+        }
+
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return Ok(None);
+        }
+
+        let value = serde_json::from_slice(line).map_err(|e| DecodeError::InvalidRows {
+            errors: vec![RowError {
+                row: self.first_row + self.rows.len(),
+                field: String::new(),
+                message: format!("invalid JSON: {}", e),
+            }],
+            total: 1,
+        })?;
+        self.rows.push(value);
+
+        if self.rows.len() < BATCH_ROWS {
+            return Ok(None);
+        }
+
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<Option<RecordBatch>, DecodeError> {
+        if self.rows.is_empty() {
+            return Ok(None);
+        }
+
+        let rows = std::mem::take(&mut self.rows);
+        let first_row = self.first_row;
+        self.first_row += rows.len();
+
+        // Errors are reported by row of the whole body, not of the batch
+        util::read_record_batch_from_json(&rows, self.schema.clone())
+            .map(Some)
+            .map_err(|e| match e {
+                DecodeError::InvalidRows { mut errors, total } => {
+                    for error in &mut errors {
+                        error.row += first_row;
+                    }
+                    DecodeError::InvalidRows { errors, total }
+                }
+                e => e,
+            })
+    }
+}
+
+impl ChunkDecoder for NdjsonDecoder {
+    fn decode(&mut self, chunk: &[u8]) -> Result<Vec<RecordBatch>, DecodeError> {
+        let mut batches = Vec::new();
+        let mut rest = chunk;
+
+        while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+            let batch = if self.partial_line.is_empty() {
+                self.push_line(&rest[..end])?
+            } else {
+                let mut line = std::mem::take(&mut self.partial_line);
+                line.extend_from_slice(&rest[..end]);
+                self.push_line(&line)?
+            };
+            batches.extend(batch);
+            rest = &rest[end + 1..];
+        }
+
+        if self.partial_line.len() + rest.len() > MAX_LINE_BYTES {
+            return Err(DecodeError::LineTooLong(MAX_LINE_BYTES));
+        }
+        self.partial_line.extend_from_slice(rest);
+        Ok(batches)
+    }
+
+    fn finish(&mut self) -> Result<Vec<RecordBatch>, DecodeError> {
+        let line = std::mem::take(&mut self.partial_line);
+        let mut batches: Vec<RecordBatch> = self.push_line(&line)?.into_iter().collect();
+        batches.extend(self.flush()?);
+        Ok(batches)
+    }
+}
+
+/// Options of CSV bodies, passed as query parameters.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CsvOptions {
+    /// Whether the first line names the columns. Without a header, columns
+    /// are in table schema order.
+    #[serde(default = "default_header")]
+    pub header: bool,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    /// Value read as null. By default empty values are null.
+    pub null: Option<String>,
+}
+
+fn default_header() -> bool {
+    true
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            header: default_header(),
+            delimiter: default_delimiter(),
+            null: None,
+        }
+    }
+}
+
+/// Decodes CSV, matching header columns to the table schema by name.
+pub struct CsvDecoder {
+    schema: SchemaRef,
+    options: CsvOptions,
+    /// Bytes of the header until it is complete.
+    header: Vec<u8>,
+    decoder: Option<Decoder>,
+}
+
+impl CsvDecoder {
+    pub fn new(schema: SchemaRef, options: CsvOptions) -> Result<Self, DecodeError> {
+        if !options.delimiter.is_ascii() {
+            return Err(DecodeError::InvalidOptions(format!(
+                "delimiter must be an ASCII character, got {:?}",
+                options.delimiter
+            )));
+        }
+
+        Ok(Self {
+            schema,
+            options,
+            header: Vec::new(),
+            decoder: None,
+        })
+    }
+
+    /// Builds the decoder once the columns of the body are known.
+    fn build_decoder(&self, header: Option<&[u8]>) -> Result<Decoder, DecodeError> {
+        let csv_schema = match header {
+            Some(header) => {
+                let mut reader = csv::ReaderBuilder::new()
+                    .has_headers(false)
+                    .delimiter(self.options.delimiter as u8)
+                    .from_reader(header);
+                let names = reader
+                    .records()
+                    .next()
+                    .transpose()
+                    .map_err(|e| DecodeError::SchemaMismatch(format!("invalid header: {}", e)))?
+                    .unwrap_or_default();
+
+                let fields = names
+                    .iter()
+                    .map(|name| {
+                        self.schema.field_with_name(name).cloned().map_err(|_| {
+                            DecodeError::SchemaMismatch(format!(
+                                "column {} is not in the table schema",
+                                name
+                            ))
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Arc::new(Schema::new(fields))
+            }
+            None => self.schema.clone(),
+        };
+
+        if let Some(field) = csv_schema.fields().iter().find(|f| {
+            matches!(
+                f.data_type(),
+                DataType::Struct(_) | DataType::List(_) | DataType::Map(..)
+            )
+        }) {
+            return Err(DecodeError::SchemaMismatch(format!(
+                "column {} of type {} can't be read from CSV",
+                field.name(),
+                field.data_type()
+            )));
+        }
+
+        let mut builder = ReaderBuilder::new(csv_schema)
+            .with_header(false)
+            .with_delimiter(self.options.delimiter as u8)
+            .with_batch_size(BATCH_ROWS);
+
+        if let Some(null) = &self.options.null {
+            let regex = Regex::new(&format!("^{}$", regex::escape(null)))
+                .map_err(|e| DecodeError::InvalidOptions(e.to_string()))?;
+            builder = builder.with_null_regex(regex);
+        }
+
+        Ok(builder.build_decoder())
+    }
+
+    fn decode_rows(&mut self, mut rest: &[u8]) -> Result<Vec<RecordBatch>, DecodeError> {
+        let Some(decoder) = self.decoder.as_mut() else {
+            return Ok(Vec::new());
+        };
+
+        let mut batches = Vec::new();
+
+        // Decoding stops whenever a batch is full; an empty input ends the body
+        loop {
+            let read = decoder.decode(rest)?;
+            rest = &rest[read..];
+
+            if decoder.capacity() == 0 || rest.is_empty() {
+                if decoder.capacity() == 0 {
+                    if let Some(batch) = decoder.flush()? {
+                        batches.push(util::conform_batch(batch, self.schema.clone())?);
+                    }
+                }
+                if rest.is_empty() {
+                    break;
+                }
+            }
+        }
+
+        Ok(batches)
+    }
+}
+
+impl ChunkDecoder for CsvDecoder {
+    fn decode(&mut self, chunk: &[u8]) -> Result<Vec<RecordBatch>, DecodeError> {
+        if self.decoder.is_some() {
+            return self.decode_rows(chunk);
+        }
+
+        if !self.options.header {
+            self.decoder = Some(self.build_decoder(None)?);
+            return self.decode_rows(chunk);
+        }
+
+        let header_end = chunk.iter().position(|&b| b == b'\n');
+        if self.header.len() + header_end.unwrap_or(chunk.len()) > MAX_LINE_BYTES {
+            return Err(DecodeError::LineTooLong(MAX_LINE_BYTES));
+        }
+
+        // The header is decoded on its own, as its columns pick the schema
+        match header_end {
+            Some(end) => {
+                self.header.extend_from_slice(&chunk[..end]);
+                let header = std::mem::take(&mut self.header);
+                self.decoder = Some(self.build_decoder(Some(&header))?);
+                self.decode_rows(&chunk[end + 1..])
+            }
+            None => {
+                self.header.extend_from_slice(chunk);
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    fn finish(&mut self) -> Result<Vec<RecordBatch>, DecodeError> {
+        if self.decoder.is_none() {
+            // The body ended within the header, so it has no rows
+            let header = std::mem::take(&mut self.header);
+            let header = self.options.header.then_some(header.as_slice());
+            self.decoder = Some(self.build_decoder(header)?);
+        }
+
+        let mut batches = self.decode_rows(&[])?;
+        if let Some(batch) = self.decoder.as_mut().and_then(|d| d.flush().transpose()) {
+            batches.push(util::conform_batch(batch?, self.schema.clone())?);
+        }
+
+        Ok(batches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, AsArray};
+    use arrow::datatypes::{Field, Int32Type};
+
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("name", DataType::Utf8, false),
+            Field::new("age", DataType::Int32, true),
+        ]))
+    }
+
+    /// Feeds the body in small chunks, splitting rows across them.
+    fn decode_all(
+        decoder: &mut dyn ChunkDecoder,
+        body: &str,
+    ) -> Result<Vec<RecordBatch>, DecodeError> {
+        let mut batches = Vec::new();
+        for chunk in body.as_bytes().chunks(5) {
+            batches.extend(decoder.decode(chunk)?);
+        }
+        batches.extend(decoder.finish()?);
+        Ok(batches)
+    }
+
+    #[test]
+    fn test_ndjson_decoder() {
+        let body = "{\"name\": \"Alice\", \"age\": 30}\n\n{\"name\": \"Bob\"}";
+        let batches = decode_all(&mut NdjsonDecoder::new(schema()), body).unwrap();
+
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].num_rows(), 2);
+        assert_eq!(batches[0].column(0).as_string::<i32>().value(1), "Bob");
+
+        let body = "{\"name\": \"Alice\"}\n{\"name\": 1}\n";
+        let Err(DecodeError::InvalidRows { errors, .. }) =
+            decode_all(&mut NdjsonDecoder::new(schema()), body)
+        else {
+            panic!("expected invalid rows");
+        };
+        assert_eq!(errors[0].row, 1);
+        assert_eq!(errors[0].field, "name");
+
+        let body = "{\"name\": \"Alice\"}\n{\"name\"\n";
+        let Err(DecodeError::InvalidRows { errors, .. }) =
+            decode_all(&mut NdjsonDecoder::new(schema()), body)
+        else {
+            panic!("expected invalid rows");
+        };
+        assert_eq!(errors[0].row, 1);
+    }
+
+    #[test]
+    fn test_csv_decoder() {
+        let options = CsvOptions {
+            header: true,
+            delimiter: ';',
+            null: Some("NA".to_string()),
+        };
+        let mut decoder = CsvDecoder::new(schema(), options).unwrap();
+        let body = "age;name\n30;Alice\nNA;\"Bob; Jr\"\n";
+        let batches = decode_all(&mut decoder, body).unwrap();
+
+        let batch = &batches[0];
+        assert_eq!(batch.schema(), schema());
+        assert_eq!(batch.column(0).as_string::<i32>().value(1), "Bob; Jr");
+        let age = batch.column(1).as_primitive::<Int32Type>();
+        assert_eq!(age.value(0), 30);
+        assert!(age.is_null(1));
+
+        let options = CsvOptions {
+            header: false,
+            ..CsvOptions::default()
+        };
+        let mut decoder = CsvDecoder::new(schema(), options).unwrap();
+        let batches = decode_all(&mut decoder, "Alice,30\nBob,25").unwrap();
+        assert_eq!(batches[0].num_rows(), 2);
+
+        let mut decoder = CsvDecoder::new(schema(), CsvOptions::default()).unwrap();
+        let result = decode_all(&mut decoder, "name,color\nAlice,red\n");
+        assert!(matches!(result, Err(DecodeError::SchemaMismatch(_))));
+    }
+
+    #[test]
+    fn test_unterminated_lines_are_bounded() {
+        let chunk = vec![b'x'; MAX_LINE_BYTES / 2 + 1];
+
+        // Neither an NDJSON line nor a CSV header ends, so both keep growing
+        let mut decoder = NdjsonDecoder::new(schema());
+        decoder.decode(&chunk).unwrap();
+        let result = decoder.decode(&chunk);
+        assert!(matches!(result, Err(DecodeError::LineTooLong(_))));
+
+        let mut decoder = CsvDecoder::new(schema(), CsvOptions::default()).unwrap();
+        decoder.decode(&chunk).unwrap();
+        let result = decoder.decode(&chunk);
+        assert!(matches!(result, Err(DecodeError::LineTooLong(_))));
+    }
+}
@@ -1,86 +1,503 @@
+use arrow::{compute::concat_batches, datatypes::SchemaRef, record_batch::RecordBatch};
 use axum::{
-    extract::{Json, State},
-    http::StatusCode,
+    body::{Body, Bytes},
+    extract::{DefaultBodyLimit, Json, Query, State},
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
     routing::post,
     Router,
 };
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
 
+mod buffer;
+mod catalog;
+mod cluster;
+mod ingest;
+mod properties;
+mod rewrite;
+mod schema;
+mod sort;
+mod stats;
 mod store;
 mod util;
+mod wal;
 
-use store::{LocalStore, RemoteStore, Store};
+use buffer::{FlushPolicy, WriteBuffer};
+use catalog::{CatalogClient, CatalogTable, TableIdentifier};
+use cluster::Clustering;
+use fileio::{FileIO, S3Config};
+use ingest::{ChunkDecoder, CsvDecoder, CsvOptions, NdjsonDecoder};
+use properties::FileOptions;
+use rewrite::MAX_REWRITE_BYTES;
+use store::{DataFile, DataFilePaths, FileStore, Store};
+use util::{DecodeError, RowError};
+use wal::Wal;
+
+/// Content type of Arrow IPC stream bodies.
+const ARROW_STREAM: &str = "application/vnd.apache.arrow.stream";
+const NDJSON: &str = "application/x-ndjson";
+const CSV: &str = "text/csv";
+
+/// Arrow streams can be much larger than the default 2 MB body limit.
+/// NDJSON and CSV bodies are streamed and have no limit.
+const MAX_BODY_BYTES: usize = 256 * 1024 * 1024;
+
+/// Rows per data file of streamed writes.
+const FILE_ROWS: usize = 1_000_000;
 
 #[derive(Deserialize)]
 struct WriteRequest {
     data: Vec<Value>,
-    path: String,
+    table: String,
+}
+
+/// Table of Arrow stream, NDJSON and CSV writes, whose body is only data.
+#[derive(Deserialize)]
+struct WriteParams {
+    table: Option<String>,
+}
+
+enum WriteData {
+    Json(Vec<Value>),
+    ArrowStream(Bytes),
+    Ndjson(Body),
+    Csv(Body, CsvOptions),
 }
 
 #[derive(Serialize)]
 struct WriteResponse {
     status: String,
+    /// Values that don't match the table schema.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<RowError>,
+    /// Paths of the data files written.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    files: Vec<String>,
+}
+
+impl WriteResponse {
+    fn error(status: StatusCode, message: impl std::fmt::Display) -> (StatusCode, Json<Self>) {
+        let response = WriteResponse {
+            status: format!("error: {}", message),
+            errors: Vec::new(),
+            files: Vec::new(),
+        };
+        (status, Json(response))
+    }
 }
 
 #[derive(Clone)]
 struct AppState {
     store: Arc<Box<dyn Store>>,
+    buffer: WriteBuffer,
+    catalog: CatalogClient,
 }
 
+/// Writes JSON rows, or Arrow IPC stream, NDJSON and CSV bodies with the
+/// table passed as a query parameter. Data files are named by the service,
+/// under the table's location.
 async fn write_handler(
     State(state): State<AppState>,
-    Json(req): Json<WriteRequest>,
+    Query(params): Query<WriteParams>,
+    Query(csv_options): Query<CsvOptions>,
+    headers: HeaderMap,
+    body: Body,
 ) -> (StatusCode, Json<WriteResponse>) {
-    let data = util::read_record_batch_from_json(&req.data);
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(str::trim)
+        .unwrap_or("application/json");
 
-    if let Err(e) = data {
-        let response = Json(WriteResponse {
-            status: format!("error: {}", e),
-        });
-        return (StatusCode::BAD_REQUEST, response);
+    let data = match content_type {
+        ARROW_STREAM => match axum::body::to_bytes(body, MAX_BODY_BYTES).await {
+            Ok(bytes) => WriteData::ArrowStream(bytes),
+            Err(e) => return WriteResponse::error(StatusCode::BAD_REQUEST, e),
+        },
+        NDJSON => WriteData::Ndjson(body),
+        CSV => WriteData::Csv(body, csv_options),
+        "application/json" => {
+            let bytes = match axum::body::to_bytes(body, MAX_BODY_BYTES).await {
+                Ok(bytes) => bytes,
+                Err(e) => return WriteResponse::error(StatusCode::BAD_REQUEST, e),
+            };
+            match serde_json::from_slice::<WriteRequest>(&bytes) {
+                Ok(req) => return write(state, req.table, WriteData::Json(req.data)).await,
+                Err(e) => return WriteResponse::error(StatusCode::BAD_REQUEST, e),
+            }
+        }
+        other => {
+            let message = format!("unsupported content type: {}", other);
+            return WriteResponse::error(StatusCode::UNSUPPORTED_MEDIA_TYPE, message);
+        }
+    };
+
+    match params.table {
+        Some(table) => write(state, table, data).await,
+        None => {
+            let message = "table query parameter is required";
+            WriteResponse::error(StatusCode::BAD_REQUEST, message)
+        }
     }
+}
 
-    let store = state.store;
-    if let Err(e) = store.write(data.unwrap(), &req.path).await {
-        let response = Json(WriteResponse {
-            status: format!("error: {}", e),
-        });
-        return (StatusCode::INTERNAL_SERVER_ERROR, response);
+/// A table as writes and rewrites of its files need it.
+struct LoadedTable {
+    /// Fully qualified name.
+    name: String,
+    location: String,
+    schema: SchemaRef,
+    options: FileOptions,
+}
+
+async fn load_table(
+    catalog: &CatalogClient,
+    table: &str,
+) -> Result<LoadedTable, (StatusCode, Json<WriteResponse>)> {
+    // Buffers, WAL segments and catalog requests are keyed by the fully
+    // qualified name, so `traffic` and `default.traffic` are the same table
+    let name = match table.parse::<TableIdentifier>() {
+        Ok(identifier) => identifier.to_string(),
+        Err(e) => return Err(WriteResponse::error(StatusCode::BAD_REQUEST, e)),
+    };
+
+    let catalog_table = match catalog.get_table(&name).await {
+        Ok(Some(catalog_table)) => catalog_table,
+        Ok(None) => {
+            let message = format!("table not found: {}", name);
+            return Err(WriteResponse::error(StatusCode::NOT_FOUND, message));
+        }
+        Err(e) => return Err(WriteResponse::error(StatusCode::BAD_GATEWAY, e)),
+    };
+
+    let schema = schema::arrow_schema(&catalog_table.schema)
+        .map_err(|e| WriteResponse::error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    // Settings the catalog accepted before it checked them
+    let options = file_options(&catalog_table)
+        .map_err(|e| WriteResponse::error(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    Ok(LoadedTable {
+        name,
+        location: catalog_table.location,
+        schema: Arc::new(schema),
+        options,
+    })
+}
+
+async fn write(
+    state: AppState,
+    table: String,
+    data: WriteData,
+) -> (StatusCode, Json<WriteResponse>) {
+    let LoadedTable {
+        name: table,
+        location,
+        schema,
+        options,
+    } = match load_table(&state.catalog, &table).await {
+        Ok(table) => table,
+        Err(response) => return response,
+    };
+
+    let store = state.store.as_ref().as_ref();
+    let location = &location;
+    let files = match data {
+        WriteData::Ndjson(body) => {
+            let decoder = Box::new(NdjsonDecoder::new(schema.clone()));
+            write_stream(store, &table, location, &options, body, decoder, schema).await
+        }
+        WriteData::Csv(body, csv_options) => match CsvDecoder::new(schema.clone(), csv_options) {
+            Ok(decoder) => {
+                let decoder = Box::new(decoder);
+                write_stream(store, &table, location, &options, body, decoder, schema).await
+            }
+            Err(e) => Err(decode_error(e)),
+        },
+        WriteData::Json(rows) => {
+            let data = util::read_record_batch_from_json(&rows, schema);
+            write_buffered(&state.buffer, &table, location, &options, data).await
+        }
+        WriteData::ArrowStream(bytes) => {
+            let data = decode_arrow_stream(&bytes, schema);
+            write_buffered(&state.buffer, &table, location, &options, data).await
+        }
+    };
+
+    match files {
+        Ok(files) => (
+            StatusCode::OK,
+            Json(WriteResponse {
+                status: "ok".to_string(),
+                errors: Vec::new(),
+                files,
+            }),
+        ),
+        Err(response) => response,
+    }
+}
+
+/// Reads how the table's files are written from its properties and sort
+/// order, clustering taking the place of the sort order when set. Settings
+/// are checked before any data is accepted, buffered writes building them
+/// again when they are flushed.
+fn file_options(table: &CatalogTable) -> Result<FileOptions, String> {
+    let sort_keys = match &table.sort_order {
+        Some(order) => sort::sort_keys(order).map_err(|e| format!("invalid sort order: {}", e))?,
+        None => Vec::new(),
+    };
+    let clustering = match table.properties.get(cluster::CLUSTERING_PROPERTY) {
+        Some(value) => Some(Clustering::parse(value, &table.schema)?),
+        None => None,
+    };
+    let options = FileOptions {
+        properties: table.properties.clone(),
+        sort_keys,
+        clustering,
+    };
+    options.writer_properties().map_err(|e| e.to_string())?;
+
+    Ok(options)
+}
+
+#[derive(Deserialize)]
+struct RewriteParams {
+    table: String,
+}
+
+/// Rewrites the oldest data files of a table, up to [`MAX_REWRITE_BYTES`],
+/// so that its clustering or sort order holds across them, and replaces them
+/// with the files written in one snapshot. The files replaced stay in storage
+/// for older snapshots.
+async fn rewrite_handler(
+    State(state): State<AppState>,
+    Query(params): Query<RewriteParams>,
+) -> (StatusCode, Json<WriteResponse>) {
+    let table = match load_table(&state.catalog, &params.table).await {
+        Ok(table) => table,
+        Err(response) => return response,
+    };
+
+    let files = match state.catalog.list_data_files(&table.name).await {
+        Ok(files) => rewrite::plan(&files, MAX_REWRITE_BYTES),
+        Err(e) => return WriteResponse::error(StatusCode::BAD_GATEWAY, e),
+    };
+
+    let store = state.store.as_ref().as_ref();
+    let written = match rewrite::rewrite(
+        store,
+        &table.location,
+        &table.options,
+        &table.schema,
+        &files,
+        FILE_ROWS,
+    )
+    .await
+    {
+        Ok(written) => written,
+        Err(e) => return WriteResponse::error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    };
+
+    if let Err(e) = state
+        .catalog
+        .replace_data_files(&table.name, &written, &files)
+        .await
+    {
+        for file in &written {
+            let _ = store.delete(&file.path).await;
+        }
+        // Another rewrite replaced some of the files first
+        let status = match e.status() {
+            Some(StatusCode::CONFLICT) => StatusCode::CONFLICT,
+            _ => StatusCode::BAD_GATEWAY,
+        };
+        return WriteResponse::error(status, e);
+    }
+
+    let response = WriteResponse {
+        status: "ok".to_string(),
+        errors: Vec::new(),
+        files: written.into_iter().map(|file| file.path).collect(),
+    };
+    (StatusCode::OK, Json(response))
+}
+
+fn decode_error(error: DecodeError) -> (StatusCode, Json<WriteResponse>) {
+    match error {
+        DecodeError::InvalidRows { errors, total } => {
+            let response = WriteResponse {
+                status: format!("error: {} values don't match the table schema", total),
+                errors,
+                files: Vec::new(),
+            };
+            (StatusCode::BAD_REQUEST, Json(response))
+        }
+        e @ DecodeError::LineTooLong(_) => WriteResponse::error(StatusCode::PAYLOAD_TOO_LARGE, e),
+        e => WriteResponse::error(StatusCode::BAD_REQUEST, e),
     }
+}
 
-    if let Err(e) = store.notify_catalog(&req.path).await {
-        let response = Json(WriteResponse {
-            status: format!("error: {}", e),
+/// Decodes a body as it arrives, writing a data file every [`FILE_ROWS`]
+/// rows. Streamed writes are large enough not to be buffered. Files are only
+/// registered with the catalog once the whole body decoded, all in one
+/// snapshot, so a failed write commits none of its rows.
+async fn write_stream(
+    store: &dyn Store,
+    table: &str,
+    location: &str,
+    options: &FileOptions,
+    body: Body,
+    decoder: Box<dyn ChunkDecoder>,
+    schema: SchemaRef,
+) -> Result<Vec<String>, (StatusCode, Json<WriteResponse>)> {
+    let mut files = Vec::new();
+    let written = write_parts(store, location, options, body, decoder, schema, &mut files)
+        .await
+        .and_then(|()| match files.is_empty() {
+            true => Err(decode_error(DecodeError::Empty)),
+            false => Ok(()),
         });
-        return (StatusCode::INTERNAL_SERVER_ERROR, response);
+
+    // Files of a failed write are never registered, so nothing would read or
+    // remove them
+    if let Err(response) = written {
+        for file in &files {
+            let _ = store.delete(&file.path).await;
+        }
+        return Err(response);
     }
 
-    (
-        StatusCode::OK,
-        Json(WriteResponse {
-            status: "ok".to_string(),
-        }),
-    )
+    if let Err(e) = store.notify_catalog(table, &files).await {
+        return Err(WriteResponse::error(StatusCode::INTERNAL_SERVER_ERROR, e));
+    }
+
+    Ok(files.into_iter().map(|file| file.path).collect())
+}
+
+/// Writes the data files of a streamed body, adding each to `files` once
+/// written.
+async fn write_parts(
+    store: &dyn Store,
+    location: &str,
+    options: &FileOptions,
+    body: Body,
+    mut decoder: Box<dyn ChunkDecoder>,
+    schema: SchemaRef,
+    files: &mut Vec<DataFile>,
+) -> Result<(), (StatusCode, Json<WriteResponse>)> {
+    let paths = DataFilePaths::new(location);
+    let properties = options
+        .writer_properties()
+        .map_err(|e| WriteResponse::error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let mut stream = body.into_data_stream();
+    let mut pending = Vec::new();
+    let mut pending_rows = 0;
+
+    loop {
+        let (batches, finished) = match stream.next().await {
+            Some(Ok(chunk)) => (decoder.decode(&chunk), false),
+            Some(Err(e)) => return Err(WriteResponse::error(StatusCode::BAD_REQUEST, e)),
+            None => (decoder.finish(), true),
+        };
+        let batches = batches.map_err(decode_error)?;
+
+        pending_rows += batches.iter().map(RecordBatch::num_rows).sum::<usize>();
+        pending.extend(batches);
+
+        if pending_rows >= FILE_ROWS || (finished && pending_rows > 0) {
+            let data = concat_batches(&schema, &std::mem::take(&mut pending))
+                .and_then(|data| options.arrange(data))
+                .map_err(|e| decode_error(e.into()))?;
+            pending_rows = 0;
+
+            let path = paths.path(files.len());
+            match store.write(data, &path, properties.clone()).await {
+                Ok(file) => files.push(file),
+                Err(e) => return Err(WriteResponse::error(StatusCode::INTERNAL_SERVER_ERROR, e)),
+            }
+        }
+
+        if finished {
+            return Ok(());
+        }
+    }
+}
+
+/// Adds a write to the table's buffer, returning once it is flushed.
+async fn write_buffered(
+    buffer: &WriteBuffer,
+    table: &str,
+    location: &str,
+    options: &FileOptions,
+    data: Result<RecordBatch, DecodeError>,
+) -> Result<Vec<String>, (StatusCode, Json<WriteResponse>)> {
+    let data = data.map_err(decode_error)?;
+
+    buffer
+        .write(table, location, options, data)
+        .await
+        .map_err(|e| WriteResponse::error(StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// Decodes an Arrow stream into a single batch, so it lands in one data file.
+fn decode_arrow_stream(bytes: &[u8], schema: SchemaRef) -> Result<RecordBatch, DecodeError> {
+    let batches = util::read_record_batches_from_vec(bytes, schema.clone())?;
+    Ok(concat_batches(&schema, &batches)?)
 }
 
 #[tokio::main]
 async fn main() {
-    let store: Box<dyn Store> = if cfg!(debug_assertions) {
-        Box::new(LocalStore {
-            base_path: "./data".to_string(),
-        })
+    let catalog = CatalogClient::new("http://localhost:3002");
+
+    let config = S3Config::from_env();
+    let root = if cfg!(debug_assertions) {
+        "./data".to_string()
     } else {
-        Box::new(RemoteStore {})
+        format!("s3://{}/{}", config.bucket, config.prefix.trim_matches('/'))
     };
+    let store: Box<dyn Store> =
+        Box::new(FileStore::new(FileIO::new(config), &root, catalog.clone()).unwrap());
 
     let store = Arc::new(store);
 
-    let app_state = AppState { store };
+    // Rows accepted before a crash are committed before new writes
+    let wal_dir = std::env::var("WRITE_WAL_DIR").unwrap_or_else(|_| "./data/wal".to_string());
+    let wal = Wal::open(wal_dir).unwrap();
+    match wal.replay(store.as_ref().as_ref()).await {
+        Ok(replay) => {
+            for (path, e) in replay.quarantined {
+                eprintln!(
+                    "Quarantined write-ahead log segment {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+            for (path, e) in replay.kept {
+                eprintln!(
+                    "Kept write-ahead log segment {} for the next start: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        Err(e) => eprintln!("Failed to replay write-ahead log: {}", e),
+    }
+
+    let buffer = WriteBuffer::new(store.clone(), FlushPolicy::from_env()).with_wal(wal);
+
+    let app_state = AppState {
+        store,
+        buffer,
+        catalog,
+    };
 
     let app = Router::new()
         .route("/write", post(write_handler))
+        .route("/rewrite", post(rewrite_handler))
+        .layer(DefaultBodyLimit::max(MAX_BODY_BYTES))
         .with_state(app_state);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3001").await.unwrap();
@@ -0,0 +1,293 @@
+//! Parquet writer settings from table properties, named as Iceberg names
+//! them where it has a name for them.
+//!
+//! | Property | Value |
+//! |---|---|
+//! | `write.parquet.compression-codec` | `zstd`, `gzip`, `brotli`, `snappy`, `lz4`, `uncompressed` |
+//! | `write.parquet.compression-level` | Level of `zstd`, `gzip` and `brotli` |
+//! | `write.parquet.row-group-limit` | Rows per row group |
+//! | `write.parquet.page-size-bytes` | Data page size |
+//! | `write.parquet.page-row-limit` | Rows per data page |
+//! | `write.parquet.dict-size-bytes` | Dictionary page size |
+//! | `write.parquet.dictionary-enabled` | `true` or `false` |
+//! | `write.parquet.statistics-level` | `none`, `chunk` or `page` |
+//! | `write.parquet.bloom-filter-enabled.column.<col>` | `true` or `false` |
+//! | `write.parquet.bloom-filter-fpp.column.<col>` | False positive rate, between 0 and 1 |
+//! | `write.parquet.bloom-filter-ndv.column.<col>` | Expected distinct values |
+//!
+//! Dictionary encoding and statistics can also be set per column, with
+//! `.column.<col>` appended. Nested columns are written `a.b`. The order of
+//! rows is set by `write.clustering`, see [`crate::cluster`].
+
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use parquet::{
+    basic::{BrotliLevel, Compression, GzipLevel, ZstdLevel},
+    file::properties::WriterProperties,
+    schema::types::ColumnPath,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use arrow::{error::ArrowError, record_batch::RecordBatch};
+
+use crate::{
+    cluster::Clustering,
+    sort::{sort_batch, SortKey},
+};
+
+const PREFIX: &str = "write.parquet.";
+const COLUMN_INFIX: &str = ".column.";
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PropertyError {
+    #[error("Invalid value {value:?} of table property {key}: {reason}")]
+    InvalidValue {
+        key: String,
+        value: String,
+        reason: String,
+    },
+}
+
+/// How the data files of a table are written, as buffered writes and their
+/// log segments remember it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileOptions {
+    /// Table properties, of which the `write.parquet.` ones are used.
+    pub properties: HashMap<String, String>,
+    /// Columns rows are sorted by, none if the table isn't sorted.
+    pub sort_keys: Vec<SortKey>,
+    /// Curve rows are ordered along instead of the sort keys, if any.
+    #[serde(default)]
+    pub clustering: Option<Clustering>,
+}
+
+impl FileOptions {
+    pub fn writer_properties(&self) -> Result<WriterProperties, PropertyError> {
+        writer_properties(&self.properties)
+    }
+
+    /// Orders the rows of a data file, by clustering or the sort keys.
+    pub fn arrange(&self, data: RecordBatch) -> Result<RecordBatch, ArrowError> {
+        match &self.clustering {
+            Some(clustering) => clustering.cluster(data),
+            None => sort_batch(data, &self.sort_keys),
+        }
+    }
+}
+
+/// Builds the writer settings of a table, keeping Parquet defaults for those
+/// its properties don't set. Properties this writer has no setting for are
+/// ignored, as Iceberg writers do.
+pub fn writer_properties(
+    properties: &HashMap<String, String>,
+) -> Result<WriterProperties, PropertyError> {
+    let mut builder = WriterProperties::builder();
+
+    let codec = properties.get("write.parquet.compression-codec");
+    let level = properties.get("write.parquet.compression-level");
+    if codec.is_some() || level.is_some() {
+        builder = builder.set_compression(compression(codec, level)?);
+    }
+
+    // Keys are applied in order, so that a column setting overrides the table
+    // one whatever the map's order
+    let mut keys: Vec<&String> = properties.keys().collect();
+    keys.sort_by_key(|key| (key.contains(COLUMN_INFIX), key.as_str()));
+
+    // Setting a column's bloom filter fpp or ndv enables its filter in
+    // Parquet, but only tunes an enabled one in Iceberg. `bloom-filter-enabled`
+    // sorts before them, so it is known when they are applied.
+    let mut bloom_filter_columns = HashSet::new();
+
+    for key in keys {
+        let Some(name) = key.strip_prefix(PREFIX) else {
+            continue;
+        };
+        let value = &properties[key];
+        let (name, column) = match name.split_once(COLUMN_INFIX) {
+            Some((name, column)) => (name, Some(ColumnPath::new(split_column(column)))),
+            None => (name, None),
+        };
+
+        builder = match (name, column) {
+            ("compression-codec" | "compression-level", None) => builder,
+            ("row-group-limit", None) => builder.set_max_row_group_size(positive(key, value)?),
+            ("page-size-bytes", None) => builder.set_data_page_size_limit(positive(key, value)?),
+            ("page-row-limit", None) => {
+                builder.set_data_page_row_count_limit(positive(key, value)?)
+            }
+            ("dict-size-bytes", None) => {
+                builder.set_dictionary_page_size_limit(positive(key, value)?)
+            }
+            ("dictionary-enabled", None) => builder.set_dictionary_enabled(parse(key, value)?),
+            ("dictionary-enabled", Some(column)) => {
+                builder.set_column_dictionary_enabled(column, parse(key, value)?)
+            }
+            ("statistics-level", None) => builder.set_statistics_enabled(parse(key, value)?),
+            ("statistics-level", Some(column)) => {
+                builder.set_column_statistics_enabled(column, parse(key, value)?)
+            }
+            ("bloom-filter-enabled", Some(column)) => {
+                let enabled = parse(key, value)?;
+                if enabled {
+                    bloom_filter_columns.insert(column.clone());
+                }
+                builder.set_column_bloom_filter_enabled(column, enabled)
+            }
+            ("bloom-filter-fpp", Some(column)) => {
+                let fpp: f64 = parse(key, value)?;
+                if !(fpp > 0.0 && fpp < 1.0) {
+                    return Err(invalid(key, value, "must be between 0 and 1"));
+                }
+                match bloom_filter_columns.contains(&column) {
+                    true => builder.set_column_bloom_filter_fpp(column, fpp),
+                    false => builder,
+                }
+            }
+            ("bloom-filter-ndv", Some(column)) => {
+                let ndv = positive(key, value)? as u64;
+                match bloom_filter_columns.contains(&column) {
+                    true => builder.set_column_bloom_filter_ndv(column, ndv),
+                    false => builder,
+                }
+            }
+            // Such as Iceberg's `row-group-size-bytes`, which Parquet writers
+            // of other engines may be configured with
+            _ => builder,
+        };
+    }
+
+    Ok(builder.build())
+}
+
+/// Codec and level, given separately as in Iceberg. The codec defaults to
+/// `zstd`, and levels to those Parquet uses.
+fn compression(
+    codec: Option<&String>,
+    level: Option<&String>,
+) -> Result<Compression, PropertyError> {
+    let codec = codec.map_or("zstd".to_string(), |codec| codec.trim().to_lowercase());
+
+    let compression = match (codec.as_str(), level) {
+        ("zstd", None) => Ok(Compression::ZSTD(ZstdLevel::default())),
+        ("gzip", None) => Ok(Compression::GZIP(GzipLevel::default())),
+        ("brotli", None) => Ok(Compression::BROTLI(BrotliLevel::default())),
+        (_, None) => Compression::from_str(&codec),
+        (_, Some(level)) => Compression::from_str(&format!("{}({})", codec, level.trim())),
+    };
+
+    compression.map_err(|e| {
+        let value = match level {
+            Some(level) => format!("{} level {}", codec, level),
+            None => codec.clone(),
+        };
+        invalid("write.parquet.compression-codec", &value, &e.to_string())
+    })
+}
+
+fn split_column(column: &str) -> Vec<String> {
+    column.split('.').map(str::to_string).collect()
+}
+
+fn parse<T: FromStr>(key: &str, value: &str) -> Result<T, PropertyError>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .trim()
+        .to_lowercase()
+        .parse()
+        .map_err(|e: T::Err| invalid(key, value, &e.to_string()))
+}
+
+fn positive(key: &str, value: &str) -> Result<usize, PropertyError> {
+    match parse(key, value)? {
+        0 => Err(invalid(key, value, "must be positive")),
+        n => Ok(n),
+    }
+}
+
+fn invalid(key: &str, value: &str, reason: &str) -> PropertyError {
+    PropertyError::InvalidValue {
+        key: key.to_string(),
+        value: value.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::properties::EnabledStatistics;
+
+    fn properties(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_writer_properties() {
+        let writer = writer_properties(&properties(&[
+            ("write.parquet.compression-codec", "ZSTD"),
+            ("write.parquet.compression-level", "9"),
+            ("write.parquet.row-group-limit", "10000"),
+            ("write.parquet.dictionary-enabled", "false"),
+            ("write.parquet.dictionary-enabled.column.user.id", "true"),
+            ("write.parquet.statistics-level", "page"),
+            ("write.parquet.bloom-filter-enabled.column.id", "true"),
+            ("write.parquet.bloom-filter-fpp.column.id", "0.01"),
+            ("write.parquet.bloom-filter-ndv.column.user.id", "1000"),
+            ("write.parquet.bloom-filter-enabled.column.ts", "false"),
+            ("write.parquet.bloom-filter-fpp.column.ts", "0.01"),
+            ("write.parquet.row-group-size-bytes", "134217728"),
+            ("owner", "analytics"),
+        ]))
+        .unwrap();
+
+        let id = ColumnPath::from("id");
+        let user_id = ColumnPath::new(vec!["user".to_string(), "id".to_string()]);
+        assert_eq!(
+            writer.compression(&id),
+            Compression::ZSTD(ZstdLevel::try_new(9).unwrap())
+        );
+        assert_eq!(writer.max_row_group_size(), 10000);
+        assert!(!writer.dictionary_enabled(&id));
+        assert!(writer.dictionary_enabled(&user_id));
+        assert_eq!(writer.statistics_enabled(&id), EnabledStatistics::Page);
+        assert_eq!(writer.bloom_filter_properties(&id).unwrap().fpp, 0.01);
+        // Tuning a filter that isn't enabled doesn't enable it
+        assert!(writer.bloom_filter_properties(&user_id).is_none());
+        assert!(writer
+            .bloom_filter_properties(&ColumnPath::from("ts"))
+            .is_none());
+
+        // Parquet defaults are kept for properties not set
+        let writer = writer_properties(&HashMap::new()).unwrap();
+        assert_eq!(writer.compression(&id), Compression::UNCOMPRESSED);
+
+        for invalid in [
+            ("write.parquet.compression-codec", "zip"),
+            ("write.parquet.row-group-limit", "0"),
+            ("write.parquet.bloom-filter-fpp.column.id", "1.5"),
+            ("write.parquet.statistics-level", "all"),
+        ] {
+            let result = writer_properties(&properties(&[invalid]));
+            assert!(
+                matches!(result, Err(PropertyError::InvalidValue { ref key, .. }) if key == invalid.0),
+                "{:?}",
+                invalid
+            );
+        }
+        let result = writer_properties(&properties(&[
+            ("write.parquet.compression-codec", "snappy"),
+            ("write.parquet.compression-level", "3"),
+        ]));
+        assert!(result.is_err());
+    }
+}
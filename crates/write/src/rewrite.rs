@@ -0,0 +1,236 @@
+//! Rewrites data files of a table so that its clustering, or sort order,
+//! holds across files rather than only within each. Rows of the files
+//! rewritten are ordered together, then split into files by their position
+//! in that order, so each file covers one range of the curve and has tight
+//! bounds on every clustered column.
+
+use arrow::{
+    array::{new_null_array, ArrayRef},
+    compute::{cast, concat_batches},
+    datatypes::SchemaRef,
+    error::ArrowError,
+    record_batch::RecordBatch,
+};
+use parquet::arrow::PARQUET_FIELD_ID_META_KEY;
+use thiserror::Error;
+
+use crate::{
+    catalog::RegisteredFile,
+    properties::{FileOptions, PropertyError},
+    store::{DataFile, DataFilePaths, Store, StoreError},
+};
+
+/// Most data file bytes one rewrite reads. Larger tables are rewritten a
+/// group of files at a time, oldest first.
+pub const MAX_REWRITE_BYTES: i64 = 1024 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum RewriteError {
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    #[error("Failed to order rewritten rows: {0}")]
+    Arrow(#[from] ArrowError),
+    #[error(transparent)]
+    Properties(#[from] PropertyError),
+}
+
+/// Paths of the files the next rewrite reads: the oldest ones, up to
+/// `max_bytes` but at least one.
+pub fn plan(files: &[RegisteredFile], max_bytes: i64) -> Vec<String> {
+    let mut bytes = 0;
+    files
+        .iter()
+        .take_while(|file| {
+            bytes += file.file_size_in_bytes;
+            bytes == file.file_size_in_bytes || bytes <= max_bytes
+        })
+        .map(|file| file.path.clone())
+        .collect()
+}
+
+/// Reads `files`, orders their rows together and writes them to files of at
+/// most `file_rows` rows. The files written aren't registered, and are
+/// deleted again if the rewrite fails.
+pub async fn rewrite(
+    store: &dyn Store,
+    location: &str,
+    options: &FileOptions,
+    schema: &SchemaRef,
+    files: &[String],
+    file_rows: usize,
+) -> Result<Vec<DataFile>, RewriteError> {
+    let mut written = Vec::new();
+    let result = write_files(
+        store,
+        location,
+        options,
+        schema,
+        files,
+        file_rows,
+        &mut written,
+    )
+    .await;
+
+    if let Err(e) = result {
+        for file in &written {
+            let _ = store.delete(&file.path).await;
+        }
+        return Err(e);
+    }
+
+    Ok(written)
+}
+
+async fn write_files(
+    store: &dyn Store,
+    location: &str,
+    options: &FileOptions,
+    schema: &SchemaRef,
+    files: &[String],
+    file_rows: usize,
+    written: &mut Vec<DataFile>,
+) -> Result<(), RewriteError> {
+    let properties = options.writer_properties()?;
+
+    let mut batches = Vec::new();
+    for path in files {
+        for batch in store.read(path).await? {
+            batches.push(align(&batch, schema)?);
+        }
+    }
+    let data = options.arrange(concat_batches(schema, &batches)?)?;
+
+    let paths = DataFilePaths::new(location);
+    let mut offset = 0;
+    while offset < data.num_rows() {
+        let rows = file_rows.min(data.num_rows() - offset);
+        let path = paths.path(written.len());
+        written.push(
+            store
+                .write(data.slice(offset, rows), &path, properties.clone())
+                .await?,
+        );
+        offset += rows;
+    }
+
+    Ok(())
+}
+
+/// Reads a batch of a file as the table's current schema, matching columns
+/// by field id, or by name in files written without ids. Columns added since
+/// the file was written are null.
+fn align(batch: &RecordBatch, schema: &SchemaRef) -> Result<RecordBatch, ArrowError> {
+    let file_schema = batch.schema();
+
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let field_id = field.metadata().get(PARQUET_FIELD_ID_META_KEY);
+            let index = file_schema.fields().iter().position(|file_field| {
+                match (
+                    field_id,
+                    file_field.metadata().get(PARQUET_FIELD_ID_META_KEY),
+                ) {
+                    (Some(id), Some(file_id)) => id == file_id,
+                    _ => file_field.name() == field.name(),
+                }
+            });
+
+            match index {
+                Some(index) => cast(batch.column(index), field.data_type()),
+                None => Ok(new_null_array(field.data_type(), batch.num_rows())),
+            }
+        })
+        .collect::<Result<Vec<ArrayRef>, _>>()?;
+
+    RecordBatch::try_new(schema.clone(), columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use arrow::{
+        array::Int32Array,
+        datatypes::{DataType, Field, Schema},
+    };
+
+    use super::*;
+    use crate::{
+        cluster::{Clustering, Curve},
+        store::tests::MemoryStore,
+    };
+
+    fn int32(batch: &RecordBatch, column: usize) -> Vec<i32> {
+        let values = batch.column(column).as_any().downcast_ref::<Int32Array>();
+        values.unwrap().values().to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_clusters_across_files() {
+        let field = |name: &str, id: u32| {
+            Field::new(name, DataType::Int32, false).with_metadata(HashMap::from([(
+                PARQUET_FIELD_ID_META_KEY.to_string(),
+                id.to_string(),
+            )]))
+        };
+        let schema = Arc::new(Schema::new(vec![field("x", 1), field("y", 2)]));
+
+        // Two files spread over the whole 4 x 4 grid
+        let store = MemoryStore::default();
+        let mut files = Vec::new();
+        for half in 0..2 {
+            let points: Vec<(i32, i32)> = (0..16)
+                .map(|i| (i / 4, i % 4))
+                .filter(|(x, y)| (x + y) % 2 == half)
+                .collect();
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int32Array::from_iter_values(points.iter().map(|p| p.0))),
+                    Arc::new(Int32Array::from_iter_values(points.iter().map(|p| p.1))),
+                ],
+            )
+            .unwrap();
+            let path = format!("/warehouse/db/t/data/{}.parquet", half);
+            store.write(batch, &path, Default::default()).await.unwrap();
+            files.push(path);
+        }
+
+        let options = FileOptions {
+            clustering: Some(Clustering {
+                curve: Curve::ZOrder,
+                field_ids: vec![1, 2],
+            }),
+            ..FileOptions::default()
+        };
+        let written = rewrite(&store, "/warehouse/db/t", &options, &schema, &files, 4)
+            .await
+            .unwrap();
+
+        // Each file holds one quadrant of the grid
+        assert_eq!(written.len(), 4);
+        for file in &written {
+            let batch = store.read(&file.path).await.unwrap().remove(0);
+            for column in 0..2 {
+                let values = int32(&batch, column);
+                let (min, max) = (values.iter().min(), values.iter().max());
+                assert_eq!(max.unwrap() - min.unwrap(), 1, "{:?}", values);
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let file = |path: &str, bytes: i64| RegisteredFile {
+            path: path.to_string(),
+            file_size_in_bytes: bytes,
+        };
+
+        let files = [file("a", 60), file("b", 30), file("c", 20)];
+        assert_eq!(plan(&files, 100), ["a", "b"]);
+        assert_eq!(plan(&files, 10), ["a"]);
+        assert!(plan(&[], 100).is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+use std::{collections::HashMap, sync::Arc};
+
+use arrow::datatypes::{DataType, Field, Fields, Schema, TimeUnit};
+use parquet::arrow::PARQUET_FIELD_ID_META_KEY;
+use serde::Deserialize;
+
+/// A field of a table schema, as the catalog describes it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CatalogField {
+    pub id: Option<u32>,
+    #[serde(default)]
+    pub field: String,
+    #[serde(rename = "type")]
+    pub field_type: String,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub fields: Vec<CatalogField>,
+    pub element: Option<Box<CatalogField>>,
+    pub key: Option<Box<CatalogField>>,
+    pub value: Option<Box<CatalogField>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CatalogSchema {
+    pub fields: Vec<CatalogField>,
+}
+
+/// Converts a table schema to the Arrow schema its data files are written
+/// with. Field ids are kept so Parquet files carry them.
+pub fn arrow_schema(schema: &CatalogSchema) -> Result<Schema, String> {
+    let fields = schema
+        .fields
+        .iter()
+        .map(arrow_field)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Schema::new(fields))
+}
+
+fn arrow_field(field: &CatalogField) -> Result<Field, String> {
+    let nested = |child: &Option<Box<CatalogField>>, name: &str| {
+        child
+            .as_deref()
+            .ok_or_else(|| format!("Field {} has no {}", field.field, name))
+            .and_then(arrow_field)
+    };
+
+    let data_type = match field.field_type.as_str() {
+        "boolean" => DataType::Boolean,
+        "int" => DataType::Int32,
+        "long" => DataType::Int64,
+        "float" => DataType::Float32,
+        "double" => DataType::Float64,
+        "date" => DataType::Date32,
+        "time" => DataType::Time64(TimeUnit::Microsecond),
+        "timestamp" => DataType::Timestamp(TimeUnit::Microsecond, None),
+        "string" | "uuid" => DataType::Utf8,
+        "binary" => DataType::Binary,
+        "struct" => DataType::Struct(
+            field
+                .fields
+                .iter()
+                .map(arrow_field)
+                .collect::<Result<Fields, _>>()?,
+        ),
+        "list" => DataType::List(Arc::new(nested(&field.element, "element")?)),
+        "map" => {
+            let entries = Field::new(
+                "entries",
+                DataType::Struct(Fields::from(vec![
+                    nested(&field.key, "key")?,
+                    nested(&field.value, "value")?,
+                ])),
+                false,
+            );
+            DataType::Map(Arc::new(entries), false)
+        }
+        other => parse_parameterized(other)
+            .ok_or_else(|| format!("Unsupported type {} of field {}", other, field.field))?,
+    };
+
+    let arrow_field = Field::new(&field.field, data_type, !field.required);
+
+    Ok(match field.id {
+        Some(id) => arrow_field.with_metadata(HashMap::from([(
+            PARQUET_FIELD_ID_META_KEY.to_string(),
+            id.to_string(),
+        )])),
+        None => arrow_field,
+    })
+}
+
+/// Parses `fixed[length]` and `decimal(precision, scale)`.
+fn parse_parameterized(field_type: &str) -> Option<DataType> {
+    if let Some(length) = field_type
+        .strip_prefix("fixed[")
+        .and_then(|s| s.strip_suffix(']'))
+    {
+        return length.parse().ok().map(DataType::FixedSizeBinary);
+    }
+
+    let (precision, scale) = field_type
+        .strip_prefix("decimal(")
+        .and_then(|s| s.strip_suffix(')'))?
+        .split_once(',')?;
+
+    Some(DataType::Decimal128(
+        precision.trim().parse().ok()?,
+        scale.trim().parse().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_arrow_schema() {
+        let schema: CatalogSchema = serde_json::from_value(json!({"fields": [
+            {"id": 1, "field": "user_id", "type": "uuid", "required": true},
+            {"id": 2, "field": "price", "type": "decimal(10, 2)"},
+            {"id": 3, "field": "tags", "type": "list",
+             "element": {"id": 4, "field": "element", "type": "string"}}
+        ]}))
+        .unwrap();
+
+        let schema = arrow_schema(&schema).unwrap();
+
+        let user_id = schema.field(0);
+        assert_eq!(user_id.data_type(), &DataType::Utf8);
+        assert!(!user_id.is_nullable());
+        assert_eq!(user_id.metadata()[PARQUET_FIELD_ID_META_KEY], "1");
+
+        assert_eq!(schema.field(1).data_type(), &DataType::Decimal128(10, 2));
+
+        let DataType::List(element) = schema.field(2).data_type() else {
+            panic!("tags is not a list");
+        };
+        assert_eq!(element.data_type(), &DataType::Utf8);
+        assert_eq!(element.metadata()[PARQUET_FIELD_ID_META_KEY], "4");
+    }
+}
@@ -0,0 +1,154 @@
+//! Sorts the rows of data files by the table's sort order, so that column
+//! bounds of files overlap less and range filters skip more of them.
+
+use arrow::{
+    compute::{lexsort_to_indices, take_record_batch, SortColumn, SortOptions},
+    error::ArrowError,
+    record_batch::RecordBatch,
+};
+use iceberg::{
+    parser::{sort_order, ParserError},
+    sort::{NullOrder, SortDirection},
+};
+use parquet::arrow::PARQUET_FIELD_ID_META_KEY;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A column rows are sorted by.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SortKey {
+    pub field_id: u32,
+    pub descending: bool,
+    pub nulls_first: bool,
+}
+
+/// Columns to sort by for an Iceberg sort order. Sorting by a source column
+/// only sorts by fields whose transform keeps the order, such as `identity`
+/// or `day`, so the keys stop at the first field hashing or dropping values.
+/// They also stop after the first other field that keeps it: sorting by the
+/// source of `day(ts)` orders rows within a day by `ts`, not by the fields
+/// that follow.
+pub fn sort_keys(order: &Value) -> Result<Vec<SortKey>, ParserError> {
+    let order = sort_order::from_json_value(order)?;
+
+    let mut keys = Vec::new();
+    for field in order.fields() {
+        if !field.preserves_order() {
+            break;
+        }
+
+        keys.push(SortKey {
+            field_id: field.source_id,
+            descending: field.direction == SortDirection::Descending,
+            nulls_first: field.null_order == NullOrder::NullsFirst,
+        });
+
+        if field.transform != "identity" {
+            break;
+        }
+    }
+
+    Ok(keys)
+}
+
+/// Sorts a batch by the top-level columns of `keys`. Keys after one that
+/// isn't a top-level column of the batch are ignored, like those following
+/// a transform that doesn't keep the order.
+pub fn sort_batch(batch: RecordBatch, keys: &[SortKey]) -> Result<RecordBatch, ArrowError> {
+    let schema = batch.schema();
+    let mut columns = Vec::with_capacity(keys.len());
+
+    for key in keys {
+        let field_id = key.field_id.to_string();
+        let Some(index) = schema
+            .fields()
+            .iter()
+            .position(|field| field.metadata().get(PARQUET_FIELD_ID_META_KEY) == Some(&field_id))
+        else {
+            break;
+        };
+
+        columns.push(SortColumn {
+            values: batch.column(index).clone(),
+            options: Some(SortOptions {
+                descending: key.descending,
+                nulls_first: key.nulls_first,
+            }),
+        });
+    }
+
+    if columns.is_empty() || batch.num_rows() < 2 {
+        return Ok(batch);
+    }
+
+    let indices = lexsort_to_indices(&columns, None)?;
+    take_record_batch(&batch, &indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use arrow::{
+        array::{ArrayRef, Int32Array, StringArray},
+        datatypes::{DataType, Field, Schema},
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_sort_batch() {
+        let field = |name: &str, data_type: DataType, id: u32| {
+            Field::new(name, data_type, true).with_metadata(HashMap::from([(
+                PARQUET_FIELD_ID_META_KEY.to_string(),
+                id.to_string(),
+            )]))
+        };
+        let schema = Arc::new(Schema::new(vec![
+            field("device", DataType::Utf8, 1),
+            field("clicks", DataType::Int32, 2),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(vec!["tv", "mobile", "tv", "mobile"])) as ArrayRef,
+                Arc::new(Int32Array::from(vec![Some(1), Some(7), None, Some(3)])) as ArrayRef,
+            ],
+        )
+        .unwrap();
+
+        let keys = sort_keys(&json!({
+            "order-id": 1,
+            "fields": [
+                {"source-id": 1, "transform": "identity", "direction": "asc", "null-order": "nulls-first"},
+                {"source-id": 2, "transform": "identity", "direction": "desc", "null-order": "nulls-first"},
+                {"source-id": 2, "transform": "bucket[4]", "direction": "asc", "null-order": "nulls-first"}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(keys.len(), 2);
+
+        let sorted = sort_batch(batch, &keys).unwrap();
+        let clicks = sorted
+            .column(1)
+            .as_any()
+            .downcast_ref::<Int32Array>()
+            .unwrap();
+        assert_eq!(
+            clicks.iter().collect::<Vec<_>>(),
+            vec![Some(7), Some(3), None, Some(1)]
+        );
+
+        // Nothing after a field ordered by a transform of its source
+        let keys = sort_keys(&json!({
+            "order-id": 1,
+            "fields": [
+                {"source-id": 1, "transform": "day", "direction": "asc", "null-order": "nulls-first"},
+                {"source-id": 2, "transform": "identity", "direction": "asc", "null-order": "nulls-first"}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(keys.len(), 1);
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+use arrow::{
+    array::{Array, AsArray},
+    datatypes::{DataType, Field, Float32Type, Float64Type},
+    record_batch::RecordBatch,
+};
+use parquet::{
+    arrow::PARQUET_FIELD_ID_META_KEY,
+    file::{metadata::ParquetMetaData, statistics::Statistics},
+};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Column bounds, keyed by field id.
+pub type Bounds = HashMap<i32, Value>;
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+enum Bound {
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    Bytes(Vec<u8>),
+}
+
+impl Bound {
+    fn from_statistics(stats: &Statistics) -> Option<(Bound, Bound)> {
+        if !stats.has_min_max_set() {
+            return None;
+        }
+
+        let bounds = match stats {
+            Statistics::Boolean(s) => (Bound::Boolean(*s.min()), Bound::Boolean(*s.max())),
+            Statistics::Int32(s) => (
+                Bound::Integer(*s.min() as i64),
+                Bound::Integer(*s.max() as i64),
+            ),
+            Statistics::Int64(s) => (Bound::Integer(*s.min()), Bound::Integer(*s.max())),
+            Statistics::Float(s) => (Bound::Float(*s.min() as f64), Bound::Float(*s.max() as f64)),
+            Statistics::Double(s) => (Bound::Float(*s.min()), Bound::Float(*s.max())),
+            Statistics::ByteArray(s) => (
+                Bound::Bytes(s.min().data().to_vec()),
+                Bound::Bytes(s.max().data().to_vec()),
+            ),
+            Statistics::FixedLenByteArray(s) => (
+                Bound::Bytes(s.min().data().to_vec()),
+                Bound::Bytes(s.max().data().to_vec()),
+            ),
+            // INT96 is only used for legacy timestamps, which we don't write
+            Statistics::Int96(_) => return None,
+        };
+
+        Some(bounds)
+    }
+
+    fn into_json(self) -> Option<Value> {
+        match self {
+            Bound::Boolean(b) => Some(Value::from(b)),
+            Bound::Integer(i) => Some(Value::from(i)),
+            // NaN and infinities have no JSON representation
+            Bound::Float(f) => serde_json::Number::from_f64(f).map(Value::Number),
+            Bound::Bytes(b) => String::from_utf8(b).ok().map(Value::String),
+        }
+    }
+}
+
+/// Column statistics of a data file, keyed by field id as in Iceberg's
+/// `DataFile`. Columns without a field id are left out.
+#[derive(Debug, Default, Serialize)]
+pub struct ColumnStats {
+    /// Values per column, nulls included.
+    pub value_counts: HashMap<i32, i64>,
+    pub null_value_counts: HashMap<i32, i64>,
+    /// NaN values per floating point column.
+    pub nan_value_counts: HashMap<i32, i64>,
+    pub lower_bounds: Bounds,
+    pub upper_bounds: Bounds,
+}
+
+/// Collects the statistics of a Parquet file from the row group statistics
+/// in its footer. Footers have no NaN counts, which are counted in `data`,
+/// the rows the file was written from.
+///
+/// Columns without statistics in every row group get no null count or
+/// bounds, since those are unknown.
+pub fn column_stats(metadata: &ParquetMetaData, data: &RecordBatch) -> ColumnStats {
+    let mut stats = ColumnStats::default();
+    let mut null_counts: HashMap<i32, Option<i64>> = HashMap::new();
+    let mut bounds: HashMap<i32, Option<(Bound, Bound)>> = HashMap::new();
+
+    for row_group in metadata.row_groups() {
+        for column in row_group.columns() {
+            let info = column.column_descr().self_type().get_basic_info();
+            if !info.has_id() {
+                continue;
+            }
+            let id = info.id();
+
+            *stats.value_counts.entry(id).or_default() += column.num_values();
+
+            let null_count = column.statistics().map(|s| s.null_count() as i64);
+            let count = null_counts.entry(id).or_insert(Some(0));
+            *count = count.zip(null_count).map(|(a, b)| a + b);
+
+            let row_group_bounds = column.statistics().and_then(Bound::from_statistics);
+            let merged = match (bounds.remove(&id), row_group_bounds) {
+                (None, current) => current,
+                (Some(Some((min, max))), Some((rg_min, rg_max))) => Some((
+                    if rg_min < min { rg_min } else { min },
+                    if rg_max > max { rg_max } else { max },
+                )),
+                _ => None,
+            };
+            bounds.insert(id, merged);
+        }
+    }
+
+    stats.null_value_counts = null_counts
+        .into_iter()
+        .filter_map(|(id, count)| Some((id, count?)))
+        .collect();
+
+    for (id, column_bounds) in bounds {
+        if let Some((min, max)) = column_bounds {
+            if let (Some(min), Some(max)) = (min.into_json(), max.into_json()) {
+                stats.lower_bounds.insert(id, min);
+                stats.upper_bounds.insert(id, max);
+            }
+        }
+    }
+
+    for (field, column) in data.schema().fields().iter().zip(data.columns()) {
+        count_nans(field, column, &mut stats.nan_value_counts);
+    }
+
+    stats
+}
+
+/// Counts NaN values of the floating point columns of an array and its
+/// children.
+fn count_nans(field: &Field, array: &dyn Array, counts: &mut HashMap<i32, i64>) {
+    let nans = match array.data_type() {
+        DataType::Float32 => array
+            .as_primitive::<Float32Type>()
+            .iter()
+            .filter(|v| v.is_some_and(f32::is_nan))
+            .count(),
+        DataType::Float64 => array
+            .as_primitive::<Float64Type>()
+            .iter()
+            .filter(|v| v.is_some_and(f64::is_nan))
+            .count(),
+        DataType::Struct(fields) => {
+            for (field, column) in fields.iter().zip(array.as_struct().columns()) {
+                count_nans(field, column, counts);
+            }
+            return;
+        }
+        DataType::List(element) => {
+            return count_nans(element, array.as_list::<i32>().values(), counts);
+        }
+        DataType::Map(entries, _) => {
+            return count_nans(entries, array.as_map().entries(), counts);
+        }
+        _ => return,
+    };
+
+    if let Some(id) = field_id(field) {
+        *counts.entry(id).or_default() += nans as i64;
+    }
+}
+
+fn field_id(field: &Field) -> Option<i32> {
+    field
+        .metadata()
+        .get(PARQUET_FIELD_ID_META_KEY)?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::{ArrayRef, Float64Array, Int64Array, StringArray};
+    use arrow::datatypes::Schema;
+    use bytes::Bytes;
+    use parquet::arrow::ArrowWriter;
+    use parquet::file::footer::parse_metadata;
+    use parquet::file::properties::WriterProperties;
+    use serde_json::json;
+
+    use super::*;
+
+    fn field(name: &str, data_type: DataType, id: i32) -> Field {
+        Field::new(name, data_type, true).with_metadata(HashMap::from([(
+            PARQUET_FIELD_ID_META_KEY.to_string(),
+            id.to_string(),
+        )]))
+    }
+
+    #[test]
+    fn test_column_stats_across_row_groups() {
+        let schema = Arc::new(Schema::new(vec![
+            field("clicks", DataType::Int64, 1),
+            field("device", DataType::Utf8, 2),
+            field("score", DataType::Float64, 3),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![Some(10), None, Some(-3), Some(55)])) as ArrayRef,
+                Arc::new(StringArray::from(vec!["mobile", "tv", "desktop", "tablet"])) as ArrayRef,
+                Arc::new(Float64Array::from(vec![
+                    Some(0.5),
+                    Some(f64::NAN),
+                    None,
+                    Some(2.0),
+                ])) as ArrayRef,
+            ],
+        )
+        .unwrap();
+
+        // Two rows per row group, so the statistics have to be merged
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(2)
+            .build();
+        let mut buffer = Vec::new();
+        let mut writer = ArrowWriter::try_new(&mut buffer, schema, Some(props)).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let metadata = parse_metadata(&Bytes::from(buffer)).unwrap();
+        assert_eq!(metadata.num_row_groups(), 2);
+
+        let stats = column_stats(&metadata, &batch);
+
+        assert_eq!(stats.value_counts, HashMap::from([(1, 4), (2, 4), (3, 4)]));
+        assert_eq!(
+            stats.null_value_counts,
+            HashMap::from([(1, 1), (2, 0), (3, 1)])
+        );
+        assert_eq!(stats.nan_value_counts, HashMap::from([(3, 1)]));
+        assert_eq!(stats.lower_bounds[&1], json!(-3));
+        assert_eq!(stats.upper_bounds[&1], json!(55));
+        assert_eq!(stats.lower_bounds[&2], json!("desktop"));
+        assert_eq!(stats.upper_bounds[&2], json!("tv"));
+        // NaN is neither bound
+        assert_eq!(stats.lower_bounds[&3], json!(0.5));
+        assert_eq!(stats.upper_bounds[&3], json!(2.0));
+    }
+}
@@ -1,75 +1,373 @@
-use std::{fs::{self, File}, path::Path};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use async_trait::async_trait;
 use arrow::record_batch::RecordBatch;
-use parquet::file::properties::WriterProperties;
+use async_trait::async_trait;
+use fileio::{FileIO, FileIOError};
+use futures::TryStreamExt;
+use object_store::buffered::BufWriter;
+use parquet::{
+    arrow::{
+        async_reader::{AsyncFileReader, ParquetObjectReader},
+        AsyncArrowWriter, ParquetRecordBatchStreamBuilder,
+    },
+    file::properties::WriterProperties,
+};
+use serde::Serialize;
 use thiserror::Error;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    catalog::CatalogClient,
+    stats::{self, ColumnStats},
+};
 
 #[derive(Error, Debug)]
 pub enum StoreError {
-    #[error("Failed to create directory: {0}")]
-    DirectoryCreationError(#[from] std::io::Error),
-    #[error("Failed to create file: {0}")]
-    FileCreationError(std::io::Error),
     #[error("Failed to create Parquet writer: {0}")]
     ParquetWriterCreationError(#[from] parquet::errors::ParquetError),
+    #[error("Failed to notify catalog: {0}")]
+    CatalogNotification(#[from] reqwest::Error),
+    #[error("Invalid data file path: {0}")]
+    InvalidPath(String),
+    #[error("Object store request failed: {0}")]
+    ObjectStore(#[from] object_store::Error),
+    #[error("Failed to open storage: {0}")]
+    FileIO(#[from] FileIOError),
+    #[error("Failed to read data file: {0}")]
+    FileReadError(String),
+}
+
+/// A data file produced by a store, as registered with the catalog.
+#[derive(Debug, Serialize)]
+pub struct DataFile {
+    pub path: String,
+    pub record_count: i64,
+    pub file_size_in_bytes: i64,
+    #[serde(flatten)]
+    pub stats: ColumnStats,
+    pub written_at_ms: i64,
+}
+
+/// Names the data files of a write, so that writes never overwrite each
+/// other's files.
+pub struct DataFilePaths {
+    /// `{location}/data/{write id}`
+    prefix: String,
+}
+
+impl DataFilePaths {
+    pub fn new(location: &str) -> Self {
+        Self {
+            prefix: format!("{}/data/{}", location.trim_end_matches('/'), Uuid::new_v4()),
+        }
+    }
+
+    /// Path of the `index`th data file of the write.
+    pub fn path(&self, index: usize) -> String {
+        format!("{}-{:05}.parquet", self.prefix, index)
+    }
 }
 
 // Trait for storage operations
 #[async_trait]
 pub trait Store: Send + Sync {
-    async fn write(&self, data: RecordBatch, path: &str) -> Result<(), StoreError>;
-    async fn notify_catalog(&self, path: &str) -> Result<(), StoreError>;
+    async fn write(
+        &self,
+        data: RecordBatch,
+        path: &str,
+        properties: WriterProperties,
+    ) -> Result<DataFile, StoreError>;
+    /// Registers files with the catalog as one snapshot, all or none.
+    async fn notify_catalog(&self, table: &str, files: &[DataFile]) -> Result<(), StoreError>;
+    /// Deletes a file this store wrote, by the path of its [`DataFile`].
+    async fn delete(&self, path: &str) -> Result<(), StoreError>;
+    /// Reads a file this store wrote, by the path of its [`DataFile`].
+    async fn read(&self, path: &str) -> Result<Vec<RecordBatch>, StoreError>;
 }
 
-// Remote store implementation
-pub struct RemoteStore {
-    // Add fields for remote storage configuration
+/// Files larger than this are uploaded in parts of this size.
+const MULTIPART_PART_BYTES: usize = 10 * 1024 * 1024;
+
+/// Writes data files through [`FileIO`]: to local files, S3-compatible
+/// buckets or memory, by the scheme of the root.
+pub struct FileStore {
+    io: FileIO,
+    /// Location plain paths are put under, e.g. `./data` or
+    /// `s3://bucket/prefix`.
+    root: Url,
+    catalog: CatalogClient,
+}
+
+impl FileStore {
+    pub fn new(io: FileIO, root: &str, catalog: CatalogClient) -> Result<Self, StoreError> {
+        Ok(Self {
+            io,
+            root: FileIO::parse_location(root)?,
+            catalog,
+        })
+    }
+
+    /// Location of a data file. URIs are used as they are if they're of the
+    /// root's bucket, other paths are put under the root. `..` is refused so
+    /// that a path can't leave the directory it names.
+    fn location(&self, path: &str) -> Result<String, StoreError> {
+        let invalid = || StoreError::InvalidPath(path.to_string());
+
+        if path.split('/').any(|segment| segment == "..") {
+            return Err(invalid());
+        }
+
+        if !path.contains("://") {
+            return Ok(format!(
+                "{}/{}",
+                self.root.as_str().trim_end_matches('/'),
+                path.trim_start_matches('/')
+            ));
+        }
+
+        let url = FileIO::parse_location(path)?;
+        if url.scheme() != self.root.scheme() || url.authority() != self.root.authority() {
+            return Err(invalid());
+        }
+        Ok(url.to_string())
+    }
 }
 
 #[async_trait]
-impl Store for RemoteStore {
-    async fn write(&self, data: RecordBatch, path: &str) -> Result<(), StoreError> {
-        // Implement remote write logic
-        todo!()
+impl Store for FileStore {
+    async fn write(
+        &self,
+        data: RecordBatch,
+        path: &str,
+        properties: WriterProperties,
+    ) -> Result<DataFile, StoreError> {
+        let location = self.location(path)?;
+        let (store, key) = self.io.object_store(&location)?;
+
+        // Parts are uploaded as the writer fills them, large files never
+        // being held in memory whole
+        let upload = BufWriter::with_capacity(store.clone(), key.clone(), MULTIPART_PART_BYTES);
+        let mut writer = AsyncArrowWriter::try_new(upload, data.schema(), Some(properties))?;
+        writer.write(&data).await?;
+        writer.close().await?;
+
+        // Read back the footer for the row group statistics
+        let meta = store.head(&key).await?;
+        let file_size_in_bytes = meta.size as i64;
+        let metadata = ParquetObjectReader::new(store, meta).get_metadata().await?;
+        let stats = stats::column_stats(&metadata, &data);
+
+        Ok(DataFile {
+            path: location,
+            record_count: metadata.file_metadata().num_rows(),
+            file_size_in_bytes,
+            stats,
+            written_at_ms: now_millis(),
+        })
     }
 
-    async fn notify_catalog(&self, path: &str) -> Result<(), StoreError> {
-        // Implement catalog notification logic
-        todo!()
+    async fn notify_catalog(&self, table: &str, files: &[DataFile]) -> Result<(), StoreError> {
+        self.catalog.register_data_files(table, files).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), StoreError> {
+        self.io.delete(&self.location(path)?).await?;
+        Ok(())
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<RecordBatch>, StoreError> {
+        let read_error =
+            |e: parquet::errors::ParquetError| StoreError::FileReadError(e.to_string());
+
+        let (store, key) = self.io.object_store(&self.location(path)?)?;
+        let meta = store.head(&key).await?;
+        let reader = ParquetObjectReader::new(store, meta);
+        let stream = ParquetRecordBatchStreamBuilder::new(reader)
+            .await
+            .and_then(|builder| builder.build())
+            .map_err(read_error)?;
+
+        stream.try_collect().await.map_err(read_error)
     }
 }
 
-// Local store wrapper for development
-pub struct LocalStore {
-    pub base_path: String,
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
 }
 
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
 
-#[async_trait]
-impl Store for LocalStore {
-    async fn write(&self, data: RecordBatch, path: &str) -> Result<(), StoreError> {
-        let full_path = Path::new(&self.base_path).join(path);
-    
-        // Ensure the directory exists
-        if let Some(parent) = full_path.parent() {
-            fs::create_dir_all(parent).map_err(StoreError::DirectoryCreationError)?;
+    use super::*;
+
+    /// Path and row count of each file written to a [`MemoryStore`].
+    pub type WrittenFiles = Arc<Mutex<Vec<(String, usize)>>>;
+
+    /// Keeps which files were written, and their data.
+    #[derive(Default)]
+    pub struct MemoryStore {
+        pub files: WrittenFiles,
+        pub data: Arc<Mutex<HashMap<String, RecordBatch>>>,
+    }
+
+    #[async_trait]
+    impl Store for MemoryStore {
+        async fn write(
+            &self,
+            data: RecordBatch,
+            path: &str,
+            _properties: WriterProperties,
+        ) -> Result<DataFile, StoreError> {
+            self.files
+                .lock()
+                .unwrap()
+                .push((path.to_string(), data.num_rows()));
+            let record_count = data.num_rows() as i64;
+            self.data.lock().unwrap().insert(path.to_string(), data);
+
+            Ok(DataFile {
+                path: path.to_string(),
+                record_count,
+                file_size_in_bytes: 0,
+                stats: Default::default(),
+                written_at_ms: 0,
+            })
+        }
+
+        async fn notify_catalog(
+            &self,
+            _table: &str,
+            _files: &[DataFile],
+        ) -> Result<(), StoreError> {
+            Ok(())
+        }
+
+        async fn delete(&self, path: &str) -> Result<(), StoreError> {
+            self.files.lock().unwrap().retain(|(file, _)| file != path);
+            self.data.lock().unwrap().remove(path);
+            Ok(())
+        }
+
+        async fn read(&self, path: &str) -> Result<Vec<RecordBatch>, StoreError> {
+            match self.data.lock().unwrap().get(path) {
+                Some(data) => Ok(vec![data.clone()]),
+                None => Err(StoreError::FileReadError(path.to_string())),
+            }
         }
-    
-        let file = File::create(&full_path).map_err(StoreError::FileCreationError)?;
-
-        let mut writer = parquet::arrow::ArrowWriter::try_new(
-            file,
-            data.schema(),
-            Some(WriterProperties::builder().build()),
-        )?;
-        writer.write(&data)?;
-        writer.close()?;
-        Ok(())
     }
 
-    async fn notify_catalog(&self, path: &str) -> Result<(), StoreError> {
-        println!("Notifying catalog about: {}", path);
-        Ok(())
+    #[test]
+    fn test_location() {
+        let catalog = CatalogClient::new("http://localhost:3002");
+        let store = FileStore::new(FileIO::default(), "/data", catalog.clone()).unwrap();
+
+        assert_eq!(
+            store.location("default/traffic/data/a.parquet").unwrap(),
+            "file:///data/default/traffic/data/a.parquet"
+        );
+        assert_eq!(
+            store
+                .location("file:///warehouse/default/traffic/data/a.parquet")
+                .unwrap(),
+            "file:///warehouse/default/traffic/data/a.parquet"
+        );
+        assert!(matches!(
+            store.location("default/traffic/../../../etc/a.parquet"),
+            Err(StoreError::InvalidPath(_))
+        ));
+
+        let store = FileStore::new(FileIO::default(), "s3://lake/tables/", catalog).unwrap();
+        assert_eq!(
+            store.location("/warehouse/db/t/data/a.parquet").unwrap(),
+            "s3://lake/tables/warehouse/db/t/data/a.parquet"
+        );
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_file_store_write() {
+        use arrow::{
+            array::Int32Array,
+            datatypes::{DataType, Field, Schema},
+        };
+        use parquet::basic::Compression;
+
+        let io = FileIO::default();
+        let store = FileStore::new(
+            io.clone(),
+            "memory://lake/tables/",
+            CatalogClient::new("http://localhost:3002"),
+        )
+        .unwrap();
+
+        let field = Field::new("a", DataType::Int32, false).with_metadata(HashMap::from([(
+            parquet::arrow::PARQUET_FIELD_ID_META_KEY.to_string(),
+            "1".to_string(),
+        )]));
+        let schema = Arc::new(Schema::new(vec![field]));
+        let data =
+            RecordBatch::try_new(schema, vec![Arc::new(Int32Array::from(vec![3, 1, 2]))]).unwrap();
+
+        let properties = HashMap::from([(
+            "write.parquet.compression-codec".to_string(),
+            "zstd".to_string(),
+        )]);
+        let properties = crate::properties::writer_properties(&properties).unwrap();
+
+        let file = store
+            .write(data.clone(), "/warehouse/db/t/data/a.parquet", properties)
+            .await
+            .unwrap();
+        assert_eq!(
+            file.path,
+            "memory://lake/tables/warehouse/db/t/data/a.parquet"
+        );
+        assert_eq!(file.record_count, 3);
+        assert_eq!(file.stats.lower_bounds[&1], 1);
+        let read = store.read(&file.path).await.unwrap();
+        assert_eq!(read.iter().map(RecordBatch::num_rows).sum::<usize>(), 3);
+
+        let (bucket, key) = io.object_store(&file.path).unwrap();
+        let meta = bucket.head(&key).await.unwrap();
+        assert_eq!(meta.size as i64, file.file_size_in_bytes);
+        let metadata = ParquetObjectReader::new(bucket, meta)
+            .get_metadata()
+            .await
+            .unwrap();
+        assert!(matches!(
+            metadata.row_group(0).column(0).compression(),
+            Compression::ZSTD(_)
+        ));
+
+        // URIs of the bucket are kept, other buckets and `..` are refused
+        let file = store
+            .write(
+                data.clone(),
+                "memory://lake/other/b.parquet",
+                Default::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(file.path, "memory://lake/other/b.parquet");
+        assert!(matches!(
+            store
+                .write(data.clone(), "memory://other/b.parquet", Default::default())
+                .await,
+            Err(StoreError::InvalidPath(_))
+        ));
+        assert!(matches!(
+            store
+                .write(data, "/warehouse/../../b.parquet", Default::default())
+                .await,
+            Err(StoreError::InvalidPath(_))
+        ));
+    }
+}
@@ -1,103 +1,709 @@
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
-use arrow::array::{ArrayRef, StringArray};
-use arrow::datatypes::{DataType, Field, Schema};
+use arrow::array::{
+    new_null_array, ArrayRef, BinaryArray, BooleanArray, Date32Array, Decimal128Array,
+    FixedSizeBinaryArray, Float32Array, Float64Array, Int32Array, Int64Array, ListArray, MapArray,
+    StringArray, StructArray, Time64MicrosecondArray, TimestampMicrosecondArray,
+};
+use arrow::buffer::{NullBuffer, OffsetBuffer};
+use arrow::compute::kernels::cast_utils::{parse_decimal, Parser};
+use arrow::compute::{cast_with_options, CastOptions};
+use arrow::datatypes::{
+    DataType, Date32Type, Decimal128Type, FieldRef, Fields, SchemaRef, Time64MicrosecondType,
+    TimeUnit, TimestampMicrosecondType,
+};
+use arrow::error::ArrowError;
 use arrow::ipc::reader::StreamReader;
-use arrow::record_batch::RecordBatch;
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use serde::Serialize;
 use serde_json::Value;
+use thiserror::Error;
 
-type ArrowResult<T> = arrow::error::Result<T>;
+/// Rows with errors reported back to the client, the rest are only counted.
+const MAX_ROW_ERRORS: usize = 100;
 
-pub fn read_record_batch_from_vec(bytes: Vec<u8>) -> ArrowResult<RecordBatch> {
-    let mut stream_reader = StreamReader::try_new(bytes.as_slice(), None)?;
-    let record_batch = stream_reader.next().unwrap()?;
+/// Reads every batch of an Arrow IPC stream and conforms it to the given
+/// schema.
+pub fn read_record_batches_from_vec(
+    bytes: &[u8],
+    schema: SchemaRef,
+) -> Result<Vec<RecordBatch>, DecodeError> {
+    let stream_reader = StreamReader::try_new(bytes, None)?;
 
-    Ok(record_batch)
+    let batches = stream_reader
+        .map(|batch| conform_batch(batch?, schema.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if batches.iter().all(|batch| batch.num_rows() == 0) {
+        return Err(DecodeError::Empty);
+    }
+
+    Ok(batches)
+}
+
+/// Matches the columns of a batch to a schema by name. Columns of other types
+/// are cast, failing rather than truncating values, and missing nullable
+/// columns are filled with nulls.
+pub fn conform_batch(batch: RecordBatch, schema: SchemaRef) -> Result<RecordBatch, DecodeError> {
+    let options = CastOptions {
+        safe: false,
+        ..Default::default()
+    };
+
+    for field in batch.schema().fields() {
+        if schema.field_with_name(field.name()).is_err() {
+            return Err(DecodeError::SchemaMismatch(format!(
+                "column {} is not in the table schema",
+                field.name()
+            )));
+        }
+    }
+
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| match batch.column_by_name(field.name()) {
+            Some(column) if column.data_type() == field.data_type() => Ok(column.clone()),
+            Some(column) => cast_with_options(column, field.data_type(), &options).map_err(|e| {
+                DecodeError::SchemaMismatch(format!(
+                    "column {} of type {} doesn't fit {}: {}",
+                    field.name(),
+                    column.data_type(),
+                    field.data_type(),
+                    e
+                ))
+            }),
+            None if field.is_nullable() => Ok(new_null_array(field.data_type(), batch.num_rows())),
+            None => Err(DecodeError::SchemaMismatch(format!(
+                "required column {} is missing",
+                field.name()
+            ))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let options = RecordBatchOptions::new().with_row_count(Some(batch.num_rows()));
+    RecordBatch::try_new_with_options(schema, columns, &options).map_err(|e| match e {
+        ArrowError::InvalidArgumentError(message) => DecodeError::SchemaMismatch(message),
+        e => e.into(),
+    })
+}
+
+/// A value of a row that doesn't match the table schema.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RowError {
+    pub row: usize,
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "row {}, field {}: {}",
+            self.row, self.field, self.message
+        )
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DecodeError {
+    #[error("Empty JSON data")]
+    Empty,
+    #[error("{} invalid values, first at {}", .total, .errors[0])]
+    InvalidRows {
+        /// The first errors, up to [`MAX_ROW_ERRORS`].
+        errors: Vec<RowError>,
+        total: usize,
+    },
+    #[error("Data doesn't match the table schema: {0}")]
+    SchemaMismatch(String),
+    #[error("Invalid options: {0}")]
+    InvalidOptions(String),
+    /// A line of a streamed body, or a CSV header, exceeds the size buffered
+    /// until it is complete.
+    #[error("Line longer than {0} bytes")]
+    LineTooLong(usize),
+    #[error("Failed to build record batch: {0}")]
+    Arrow(#[from] ArrowError),
 }
 
+/// Decodes JSON objects into a record batch of the given schema.
+///
+/// Timestamps are RFC 3339 strings or milliseconds since the epoch, dates and
+/// times ISO 8601 strings, binary values hex strings and decimals numbers or
+/// strings. Values that don't fit their column are reported with their row
+/// index and field path.
 pub fn read_record_batch_from_json(
     json_data: &[Value],
-) -> Result<RecordBatch, Box<dyn std::error::Error>> {
+    schema: SchemaRef,
+) -> Result<RecordBatch, DecodeError> {
     if json_data.is_empty() {
-        return Err("Empty JSON data".into());
+        return Err(DecodeError::Empty);
     }
 
-    // Extract field names from the first JSON object
-    let fields: Vec<Field> = json_data[0]
-        .as_object()
-        .ok_or("First item is not a JSON object")?
-        .keys()
-        .map(|k| Field::new(k, DataType::Utf8, true))
+    let rows: Vec<Cell> = json_data
+        .iter()
+        .enumerate()
+        .map(|(row, value)| Cell {
+            row,
+            value: Some(value),
+            in_parent: true,
+        })
         .collect();
 
-    let schema = Schema::new(fields);
+    let mut decoder = Decoder::default();
+    let (columns, validity) = decoder.decode_objects(schema.fields(), "", &rows);
+
+    for (cell, valid) in rows.iter().zip(validity) {
+        if !valid {
+            decoder.error(cell.row, "", "row is not a JSON object".to_string());
+        }
+    }
+
+    if decoder.total_errors > 0 {
+        return Err(DecodeError::InvalidRows {
+            errors: decoder.errors,
+            total: decoder.total_errors,
+        });
+    }
+
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
+
+/// A value to decode and the row it belongs to.
+#[derive(Clone, Copy)]
+struct Cell<'a> {
+    row: usize,
+    value: Option<&'a Value>,
+    /// False below a null struct, where required fields may be missing.
+    in_parent: bool,
+}
+
+impl<'a> Cell<'a> {
+    fn child(&self, value: Option<&'a Value>, in_parent: bool) -> Cell<'a> {
+        Cell {
+            row: self.row,
+            value,
+            in_parent,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Decoder {
+    errors: Vec<RowError>,
+    total_errors: usize,
+}
+
+impl Decoder {
+    fn error(&mut self, row: usize, field: &str, message: String) {
+        self.total_errors += 1;
+
+        if self.errors.len() < MAX_ROW_ERRORS {
+            self.errors.push(RowError {
+                row,
+                field: field.to_string(),
+                message,
+            });
+        }
+    }
+
+    /// Parses the non-null values of a column, reporting values that don't
+    /// parse and missing required values.
+    fn values<T>(
+        &mut self,
+        path: &str,
+        nullable: bool,
+        cells: &[Cell],
+        parse: impl Fn(&Value) -> Result<T, String>,
+    ) -> Vec<Option<T>> {
+        cells
+            .iter()
+            .map(|cell| match cell.value {
+                None | Some(Value::Null) => {
+                    if !nullable && cell.in_parent {
+                        self.error(cell.row, path, "missing required value".to_string());
+                    }
+                    None
+                }
+                Some(value) => match parse(value) {
+                    Ok(value) => Some(value),
+                    Err(message) => {
+                        self.error(cell.row, path, message);
+                        None
+                    }
+                },
+            })
+            .collect()
+    }
+
+    fn decode(&mut self, field: &FieldRef, path: &str, cells: &[Cell]) -> ArrayRef {
+        let nullable = field.is_nullable();
+
+        match field.data_type() {
+            DataType::Boolean => Arc::new(BooleanArray::from(self.values(
+                path,
+                nullable,
+                cells,
+                |v| v.as_bool().ok_or_else(|| mismatch("boolean", v)),
+            ))),
+            DataType::Int32 => {
+                Arc::new(Int32Array::from(self.values(path, nullable, cells, |v| {
+                    v.as_i64()
+                        .and_then(|n| i32::try_from(n).ok())
+                        .ok_or_else(|| mismatch("int", v))
+                })))
+            }
+            DataType::Int64 => {
+                Arc::new(Int64Array::from(self.values(path, nullable, cells, |v| {
+                    v.as_i64().ok_or_else(|| mismatch("long", v))
+                })))
+            }
+            DataType::Float32 => Arc::new(Float32Array::from(self.values(
+                path,
+                nullable,
+                cells,
+                |v| {
+                    v.as_f64()
+                        .map(|n| n as f32)
+                        .ok_or_else(|| mismatch("float", v))
+                },
+            ))),
+            DataType::Float64 => Arc::new(Float64Array::from(self.values(
+                path,
+                nullable,
+                cells,
+                |v| v.as_f64().ok_or_else(|| mismatch("double", v)),
+            ))),
+            DataType::Date32 => {
+                Arc::new(Date32Array::from(self.values(path, nullable, cells, |v| {
+                    v.as_str()
+                        .and_then(Date32Type::parse)
+                        .ok_or_else(|| mismatch("date", v))
+                })))
+            }
+            DataType::Time64(TimeUnit::Microsecond) => Arc::new(Time64MicrosecondArray::from(
+                self.values(path, nullable, cells, |v| {
+                    v.as_str()
+                        .and_then(Time64MicrosecondType::parse)
+                        .ok_or_else(|| mismatch("time", v))
+                }),
+            )),
+            DataType::Timestamp(TimeUnit::Microsecond, None) => Arc::new(
+                TimestampMicrosecondArray::from(self.values(path, nullable, cells, |v| {
+                    match v {
+                        Value::String(s) => TimestampMicrosecondType::parse(s),
+                        _ => v.as_i64().and_then(|millis| millis.checked_mul(1000)),
+                    }
+                    .ok_or_else(|| mismatch("timestamp", v))
+                })),
+            ),
+            DataType::Utf8 => {
+                Arc::new(StringArray::from(self.values(path, nullable, cells, |v| {
+                    v.as_str()
+                        .map(str::to_string)
+                        .ok_or_else(|| mismatch("string", v))
+                })))
+            }
+            DataType::Binary => Arc::new(BinaryArray::from_iter(self.values(
+                path,
+                nullable,
+                cells,
+                |v| {
+                    v.as_str()
+                        .and_then(decode_hex)
+                        .ok_or_else(|| mismatch("hex string", v))
+                },
+            ))),
+            DataType::FixedSizeBinary(length) => {
+                let length = *length;
+                let values = self.values(path, nullable, cells, |v| {
+                    v.as_str()
+                        .and_then(decode_hex)
+                        .filter(|bytes| bytes.len() == length as usize)
+                        .ok_or_else(|| mismatch(&format!("hex string of {} bytes", length), v))
+                });
+                FixedSizeBinaryArray::try_from_sparse_iter_with_size(values.into_iter(), length)
+                    .map(|array| Arc::new(array) as ArrayRef)
+                    .unwrap_or_else(|_| new_null_array(field.data_type(), cells.len()))
+            }
+            DataType::Decimal128(precision, scale) => {
+                let (precision, scale) = (*precision, *scale);
+                let values = self.values(path, nullable, cells, |v| {
+                    let text = match v {
+                        Value::String(s) => s.clone(),
+                        Value::Number(n) => n.to_string(),
+                        _ => return Err(mismatch("decimal", v)),
+                    };
+                    parse_decimal::<Decimal128Type>(&text, precision, scale)
+                        .map_err(|_| mismatch(&format!("decimal({}, {})", precision, scale), v))
+                });
+                Decimal128Array::from(values)
+                    .with_precision_and_scale(precision, scale)
+                    .map(|array| Arc::new(array) as ArrayRef)
+                    .unwrap_or_else(|_| new_null_array(field.data_type(), cells.len()))
+            }
+            DataType::Struct(fields) => {
+                let (children, validity) = self.decode_objects(fields, path, cells);
+                self.check_required(field, path, cells, &validity);
+                StructArray::try_new(fields.clone(), children, null_buffer(validity))
+                    .map(|array| Arc::new(array) as ArrayRef)
+                    .unwrap_or_else(|_| new_null_array(field.data_type(), cells.len()))
+            }
+            DataType::List(element) => self.decode_list(field, element, path, cells),
+            DataType::Map(entries, sorted) => self.decode_map(field, entries, *sorted, path, cells),
+            other => {
+                for cell in cells {
+                    self.error(cell.row, path, format!("unsupported type {}", other));
+                }
+                new_null_array(other, cells.len())
+            }
+        }
+    }
+
+    /// Decodes the fields of JSON objects. Values that aren't objects are
+    /// null and returned as invalid, so the caller can report them.
+    fn decode_objects(
+        &mut self,
+        fields: &Fields,
+        path: &str,
+        cells: &[Cell],
+    ) -> (Vec<ArrayRef>, Vec<bool>) {
+        let mut validity = Vec::with_capacity(cells.len());
+
+        for cell in cells {
+            match cell.value {
+                Some(Value::Object(object)) => {
+                    for key in object.keys() {
+                        if fields.find(key).is_none() {
+                            let message = "field is not in the table schema".to_string();
+                            self.error(cell.row, &field_path(path, key), message);
+                        }
+                    }
+                    validity.push(true);
+                }
+                _ => validity.push(false),
+            }
+        }
+
+        let children = fields
+            .iter()
+            .map(|child| {
+                let child_cells: Vec<Cell> = cells
+                    .iter()
+                    .zip(&validity)
+                    .map(|(cell, &valid)| {
+                        let value = cell.value.and_then(|v| v.get(child.name()));
+                        cell.child(value, valid && cell.in_parent)
+                    })
+                    .collect();
+                self.decode(child, &field_path(path, child.name()), &child_cells)
+            })
+            .collect();
+
+        (children, validity)
+    }
+
+    /// Reports values of a nested field that are missing although required,
+    /// or present but of the wrong JSON type.
+    fn check_required(&mut self, field: &FieldRef, path: &str, cells: &[Cell], validity: &[bool]) {
+        for (cell, valid) in cells.iter().zip(validity) {
+            match cell.value {
+                None | Some(Value::Null) => {
+                    if !field.is_nullable() && cell.in_parent {
+                        self.error(cell.row, path, "missing required value".to_string());
+                    }
+                }
+                Some(value) if !valid => {
+                    let expected = match field.data_type() {
+                        DataType::List(_) => "list",
+                        _ => "object",
+                    };
+                    self.error(cell.row, path, mismatch(expected, value));
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn decode_list(
+        &mut self,
+        field: &FieldRef,
+        element: &FieldRef,
+        path: &str,
+        cells: &[Cell],
+    ) -> ArrayRef {
+        let mut validity = Vec::with_capacity(cells.len());
+        let mut lengths = Vec::with_capacity(cells.len());
+        let mut elements = Vec::new();
+
+        for cell in cells {
+            match cell.value {
+                Some(Value::Array(values)) => {
+                    elements.extend(values.iter().map(|v| cell.child(Some(v), true)));
+                    lengths.push(values.len());
+                    validity.push(true);
+                }
+                _ => {
+                    lengths.push(0);
+                    validity.push(false);
+                }
+            }
+        }
+
+        let values = self.decode(element, &field_path(path, element.name()), &elements);
+        self.check_required(field, path, cells, &validity);
+
+        ListArray::try_new(
+            element.clone(),
+            OffsetBuffer::from_lengths(lengths),
+            values,
+            null_buffer(validity),
+        )
+        .map(|array| Arc::new(array) as ArrayRef)
+        .unwrap_or_else(|_| new_null_array(field.data_type(), cells.len()))
+    }
+
+    fn decode_map(
+        &mut self,
+        field: &FieldRef,
+        entries: &FieldRef,
+        sorted: bool,
+        path: &str,
+        cells: &[Cell],
+    ) -> ArrayRef {
+        let DataType::Struct(entry_fields) = entries.data_type() else {
+            return new_null_array(field.data_type(), cells.len());
+        };
+        let (key_field, value_field) = (&entry_fields[0], &entry_fields[1]);
 
-    // Convert JSON data to columns
-    let mut columns: Vec<ArrayRef> = Vec::new();
-    for field in schema.fields() {
-        let column: Vec<Option<String>> = json_data
+        // JSON object keys are strings, other key types are parsed from them
+        let keys: Vec<Vec<Value>> = cells
             .iter()
-            .map(|obj| {
-                obj.get(field.name())
-                    .and_then(|v| Some(v.to_string()))
+            .map(|cell| match cell.value {
+                Some(Value::Object(object)) => object
+                    .keys()
+                    .map(|key| match key_field.data_type() {
+                        DataType::Utf8 => Value::String(key.clone()),
+                        _ => serde_json::from_str(key).unwrap_or(Value::String(key.clone())),
+                    })
+                    .collect(),
+                _ => Vec::new(),
             })
             .collect();
-        columns.push(Arc::new(StringArray::from(column)) as ArrayRef);
+
+        let mut validity = Vec::with_capacity(cells.len());
+        let mut lengths = Vec::with_capacity(cells.len());
+        let mut key_cells = Vec::new();
+        let mut value_cells = Vec::new();
+
+        for (cell, keys) in cells.iter().zip(&keys) {
+            match cell.value {
+                Some(Value::Object(object)) => {
+                    key_cells.extend(keys.iter().map(|k| cell.child(Some(k), true)));
+                    value_cells.extend(object.values().map(|v| cell.child(Some(v), true)));
+                    lengths.push(object.len());
+                    validity.push(true);
+                }
+                _ => {
+                    lengths.push(0);
+                    validity.push(false);
+                }
+            }
+        }
+
+        let keys = self.decode(key_field, &field_path(path, key_field.name()), &key_cells);
+        let values = self.decode(
+            value_field,
+            &field_path(path, value_field.name()),
+            &value_cells,
+        );
+        self.check_required(field, path, cells, &validity);
+
+        StructArray::try_new(entry_fields.clone(), vec![keys, values], None)
+            .and_then(|entries_array| {
+                MapArray::try_new(
+                    entries.clone(),
+                    OffsetBuffer::from_lengths(lengths),
+                    entries_array,
+                    null_buffer(validity),
+                    sorted,
+                )
+            })
+            .map(|array| Arc::new(array) as ArrayRef)
+            .unwrap_or_else(|_| new_null_array(field.data_type(), cells.len()))
+    }
+}
+
+fn field_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", parent, name)
+    }
+}
+
+fn mismatch(expected: &str, value: &Value) -> String {
+    format!("expected {}, found {}", expected, value)
+}
+
+fn null_buffer(validity: Vec<bool>) -> Option<NullBuffer> {
+    let nulls = NullBuffer::from(validity);
+    (nulls.null_count() > 0).then_some(nulls)
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
     }
 
-    RecordBatch::try_new(Arc::new(schema), columns).map_err(|e| e.into())
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use arrow::array::{Array, AsArray};
+    use arrow::datatypes::{Field, Int32Type, Schema};
+    use arrow::ipc::writer::StreamWriter;
     use serde_json::json;
 
+    fn schema() -> SchemaRef {
+        let tags = Field::new("element", DataType::Utf8, true);
+        let device = Fields::from(vec![
+            Field::new("type", DataType::Utf8, false),
+            Field::new("tags", DataType::List(Arc::new(tags)), true),
+        ]);
+
+        Arc::new(Schema::new(vec![
+            Field::new("name", DataType::Utf8, false),
+            Field::new("age", DataType::Int32, true),
+            Field::new("score", DataType::Float64, true),
+            Field::new("active", DataType::Boolean, true),
+            Field::new(
+                "seen_at",
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+                true,
+            ),
+            Field::new("device", DataType::Struct(device), true),
+        ]))
+    }
+
     #[test]
-    fn test_read_record_batch_from_vec() {
+    fn test_read_record_batch_from_json() {
         let json_data = vec![
-            json!({"name": "Alice", "age": "30", "city": "New York"}),
-            json!({"name": "Bob", "age": "25", "city": "San Francisco"}),
+            json!({"name": "Alice", "age": 30, "score": 1.5, "active": true,
+                   "seen_at": "2024-07-01T12:00:00Z",
+                   "device": {"type": "mobile", "tags": ["a", "b"]}}),
+            json!({"name": "Bob", "age": null, "score": 2, "seen_at": 1719835200000i64}),
         ];
 
-        let record_batch = read_record_batch_from_json(&json_data).unwrap();
+        let batch = read_record_batch_from_json(&json_data, schema()).unwrap();
 
-        assert_eq!(record_batch.num_columns(), 3);
-        assert_eq!(record_batch.num_rows(), 2);
+        assert_eq!(batch.num_rows(), 2);
 
-        let schema = record_batch.schema();
-        let mut field_names = schema
-            .fields()
-            .iter()
-            .map(|f| f.name())
-            .collect::<Vec<&String>>();
-
-        field_names.sort();
-
-        assert_eq!(field_names, &["age", "city", "name"]);
-
-        let age_array = record_batch
-            .column(0)
-            .as_any()
-            .downcast_ref::<StringArray>()
-            .unwrap();
-        assert_eq!(age_array.value(0), "30");
-        assert_eq!(age_array.value(1), "25");
-
-        let city_array = record_batch
-            .column(1)
-            .as_any()
-            .downcast_ref::<StringArray>()
-            .unwrap();
-        assert_eq!(city_array.value(0), "New York");
-        assert_eq!(city_array.value(1), "San Francisco");
-
-        let name_array = record_batch
-            .column(2)
-            .as_any()
-            .downcast_ref::<StringArray>()
-            .unwrap();
-        assert_eq!(name_array.value(0), "Alice");
-        assert_eq!(name_array.value(1), "Bob");
+        let name = batch.column(0).as_string::<i32>();
+        assert_eq!(name.value(0), "Alice");
+        assert_eq!(name.value(1), "Bob");
+
+        let age = batch.column(1).as_primitive::<Int32Type>();
+        assert_eq!(age.value(0), 30);
+        assert!(age.is_null(1));
+
+        let seen_at = batch.column(4).as_primitive::<TimestampMicrosecondType>();
+        assert_eq!(seen_at.value(0), 1_719_835_200_000_000);
+        assert_eq!(seen_at.value(1), 1_719_835_200_000_000);
+
+        let device = batch.column(5).as_struct();
+        assert!(device.is_null(1));
+        assert_eq!(device.column(0).as_string::<i32>().value(0), "mobile");
+        let tags = device.column(1).as_list::<i32>();
+        assert_eq!(tags.value(0).as_string::<i32>().value(1), "b");
+    }
+
+    #[test]
+    fn test_read_record_batch_from_json_reports_row_errors() {
+        let json_data = vec![
+            json!({"name": "Alice", "age": "30"}),
+            json!({"age": 25, "device": {"tags": "a"}}),
+            json!({"name": "Carol", "color": "red"}),
+            json!("Dave"),
+        ];
+
+        let Err(DecodeError::InvalidRows { errors, total }) =
+            read_record_batch_from_json(&json_data, schema())
+        else {
+            panic!("expected invalid rows");
+        };
+
+        let error = |row: usize, field: &str, message: &str| RowError {
+            row,
+            field: field.to_string(),
+            message: message.to_string(),
+        };
+
+        assert_eq!(total, errors.len());
+        assert_eq!(
+            errors,
+            vec![
+                error(2, "color", "field is not in the table schema"),
+                error(1, "name", "missing required value"),
+                error(0, "age", "expected int, found \"30\""),
+                error(1, "device.type", "missing required value"),
+                error(1, "device.tags", "expected list, found \"a\""),
+                error(3, "", "row is not a JSON object"),
+            ]
+        );
+    }
+
+    fn ipc_stream(batches: &[RecordBatch]) -> Vec<u8> {
+        let mut writer = StreamWriter::try_new(Vec::new(), &batches[0].schema()).unwrap();
+        for batch in batches {
+            writer.write(batch).unwrap();
+        }
+        writer.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_read_record_batches_from_vec() {
+        let producer_schema = Arc::new(Schema::new(vec![
+            Field::new("name", DataType::Utf8, false),
+            Field::new("age", DataType::Int64, false),
+        ]));
+        let batch = |names: Vec<&str>, ages: Vec<i64>| {
+            RecordBatch::try_new(
+                producer_schema.clone(),
+                vec![
+                    Arc::new(StringArray::from(names)),
+                    Arc::new(Int64Array::from(ages)),
+                ],
+            )
+            .unwrap()
+        };
+
+        let bytes = ipc_stream(&[
+            batch(vec!["Alice", "Bob"], vec![30, 25]),
+            batch(vec!["Carol"], vec![41]),
+        ]);
+
+        let batches = read_record_batches_from_vec(&bytes, schema()).unwrap();
+
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[1].schema(), schema());
+        assert_eq!(batches[1].column(0).as_string::<i32>().value(0), "Carol");
+        assert_eq!(
+            batches[1].column(1).as_primitive::<Int32Type>().value(0),
+            41
+        );
+        assert!(batches[1].column(5).is_null(0));
+
+        // Ages don't fit the table's int column
+        let bytes = ipc_stream(&[batch(vec!["Dave"], vec![i64::MAX])]);
+        let result = read_record_batches_from_vec(&bytes, schema());
+        assert!(matches!(result, Err(DecodeError::SchemaMismatch(_))));
     }
 }
@@ -0,0 +1,363 @@
+//! Write-ahead log of buffered writes, so rows accepted but not yet flushed
+//! survive a crash.
+//!
+//! Every table buffer has its own segment, an Arrow IPC stream whose schema
+//! metadata names the table, its location, properties and sort order. Batches are synced to the
+//! segment before they are buffered, and the segment is deleted once the
+//! buffer is flushed. Segments left on disk at startup were never flushed and
+//! are replayed. A segment that can't be read is renamed out of the way
+//! rather than failing the start, and one whose rows can't be committed is
+//! kept for the next start.
+
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arrow::{
+    compute::concat_batches,
+    datatypes::{Schema, SchemaRef},
+    error::ArrowError,
+    ipc::{
+        reader::StreamReader,
+        writer::{write_message, DictionaryTracker, IpcDataGenerator, IpcWriteOptions},
+    },
+    record_batch::RecordBatch,
+};
+use parquet::file::properties::WriterProperties;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+    properties::{FileOptions, PropertyError},
+    store::{DataFilePaths, Store, StoreError},
+};
+
+const TABLE_KEY: &str = "wal.table";
+const LOCATION_KEY: &str = "wal.location";
+const PROPERTIES_KEY: &str = "wal.properties";
+const SORT_KEYS_KEY: &str = "wal.sort-keys";
+const CLUSTERING_KEY: &str = "wal.clustering";
+const SEGMENT_EXTENSION: &str = "wal";
+const QUARANTINE_EXTENSION: &str = "wal.failed";
+
+#[derive(Error, Debug)]
+pub enum WalError {
+    #[error("Write-ahead log I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to encode write-ahead log segment: {0}")]
+    Arrow(#[from] ArrowError),
+    #[error("Failed to replay write-ahead log segment: {0}")]
+    Store(#[from] StoreError),
+    #[error("Invalid write-ahead log segment {0}")]
+    InvalidSegment(String),
+    #[error("Invalid table properties in write-ahead log segment: {0}")]
+    Properties(#[from] PropertyError),
+}
+
+/// Directory of write-ahead log segments.
+#[derive(Debug, Clone)]
+pub struct Wal {
+    dir: PathBuf,
+}
+
+/// Outcome of replaying the segments left by a previous run.
+#[derive(Debug, Default)]
+pub struct Replay {
+    /// Segments whose rows were committed.
+    pub committed: usize,
+    /// Segments that couldn't be read, renamed so they aren't replayed again.
+    pub quarantined: Vec<(PathBuf, WalError)>,
+    /// Segments whose rows couldn't be committed, left for the next start.
+    pub kept: Vec<(PathBuf, WalError)>,
+}
+
+/// Table and batches of a segment left by a previous run.
+struct SegmentData {
+    table: String,
+    location: String,
+    options: FileOptions,
+    data: RecordBatch,
+}
+
+/// Batches buffered for one table, as they were accepted.
+pub struct Segment {
+    path: PathBuf,
+    file: File,
+    generator: IpcDataGenerator,
+    tracker: DictionaryTracker,
+    options: IpcWriteOptions,
+}
+
+impl Wal {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, WalError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Starts the segment of a new table buffer.
+    pub fn create_segment(
+        &self,
+        table: &str,
+        location: &str,
+        options: &FileOptions,
+        schema: &Schema,
+    ) -> Result<Segment, WalError> {
+        let path = self
+            .dir
+            .join(format!("{}.{}", Uuid::new_v4(), SEGMENT_EXTENSION));
+        let mut file = File::create(&path)?;
+
+        let mut metadata = schema.metadata().clone();
+        metadata.insert(TABLE_KEY.to_string(), table.to_string());
+        metadata.insert(LOCATION_KEY.to_string(), location.to_string());
+        metadata.insert(
+            PROPERTIES_KEY.to_string(),
+            serde_json::to_string(&options.properties).expect("string maps serialize"),
+        );
+        metadata.insert(
+            SORT_KEYS_KEY.to_string(),
+            serde_json::to_string(&options.sort_keys).expect("sort keys serialize"),
+        );
+        if let Some(clustering) = &options.clustering {
+            metadata.insert(
+                CLUSTERING_KEY.to_string(),
+                serde_json::to_string(clustering).expect("clustering serializes"),
+            );
+        }
+        let schema = schema.clone().with_metadata(metadata);
+
+        let generator = IpcDataGenerator::default();
+        let options = IpcWriteOptions::default();
+        write_message(
+            &mut file,
+            generator.schema_to_bytes(&schema, &options),
+            &options,
+        )?;
+        file.sync_data()?;
+
+        Ok(Segment {
+            path,
+            file,
+            generator,
+            tracker: DictionaryTracker::new(false),
+            options,
+        })
+    }
+
+    /// Writes and registers the batches of segments left by a previous run,
+    /// deleting each segment once its data is committed.
+    pub async fn replay(&self, store: &dyn Store) -> Result<Replay, WalError> {
+        let mut segments = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == SEGMENT_EXTENSION) {
+                segments.push(path);
+            }
+        }
+        segments.sort();
+
+        let mut replay = Replay::default();
+        for path in segments {
+            let segment =
+                match read_segment(&path).and_then(|segment| segment.map(prepare).transpose()) {
+                    Ok(segment) => segment,
+                    Err(e) => {
+                        fs::rename(&path, path.with_extension(QUARANTINE_EXTENSION))?;
+                        replay.quarantined.push((path, e));
+                        continue;
+                    }
+                };
+
+            if let Some((table, location, properties, data)) = segment {
+                let data_path = DataFilePaths::new(&location).path(0);
+                let committed = match store.write(data, &data_path, properties).await {
+                    Ok(file) => store.notify_catalog(&table, &[file]).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = committed {
+                    replay.kept.push((path, e.into()));
+                    continue;
+                }
+            }
+
+            fs::remove_file(&path)?;
+            replay.committed += 1;
+        }
+
+        Ok(replay)
+    }
+}
+
+impl Segment {
+    /// Appends a batch, returning once it is on disk.
+    pub fn append(&mut self, batch: &RecordBatch) -> Result<(), WalError> {
+        let (dictionaries, message) =
+            self.generator
+                .encoded_batch(batch, &mut self.tracker, &self.options)?;
+
+        for dictionary in dictionaries {
+            write_message(&mut self.file, dictionary, &self.options)?;
+        }
+        write_message(&mut self.file, message, &self.options)?;
+        self.file.sync_data()?;
+
+        Ok(())
+    }
+
+    /// Deletes the segment once its batches were flushed.
+    pub fn remove(self) -> Result<(), WalError> {
+        fs::remove_file(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Table, location, writer properties and arranged rows of a segment.
+fn prepare(
+    segment: SegmentData,
+) -> Result<(String, String, WriterProperties, RecordBatch), WalError> {
+    let properties = segment.options.writer_properties()?;
+    let data = segment.options.arrange(segment.data)?;
+    Ok((segment.table, segment.location, properties, data))
+}
+
+/// Reads the table and batches of a segment. A batch cut short by a crash or
+/// a failed append was never acknowledged, and nothing is logged after it, so
+/// reading stops there.
+fn read_segment(path: &Path) -> Result<Option<SegmentData>, WalError> {
+    let invalid = || WalError::InvalidSegment(path.display().to_string());
+
+    let reader = StreamReader::try_new(BufReader::new(File::open(path)?), None)?;
+    let mut metadata = reader.schema().metadata().clone();
+    let table = metadata.remove(TABLE_KEY).ok_or_else(invalid)?;
+    let location = metadata.remove(LOCATION_KEY).ok_or_else(invalid)?;
+    // Segments of versions that didn't log these settings have none
+    let mut options = FileOptions::default();
+    if let Some(properties) = metadata.remove(PROPERTIES_KEY) {
+        options.properties = serde_json::from_str(&properties).map_err(|_| invalid())?;
+    }
+    if let Some(sort_keys) = metadata.remove(SORT_KEYS_KEY) {
+        options.sort_keys = serde_json::from_str(&sort_keys).map_err(|_| invalid())?;
+    }
+    if let Some(clustering) = metadata.remove(CLUSTERING_KEY) {
+        options.clustering = Some(serde_json::from_str(&clustering).map_err(|_| invalid())?);
+    }
+
+    // The data files get the table's schema, without the log's metadata
+    let schema: SchemaRef = Arc::new(reader.schema().as_ref().clone().with_metadata(metadata));
+    let batches = reader
+        .map_while(Result::ok)
+        .map(|batch| RecordBatch::try_new(schema.clone(), batch.columns().to_vec()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if batches.is_empty() {
+        return Ok(None);
+    }
+
+    let data = concat_batches(&schema, &batches)?;
+    Ok(Some(SegmentData {
+        table,
+        location,
+        options,
+        data,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::store::tests::MemoryStore;
+    use arrow::{
+        array::Int32Array,
+        datatypes::{DataType, Field},
+    };
+
+    fn batch(values: Vec<i32>) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(Int32Array::from(values))]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal = Wal::open(dir.path()).unwrap();
+
+        let mut segment = wal
+            .create_segment(
+                "db.t",
+                "/warehouse/db/t",
+                &FileOptions::default(),
+                &batch(vec![]).schema(),
+            )
+            .unwrap();
+        segment.append(&batch(vec![1, 2])).unwrap();
+        segment.append(&batch(vec![3])).unwrap();
+
+        // A flushed buffer leaves no segment behind
+        let flushed = wal
+            .create_segment(
+                "db.u",
+                "/warehouse/db/u",
+                &FileOptions::default(),
+                &batch(vec![]).schema(),
+            )
+            .unwrap();
+        flushed.remove().unwrap();
+
+        // The crash cut the last batch short
+        let path = segment.path.clone();
+        drop(segment);
+        File::options()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&[0xff, 0xff, 0xff, 0xff, 0x10, 0x00])
+            .unwrap();
+
+        let store = MemoryStore::default();
+        assert_eq!(wal.replay(&store).await.unwrap().committed, 1);
+
+        let files = store.files.lock().unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].0.starts_with("/warehouse/db/t/data/"));
+        assert_eq!(files[0].1, 3);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_replay_quarantines_unreadable_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal = Wal::open(dir.path()).unwrap();
+
+        let broken = dir.path().join(format!("0.{}", SEGMENT_EXTENSION));
+        fs::write(&broken, b"not an arrow stream").unwrap();
+        let mut segment = wal
+            .create_segment(
+                "db.t",
+                "/warehouse/db/t",
+                &FileOptions::default(),
+                &batch(vec![]).schema(),
+            )
+            .unwrap();
+        segment.append(&batch(vec![1])).unwrap();
+
+        let store = MemoryStore::default();
+        let replay = wal.replay(&store).await.unwrap();
+
+        // The readable segment is still committed
+        assert_eq!(replay.committed, 1);
+        assert_eq!(store.files.lock().unwrap().len(), 1);
+        assert_eq!(replay.quarantined.len(), 1);
+        assert!(!broken.exists());
+        assert!(broken.with_extension(QUARANTINE_EXTENSION).exists());
+
+        // and the quarantined one isn't replayed again
+        let replay = wal.replay(&store).await.unwrap();
+        assert_eq!(replay.committed + replay.quarantined.len(), 0);
+    }
+}